@@ -335,8 +335,6 @@
 
 #define TIME_MASK           0xFFFFFFFF
 
-#define SHM_ID_KEY          444
-
 /* Maximum allocator request size (keep well under INT_MAX): */
 
 #define MAX_ALLOC           0x40000000
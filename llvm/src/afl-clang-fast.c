@@ -135,11 +135,23 @@ static void edit_params(u32 argc, char** argv) {
     cc_params[cc_par_cnt++] = "-fsanitize-coverage=trace-pc-guard";
   }
 
+  /* abstraktor picks the pass built for the selected clang through
+     ABSTRAKTOR_PASS, and ABSTRAKTOR_LLVM_MAJOR tells how to load it: as a
+     new pass manager plugin from LLVM 13 on, through -load before that. */
+
+  u8* pass_path = getenv("ABSTRAKTOR_PASS");
+  u8* llvm_major = getenv("ABSTRAKTOR_LLVM_MAJOR");
+  if (!pass_path) pass_path = alloc_printf("%s/afl-llvm-pass.so", obj_path);
+
   cc_params[cc_par_cnt++] = "-lstdc++";
-  cc_params[cc_par_cnt++] = "-Xclang";
-  cc_params[cc_par_cnt++] = "-load";
-  cc_params[cc_par_cnt++] = "-Xclang";
-  cc_params[cc_par_cnt++] = alloc_printf("%s/afl-llvm-pass.so", obj_path);
+  if (llvm_major && atoi(llvm_major) >= 13) {
+    cc_params[cc_par_cnt++] = alloc_printf("-fpass-plugin=%s", pass_path);
+  } else {
+    cc_params[cc_par_cnt++] = "-Xclang";
+    cc_params[cc_par_cnt++] = "-load";
+    cc_params[cc_par_cnt++] = "-Xclang";
+    cc_params[cc_par_cnt++] = pass_path;
+  }
 
   cc_params[cc_par_cnt++] = "-Qunused-arguments";
 
@@ -337,7 +349,8 @@ int main(int argc, char** argv) {
          "In contrast to the traditional afl-clang tool, this version is implemented as\n"
          "an LLVM pass and tends to offer improved performance with slow programs.\n\n"
 
-         "You can specify custom next-stage toolchain via AFL_CC and AFL_CXX. Setting\n"
+         "You can specify custom next-stage toolchain via AFL_CC and AFL_CXX, and the\n"
+         "pass built for it via ABSTRAKTOR_PASS and ABSTRAKTOR_LLVM_MAJOR. Setting\n"
          "AFL_HARDEN enables hardening optimizations in the compiled code.\n\n",
          BIN_PATH, BIN_PATH);
 
@@ -42,20 +42,28 @@
 #include <string>
 #include <unordered_map>
 #include <set>
-#include <sys/shm.h>
 #include <cxxabi.h>
 #include <iostream>
 
 #include "llvm/ADT/Statistic.h"
+#include "llvm/Config/llvm-config.h"
 #include "llvm/IR/IRBuilder.h"
+#include "llvm/IR/IntrinsicInst.h"
 #include "llvm/IR/LegacyPassManager.h"
 #include "llvm/IR/Module.h"
 #include "llvm/Support/Debug.h"
 #include "llvm/IR/DebugInfoMetadata.h"
+#if LLVM_VERSION_MAJOR < 16
 #include "llvm/Transforms/IPO/PassManagerBuilder.h"
+#endif
+#if LLVM_VERSION_MAJOR >= 13
+#include "llvm/Passes/PassBuilder.h"
+#include "llvm/Passes/PassPlugin.h"
+#endif
 #include "llvm/IR/TypeFinder.h"
 #include "llvm/Support/FileSystem.h"
 #include "./targets_types.h"
+#include "./llvm-compat.h"
 
 #include "../rustc-demangle/crates/capi/include/rustc_demangle.h"
 #include <nlohmann/json.hpp>
@@ -75,6 +83,14 @@ namespace
   struct ValueInfo {
     Type* type;
     std::vector<unsigned int> indexes;
+    // Source type of the value, used to find what an opaque pointer points to
+    DIType* di_type;
+  };
+
+  // What one annotated variable resolved to, for the instrumentation report
+  struct CapturedValue {
+    std::string type;
+    bool resolved;
   };
 
 
@@ -103,10 +119,14 @@ namespace
     PointerType *Int64PtrTy;
     
 
-    // Store mapping data from basicblock location to ID
+    // Manifest mapping event IDs to their source location (JSON lines),
+    // written to $ABSTRAKTOR_MANIFEST_FILE when set
     std::ofstream bbToID;
 
-    u16 *get_ID_ptr();
+    // What happened to each annotated line (JSON lines), written to
+    // $ABSTRAKTOR_REPORT_FILE when set and read back by `abstraktor llvm`
+    std::ofstream reportFile;
+
     static void get_debug_loc(const Instruction *I, std::string &Filename, unsigned &Line);
     static void load_instr_targets(TARGETS_TYPE &bb_targets, TargetsTypes &func_targets, TargetsTypes &block_targets, CONST_TARGETS_TYPE &const_targets, GROUP_NAMES_TYPE &group_transition_names);
 
@@ -114,29 +134,36 @@ namespace
     static u8 is_target_loc(std::string codefile, unsigned line, TARGETS_TYPE &bb_targets, TargetsTypes &func_targets, TargetsTypes &block_targets, CONST_TARGETS_TYPE &const_targets);
 
     u8 check_code_language(std::string codefile);
-    void printFuncLog(std::string filename, unsigned line, u16 evtID, std::string func_name);
+    std::string demangledName(Function &F, std::string codefile);
+    void recordManifest(u16 evtID, std::string kind, std::string filename, unsigned line, std::string func_name, std::string transition_name);
+    void recordReport(std::string kind, std::string filename, unsigned line, std::string status, u16 evtID, Function &F, unsigned ir_block, const nlohmann::json &variables);
+    nlohmann::json argumentVariables(const std::vector<std::string> &instrumented_parameters, const std::vector<std::vector<unsigned int>> &selected_fields, iterator_range<Function::arg_iterator> iterator_arguments, const std::vector<CapturedValue> &captured);
+    static std::string typeName(llvm::Type *T);
+    void printFuncLog(std::string filename, unsigned line, u16 evtID, std::string func_name, std::string transition_name);
     void printBBLog(std::string filename, unsigned line, u16 evtID);
-    void printBlockLog(std::string filename, unsigned line, u16 evtID);
+    void printBlockLog(std::string filename, unsigned line, u16 evtID, std::string func_name, std::string transition_name);
     void printConstLog(std::string filename, unsigned line, u16 evtID, std::string const_name);
     std::vector<std::string> getArgumentTypeDebug(std::vector<std::string> instrumented_parameters, iterator_range<Function::arg_iterator> iterator_arguments);
     std::vector<std::pair<llvm::Value*, ValueInfo>> getArgument(const std::vector<std::string> &instrumented_parameters, iterator_range<Function::arg_iterator> iterator_arguments, std::vector<std::vector<unsigned int>> &default_indices);
-    std::vector<llvm::Value*> getValues(std::vector<std::string> &vec, iterator_range<Function::arg_iterator> args, std::vector<std::vector<unsigned int>> &vec_selected_fields, IRBuilder<> &IRB);
-    void changeStructPointersToStructTypes(std::vector<std::pair<llvm::Value*, ValueInfo>> &valueTypeMap);
-    void extractValuesFromArgumentMap(std::vector<std::pair<llvm::Value*, ValueInfo>> &argument_map, IRBuilder<> &IRB,std::vector<llvm::Value*> &out_values); 
+    std::vector<llvm::Value*> getValues(std::vector<std::string> &vec, iterator_range<Function::arg_iterator> args, std::vector<std::vector<unsigned int>> &vec_selected_fields, IRBuilder<> &IRB, std::vector<CapturedValue> *captured = nullptr);
+    void changeStructPointersToStructTypes(Module &M, std::vector<std::pair<llvm::Value*, ValueInfo>> &valueTypeMap);
+    void extractValuesFromArgumentMap(std::vector<std::pair<llvm::Value*, ValueInfo>> &argument_map, IRBuilder<> &IRB,std::vector<llvm::Value*> &out_values, std::vector<CapturedValue> *captured = nullptr);
     Value* buildValuesArrayForFunction(std::vector<llvm::Value*> &values, IRBuilder<>& IRB);
     static void processTargets(const std::string &codefile, TargetsTypes &targets, const nlohmann::json &targets_json);
-    bool isPointerToPointer(llvm::Value *v);
-    bool isPointerToStruct(llvm::Value* v);
     bool isClangUnionType(llvm::Type *T);
-    bool isPointerToUnion(llvm::Type *T);
+    bool isPointerToUnion(Module &M, llvm::Type *T, DIType *di_type);
     bool isUnionValue(llvm::Type *T);
+    static DIType *stripQualifiers(DIType *T);
+    static DIType *pointeeDebugType(DIType *T);
+    static DIType *memberDebugType(DIType *T, unsigned index);
+    static DIType *argumentDebugType(Argument &Arg);
+    static DIType *storedDebugType(StoreInst *SI);
+    static StructType *structForDebugType(Module &M, DIType *T);
+    static StructType *pointeeStruct(Module &M, llvm::Type *T, DIType *di_type);
   };
 
 }
 
-std::error_code EC;
-llvm::raw_fd_ostream file2("mipass.log", EC, llvm::sys::fs::OpenFlags::OF_Append);
-
 void AFLCoverage::processTargets(const std::string &codefile, TargetsTypes &targets, const nlohmann::json &targets_json) {
   for (auto it = targets_json.begin(); it != targets_json.end(); ++it) {
       TargetsTypes::LineNum line_num = std::stoul(it.key());
@@ -170,7 +197,7 @@ void AFLCoverage::processTargets(const std::string &codefile, TargetsTypes &targ
 Value* AFLCoverage::buildValuesArrayForFunction(std::vector<llvm::Value*> &values, IRBuilder<>& IRB) {
     LLVMContext& Ctx = IRB.getContext();
     Type* Int32Ty = Type::getInt32Ty(Ctx);
-    Type *VoidPtrTy = IRB.getInt8PtrTy();
+    Type *VoidPtrTy = compat::int8PtrTy(Ctx);
 
     Function *F = IRB.GetInsertBlock()->getParent();
     IRBuilder<> EntryBuilder(&F->getEntryBlock(), F->getEntryBlock().getFirstInsertionPt());
@@ -208,29 +235,12 @@ Value* AFLCoverage::buildValuesArrayForFunction(std::vector<llvm::Value*> &value
 }
 
 
-bool AFLCoverage::isPointerToPointer(llvm::Value* v) {
-    if (auto *ptrTy = llvm::dyn_cast<llvm::PointerType>(v->getType())) {
-        return ptrTy->getElementType()->isPointerTy();
-    }
-    return false;
-}
-
-bool AFLCoverage::isPointerToStruct(llvm::Value* v) {
-    auto ptrTy = v->getType();
-    bool isStruct = true;
-    if (ptrTy->isPointerTy()) {
-        auto *elem = ptrTy->getPointerElementType();
-        isStruct = elem->getPointerElementType()->isStructTy();
-    }
-    return isStruct;
-}
-
 bool AFLCoverage::isClangUnionType(llvm::Type *T) {
-    auto *ST = llvm::dyn_cast<llvm::StructType>(T);
+    auto *ST = llvm::dyn_cast_or_null<llvm::StructType>(T);
     if (!ST) return false;
 
     llvm::StringRef name = ST->getName();
-    return name.startswith("union.") || name.contains("union");
+    return compat::startsWith(name, "union.") || name.contains("union");
 }
 
 
@@ -238,26 +248,119 @@ bool AFLCoverage::isUnionValue(llvm::Type *T) {
     return isClangUnionType(T);
 }
 
-bool AFLCoverage::isPointerToUnion(llvm::Type *T) {
-    if (!T->isPointerTy()) return false;
-    return isClangUnionType(T->getPointerElementType());
+bool AFLCoverage::isPointerToUnion(Module &M, llvm::Type *T, DIType *di_type) {
+    return isClangUnionType(pointeeStruct(M, T, di_type));
+}
+
+/***
+ * Opaque pointers (the default from LLVM 15 on) do not say what they point
+ * to, so struct types are recovered from the debug info of the annotated
+ * variables. Typed pointers still answer directly.
+ ***/
+DIType *AFLCoverage::stripQualifiers(DIType *T) {
+    while (auto *DT = dyn_cast_or_null<DIDerivedType>(T)) {
+        unsigned tag = DT->getTag();
+        if (tag != dwarf::DW_TAG_typedef && tag != dwarf::DW_TAG_const_type &&
+            tag != dwarf::DW_TAG_volatile_type && tag != dwarf::DW_TAG_restrict_type &&
+            tag != dwarf::DW_TAG_atomic_type && tag != dwarf::DW_TAG_member)
+            break;
+        T = DT->getBaseType();
+    }
+    return T;
+}
+
+DIType *AFLCoverage::pointeeDebugType(DIType *T) {
+    auto *DT = dyn_cast_or_null<DIDerivedType>(stripQualifiers(T));
+    if (!DT) return nullptr;
+    if (DT->getTag() != dwarf::DW_TAG_pointer_type && DT->getTag() != dwarf::DW_TAG_reference_type)
+        return nullptr;
+    return DT->getBaseType();
+}
+
+/* The type of the index-th non-static member, matching the IR field order */
+DIType *AFLCoverage::memberDebugType(DIType *T, unsigned index) {
+    auto *CT = dyn_cast_or_null<DICompositeType>(stripQualifiers(T));
+    if (!CT) return nullptr;
+    unsigned i = 0;
+    for (DINode *element : CT->getElements()) {
+        auto *member = dyn_cast<DIDerivedType>(element);
+        if (!member || member->getTag() != dwarf::DW_TAG_member || member->isStaticMember())
+            continue;
+        if (i++ == index) return member->getBaseType();
+    }
+    return nullptr;
+}
+
+DIType *AFLCoverage::argumentDebugType(Argument &Arg) {
+    DISubprogram *SP = Arg.getParent()->getSubprogram();
+    if (!SP || !SP->getType()) return nullptr;
+    // element 0 is the return type
+    auto types = SP->getType()->getTypeArray();
+    unsigned index = Arg.getArgNo() + 1;
+    return index < types.size() ? types[index] : nullptr;
+}
+
+/* The declared type of the local variable a store writes to */
+DIType *AFLCoverage::storedDebugType(StoreInst *SI) {
+    DILocalVariable *variable = compat::declaredVariable(SI->getPointerOperand());
+    return variable ? variable->getType() : nullptr;
+}
+
+/* The IR struct clang emitted for a source struct, union or class */
+StructType *AFLCoverage::structForDebugType(Module &M, DIType *T) {
+    StringRef typedef_name;
+    while (auto *DT = dyn_cast_or_null<DIDerivedType>(T)) {
+        if (DT->getTag() == dwarf::DW_TAG_typedef) {
+            if (typedef_name.empty()) typedef_name = DT->getName();
+        } else if (DT->getTag() != dwarf::DW_TAG_const_type &&
+                   DT->getTag() != dwarf::DW_TAG_volatile_type) {
+            return nullptr;
+        }
+        T = DT->getBaseType();
+    }
+    auto *CT = dyn_cast_or_null<DICompositeType>(T);
+    if (!CT) return nullptr;
+    // anonymous structs are named after their typedef
+    StringRef name = CT->getName().empty() ? typedef_name : CT->getName();
+    if (name.empty()) return nullptr;
+    for (const char *prefix : {"struct.", "union.", "class."}) {
+        if (StructType *ST = compat::structTypeByName(M, (prefix + name).str()))
+            return ST;
+    }
+    return nullptr;
+}
+
+StructType *AFLCoverage::pointeeStruct(Module &M, llvm::Type *T, DIType *di_type) {
+    if (!T->isPointerTy()) return nullptr;
+    if (Type *pointee = compat::typedPointee(T))
+        return dyn_cast<StructType>(pointee);
+    return structForDebugType(M, pointeeDebugType(di_type));
 }
 
 void AFLCoverage::extractValuesFromArgumentMap(
     std::vector<std::pair<llvm::Value*, ValueInfo>> &argument_map,
     IRBuilder<> &IRB,
-    std::vector<llvm::Value*> &out_values
+    std::vector<llvm::Value*> &out_values,
+    std::vector<CapturedValue> *captured
 ) {
-    changeStructPointersToStructTypes(argument_map);
+    Module &M = *IRB.GetInsertBlock()->getModule();
+    changeStructPointersToStructTypes(M, argument_map);
     for (auto &pair : argument_map) {
       std::vector<llvm::Value*> tmp;
       llvm::Value* target_value = pair.first;
       llvm::Type* target_type = pair.second.type;
+      DIType* target_di = pair.second.di_type;
+      size_t values_before = out_values.size();
       if (pair.second.indexes.empty()) {
           out_values.push_back(target_value);
+          if (captured) captured->push_back({typeName(target_value->getType()), true});
           continue;
       }
       for(auto &selected_field: pair.second.indexes){
+        auto *target_struct = dyn_cast<StructType>(target_type);
+        if (!target_struct || selected_field >= target_struct->getNumElements()) {
+          break;
+        }
         llvm::Value* zero  = llvm::ConstantInt::get(IRB.getInt32Ty(), 0);
         llvm::Value* offset = llvm::ConstantInt::get(IRB.getInt32Ty(), selected_field);
         llvm::Value* target_ptr = IRB.CreateGEP(
@@ -269,49 +372,49 @@ void AFLCoverage::extractValuesFromArgumentMap(
         //llvm::Type* field_ty = target_ptr->getType();
        
 
-        llvm::Value* field_value = IRB.CreateLoad(target_ptr);
+        llvm::Type* field_ty = target_struct->getElementType(selected_field);
+        DIType* field_di = memberDebugType(target_di, selected_field);
 
-        llvm::Type* field_ty = field_value->getType();
+        llvm::Value* field_value = IRB.CreateLoad(field_ty, target_ptr);
 
 
-        //field_ty->print(file2);
-        //file2 << "\n";
-
         // union by value
         if (isUnionValue(field_ty)) {
 
             std::string wanted = "struct.candidate_state";
-            llvm::StructType *realStruct = IRB.GetInsertBlock()->getModule()->getTypeByName(wanted);
+            llvm::StructType *realStruct = compat::structTypeByName(M, wanted);
 
             llvm::Value *casted = IRB.CreateBitCast(target_ptr, realStruct->getPointerTo());
 
             target_value = casted;
             target_type  = realStruct;
+            target_di = nullptr;
             continue;
         }
 
 
-        if (isPointerToUnion(field_ty)) {
+        if (isPointerToUnion(M, field_ty, field_di)) {
 
 
           std::string wanted = "struct.candidate_state";
 
-          llvm::StructType *realStruct = IRB.GetInsertBlock()->getModule()->getTypeByName(wanted);
+          llvm::StructType *realStruct = compat::structTypeByName(M, wanted);
 
           llvm::Value *casted =
               IRB.CreateBitCast(field_value, realStruct->getPointerTo());
 
           target_value = casted;
           target_type  = realStruct;
+          target_di = nullptr;
           continue;
         }
 
         // struct*
-        if (field_ty->isPointerTy() &&
-            field_ty->getPointerElementType()->isStructTy()) {
+        if (StructType *pointee = pointeeStruct(M, field_ty, field_di)) {
 
             target_value = field_value;
-            target_type = field_ty->getPointerElementType();
+            target_type = pointee;
+            target_di = pointeeDebugType(field_di);
             continue;
         }
 
@@ -322,14 +425,21 @@ void AFLCoverage::extractValuesFromArgumentMap(
 
             target_value = tmp;
             target_type = field_ty;
+            target_di = field_di;
             continue;
         }
 
         // primitivo u otra cosa
         out_values.push_back(field_value);
+        if (captured) captured->push_back({typeName(field_ty), true});
         break;
         
       }
+
+      // the field path ended on a struct, nothing was captured
+      if (captured && out_values.size() == values_before) {
+        captured->push_back({typeName(target_type), false});
+      }
     }
   }
 
@@ -337,11 +447,12 @@ void AFLCoverage::extractValuesFromArgumentMap(
 std::vector<llvm::Value*> AFLCoverage::getValues(
                                     std::vector<std::string> &vec, 
                                     iterator_range<Function::arg_iterator> args, 
-                                    std::vector<std::vector<unsigned int>> &vec_selected_fields, IRBuilder<> &IRB
+                                    std::vector<std::vector<unsigned int>> &vec_selected_fields, IRBuilder<> &IRB,
+                                    std::vector<CapturedValue> *captured
                                   ){
     std::vector<std::pair<llvm::Value*, ValueInfo>> argument_map = getArgument(vec, args, vec_selected_fields);
     std::vector<llvm::Value*> res;
-    extractValuesFromArgumentMap(argument_map, IRB, res);
+    extractValuesFromArgumentMap(argument_map, IRB, res, captured);
     return res;
 }
 
@@ -352,7 +463,6 @@ std::vector<llvm::Value*> AFLCoverage::getValues(
 void AFLCoverage::load_instr_targets(TARGETS_TYPE &bb_targets, TargetsTypes &func_targets, TargetsTypes &block_targets, CONST_TARGETS_TYPE &const_targets, GROUP_NAMES_TYPE &group_transition_names)
 {
   char *target_file = getenv("TARGETS_FILE");
-  //file2 << "Target File: " << target_file  << "\n";
   if (!target_file) {
     outs() << "[!!] TARGETS_FILE environment variable not set\n";
     return;
@@ -502,89 +612,156 @@ void AFLCoverage::get_debug_loc(const Instruction *I, std::string &Filename, uns
   }
 }
 
-/***
- * Assign event ID in increasing order, instead of random assignment in AFL
- ***/
-u16 *AFLCoverage::get_ID_ptr()
+u8 AFLCoverage::check_code_language(std::string codefile)
 {
-  // Create the shared memory if it does not exist, otherwise get the existing one
-  int shmid = shmget((key_t)SHM_ID_KEY, sizeof(u16), IPC_CREAT | IPC_EXCL | 0666);
-  if (shmid != 0)
-  {
-    shmid = shmget((key_t)SHM_ID_KEY, sizeof(u16), 0666);
-  }
-
-  // FIXME: If compilation is done in Docker (in subsequent RUN layers), the
-  // shared memory is not carried over between layers, so IDs will conflict
-
-  u16 *_id_ptr;
-  if (shmid >= 0)
+  // Check if the code is written in Rust (return 1) or C/C++ (return 2)
+  if (codefile.find(".rs") != std::string::npos)
   {
-    _id_ptr = (u16 *)shmat(shmid, NULL, 0);
-    if (_id_ptr == (u16 *)-1)
-    {
-      ABORT("!!! shared memory error: fail to connect");
-      _exit(1);
-    }
-    return _id_ptr;
+    return 1;
   }
   else
   {
-    ABORT("!!! shared memory error: fail to create");
-    _exit(1);
+    return 2;
   }
 }
 
-u8 AFLCoverage::check_code_language(std::string codefile)
+/***
+ * Demangle a C++ or Rust function name, falling back to the raw symbol
+ ***/
+std::string AFLCoverage::demangledName(Function &F, std::string codefile)
 {
-  // Check if the code is written in Rust (return 1) or C/C++ (return 2)
-  if (codefile.find(".rs") != std::string::npos)
+  std::string func_name = F.getName().str();
+  int demangled_status = -1;
+  char *demangled_char;
+  if (check_code_language(codefile) == 2)
   {
-    return 1;
+    demangled_char = abi::__cxa_demangle(F.getName().data(), nullptr, nullptr, &demangled_status);
   }
   else
   {
-    return 2;
+    demangled_char = rustc_demangle(F.getName().data(), &demangled_status);
+  }
+  if (demangled_status == 0)
+  {
+    func_name = demangled_char;
+  }
+  return func_name;
+}
+
+/***
+ * Record where an event ID was assigned, read by `abstraktor coverage`
+ ***/
+void AFLCoverage::recordManifest(u16 evtID, std::string kind, std::string filename, unsigned line, std::string func_name, std::string transition_name)
+{
+  if (!bbToID.is_open()) return;
+  nlohmann::json entry = {
+      {"id", evtID},
+      {"kind", kind},
+      {"file", filename},
+      {"line", line},
+      {"function", func_name},
+      {"transition", transition_name},
+  };
+  bbToID << entry.dump() << std::endl;
+}
+
+/***
+ * Record what happened to an annotated line, read by `abstraktor llvm`
+ ***/
+void AFLCoverage::recordReport(std::string kind, std::string filename, unsigned line, std::string status, u16 evtID, Function &F, unsigned ir_block, const nlohmann::json &variables)
+{
+  if (!reportFile.is_open()) return;
+  nlohmann::json entry = {
+      {"file", filename},
+      {"line", line},
+      {"kind", kind},
+      {"status", status},
+      {"id", evtID},
+      {"function", demangledName(F, filename)},
+      {"ir_function", F.getName().str()},
+      {"ir_block", ir_block},
+      {"variables", variables},
+  };
+  reportFile << entry.dump() << std::endl;
+}
+
+/***
+ * Report entries for the parameters a function annotation names, in the
+ * order getArgument captures them, followed by the names no parameter has
+ ***/
+nlohmann::json AFLCoverage::argumentVariables(
+    const std::vector<std::string> &instrumented_parameters,
+    const std::vector<std::vector<unsigned int>> &selected_fields,
+    iterator_range<Function::arg_iterator> iterator_arguments,
+    const std::vector<CapturedValue> &captured)
+{
+  nlohmann::json variables = nlohmann::json::array();
+  std::set<std::string> found;
+  size_t idx = 0;
+  for (auto &Arg : iterator_arguments) {
+    for (const std::string &param : instrumented_parameters) {
+      if (param == Arg.getName().str() && idx < captured.size()) {
+        variables.push_back({
+            {"name", param},
+            {"type", captured[idx].type},
+            {"fields", selected_fields[idx]},
+            {"resolved", captured[idx].resolved},
+        });
+        found.insert(param);
+        idx++;
+      }
+    }
+  }
+  for (const std::string &param : instrumented_parameters) {
+    if (!found.count(param)) {
+      variables.push_back({{"name", param}, {"type", ""}, {"fields", nlohmann::json::array()}, {"resolved", false}});
+    }
   }
+  return variables;
+}
+
+std::string AFLCoverage::typeName(llvm::Type *T)
+{
+  std::string typeStr;
+  llvm::raw_string_ostream rso(typeStr);
+  T->print(rso);
+  return rso.str();
 }
 
 /***
  * Print compilation log
  ***/
-void AFLCoverage::printFuncLog(std::string filename, unsigned line, u16 evtID, std::string func_name)
+void AFLCoverage::printFuncLog(std::string filename, unsigned line, u16 evtID, std::string func_name, std::string transition_name)
 {
   OKF("Instrument %u at %s: at line %u for function %s", evtID, filename.c_str(), line, func_name.c_str());
-  bbToID << evtID << ": at " << filename << " ; at line " << line << " for function " << func_name << std::endl;
+  recordManifest(evtID, "function", filename, line, func_name, transition_name);
 }
 
 void AFLCoverage::printBBLog(std::string filename, unsigned line, u16 evtID)
 {
-  bbToID << evtID << ": at " << filename << " ; at line " << line << " for block" << std::endl;
+  recordManifest(evtID, "bb", filename, line, "", "");
   OKF("Instrument %u at %s: at line %u for block", evtID, filename.c_str(), line);
 }
 
-void AFLCoverage::printBlockLog(std::string filename, unsigned line, u16 evtID)
+void AFLCoverage::printBlockLog(std::string filename, unsigned line, u16 evtID, std::string func_name, std::string transition_name)
 {
-  bbToID << evtID << ": at " << filename << " ; at line " << line << " for block" << std::endl;
+  recordManifest(evtID, "block", filename, line, func_name, transition_name);
   OKF("Instrument %u at %s: at line %u for block", evtID, filename.c_str(), line);
 }
 
 void AFLCoverage::printConstLog(std::string filename, unsigned line, u16 evtID, std::string const_name)
 {
-  bbToID << evtID << ": at " << filename << " ; at line " << line << " for const " << const_name << std::endl;
+  recordManifest(evtID, "const", filename, line, "", const_name);
   OKF("Instrument %u at %s: at line %u for const %s", evtID, filename.c_str(), line, const_name.c_str());
 }
 
-void AFLCoverage::changeStructPointersToStructTypes(std::vector<std::pair<llvm::Value*, ValueInfo>> &valueTypeMap) {
+void AFLCoverage::changeStructPointersToStructTypes(Module &M, std::vector<std::pair<llvm::Value*, ValueInfo>> &valueTypeMap) {
     for (auto &pair : valueTypeMap) {
         llvm::Type* type = pair.second.type;
 
-        if (type->isPointerTy()) {
-            llvm::Type* elemTy = type->getPointerElementType();
-
-            if (auto *structTy = dyn_cast<StructType>(elemTy)) {
-                pair.second.type = structTy; 
-            }
+        if (auto *structTy = pointeeStruct(M, type, pair.second.di_type)) {
+            pair.second.type = structTy; 
+            pair.second.di_type = pointeeDebugType(pair.second.di_type);
         }
     }
 }
@@ -617,6 +794,7 @@ std::vector<std::pair<llvm::Value*, ValueInfo>> AFLCoverage::getArgument(
                 struct ValueInfo valueInfoTmp;
                 valueInfoTmp.type = Arg.getType();
                 valueInfoTmp.indexes = default_indices[idx];
+                valueInfoTmp.di_type = argumentDebugType(Arg);
                 valueContainer.push_back(std::make_pair(&Arg, valueInfoTmp));
                 idx++;
             }
@@ -634,13 +812,13 @@ bool AFLCoverage::runOnModule(Module &M)
   LLVMContext &C = M.getContext();
 
   VoidTy = Type::getVoidTy(C);
-  Int8PtrTy = Type::getInt8PtrTy(C);
+  Int8PtrTy = compat::int8PtrTy(C);
   Int8Ty = IntegerType::getInt8Ty(C);
   Int16Ty = IntegerType::getInt16Ty(C);
   Int32Ty = IntegerType::getInt32Ty(C);
   Int64Ty = IntegerType::getInt64Ty(C);
-  Int64PtrTy = Type::getInt64PtrTy(C);
-  VoidPtrTy = Type::getInt8PtrTy(C);
+  Int64PtrTy = compat::int64PtrTy(C);
+  VoidPtrTy = compat::int8PtrTy(C);
 
   /* Show a banner */
 
@@ -692,7 +870,18 @@ bool AFLCoverage::runOnModule(Module &M)
   std::map<TargetsTypes::GroupID, std::vector<Value*>> groupsPointerValues;
   std::set<std::pair<std::string, int>> instrumented_const_targets;
   load_instr_targets(bb_targets, func_targets, block_targets, const_targets, group_transition_names);
-  u8 codeLang = 0;
+
+  char *manifest_file = getenv("ABSTRAKTOR_MANIFEST_FILE");
+  if (manifest_file)
+  {
+    bbToID.open(manifest_file, std::ios::app);
+  }
+
+  char *report_file = getenv("ABSTRAKTOR_REPORT_FILE");
+  if (report_file)
+  {
+    reportFile.open(report_file, std::ios::app);
+  }
 
   static const std::string Xlibs("/usr/");
 
@@ -706,9 +895,11 @@ bool AFLCoverage::runOnModule(Module &M)
     unsigned targetLine = 0;
     
     bool notBreakFunction = false;
+    unsigned bb_index = 0;
     for (auto &BB : F) 
     {
-      std::set<std::tuple<unsigned, bool, llvm::Value*, llvm::Instruction*, llvm::BasicBlock*>> block_lines;
+      unsigned current_bb = bb_index++;
+      std::set<std::tuple<unsigned, bool, llvm::Value*, llvm::Instruction*, llvm::BasicBlock*, DIType*>> block_lines;
       BasicBlock::iterator IP = BB.getFirstInsertionPt();
 
       // in each basic block, check if it is a target
@@ -728,8 +919,6 @@ bool AFLCoverage::runOnModule(Module &M)
         }
         u16 isTarget = is_target_loc(filename, line, bb_targets, func_targets, block_targets, const_targets);
 
-        //file2 << "Target: " << isTarget << " For: " << F.getName().str() << ":" << line << "\n";
-
         if (isTarget == 2 || isTarget == 5)
         {
           targetLine = line;
@@ -744,7 +933,7 @@ bool AFLCoverage::runOnModule(Module &M)
             valueOperandLeftSize = SI->getValueOperand();
             nextI = I.getNextNode();
             if (nextI == nullptr) nextIFinal = I.getParent();
-            block_lines.insert(std::make_tuple(line, isTarget == 6, valueOperandLeftSize, nextI, nextIFinal));
+            block_lines.insert(std::make_tuple(line, isTarget == 6, valueOperandLeftSize, nextI, nextIFinal, storedDebugType(SI)));
           }
         }
         else if (isTarget == 4)
@@ -788,13 +977,16 @@ bool AFLCoverage::runOnModule(Module &M)
           vec_selected_fields.push_back(index_row);
         }
 
-        std::vector<llvm::Value*> res = getValues(vec, F.args(), vec_selected_fields, IRB);
+        std::vector<CapturedValue> captured;
+        std::vector<llvm::Value*> res = getValues(vec, F.args(), vec_selected_fields, IRB, &captured);
+        nlohmann::json variables = argumentVariables(vec, vec_selected_fields, F.args(), captured);
       
         if(res.size() == 0){
-
+          recordReport("function", filename, targetLine, "no_values", (u16)func_targets.getGroupID(filename, targetLine), F, 0, variables);
         } else {
 
           TargetsTypes::GroupID groupID = func_targets.getGroupID(filename, targetLine);
+          recordReport("function", filename, targetLine, "instrumented", (u16)groupID, F, 0, variables);
 
           std::vector<llvm::Value*> v = groupsPointerValues[groupID]; 
 
@@ -810,10 +1002,9 @@ bool AFLCoverage::runOnModule(Module &M)
 
             groupsPointerValues.erase(groupID);
 
-            u16 *evtIDPtr = get_ID_ptr();
-            u16 evtID = *evtIDPtr;
+            /* group IDs are derived from the annotations by abstraktor */
+            u16 evtID = (u16)groupID;
 
-            file2 << "Instrumenting function event for " << F.getName().str() << " with evtID " << evtID << " values \n";
             Value *evtValue = ConstantInt::get(Int16Ty, evtID);
 
             // Cast to double pointer
@@ -835,46 +1026,15 @@ bool AFLCoverage::runOnModule(Module &M)
             IRB.CreateCall(helper_const, {evtValue, transition_name_value, arrPtr, ConstantInt::get(Int64Ty, v.size())});
             
             group_transition_names.erase(groupID);
-            /* increase counter */
-            *evtIDPtr = ++evtID;
-            get_debug_loc(&(*InsertPoint), filename, targetLine);
-            std::string func_name = F.getName().str();
-            if (codeLang == 0)
-            {
-              codeLang = check_code_language(filename);
-            }
-
-            if (codeLang == 2)
-            {
-              int demangled_status = -1;
-              char *demangled_char = abi::__cxa_demangle(F.getName().data(), nullptr,
-                                                        nullptr, &demangled_status);
-              if (demangled_status == 0)
-              {
-                func_name = demangled_char;
-              }
-            }
-            else
-            {
-              int demangled_status = -1;
-              char *demangled_char = rustc_demangle(F.getName().data(), &demangled_status);
-              if (demangled_status == 0)
-              {
-                func_name = demangled_char;
-              }
-            }
-            printFuncLog(filename, line, evtID, func_name);
-
-            /* increase counter */
-            //*evtIDPtr = ++evtID;
+            /* store function ID info at the annotated line */
+            printFuncLog(filename, targetLine, evtID, demangledName(F, filename), transition_name);
           }
         }
       }
       
       if (isTargetBlockEvent)
       {
-        for (auto [block_line, not_break, valueOperandLeftSize, nextI, nextIFinal] : block_lines) {
-          file2 << "Processing block event for line " << block_lines.size() << "\n";
+        for (auto [block_line, not_break, valueOperandLeftSize, nextI, nextIFinal, stored_di] : block_lines) {
           Instruction *Pos = nextI ? nextI : nextIFinal->getTerminator();
           IRBuilder<> IRB(Pos);
 
@@ -893,23 +1053,35 @@ bool AFLCoverage::runOnModule(Module &M)
             struct ValueInfo valueInfo;
             valueInfo.type = valueOperandLeftSizeType;
             valueInfo.indexes = entry.second;
+            valueInfo.di_type = stored_di;
             argument_map.push_back(std::make_pair(valueOperandLeftSize, valueInfo));
           }
 
           std::vector<llvm::Value*> res;
-          extractValuesFromArgumentMap(argument_map, IRB, res);
+          std::vector<CapturedValue> captured;
+          extractValuesFromArgumentMap(argument_map, IRB, res, &captured);
+
+          nlohmann::json variables = nlohmann::json::array();
+          for (size_t i = 0; i < entries.size() && i < captured.size(); ++i) {
+            variables.push_back({
+                {"name", entries[i].first},
+                {"type", captured[i].type},
+                {"fields", entries[i].second},
+                {"resolved", captured[i].resolved},
+            });
+          }
           
           if(res.size() == 0){
-
+            recordReport("block", filename, block_line, "no_values", (u16)block_targets.getGroupID(filename, block_line), F, current_bb, variables);
           } else {
               
-              u16 *evtIDPtr = get_ID_ptr();
-              u16 evtID = *evtIDPtr;
-              
               TargetsTypes::GroupID groupID = block_targets.getGroupID(filename, block_line);
+              recordReport("block", filename, block_line, "instrumented", (u16)groupID, F, current_bb, variables);
+
+              /* group IDs are derived from the annotations by abstraktor */
+              u16 evtID = (u16)groupID;
 
               std::vector<llvm::Value*>v = groupsPointerValues[groupID];
-              //file2 << "V: " << v.size() << "\n";
   
               v.insert(v.end(), res.begin(), res.end());
 
@@ -931,8 +1103,6 @@ bool AFLCoverage::runOnModule(Module &M)
                 Type *VoidPtrPtrTy = PointerType::getUnqual(VoidPtrTy); 
 
                 auto *helperTy_const = FunctionType::get(VoidTy, {Int16Ty, Int8PtrTy, VoidPtrPtrTy, Int64Ty}, false);
-                //file2 << "Not breaking function: " << F.getName().str() << " with " << v.size() << " values \n";
-                file2 << "Instrumenting block event for " << F.getName().str() << " with evtID " << evtID << " values \n";
 
                 auto helper_const = M.getOrInsertFunction("trigger_block_event", helperTy_const);
 
@@ -948,48 +1118,12 @@ bool AFLCoverage::runOnModule(Module &M)
                 group_transition_names.erase(groupID);
 
                 /* store BB ID info */
-                printBlockLog(filename, block_line, evtID);
-
-                /* increase counter */
-                *evtIDPtr = ++evtID;
+                printBlockLog(filename, block_line, evtID, demangledName(F, filename), transition_name);
               }
             }
         }
       }
-      
-      // if (false)
-      // {
-
-        
-      //   std::pair<std::string, int> const_key = std::make_pair(filename, const_line);
-      //   if (instrumented_const_targets.find(const_key) == instrumented_const_targets.end())
-      //   {
-      //     instrumented_const_targets.insert(const_key);
-          
-      //     std::string constName = const_targets[filename][const_line];
-      //     u16 *evtIDPtr = get_ID_ptr();
-      //     u16 evtID = *evtIDPtr;
-      //     Value *evtValue = ConstantInt::get(Int16Ty, evtID);
-
-      //     // Create a global string constant for the const name
-      //     Value *constNameValue = IRB.CreateGlobalString(StringRef(constName), "const_name");
-
-      //     auto *helperTy_const = FunctionType::get(VoidTy, {Int16Ty, Int8PtrTy, Int8PtrTy}, false);
-      //     auto helper_const = M.getOrInsertFunction("trigger_const_event", helperTy_const);
-          
-      //     std::string function_name = F.getName().str();
-      //     Value* function_name_value = IRB.CreateGlobalString(StringRef(function_name),"varName");
 
-      //     IRB.CreateCall(helper_const, {evtValue, function_name_value, constNameValue});
-
-      //     /* store const ID info */
-      //     printConstLog(filename, const_line, evtID, constName);
-
-      //     /* increase counter */
-      //     *evtIDPtr = ++evtID;
-      //   }
-      // }
-    
       if (getenv("USE_TRADITIONAL_BRANCH")){
         Instruction *Term = BB.getTerminator();
         IRBuilder<> IRB(Term);
@@ -1000,30 +1134,30 @@ bool AFLCoverage::runOnModule(Module &M)
 
         /* Load prev_loc */
 
-        LoadInst *PrevLoc = IRB.CreateLoad(AFLPrevLoc);
-        PrevLoc->setMetadata(M.getMDKindID("nosanitize"), MDNode::get(C, None));
+        LoadInst *PrevLoc = IRB.CreateLoad(Int32Ty, AFLPrevLoc);
+        PrevLoc->setMetadata(M.getMDKindID("nosanitize"), MDNode::get(C, {}));
         Value *PrevLocCasted = IRB.CreateZExt(PrevLoc, IRB.getInt32Ty());
 
         /* Load SHM pointer */
 
-        LoadInst *MapPtr = IRB.CreateLoad(AFLMapPtr);
-        MapPtr->setMetadata(M.getMDKindID("nosanitize"), MDNode::get(C, None));
+        LoadInst *MapPtr = IRB.CreateLoad(AFLMapPtr->getValueType(), AFLMapPtr);
+        MapPtr->setMetadata(M.getMDKindID("nosanitize"), MDNode::get(C, {}));
         Value *MapPtrIdx =
-            IRB.CreateGEP(MapPtr, IRB.CreateXor(PrevLocCasted, CurLoc));
+            IRB.CreateGEP(Int8Ty, MapPtr, IRB.CreateXor(PrevLocCasted, CurLoc));
 
         /* Update bitmap */
 
-        LoadInst *Counter = IRB.CreateLoad(MapPtrIdx);
-        Counter->setMetadata(M.getMDKindID("nosanitize"), MDNode::get(C, None));
+        LoadInst *Counter = IRB.CreateLoad(Int8Ty, MapPtrIdx);
+        Counter->setMetadata(M.getMDKindID("nosanitize"), MDNode::get(C, {}));
         Value *Incr = IRB.CreateAdd(Counter, ConstantInt::get(Int8Ty, 1));
         IRB.CreateStore(Incr, MapPtrIdx)
-            ->setMetadata(M.getMDKindID("nosanitize"), MDNode::get(C, None));
+            ->setMetadata(M.getMDKindID("nosanitize"), MDNode::get(C, {}));
 
         /* Set prev_loc to cur_loc >> 1 */
 
         StoreInst *Store =
             IRB.CreateStore(ConstantInt::get(Int32Ty, cur_loc >> 1), AFLPrevLoc);
-        Store->setMetadata(M.getMDKindID("nosanitize"), MDNode::get(C, None));
+        Store->setMetadata(M.getMDKindID("nosanitize"), MDNode::get(C, {}));
       }    
     }
 
@@ -1032,10 +1166,21 @@ bool AFLCoverage::runOnModule(Module &M)
     
   }
   
-  file2.close();
+  if (bbToID.is_open())
+  {
+    bbToID.close();
+  }
+  if (reportFile.is_open())
+  {
+    reportFile.close();
+  }
   return true;
 }
 
+#if LLVM_VERSION_MAJOR < 16
+
+/* Loaded with -Xclang -load by afl-clang-fast on the legacy pass manager */
+
 static void registerAFLPass(const PassManagerBuilder &,
                             legacy::PassManagerBase &PM)
 {
@@ -1048,3 +1193,44 @@ static RegisterStandardPasses RegisterAFLPass(
 
 static RegisterStandardPasses RegisterAFLPass0(
     PassManagerBuilder::EP_EnabledOnOptLevel0, registerAFLPass);
+
+#endif
+
+#if LLVM_VERSION_MAJOR >= 13
+
+/* Loaded with -fpass-plugin by afl-clang-fast on the new pass manager */
+
+namespace
+{
+
+  struct AFLCoveragePass : PassInfoMixin<AFLCoveragePass> {
+    PreservedAnalyses run(Module &M, ModuleAnalysisManager &) {
+      AFLCoverage pass;
+      pass.runOnModule(M);
+      return PreservedAnalyses::none();
+    }
+
+    // keep running on optnone functions at -O0
+    static bool isRequired() { return true; }
+  };
+
+}
+
+#if LLVM_VERSION_MAJOR >= 14
+using AFLOptimizationLevel = OptimizationLevel;
+#else
+using AFLOptimizationLevel = PassBuilder::OptimizationLevel;
+#endif
+
+extern "C" LLVM_ATTRIBUTE_WEAK PassPluginLibraryInfo llvmGetPassPluginInfo()
+{
+  return {LLVM_PLUGIN_API_VERSION, "abstraktor-afl-pass", VERSION,
+          [](PassBuilder &PB) {
+            PB.registerPipelineStartEPCallback(
+                [](ModulePassManager &MPM, AFLOptimizationLevel) {
+                  MPM.addPass(AFLCoveragePass());
+                });
+          }};
+}
+
+#endif
@@ -0,0 +1,92 @@
+/*
+   Shims over the LLVM APIs the pass uses that changed between LLVM 11 and
+   LLVM 21: typed versus opaque pointers, named struct lookup, StringRef
+   helpers and debug intrinsics versus debug records.
+*/
+
+#ifndef ABSTRAKTOR_LLVM_COMPAT_H
+#define ABSTRAKTOR_LLVM_COMPAT_H
+
+#include "llvm/ADT/StringRef.h"
+#include "llvm/Config/llvm-config.h"
+#include "llvm/IR/DebugInfo.h"
+#include "llvm/IR/DebugInfoMetadata.h"
+#include "llvm/IR/DerivedTypes.h"
+#include "llvm/IR/IntrinsicInst.h"
+#include "llvm/IR/Metadata.h"
+#include "llvm/IR/Module.h"
+
+namespace compat {
+
+/* i8* before opaque pointers, ptr after */
+inline llvm::PointerType *int8PtrTy(llvm::LLVMContext &C) {
+#if LLVM_VERSION_MAJOR >= 17
+  return llvm::PointerType::get(C, 0);
+#else
+  return llvm::Type::getInt8PtrTy(C);
+#endif
+}
+
+inline llvm::PointerType *int64PtrTy(llvm::LLVMContext &C) {
+#if LLVM_VERSION_MAJOR >= 17
+  return llvm::PointerType::get(C, 0);
+#else
+  return llvm::Type::getInt64PtrTy(C);
+#endif
+}
+
+inline llvm::StructType *structTypeByName(llvm::Module &M, llvm::StringRef name) {
+#if LLVM_VERSION_MAJOR >= 12
+  return llvm::StructType::getTypeByName(M.getContext(), name);
+#else
+  return M.getTypeByName(name);
+#endif
+}
+
+inline bool startsWith(llvm::StringRef s, llvm::StringRef prefix) {
+#if LLVM_VERSION_MAJOR >= 16
+  return s.starts_with(prefix);
+#else
+  return s.startswith(prefix);
+#endif
+}
+
+/* The element type of a typed pointer. Opaque pointers have none, so their
+   pointee has to come from debug info; null for those and for non-pointers. */
+inline llvm::Type *typedPointee(llvm::Type *T) {
+  auto *PT = llvm::dyn_cast<llvm::PointerType>(T);
+  if (!PT) return nullptr;
+#if LLVM_VERSION_MAJOR >= 17
+  return nullptr;
+#elif LLVM_VERSION_MAJOR >= 14
+  return PT->isOpaque() ? nullptr : PT->getNonOpaquePointerElementType();
+#else
+  return PT->getElementType();
+#endif
+}
+
+/* The local variable declared as living at `address`. Up to LLVM 18 that is
+   a dbg.declare call; from LLVM 19 on, modules carry the declaration as a
+   debug record attached to an instruction instead, and from LLVM 21 only
+   the records are looked up. */
+inline llvm::DILocalVariable *declaredVariable(llvm::Value *address) {
+#if LLVM_VERSION_MAJOR >= 19
+  auto records = llvm::findDVRDeclares(address);
+  if (!records.empty()) return records.front()->getVariable();
+#endif
+#if LLVM_VERSION_MAJOR < 21
+  auto *local = llvm::LocalAsMetadata::getIfExists(address);
+  if (!local) return nullptr;
+  auto *wrapped = llvm::MetadataAsValue::getIfExists(address->getContext(), local);
+  if (!wrapped) return nullptr;
+  for (llvm::User *U : wrapped->users()) {
+    if (auto *declare = llvm::dyn_cast<llvm::DbgDeclareInst>(U))
+      return declare->getVariable();
+  }
+#endif
+  return nullptr;
+}
+
+}
+
+#endif
@@ -409,14 +409,15 @@ impl FeedbackManager {
                     let state_str_end = state_buffer.iter().position(|&b| b == 0).unwrap_or(64);
                     let result_str = String::from_utf8_lossy(&state_buffer[..state_str_end]);
                     log::info!(
-                        "[BLOCK_EVENT_TYPE][Node {} Batch {} Entry {} / {}] BlockExecute {} @ FunctionName {} @ state {}",
+                        "[BLOCK_EVENT_TYPE][Node {} Batch {} Entry {} / {}] BlockExecute {} @ FunctionName {} @ state {} @ ts {}",
                         node_id,
                         batch_id,
                         db_entry_index,
                         db_evt_counter,
                         eid,
                         function_str,
-                        result_str
+                        result_str,
+                        ts.ts
                     );
                     Event::BlockExecute {
                         block_id: eid as u16,
@@ -438,14 +439,15 @@ impl FeedbackManager {
                     let result_str = String::from_utf8_lossy(&state_buffer[..state_str_end]);
 
                     log::info!(
-                        "[FUNC_EVENT_TYPE][Node {} Batch {} Entry {} / {}] FunctionExecute {} @ FunctionName {} @ state {}",
+                        "[FUNC_EVENT_TYPE][Node {} Batch {} Entry {} / {}] FunctionExecute {} @ FunctionName {} @ state {} @ ts {}",
                         node_id,
                         batch_id,
                         db_entry_index,
                         db_evt_counter,
                         function_id,
                         function_str,
-                        result_str
+                        result_str,
+                        ts.ts
                     );
                     Event::FunctionExecute {
                         function_id: function_id as u16,
@@ -465,14 +467,15 @@ impl FeedbackManager {
                     let state_str_end = state_buffer.iter().position(|&b| b == 0).unwrap_or(64);
                     let result_str = String::from_utf8_lossy(&state_buffer[..state_str_end]);
                     log::info!(
-                        "[CONST_EVENT_TYPE][Node {} Batch {} Entry {} / {}] ConstantExecute {} @ FunctionName {} @ constant {}",
+                        "[CONST_EVENT_TYPE][Node {} Batch {} Entry {} / {}] ConstantExecute {} @ FunctionName {} @ constant {} @ ts {}",
                         node_id,
                         batch_id,
                         db_entry_index,
                         db_evt_counter,
                         const_id,
                         function_str,
-                        result_str
+                        result_str,
+                        ts.ts
                     );
                     Event::ConstantExecute {
                         const_id: const_id as u16,
@@ -75,6 +75,7 @@ pub struct MediatorConfig {
     shiviz_log_filename: String,
     event_log_filename: String,
     iptables_rules_filename: String,
+    planned_schedules_filename: Option<String>,
 
     num_nodes: u8,
     node_ips: Vec<String>,
@@ -792,6 +793,8 @@ fn read_configuration(schedule_type: &String, feedback_type: &String, state_simi
     let shiviz_log_filename = settings.get_string("shiviz_log_filename")?;
     let event_log_filename = settings.get_string("event_log_filename")?;
     let iptables_rules_filename = settings.get_string("iptables_rules_filename")?;
+    // Only used by the `planned` scheduler.
+    let planned_schedules_filename = settings.get_string("planned_schedules_filename").ok();
 
     let exp_ifaces = net::util::get_experiment_interfaces(experiment_network)?;
     let num_nodes = exp_ifaces.len() as u8;
@@ -832,6 +835,7 @@ fn read_configuration(schedule_type: &String, feedback_type: &String, state_simi
         shiviz_log_filename,
         event_log_filename,
         iptables_rules_filename,
+        planned_schedules_filename,
 
         num_nodes,
         node_ips,
@@ -989,7 +993,7 @@ async fn main() -> Result<(), rocket::Error> {
     let args: Vec<String> = env::args().collect();
     if args.len() < 4 {
         eprintln!(
-            "Usage: {} schedule_type feedback_type state_similarity_threshold\n schedule_type: one of [power, qlearning]\n feedback_type: one of [event_history, afl_branch, afl_branch_and_event_history] \n state_similarity_threshold: a float between 0 and 1",
+            "Usage: {} schedule_type feedback_type state_similarity_threshold\n schedule_type: one of [power, qlearning, planned]\n feedback_type: one of [event_history, afl_branch, afl_branch_and_event_history] \n state_similarity_threshold: a float between 0 and 1",
             args[0]
         );
         std::process::exit(1);
@@ -264,6 +264,7 @@ impl JepsenAdaptiveNemesis {
         );
 
         let schedule_type = &cfg.schedule_type;
+        let planned_schedules_filename = cfg.planned_schedules_filename.as_ref();
 
         let cfg = NemesisConfig { nemeses };
 
@@ -273,6 +274,7 @@ impl JepsenAdaptiveNemesis {
             reset_duration_ms,
             num_actions,
             schedule_type,
+            planned_schedules_filename,
         );
 
         let reset = ResetManager::new(reset_ops);
@@ -1,4 +1,5 @@
 pub mod noop;
+pub mod planned;
 pub mod power;
 pub mod qlearning;
 
@@ -21,7 +22,10 @@ use crate::{
     },
 };
 
-use self::{noop::NoopScheduler, power::PowerScheduler, qlearning::agent::QLearningScheduler};
+use self::{
+    noop::NoopScheduler, planned::PlannedScheduler, power::PowerScheduler,
+    qlearning::agent::QLearningScheduler,
+};
 
 use super::{AdaptiveNemesis, RewardRequestStatus};
 
@@ -350,6 +354,7 @@ impl ScheduleManager {
         reset_duration_ms: u64,
         num_actions: usize,
         schedule_type: &String,
+        planned_schedules_filename: Option<&String>,
     ) -> Self {
         let timing = TimingManager::new(
             schedule_duration_ms,
@@ -374,10 +379,21 @@ impl ScheduleManager {
         schedulers.insert(SchedulerIdentifier::Power, power);
         schedulers.insert(SchedulerIdentifier::Noop, noop);
 
+        // Only read the plan file when it is going to be replayed.
+        if schedule_type.as_str() == "planned" {
+            let planned: Box<dyn DiscreteStepScheduler> = Box::new(PlannedScheduler::new(
+                planned_schedules_filename,
+                num_actions,
+            ));
+            schedulers.insert(SchedulerIdentifier::Planned, planned);
+        }
+
         let active_scheduler = if schedule_type.as_str() == "qlearning" {
             AtomicSchedulerIdentifier::new(SchedulerIdentifier::QLearning)
         } else if schedule_type.as_str() == "power" {
             AtomicSchedulerIdentifier::new(SchedulerIdentifier::Power)
+        } else if schedule_type.as_str() == "planned" {
+            AtomicSchedulerIdentifier::new(SchedulerIdentifier::Planned)
         } else {
             AtomicSchedulerIdentifier::new(SchedulerIdentifier::Noop)
         };
@@ -443,6 +459,7 @@ impl ScheduleManager {
         if let Some(action) = current_schedule.get_recorded_action_at_step(step) {
             return (step, action, ScheduleOpStatus::AlreadyReturned);
         };
+        let schedule_id = current_schedule.schedule_id;
 
         // Get the next action from the active scheduler.
         // (And record it in the current schedule.)
@@ -452,6 +469,15 @@ impl ScheduleManager {
             .expect("Active scheduler not found in scheduler map!")
             .get_and_record_action_at_step(our_history, step, &self.timing, history);
 
+        // Window marker consumed by `abstraktor export-graphs` to tag the
+        // events that follow with the schedule and step that produced them.
+        log::info!(
+            "[WINDOW][Schedule {} Step {}] Action {}",
+            schedule_id,
+            step,
+            action
+        );
+
         (step, action, ScheduleOpStatus::New)
     }
 
@@ -586,6 +612,7 @@ pub enum SchedulerIdentifier {
     Noop,
     QLearning,
     Power,
+    Planned,
 }
 
 pub trait DiscreteStepScheduler: Send + Sync {
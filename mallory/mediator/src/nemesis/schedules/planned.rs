@@ -0,0 +1,128 @@
+use std::{
+    fs,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{
+    feedback::{producers::SummaryProducerIdentifier, reward::RewardEntry},
+    history::History,
+};
+
+use super::{
+    ActionId, ConcreteSchedule, DEFAULT_ACTION_ID, DiscreteStepScheduler, ScheduleHistory, StepId,
+    TimingManager,
+};
+
+/// Replays schedules proposed offline (e.g. by `abstraktor plan-faults`).
+/// The file holds one schedule per line as space-separated action ids;
+/// lines starting with `#` are comments. Schedules are replayed in order
+/// and the list wraps around once exhausted.
+pub struct PlannedScheduler {
+    schedules: Vec<Vec<ActionId>>,
+    /// Index of the planned schedule being replayed.
+    current: AtomicUsize,
+    /// How many schedules have been started, used to pick the next one.
+    started: AtomicUsize,
+}
+
+impl PlannedScheduler {
+    pub fn new(filename: Option<&String>, num_actions: usize) -> Self {
+        let schedules = match filename {
+            Some(filename) => match fs::read_to_string(filename) {
+                Ok(content) => Self::parse(&content, num_actions),
+                Err(e) => {
+                    log::warn!(
+                        "[Planned] Could not read planned schedules from {}: {}",
+                        filename,
+                        e
+                    );
+                    vec![]
+                }
+            },
+            None => vec![],
+        };
+        log::info!("[Planned] Loaded {} planned schedules", schedules.len());
+
+        Self {
+            schedules,
+            current: AtomicUsize::new(0),
+            started: AtomicUsize::new(0),
+        }
+    }
+
+    fn parse(content: &str, num_actions: usize) -> Vec<Vec<ActionId>> {
+        content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                line.split_whitespace()
+                    .map(|id| match id.parse::<ActionId>() {
+                        Ok(action) if action < num_actions => action,
+                        _ => {
+                            log::warn!(
+                                "[Planned] Unknown action {} replaced by the default action",
+                                id
+                            );
+                            DEFAULT_ACTION_ID
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+impl DiscreteStepScheduler for PlannedScheduler {
+    fn get_and_record_action_at_step(
+        &self,
+        our_history: &mut ScheduleHistory,
+        step: StepId,
+        _timing: &TimingManager,
+        _history: &History,
+    ) -> ActionId {
+        let planned = self.current.load(Ordering::Relaxed);
+        let action = self
+            .schedules
+            .get(planned)
+            .and_then(|s| s.get(step))
+            .copied()
+            .unwrap_or(DEFAULT_ACTION_ID);
+
+        let current_schedule = &mut our_history.current_schedule;
+        current_schedule.add_action(step, action);
+        log::info!(
+            "[Planned] Select action {} at step {} for schedule {} (planned schedule {})",
+            action,
+            step,
+            current_schedule.schedule_id,
+            planned
+        );
+        return action;
+    }
+
+    fn report_reward(
+        &self,
+        _reward_entry: &RewardEntry,
+        _summary_producers: &Vec<SummaryProducerIdentifier>,
+    ) {
+        // Nothing to do. Planned schedules do not adapt to feedback.
+    }
+
+    fn notify_schedule_start(&self, _schedule_id: usize) {
+        if self.schedules.is_empty() {
+            return;
+        }
+        let started = self.started.fetch_add(1, Ordering::Relaxed);
+        self.current
+            .store(started % self.schedules.len(), Ordering::Relaxed);
+    }
+
+    fn notify_schedule_end(&self, _past_schedule: &ConcreteSchedule, _history: &History) {
+        // Nothing to do.
+    }
+
+    fn execution_ended(&self) {
+        // Nothing to do.
+    }
+}
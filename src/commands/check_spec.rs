@@ -0,0 +1,202 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use clap::Parser;
+
+use crate::logger::Logger;
+use crate::model::spec::{SpecMapping, compare_with_spec};
+use crate::model::tlc::parse_tlc_dot;
+use crate::model::{RefinementConfig, build_event_graph, build_event_graph_with};
+
+#[derive(Parser, Debug)]
+pub struct CheckSpecArgs {
+    #[arg(
+        short = 'a',
+        long = "log-path",
+        default_value = "mediator-logs/events.log"
+    )]
+    pub log_path: String,
+
+    #[arg(
+        short = 's',
+        long = "spec-graph",
+        help = "State graph dumped by TLC with -dump dot"
+    )]
+    pub spec_graph: String,
+
+    #[arg(short = 'm', long = "mapping")]
+    pub mapping: String,
+
+    #[arg(short = 'r', long = "refinement")]
+    pub refinement: Option<String>,
+
+    #[arg(
+        short = 'n',
+        long = "node",
+        help = "Only compare the graph of this node"
+    )]
+    pub node: Option<u32>,
+
+    #[arg(short = 'o', long = "out", default_value = "spec_check.json")]
+    pub output_path: String,
+}
+
+pub fn run(args: CheckSpecArgs, logger: &Logger) -> Result<()> {
+    logger.log("Checking implementation against spec");
+
+    logger.log(format!("Reading events from {}", args.log_path));
+    let log_content = fs::read_to_string(&args.log_path)
+        .with_context(|| format!("reading log from {}", &args.log_path))?;
+    let mut graph = match &args.refinement {
+        Some(path) => {
+            logger.log(format!("Applying abstraction refinement from {}", path));
+            let refinement = RefinementConfig::from_file(path)?.compile()?;
+            build_event_graph_with(&log_content, &refinement)
+        }
+        None => build_event_graph(&log_content),
+    };
+    if let Some(node) = args.node {
+        graph.nodes.retain(|id, _| *id == node);
+        if graph.nodes.is_empty() {
+            anyhow::bail!("node {} does not appear in {}", node, args.log_path);
+        }
+    }
+
+    logger.log(format!("Reading spec graph from {}", args.spec_graph));
+    let spec_content = fs::read_to_string(&args.spec_graph)
+        .with_context(|| format!("reading spec graph from {}", &args.spec_graph))?;
+    let spec = parse_tlc_dot(&spec_content);
+    logger.debug(format!(
+        "Spec graph has {} state(s) and {} edge(s)",
+        spec.states.len(),
+        spec.edges.len()
+    ));
+    if spec.states.is_empty() {
+        anyhow::bail!("no states found in spec graph {}", args.spec_graph);
+    }
+
+    let mapping = SpecMapping::from_file(&args.mapping)?;
+    let report = compare_with_spec(&graph, &spec, &mapping);
+
+    for state in &report.unknown_states {
+        logger.warning(format!("State {} does not exist in the spec", state));
+    }
+    for unexpected in &report.unexpected_edges {
+        let e = &unexpected.edge;
+        logger.warning(format!(
+            "Edge {} --{}--> {} is not allowed by the spec (node(s) {:?})",
+            e.from, e.transition, e.to, unexpected.nodes
+        ));
+    }
+    for state in &report.unreached_states {
+        logger.debug(format!("Spec state {} was never reached", state));
+    }
+    for e in &report.unexercised_edges {
+        logger.debug(format!(
+            "Spec edge {} --{}--> {} was never exercised",
+            e.from, e.transition, e.to
+        ));
+    }
+    logger.log(format!(
+        "{} unexpected edge(s), {} unexercised spec edge(s)",
+        report.unexpected_edges.len(),
+        report.unexercised_edges.len()
+    ));
+
+    let output_path = Path::new(&args.output_path);
+    if let Some(parent) = output_path.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("creating output directory {}", parent.display()))?;
+    }
+    let json = serde_json::to_string_pretty(&report).context("serializing spec report")?;
+    fs::write(output_path, json).with_context(|| format!("writing {}", args.output_path))?;
+
+    if report.conforms() {
+        logger.success(format!(
+            "Implementation conforms to the spec. Wrote {}",
+            args.output_path
+        ));
+    } else {
+        logger.error(format!(
+            "Implementation deviates from the spec. Wrote {}",
+            args.output_path
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn create_test_logger() -> Logger {
+        Logger::new(crate::logger::LogLevel::Quiet)
+    }
+
+    fn test_args(output_path: &Path) -> CheckSpecArgs {
+        CheckSpecArgs {
+            log_path: "tests/check_spec_test/events.log".to_string(),
+            spec_graph: "tests/check_spec_test/raft.dot".to_string(),
+            mapping: "tests/check_spec_test/mapping.json".to_string(),
+            refinement: None,
+            node: None,
+            output_path: output_path.to_str().unwrap().to_string(),
+        }
+    }
+
+    #[test]
+    fn test_check_spec_writes_report() {
+        let temp_dir = TempDir::new().unwrap();
+        let output_path = temp_dir.path().join("reports").join("spec_check.json");
+
+        run(test_args(&output_path), &create_test_logger()).unwrap();
+
+        let report: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&output_path).unwrap()).unwrap();
+        let unexpected = report["unexpected_edges"].as_array().unwrap();
+        assert_eq!(unexpected.len(), 1);
+        assert_eq!(unexpected[0]["from"], "Leader");
+        assert_eq!(unexpected[0]["transition"], "heartbeat");
+        assert_eq!(unexpected[0]["to"], "Candidate");
+        assert_eq!(unexpected[0]["nodes"], serde_json::json!([2]));
+        assert_eq!(report["unreached_states"], serde_json::json!(["Down"]));
+    }
+
+    #[test]
+    fn test_check_spec_single_node() {
+        let temp_dir = TempDir::new().unwrap();
+        let output_path = temp_dir.path().join("spec_check.json");
+
+        let mut args = test_args(&output_path);
+        args.node = Some(1);
+        run(args, &create_test_logger()).unwrap();
+
+        let report: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&output_path).unwrap()).unwrap();
+        assert_eq!(report["unexpected_edges"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn test_check_spec_unknown_node() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut args = test_args(&temp_dir.path().join("spec_check.json"));
+        args.node = Some(9);
+
+        let err = run(args, &create_test_logger()).unwrap_err();
+        assert!(err.to_string().contains("node 9 does not appear"));
+    }
+
+    #[test]
+    fn test_check_spec_missing_mapping() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut args = test_args(&temp_dir.path().join("spec_check.json"));
+        args.mapping = "tests/check_spec_test/nonexistent.json".to_string();
+
+        let err = run(args, &create_test_logger()).unwrap_err();
+        assert!(err.to_string().contains("reading spec mapping from"));
+    }
+}
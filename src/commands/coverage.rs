@@ -0,0 +1,163 @@
+use std::fs;
+use std::path::{self, Path};
+
+use anyhow::{Context, Result};
+use clap::Parser;
+
+use crate::commands::llvm::DEFAULT_MANIFEST_PATH;
+use crate::logger::Logger;
+use crate::model::coverage::{Manifest, annotation_coverage};
+use crate::model::instrumentor::InstrumentationTargets;
+
+#[derive(Parser, Debug)]
+pub struct CoverageArgs {
+    #[arg(
+        short = 'a',
+        long = "log-path",
+        default_value = "mediator-logs/events.log"
+    )]
+    pub log_path: String,
+
+    #[arg(short = 'm', long = "manifest", default_value = DEFAULT_MANIFEST_PATH)]
+    pub manifest: String,
+
+    #[arg(
+        short = 't',
+        long = "targets",
+        help = "Targets file from get-targets, to also list annotations never instrumented"
+    )]
+    pub targets: Option<String>,
+
+    #[arg(
+        short = 's',
+        long = "sut",
+        help = "SUT source tree that relative manifest paths are resolved against [default: the current directory]"
+    )]
+    pub sut: Option<String>,
+
+    #[arg(short = 'o', long = "out", default_value = "coverage.json")]
+    pub output_path: String,
+}
+
+pub fn run(args: CoverageArgs, logger: &Logger) -> Result<()> {
+    logger.log("Computing annotation coverage");
+
+    logger.log(format!("Reading manifest from {}", args.manifest));
+    let manifest = Manifest::from_file(&args.manifest)?;
+    if manifest.entries.is_empty() {
+        logger.warning("Manifest is empty - was the SUT instrumented with this manifest path?");
+    }
+
+    let targets: Option<Vec<InstrumentationTargets>> = match &args.targets {
+        Some(path) => {
+            let content = fs::read_to_string(path)
+                .with_context(|| format!("reading targets from {}", path))?;
+            Some(
+                serde_json::from_str(&content)
+                    .with_context(|| format!("parsing targets {}", path))?,
+            )
+        }
+        None => None,
+    };
+
+    logger.log(format!("Reading events from {}", args.log_path));
+    let log_content = fs::read_to_string(&args.log_path)
+        .with_context(|| format!("reading log from {}", &args.log_path))?;
+    let root =
+        path::absolute(args.sut.as_deref().unwrap_or(".")).context("resolving the SUT path")?;
+    let report = annotation_coverage(&log_content, &manifest, targets.as_deref(), &root);
+
+    for hit in &report.hit {
+        let e = &hit.entry;
+        logger.debug(format!(
+            "{} at {}:{} ({}) hit {} time(s)",
+            e.transition, e.file, e.line, e.function, hit.hits
+        ));
+    }
+    for e in &report.never_hit {
+        logger.warning(format!(
+            "{} at {}:{} ({}) never fired",
+            e.transition, e.file, e.line, e.function
+        ));
+    }
+    for a in &report.not_instrumented {
+        logger.warning(format!(
+            "{} annotation at {}:{} was never instrumented",
+            a.kind, a.file, a.line
+        ));
+    }
+    if !report.unknown_ids.is_empty() {
+        logger.warning(format!(
+            "{} event id(s) in the log are not in the manifest - is it from another build?",
+            report.unknown_ids.len()
+        ));
+    }
+
+    let output_path = Path::new(&args.output_path);
+    if let Some(parent) = output_path.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("creating output directory {}", parent.display()))?;
+    }
+    let json = serde_json::to_string_pretty(&report).context("serializing coverage report")?;
+    fs::write(output_path, json).with_context(|| format!("writing {}", args.output_path))?;
+
+    logger.success(format!(
+        "{}/{} annotation(s) hit. Wrote {}",
+        report.hit.len(),
+        report.total(),
+        args.output_path
+    ));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn create_test_logger() -> Logger {
+        Logger::new(crate::logger::LogLevel::Quiet)
+    }
+
+    fn test_args(output_path: &Path) -> CoverageArgs {
+        CoverageArgs {
+            log_path: "tests/coverage_test/events.log".to_string(),
+            manifest: "tests/coverage_test/manifest.jsonl".to_string(),
+            targets: None,
+            sut: None,
+            output_path: output_path.to_str().unwrap().to_string(),
+        }
+    }
+
+    #[test]
+    fn test_coverage_report() {
+        let temp_dir = TempDir::new().unwrap();
+        let output_path = temp_dir.path().join("reports/coverage.json");
+
+        let mut args = test_args(&output_path);
+        args.targets = Some("tests/coverage_test/targets.json".to_string());
+        args.sut = Some("/home/user/SUT/dqlite".to_string());
+        run(args, &create_test_logger()).unwrap();
+
+        let report: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&output_path).unwrap()).unwrap();
+        assert_eq!(report["hit"][0]["transition"], "becomeLeader");
+        assert_eq!(report["hit"][0]["hits"], 2);
+        assert_eq!(report["hit"][0]["file"], "src/raft.c");
+        assert_eq!(report["never_hit"].as_array().unwrap().len(), 1);
+        assert_eq!(report["never_hit"][0]["function"], "raft_step_down");
+        assert_eq!(report["not_instrumented"][0]["line"], 120);
+    }
+
+    #[test]
+    fn test_coverage_missing_manifest() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut args = test_args(&temp_dir.path().join("coverage.json"));
+        args.manifest = "tests/coverage_test/missing.jsonl".to_string();
+
+        let err = run(args, &create_test_logger()).unwrap_err();
+        assert!(err.to_string().contains("reading manifest"));
+    }
+}
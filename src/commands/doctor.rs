@@ -0,0 +1,573 @@
+use std::env;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use clap::Parser;
+use xshell::Shell;
+
+use crate::logger::Logger;
+use crate::model::build_system::{BuildRecord, BuildSystem};
+use crate::model::doctor::{Check, CheckStatus, DoctorReport, elf_info};
+use crate::model::project::ProjectConfig;
+use crate::model::toolchain::{SUPPORTED_LLVM, Toolchain, select_pass};
+
+use super::llvm::{
+    BUILD_COMMAND, BuildOptions, DEFAULT_BUILD_RECORD_PATH, LLVM_INSTRUMENTOR_PATH, llvm_version_of,
+};
+use super::run::RunMediatorArgs;
+use super::run::mediator::{MEDIATOR_BIN_REL, MEDIATOR_CONFIG, validate_config};
+
+#[derive(Parser, Debug)]
+pub struct DoctorArgs {
+    #[arg(
+        short,
+        long,
+        default_value = "abstraktor.toml",
+        help = "Project config whose SUT and LLVM settings are checked, if it exists"
+    )]
+    pub config: String,
+
+    #[arg(
+        long,
+        help = "SUT directory to check (default: sut.path from the config)"
+    )]
+    pub sut: Option<String>,
+
+    #[arg(long, value_name = "PATH", help = "Also write the results as JSON")]
+    pub json: Option<String>,
+}
+
+pub fn run(args: DoctorArgs, logger: &Logger, sh: &Shell) -> Result<()> {
+    logger.log("Checking the environment");
+    let mut checks = Vec::new();
+
+    let project = if Path::new(&args.config).exists() {
+        match ProjectConfig::from_file(&args.config) {
+            Ok(project) => Some(project),
+            Err(err) => {
+                checks.push(Check::error(
+                    "project config",
+                    format!("{:#}", err),
+                    format!("fix {} or point --config elsewhere", args.config),
+                ));
+                None
+            }
+        }
+    } else {
+        None
+    };
+    let llvm_dir = project
+        .as_ref()
+        .map_or("llvm".to_string(), |p| p.llvm.dir.clone());
+    let instrumentor = project
+        .as_ref()
+        .and_then(|p| p.llvm.instrumentor.clone())
+        .unwrap_or_else(|| LLVM_INSTRUMENTOR_PATH.to_string());
+
+    let toolchain = BuildOptions {
+        cc: project.as_ref().and_then(|p| p.llvm.cc.clone()),
+        cxx: project.as_ref().and_then(|p| p.llvm.cxx.clone()),
+        llvm_version: project.as_ref().and_then(|p| p.llvm.version),
+        ..BuildOptions::default()
+    };
+    checks.extend(toolchain_checks(
+        Path::new(&llvm_dir),
+        Path::new(&instrumentor),
+        &toolchain,
+        sh,
+    ));
+    checks.push(tool_check(
+        "dot",
+        CheckStatus::Warning,
+        "install Graphviz (apt-get install graphviz); without it export-graphs uses the built-in renderer, which cannot write PNG",
+    ));
+    checks.push(tool_check(
+        "ag",
+        CheckStatus::Warning,
+        "install the silver searcher (apt-get install silversearcher-ag); cov-server uses it to detect bugs",
+    ));
+    checks.push(tool_check(
+        "docker",
+        CheckStatus::Error,
+        "install Docker Engine (https://docs.docker.com/engine/install/)",
+    ));
+    checks.push(sudo_check(sh));
+    checks.extend(mediator_checks(
+        Path::new(MEDIATOR_BIN_REL),
+        Path::new(MEDIATOR_CONFIG),
+    )?);
+
+    let sut = args
+        .sut
+        .clone()
+        .or_else(|| project.as_ref().map(|p| p.sut.path.clone()));
+    match &sut {
+        Some(sut) => {
+            let config = project.as_ref().map(|p| &p.sut);
+            checks.push(sut_check(
+                Path::new(sut),
+                config.and_then(|c| c.build.as_deref()),
+                config.and_then(|c| c.build_system.as_deref()),
+            ));
+            let record = config
+                .and_then(|c| c.build_record.as_deref())
+                .unwrap_or(DEFAULT_BUILD_RECORD_PATH);
+            checks.extend(build_record_check(Path::new(record)));
+        }
+        None => logger.debug("No SUT to check; pass --sut or write abstraktor.toml"),
+    }
+
+    let report = DoctorReport::new(checks);
+    for check in &report.checks {
+        let line = format!("{}: {}", check.name, check.detail);
+        let line = match &check.fix {
+            Some(fix) => format!("{}\n  Fix: {}", line, fix),
+            None => line,
+        };
+        match check.status {
+            CheckStatus::Ok => logger.success(line),
+            CheckStatus::Warning => logger.warning(line),
+            CheckStatus::Error => logger.error(line),
+        }
+    }
+    if let Some(path) = &args.json {
+        let content = serde_json::to_string_pretty(&report)?;
+        fs::write(path, content).with_context(|| format!("writing {}", path))?;
+        logger.log(format!("Wrote results to {}", path));
+    }
+
+    let errors = report.count(CheckStatus::Error);
+    if errors > 0 {
+        anyhow::bail!("{} of {} checks failed", errors, report.checks.len());
+    }
+    logger.success(format!(
+        "Environment ready ({} warning(s))",
+        report.count(CheckStatus::Warning)
+    ));
+    Ok(())
+}
+
+fn find_in_path(name: &str) -> Option<PathBuf> {
+    env::split_paths(&env::var_os("PATH")?)
+        .map(|dir| dir.join(name))
+        .find(|path| is_executable(path))
+}
+
+fn is_executable(path: &Path) -> bool {
+    fs::metadata(path).is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+}
+
+fn tool_check(name: &str, missing: CheckStatus, fix: &str) -> Check {
+    match find_in_path(name) {
+        Some(path) => Check::ok(name, format!("found at {}", path.display())),
+        None => Check {
+            name: name.to_string(),
+            status: missing,
+            detail: "not found in PATH".to_string(),
+            fix: Some(fix.to_string()),
+        },
+    }
+}
+
+/// The clang `llvm` hands to the instrumentor, and the pass built for it.
+fn toolchain_checks(
+    llvm_dir: &Path,
+    instrumentor: &Path,
+    options: &BuildOptions,
+    sh: &Shell,
+) -> Vec<Check> {
+    let install = format!(
+        "install clang and LLVM {} to {} (llvm/deps.sh installs 11), or set [llvm] cc",
+        SUPPORTED_LLVM.start(),
+        SUPPORTED_LLVM.end()
+    );
+    let describe = |toolchains: &[Toolchain]| {
+        toolchains
+            .iter()
+            .map(|t| format!("{} (LLVM {})", t.cc, t.major))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let mut checks = Vec::new();
+    let toolchains = options.toolchains(sh).unwrap_or_else(|err| {
+        checks.push(Check::error(
+            "clang",
+            format!("{:#}", err),
+            "set [llvm] version to the LLVM release of [llvm] cc",
+        ));
+        Vec::new()
+    });
+    let supported: Vec<_> = toolchains
+        .iter()
+        .filter(|t| t.supported())
+        .cloned()
+        .collect();
+    if !supported.is_empty() {
+        checks.push(Check::ok(
+            "clang",
+            format!("found {}", describe(&supported)),
+        ));
+    } else if !toolchains.is_empty() {
+        checks.push(Check::error(
+            "clang",
+            format!("{}, which the pass does not support", describe(&toolchains)),
+            install,
+        ));
+    } else if checks.is_empty() {
+        checks.push(Check::error("clang", "not found in PATH", install));
+    }
+    checks.push(tool_check(
+        "llvm-config",
+        CheckStatus::Error,
+        "install LLVM (llvm/deps.sh), or set LLVM_CONFIG when building the pass",
+    ));
+
+    if is_executable(instrumentor) {
+        checks.push(Check::ok(
+            "instrumentor",
+            format!("built at {}", instrumentor.display()),
+        ));
+    } else {
+        checks.push(Check::error(
+            "instrumentor",
+            format!("{} is not built", instrumentor.display()),
+            format!(
+                "make -C {} (or abstraktor pipeline --from llvm)",
+                llvm_dir.display()
+            ),
+        ));
+    }
+
+    let Some(newest) = supported.first() else {
+        return checks;
+    };
+    let fallback = llvm_version_of("llvm-config", sh);
+    let selected = supported.iter().find_map(|toolchain| {
+        select_pass(llvm_dir, toolchain.major, fallback).map(|pass| (toolchain, pass))
+    });
+    checks.push(match selected {
+        Some((toolchain, pass)) => Check::ok(
+            "llvm pass",
+            format!(
+                "{} is built for {} (LLVM {})",
+                pass.display(),
+                toolchain.cc,
+                toolchain.major
+            ),
+        ),
+        None => Check::error(
+            "llvm pass",
+            format!(
+                "no pass in {} is built for {}",
+                llvm_dir.display(),
+                describe(&supported)
+            ),
+            format!(
+                "make -C {} pass LLVM_CONFIG=llvm-config-{}",
+                llvm_dir.display(),
+                newest.major
+            ),
+        ),
+    });
+    checks
+}
+
+fn sudo_check(sh: &Shell) -> Check {
+    if find_in_path("sudo").is_none() {
+        return Check::error(
+            "sudo",
+            "not found in PATH",
+            "install sudo; the cluster and the mediator run as root",
+        );
+    }
+    let cached = sh
+        .cmd("sudo")
+        .args(["-n", "true"])
+        .quiet()
+        .ignore_stdout()
+        .ignore_stderr()
+        .run()
+        .is_ok();
+    if cached {
+        Check::ok("sudo", "runs without a password prompt")
+    } else {
+        Check::warning(
+            "sudo",
+            "asks for a password",
+            "run sudo -v before a long pipeline so it does not stop at a prompt",
+        )
+    }
+}
+
+fn mediator_checks(binary: &Path, config: &Path) -> Result<Vec<Check>> {
+    let mut checks = Vec::new();
+    let fix = "cd mallory/mediator && cargo build --release --target x86_64-unknown-linux-musl";
+    checks.push(match fs::read(binary) {
+        Err(_) => Check::error(
+            "mediator",
+            format!("{} is not built", binary.display()),
+            fix,
+        ),
+        Ok(content) => match elf_info(&content) {
+            Some(elf) if elf.machine == "x86_64" && elf.static_binary => Check::ok(
+                "mediator",
+                format!("{} is a static x86_64 binary", binary.display()),
+            ),
+            Some(elf) => Check::error(
+                "mediator",
+                format!(
+                    "{} is a {} {} binary; the control container needs x86_64-unknown-linux-musl",
+                    binary.display(),
+                    if elf.static_binary {
+                        "static"
+                    } else {
+                        "dynamic"
+                    },
+                    elf.machine
+                ),
+                fix,
+            ),
+            None => Check::error(
+                "mediator",
+                format!("{} is not an ELF binary", binary.display()),
+                fix,
+            ),
+        },
+    });
+
+    let options = RunMediatorArgs::try_parse_from(["mediator"])
+        .map(|args| args.options)
+        .context("parsing the default mediator options")?;
+    checks.push(match validate_config(config, &options) {
+        Ok(()) => Check::ok(
+            "mediator config",
+            format!("{} is complete", config.display()),
+        ),
+        Err(err) => Check::error(
+            "mediator config",
+            format!("{:#}", err),
+            format!("restore {} from git", config.display()),
+        ),
+    });
+    Ok(checks)
+}
+
+/// The SUT directory and how `llvm` builds it: the configured command, the
+/// configured build system, or the one detected from the SUT's files.
+fn sut_check(sut: &Path, build: Option<&str>, system: Option<&str>) -> Check {
+    if !sut.is_dir() {
+        return Check::error(
+            "sut",
+            format!("{} is not a directory", sut.display()),
+            "point --sut or sut.path at the SUT's source tree",
+        );
+    }
+    let build = match (build, system) {
+        (Some(build), _) => build,
+        (None, Some(system)) if system != "auto" && system != "script" => {
+            return Check::ok("sut", format!("{} is built with {}", sut.display(), system));
+        }
+        _ => match BuildSystem::detect(sut) {
+            None if system != Some("script") => {
+                return Check::error(
+                    "sut",
+                    format!(
+                        "{} has no install.sh, meson.build, CMakeLists.txt, configure or Makefile",
+                        sut.display()
+                    ),
+                    "set sut.build or sut.build_system in abstraktor.toml",
+                );
+            }
+            Some(BuildSystem::Script) | None => BUILD_COMMAND,
+            Some(detected) => {
+                return Check::ok(
+                    "sut",
+                    format!("{} is built with {}", sut.display(), detected.name()),
+                );
+            }
+        },
+    };
+    // Only commands that run a script from the SUT can be checked here.
+    let program = build.split_whitespace().next().unwrap_or_default();
+    if !program.starts_with("./") {
+        return Check::ok("sut", format!("{} is built with {}", sut.display(), build));
+    }
+    let script = sut.join(program.trim_start_matches("./"));
+    if !script.is_file() {
+        Check::error(
+            "sut",
+            format!("{} has no {}", sut.display(), program),
+            format!(
+                "add {} to the SUT, or set sut.build in abstraktor.toml",
+                program
+            ),
+        )
+    } else if !is_executable(&script) {
+        Check::error(
+            "sut",
+            format!("{} is not executable", script.display()),
+            format!("chmod +x {}", script.display()),
+        )
+    } else {
+        Check::ok("sut", format!("{} is built with {}", sut.display(), build))
+    }
+}
+
+/// Whether the instrumented build recorded by `llvm` is still on disk as it
+/// was built. Nothing to check before the first build.
+fn build_record_check(path: &Path) -> Option<Check> {
+    if !path.is_file() {
+        return None;
+    }
+    let check = match BuildRecord::from_file(path) {
+        Err(e) => Check::error(
+            "instrumented build",
+            format!("{:#}", e),
+            "run `abstraktor instrument` again",
+        ),
+        Ok(record) => match record.stale_artifacts().as_slice() {
+            [] => Check::ok(
+                "instrumented build",
+                format!("{} artifact(s) as built", record.artifacts.len()),
+            ),
+            stale => Check::warning(
+                "instrumented build",
+                format!(
+                    "{} of {} artifact(s) changed or missing since the build, e.g. {}",
+                    stale.len(),
+                    record.artifacts.len(),
+                    stale[0].path
+                ),
+                "run `abstraktor instrument` again",
+            ),
+        },
+    };
+    Some(check)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn create_test_logger() -> Logger {
+        Logger::new(crate::logger::LogLevel::Quiet)
+    }
+
+    #[test]
+    fn test_sut_check() {
+        let temp_dir = TempDir::new().unwrap();
+        let sut = temp_dir.path();
+        assert_eq!(
+            sut_check(&sut.join("missing"), Some("./install.sh"), None).status,
+            CheckStatus::Error
+        );
+
+        let check = sut_check(sut, Some("./install.sh"), None);
+        assert_eq!(check.status, CheckStatus::Error);
+        assert!(check.detail.contains("has no ./install.sh"));
+
+        let script = sut.join("install.sh");
+        fs::write(&script, "#!/bin/sh\nmake\n").unwrap();
+        let check = sut_check(sut, Some("./install.sh"), None);
+        assert_eq!(
+            check.fix.as_deref(),
+            Some(format!("chmod +x {}", script.display()).as_str())
+        );
+
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+        assert_eq!(
+            sut_check(sut, Some("./install.sh --release"), None).status,
+            CheckStatus::Ok
+        );
+        assert_eq!(
+            sut_check(sut, Some("make -j8"), None).status,
+            CheckStatus::Ok
+        );
+        assert_eq!(sut_check(sut, None, None).status, CheckStatus::Ok);
+
+        let cmake = temp_dir.path().join("cmake");
+        fs::create_dir(&cmake).unwrap();
+        let check = sut_check(&cmake, None, None);
+        assert_eq!(check.status, CheckStatus::Error);
+        assert_eq!(
+            sut_check(&cmake, None, Some("meson")).status,
+            CheckStatus::Ok
+        );
+        assert_eq!(
+            sut_check(&cmake, None, Some("script")).status,
+            CheckStatus::Error
+        );
+        fs::write(cmake.join("CMakeLists.txt"), "project(sut C)\n").unwrap();
+        let check = sut_check(&cmake, None, None);
+        assert_eq!(check.status, CheckStatus::Ok);
+        assert!(check.detail.ends_with("built with cmake"));
+    }
+
+    #[test]
+    fn test_mediator_checks() {
+        let temp_dir = TempDir::new().unwrap();
+        let binary = temp_dir.path().join("mediator");
+        let config = temp_dir.path().join("Mediator.toml");
+        fs::write(&config, "experiment_network = \"10.1.0.0/16\"\n").unwrap();
+
+        let checks = mediator_checks(&binary, &config).unwrap();
+        assert_eq!(checks[0].status, CheckStatus::Error);
+        assert_eq!(
+            checks[0].fix.as_deref(),
+            Some("cd mallory/mediator && cargo build --release --target x86_64-unknown-linux-musl")
+        );
+        assert!(checks[1].detail.contains("missing"));
+
+        fs::write(&binary, "#!/bin/sh\n").unwrap();
+        let checks = mediator_checks(&binary, Path::new(MEDIATOR_CONFIG)).unwrap();
+        assert!(checks[0].detail.contains("not an ELF binary"));
+        if Path::new(MEDIATOR_CONFIG).exists() {
+            assert_eq!(checks[1].status, CheckStatus::Ok);
+        }
+    }
+
+    #[test]
+    fn test_doctor_writes_json_summary() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = temp_dir.path().join("abstraktor.toml");
+        let cc = temp_dir.path().join("clang-fake");
+        fs::write(&cc, "#!/bin/sh\necho 'clang version 14.0.6'\n").unwrap();
+        fs::set_permissions(&cc, fs::Permissions::from_mode(0o755)).unwrap();
+        fs::write(
+            &config,
+            format!(
+                "[sut]\npath = \"{}\"\n\n[llvm]\ndir = \"{}\"\ncc = \"{}\"\n",
+                temp_dir.path().join("sut").display(),
+                temp_dir.path().join("llvm").display(),
+                cc.display()
+            ),
+        )
+        .unwrap();
+        let json = temp_dir.path().join("doctor.json");
+        let args = DoctorArgs {
+            config: config.to_str().unwrap().to_string(),
+            sut: None,
+            json: Some(json.to_str().unwrap().to_string()),
+        };
+
+        let err = run(args, &create_test_logger(), &Shell::new().unwrap()).unwrap_err();
+        assert!(err.to_string().contains("checks failed"));
+        let summary: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&json).unwrap()).unwrap();
+        assert_eq!(summary["status"], "error");
+        let checks = summary["checks"].as_array().unwrap();
+        let status = |name: &str| {
+            checks
+                .iter()
+                .find(|check| check["name"] == name)
+                .map(|check| check["status"].clone())
+        };
+        assert_eq!(status("sut").unwrap(), "error");
+        assert_eq!(status("clang").unwrap(), "ok");
+        assert_eq!(status("llvm pass").unwrap(), "error");
+        assert!(status("docker").is_some());
+    }
+}
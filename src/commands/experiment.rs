@@ -0,0 +1,392 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use anyhow::{Context, Result};
+use clap::Parser;
+use xshell::Shell;
+
+use crate::logger::Logger;
+use crate::model::dwell::DwellThresholds;
+use crate::model::event_log::{batch_time_warning, parse_event_log};
+use crate::model::experiment::{
+    ExperimentConfig, RunOutcome, csv_for_runs, experiment_report, markdown_for_experiment,
+};
+use crate::model::project::ProjectConfig;
+use crate::model::refinement::Refinement;
+use crate::model::report::Oracles;
+use crate::model::{RefinementConfig, build_event_graph_with};
+
+use super::pipeline::mallory_args;
+use super::run::{RunMalloryArgs, mallory};
+
+#[derive(Parser, Debug)]
+pub struct ExperimentArgs {
+    #[arg(short, long, default_value = "experiment.toml")]
+    pub config: String,
+
+    #[arg(
+        long,
+        default_value = "false",
+        help = "Only aggregate the runs already in the output directory"
+    )]
+    pub analyze_only: bool,
+}
+
+/// One configuration of the matrix, ready to run.
+struct PlannedConfiguration {
+    name: String,
+    run: RunMalloryArgs,
+}
+
+pub fn run(args: ExperimentArgs, logger: &Logger, sh: &Shell) -> Result<()> {
+    run_with(
+        args,
+        logger,
+        Path::new(mallory::HOST_ARTIFACTS_DIR),
+        |run| mallory::run_recorded(run, logger, sh),
+    )
+}
+
+/// `run`, with `run_mallory` doing each run and `host_dir` where the control
+/// container's `/host` is on this machine.
+fn run_with(
+    args: ExperimentArgs,
+    logger: &Logger,
+    host_dir: &Path,
+    mut run_mallory: impl FnMut(RunMalloryArgs) -> Result<()>,
+) -> Result<()> {
+    logger.log(format!("Running experiment from {}", args.config));
+    let experiment = ExperimentConfig::from_file(&args.config)?;
+    let project = ProjectConfig::from_file(&experiment.project)?;
+
+    // Every configuration is checked before the first run, so that a bad
+    // value does not surface hours into the campaign.
+    let mut planned = Vec::new();
+    for configuration in experiment.configurations() {
+        let mut config = project.clone();
+        config.test = configuration.apply(&project.test)?;
+        let run = mallory_args(&config)
+            .with_context(|| format!("invalid configuration {}", configuration.name))?;
+        if mallory::host_path(host_dir, &run.artifacts_dir).is_none() {
+            anyhow::bail!(
+                "configuration {}: artifacts dir {} is not below /host, so its mediator log cannot be collected",
+                configuration.name,
+                run.artifacts_dir
+            );
+        }
+        planned.push(PlannedConfiguration {
+            name: configuration.name,
+            run,
+        });
+    }
+    logger.log(format!(
+        "{} configuration(s) × {} repetition(s)",
+        planned.len(),
+        experiment.repetitions
+    ));
+
+    let out = Path::new(&experiment.out);
+    if !args.analyze_only {
+        // Repetitions go round the configurations so that a campaign cut
+        // short still has runs of each.
+        for repetition in 1..=experiment.repetitions {
+            for configuration in &planned {
+                let run_dir = run_dir(out, &configuration.name, repetition);
+                if run_dir.join(mallory::EXPORTED_LOG).exists() {
+                    logger.log(format!(
+                        "Skipping {} run {}: already collected",
+                        configuration.name, repetition
+                    ));
+                    continue;
+                }
+                logger.log(format!("Running {} ({})", configuration.name, repetition));
+                // Each run exports to its own directory below the configured one.
+                let mut run = configuration.run.clone();
+                run.artifacts_dir = format!(
+                    "{}/{}/run-{}",
+                    run.artifacts_dir.trim_end_matches('/'),
+                    configuration.name,
+                    repetition
+                );
+                let exported = mallory::host_path(host_dir, &run.artifacts_dir)
+                    .context("artifacts dir is not below /host")?;
+                let events_log = exported.join(mallory::EXPORTED_LOG);
+                discard_events_log(&events_log)?;
+                let started = SystemTime::now();
+                let result = run_mallory(run);
+                if !written_since(&events_log, started) {
+                    result?;
+                    anyhow::bail!(
+                        "{} run {} exported no mediator log to {}",
+                        configuration.name,
+                        repetition,
+                        events_log.display()
+                    );
+                }
+                if let Err(err) = result {
+                    logger.warning(format!(
+                        "{} run {} failed, collecting its mediator log anyway: {:#}",
+                        configuration.name, repetition, err
+                    ));
+                }
+                collect_artifacts(&experiment.collect, &exported, &run_dir)?;
+            }
+        }
+    }
+
+    let mut outcomes = Vec::new();
+    for configuration in &planned {
+        for repetition in 1..=experiment.repetitions {
+            let run_dir = run_dir(out, &configuration.name, repetition);
+            let log_path = run_dir.join(mallory::EXPORTED_LOG);
+            if !log_path.exists() {
+                logger.warning(format!("No mediator log in {}", run_dir.display()));
+                continue;
+            }
+            let log = fs::read_to_string(&log_path)
+                .with_context(|| format!("reading log from {}", log_path.display()))?;
+            let results = read_results(&run_dir.join("results.edn"))?;
+            outcomes.push(RunOutcome {
+                configuration: configuration.name.clone(),
+                repetition,
+                log,
+                results,
+            });
+        }
+    }
+    if outcomes.is_empty() {
+        anyhow::bail!("no runs to analyze in {}", out.display());
+    }
+    for outcome in &outcomes {
+        if let Some(warning) = batch_time_warning(&parse_event_log(&outcome.log)) {
+            logger.warning(format!(
+                "{} #{}: {}",
+                outcome.configuration, outcome.repetition, warning
+            ));
+        }
+    }
+
+    let refinement = match &experiment.refinement {
+        Some(path) => RefinementConfig::from_file(path)?.compile()?,
+        None => Refinement::identity(),
+    };
+    let baseline = match &experiment.baseline {
+        Some(path) => {
+            let content = fs::read_to_string(path)
+                .with_context(|| format!("reading baseline from {}", path))?;
+            Some(build_event_graph_with(&content, &refinement))
+        }
+        None => None,
+    };
+    let oracles = Oracles {
+        dwell: DwellThresholds::parse(&experiment.max_dwell)?,
+        spec: None,
+    };
+    let names: Vec<String> = planned.iter().map(|c| c.name.clone()).collect();
+    let report = experiment_report(&outcomes, &names, baseline.as_ref(), &refinement, &oracles);
+
+    for c in &report.configurations {
+        logger.log(format!(
+            "{}: {} run(s), {:.1} states, {:.1} edges, {:.1} bugs on average",
+            c.name,
+            c.runs,
+            c.states.map_or(0.0, |s| s.mean),
+            c.edges.map_or(0.0, |s| s.mean),
+            c.bugs.map_or(0.0, |s| s.mean)
+        ));
+    }
+    for c in report.comparisons.iter().filter(|c| c.test.p_value < 0.05) {
+        logger.log(format!(
+            "{} differs between {} and {} (p = {:.4})",
+            c.metric, c.a, c.b, c.test.p_value
+        ));
+    }
+
+    fs::create_dir_all(out).with_context(|| format!("creating {}", out.display()))?;
+    let summary_path = out.join("summary.md");
+    fs::write(&summary_path, markdown_for_experiment(&report))
+        .with_context(|| format!("writing {}", summary_path.display()))?;
+    let csv_path = out.join("runs.csv");
+    fs::write(&csv_path, csv_for_runs(&report.runs))
+        .with_context(|| format!("writing {}", csv_path.display()))?;
+    logger.success(format!(
+        "Wrote summary of {} run(s) to {}",
+        report.runs.len(),
+        summary_path.display()
+    ));
+    Ok(())
+}
+
+fn run_dir(out: &Path, configuration: &str, repetition: usize) -> PathBuf {
+    out.join(configuration).join(format!("run-{}", repetition))
+}
+
+fn read_results(path: &Path) -> Result<Option<String>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let results = fs::read_to_string(path)
+        .with_context(|| format!("reading results from {}", path.display()))?;
+    Ok(Some(results))
+}
+
+/// Removes the mediator log an earlier run left behind, so that it is never
+/// collected for a run that did not export one.
+fn discard_events_log(events_log: &Path) -> Result<()> {
+    match fs::remove_file(events_log) {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err)
+            .with_context(|| format!("removing stale mediator log {}", events_log.display())),
+        _ => Ok(()),
+    }
+}
+
+fn written_since(path: &Path, started: SystemTime) -> bool {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .is_ok_and(|modified| modified >= started)
+}
+
+/// Copies what the run exported, then the `collect` files. The mediator log
+/// is copied last: its presence marks the run as done.
+fn collect_artifacts(collect: &[String], exported: &Path, run_dir: &Path) -> Result<()> {
+    fs::create_dir_all(run_dir).with_context(|| format!("creating {}", run_dir.display()))?;
+    let results = exported.join(mallory::EXPORTED_RESULTS);
+    let sources = collect
+        .iter()
+        .map(PathBuf::from)
+        .chain(results.exists().then_some(results))
+        .chain([exported.join(mallory::EXPORTED_LOG)]);
+    for source in sources {
+        let Some(name) = source.file_name() else {
+            continue;
+        };
+        fs::copy(&source, run_dir.join(name))
+            .with_context(|| format!("copying {} to {}", source.display(), run_dir.display()))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn create_test_logger() -> Logger {
+        Logger::new(crate::logger::LogLevel::Quiet)
+    }
+
+    fn write_experiment(temp_dir: &TempDir, matrix: &str) -> ExperimentArgs {
+        let root = temp_dir.path();
+        let project = root.join("abstraktor.toml");
+        fs::write(&project, "[sut]\npath = \"sut\"\n").unwrap();
+        let config = root.join("experiment.toml");
+        fs::write(
+            &config,
+            format!(
+                "project = \"{}\"\nrepetitions = 2\nout = \"{}\"\n\n[matrix]\n{}",
+                project.display(),
+                root.join("out").display(),
+                matrix
+            ),
+        )
+        .unwrap();
+        ExperimentArgs {
+            config: config.to_str().unwrap().to_string(),
+            analyze_only: true,
+        }
+    }
+
+    #[test]
+    fn test_analyze_collected_runs() {
+        let temp_dir = TempDir::new().unwrap();
+        let args = write_experiment(&temp_dir, "scheduler = [\"qlearning\", \"noop\"]\n");
+        let out = temp_dir.path().join("out");
+        let logs = [
+            ("scheduler=qlearning", 1, "simple_events.log"),
+            ("scheduler=qlearning", 2, "multi_node_events.log"),
+            ("scheduler=noop", 1, "simple_events.log"),
+        ];
+        for (configuration, repetition, log) in logs {
+            let dir = run_dir(&out, configuration, repetition);
+            fs::create_dir_all(&dir).unwrap();
+            fs::copy(
+                Path::new("tests/export_graphs_test").join(log),
+                dir.join(mallory::EXPORTED_LOG),
+            )
+            .unwrap();
+        }
+
+        run(args, &create_test_logger(), &Shell::new().unwrap()).unwrap();
+        let summary = fs::read_to_string(out.join("summary.md")).unwrap();
+        assert!(summary.contains("| scheduler=qlearning | 2 |"));
+        assert!(summary.contains("| scheduler=noop | 1 |"));
+        let csv = fs::read_to_string(out.join("runs.csv")).unwrap();
+        assert_eq!(csv.lines().count(), 4);
+    }
+
+    #[test]
+    fn test_collects_what_run_mallory_exports() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut args = write_experiment(&temp_dir, "scheduler = [\"qlearning\"]\n");
+        args.analyze_only = false;
+        let host = temp_dir.path().join("host");
+        let store = Path::new("tests/jepsen_store_test/store/raft/20251110T195655.000-0300");
+        let mut exported = Vec::new();
+
+        run_with(args, &create_test_logger(), &host, |run| {
+            let dir = mallory::host_path(&host, &run.artifacts_dir).unwrap();
+            fs::create_dir_all(&dir).unwrap();
+            for file in [mallory::EXPORTED_LOG, mallory::EXPORTED_RESULTS] {
+                fs::copy(store.join(file), dir.join(file)).unwrap();
+            }
+            exported.push(run.artifacts_dir);
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(
+            exported,
+            [
+                "/host/scheduler=qlearning/run-1",
+                "/host/scheduler=qlearning/run-2"
+            ]
+        );
+        let out = temp_dir.path().join("out");
+        for repetition in [1, 2] {
+            let dir = run_dir(&out, "scheduler=qlearning", repetition);
+            assert!(dir.join(mallory::EXPORTED_LOG).exists());
+            assert!(dir.join(mallory::EXPORTED_RESULTS).exists());
+        }
+        let summary = fs::read_to_string(out.join("summary.md")).unwrap();
+        assert!(summary.contains("| scheduler=qlearning | 2 |"));
+    }
+
+    #[test]
+    fn test_invalid_configuration_fails_before_running() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut args = write_experiment(&temp_dir, "scheduler = [\"qlearning\", \"random\"]\n");
+        args.analyze_only = false;
+
+        let err = run(args, &create_test_logger(), &Shell::new().unwrap()).unwrap_err();
+        assert!(err.to_string().contains("scheduler=random"));
+        assert!(!temp_dir.path().join("out").exists());
+    }
+
+    #[test]
+    fn test_only_collects_events_logs_of_this_run() {
+        let temp_dir = TempDir::new().unwrap();
+        let log = temp_dir.path().join("events.log");
+        fs::write(&log, "left over").unwrap();
+        let started = SystemTime::now() + std::time::Duration::from_secs(60);
+        assert!(!written_since(&log, started));
+
+        discard_events_log(&log).unwrap();
+        assert!(!log.exists());
+        discard_events_log(&log).unwrap();
+
+        let started = SystemTime::now() - std::time::Duration::from_secs(1);
+        fs::write(&log, "fresh").unwrap();
+        assert!(written_since(&log, started));
+    }
+}
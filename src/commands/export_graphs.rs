@@ -5,19 +5,65 @@ use anyhow::{Context, Result};
 use clap::{Parser, ValueEnum};
 use xshell::Shell;
 
+use crate::commands::runs::{self, Invocation};
 use crate::logger::Logger;
-use crate::model::{build_event_graph, dot_for_node_graph};
+use crate::model::coverage::Manifest;
+use crate::model::dwell::{DwellThresholds, format_duration};
+use crate::model::event_graph::{EventGraph, NodeGraph};
+use crate::model::event_log::{batch_time_warning, parse_event_log};
+use crate::model::jepsen_store::{discover_runs, select_runs};
+use crate::model::layout::{pdf_for_node_graph, svg_for_node_graph};
+use crate::model::promela::promela_for_nodes;
+use crate::model::refinement::Refinement;
+use crate::model::sources::{SourceLinks, html_for_node_graph, sourced_node_graph};
+use crate::model::timeline::{
+    build_timeline, html_for_timeline, parse_jepsen_history, svg_for_timeline,
+};
+use crate::model::tla::tla_for_nodes;
+use crate::model::watch::{LogTail, WatchedGraph};
+use crate::model::{
+    RefinementConfig, build_event_graph, build_event_graph_with, build_schedule_graphs,
+    build_window_graphs, dot_for_node_graph, dwell_report, find_nondeterminism,
+    summarize_schedules,
+};
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
 pub enum OutputFormat {
     Dot,
     Png,
+    Svg,
     Pdf,
+    Promela,
+    Tla,
+    Json,
+    Html,
 }
 
-#[derive(Parser, Debug)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
+pub enum SplitBy {
+    None,
+    Schedule,
+    Window,
+}
+
+/// Who draws PNG, SVG and PDF graphs.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
+pub enum Renderer {
+    /// Graphviz when `dot` is installed, the built-in renderer otherwise.
+    Auto,
+    /// Built-in layered layout; writes SVG and PDF.
+    Builtin,
+    /// Graphviz `dot`.
+    Graphviz,
+}
+
+#[derive(Parser, Debug, Clone)]
 pub struct ExportGraphsArgs {
-    #[arg(short = 'a', long = "log-path", default_value = "mediator-logs/events.log")]
+    #[arg(
+        short = 'a',
+        long = "log-path",
+        default_value = "mediator-logs/events.log"
+    )]
     pub log_path: String,
 
     #[arg(short = 'o', long = "out", default_value = "abstractions")]
@@ -28,9 +74,307 @@ pub struct ExportGraphsArgs {
 
     #[arg(long = "keep-dot", default_value = "false")]
     pub keep_dot: bool,
+
+    #[arg(long = "renderer", value_enum, default_value = "auto")]
+    pub renderer: Renderer,
+
+    #[arg(short = 'r', long = "refinement")]
+    pub refinement: Option<String>,
+
+    #[arg(long = "split-by", value_enum, default_value = "none")]
+    pub split_by: SplitBy,
+
+    #[arg(
+        short = 'm',
+        long = "manifest",
+        help = "Event id manifest written during instrumentation, to link transitions to their source"
+    )]
+    pub manifest: Option<String>,
+
+    #[arg(
+        long = "source-url",
+        requires = "manifest",
+        help = "Source viewer URL with {file}, {line} and {function} placeholders"
+    )]
+    pub source_url: Option<String>,
+
+    #[arg(
+        long = "max-dwell",
+        value_name = "STATE=DURATION",
+        help = "Flag stays in STATE longer than DURATION (e.g. Candidate=2s, *=500ms)"
+    )]
+    pub max_dwell: Vec<String>,
+
+    #[arg(
+        long = "timeline",
+        default_value = "false",
+        help = "Also write timeline.svg and timeline.html with each node's states over time"
+    )]
+    pub timeline: bool,
+
+    #[arg(
+        long = "history",
+        requires = "timeline",
+        help = "Jepsen history.edn whose client operations are added to the timeline"
+    )]
+    pub history: Option<String>,
+
+    #[arg(
+        long = "history-offset-ms",
+        default_value = "0",
+        allow_negative_numbers = true,
+        help = "When the Jepsen test started, relative to the first logged event"
+    )]
+    pub history_offset_ms: i64,
+
+    #[arg(
+        long = "store",
+        conflicts_with = "log_path",
+        help = "Jepsen store directory; exports the mediator log of each selected run"
+    )]
+    pub store: Option<String>,
+
+    #[arg(
+        long = "list-runs",
+        requires = "store",
+        default_value = "false",
+        help = "List the runs in the store instead of exporting"
+    )]
+    pub list_runs: bool,
+
+    #[arg(
+        long = "run",
+        requires = "store",
+        value_name = "RUN",
+        help = "Run to export: <test>/<timestamp>, <timestamp>, latest or all (default: latest)"
+    )]
+    pub runs: Vec<String>,
+
+    #[arg(
+        long = "watch",
+        conflicts_with_all = ["store", "split_by", "max_dwell", "timeline"],
+        default_value = "false",
+        help = "Keep following the events log and re-render graphs as new states and edges appear"
+    )]
+    pub watch: bool,
+
+    #[arg(
+        long = "watch-interval-ms",
+        default_value = "1000",
+        help = "How often to check the events log for new lines in --watch mode"
+    )]
+    pub watch_interval_ms: u64,
+}
+
+/// `run`, recorded in the run registry. A watch only ends when it is
+/// interrupted, so it would stay `running` there forever and is not recorded.
+pub fn run_recorded(args: ExportGraphsArgs, logger: &Logger, sh: &Shell) -> Result<()> {
+    if args.watch {
+        return run(args, logger, sh);
+    }
+    let invocation = Invocation {
+        command: "export-graphs",
+        args: format!("{:?}", args),
+        mediator_config: None,
+        artifacts: vec![args.output_dir.clone()],
+    };
+    runs::record(invocation, logger, sh, || run(args, logger, sh))
+}
+
+pub fn run(mut args: ExportGraphsArgs, logger: &Logger, sh: &Shell) -> Result<()> {
+    choose_renderer(&mut args, logger, sh)?;
+    match &args.store {
+        Some(store) => export_store(store, &args, logger, sh),
+        None if args.watch => watch_log(&args, logger, sh),
+        None => export_log(&args, logger, sh),
+    }
+}
+
+/// Settles `auto` once, so every node is drawn the same way.
+fn choose_renderer(args: &mut ExportGraphsArgs, logger: &Logger, sh: &Shell) -> Result<()> {
+    if !matches!(
+        args.format,
+        OutputFormat::Png | OutputFormat::Svg | OutputFormat::Pdf
+    ) {
+        return Ok(());
+    }
+    if args.renderer == Renderer::Auto {
+        let graphviz = sh
+            .cmd("dot")
+            .arg("-V")
+            .quiet()
+            .ignore_stderr()
+            .run()
+            .is_ok();
+        args.renderer = if graphviz {
+            Renderer::Graphviz
+        } else {
+            logger.debug("Graphviz not found - using the built-in renderer");
+            Renderer::Builtin
+        };
+        if !graphviz && args.format == OutputFormat::Png {
+            anyhow::bail!(
+                "PNG needs Graphviz, which was not found, and the built-in renderer writes SVG and PDF only; install Graphviz or use -f svg"
+            );
+        }
+    }
+    if args.renderer == Renderer::Builtin && args.format == OutputFormat::Png {
+        anyhow::bail!(
+            "the built-in renderer writes SVG and PDF only; use -f svg or --renderer graphviz"
+        );
+    }
+    Ok(())
 }
 
-pub fn run(args: ExportGraphsArgs, logger: &Logger, sh: &Shell) -> Result<()> {
+fn export_store(store: &str, args: &ExportGraphsArgs, logger: &Logger, sh: &Shell) -> Result<()> {
+    logger.log(format!("Looking for runs in Jepsen store {}", store));
+    let runs = discover_runs(Path::new(store))?;
+    if runs.is_empty() {
+        logger.warning(format!("No runs with a mediator log found in {}", store));
+        return Ok(());
+    }
+
+    if args.list_runs {
+        for run in &runs {
+            let valid = match run.valid {
+                Some(true) => "valid",
+                Some(false) => "invalid",
+                None => "unknown",
+            };
+            logger.log(format!(
+                "{}  schedule={} feedback={} {}",
+                run.id(),
+                run.schedule.as_deref().unwrap_or("unknown"),
+                run.feedback.as_deref().unwrap_or("unknown"),
+                valid
+            ));
+        }
+        return Ok(());
+    }
+
+    let selected = select_runs(&runs, &args.runs)?;
+    logger.log(format!("Exporting {} run(s)", selected.len()));
+    for run in selected {
+        logger.log(format!("Run {}", run.id()));
+        let mut run_args = args.clone();
+        run_args.store = None;
+        run_args.log_path = run.mediator_log.to_string_lossy().into_owned();
+        run_args.output_dir = Path::new(&args.output_dir)
+            .join(run.output_subdir())
+            .to_string_lossy()
+            .into_owned();
+        if run_args.timeline && run_args.history.is_none() {
+            run_args.history = run.history().map(|h| h.to_string_lossy().into_owned());
+        }
+        export_log(&run_args, logger, sh)?;
+    }
+    Ok(())
+}
+
+/// Follows the log until interrupted. Truncated or rotated logs are read
+/// again from the start without forgetting what was already discovered.
+fn watch_log(args: &ExportGraphsArgs, logger: &Logger, sh: &Shell) -> Result<()> {
+    let refinement = match &args.refinement {
+        Some(path) => {
+            logger.log(format!("Applying abstraction refinement from {}", path));
+            RefinementConfig::from_file(path)?.compile()?
+        }
+        None => Refinement::identity(),
+    };
+    let sources = match &args.manifest {
+        Some(path) => Some(SourceLinks {
+            manifest: Manifest::from_file(path)?,
+            url_template: args.source_url.clone(),
+        }),
+        None => None,
+    };
+    let out_dir = Path::new(&args.output_dir);
+    create_dir(out_dir)?;
+
+    logger.log(format!(
+        "Watching {} (Ctrl-C to stop), writing to {}",
+        args.log_path, args.output_dir
+    ));
+    let mut tail = LogTail::new(&args.log_path);
+    let mut watched = WatchedGraph::new(refinement);
+    loop {
+        watch_step(
+            &mut tail,
+            &mut watched,
+            out_dir,
+            args,
+            sources.as_ref(),
+            logger,
+            sh,
+        )?;
+        std::thread::sleep(std::time::Duration::from_millis(args.watch_interval_ms));
+    }
+}
+
+/// Reads whatever was appended since the last step, reports discoveries and
+/// re-renders the nodes they touched.
+fn watch_step(
+    tail: &mut LogTail,
+    watched: &mut WatchedGraph,
+    out_dir: &Path,
+    args: &ExportGraphsArgs,
+    sources: Option<&SourceLinks>,
+    logger: &Logger,
+    sh: &Shell,
+) -> Result<()> {
+    let update = tail.poll()?;
+    if update.restarted {
+        logger.warning(format!(
+            "{} was truncated or rotated - reading it from the start",
+            args.log_path
+        ));
+    }
+    if update.lines.is_empty() {
+        return Ok(());
+    }
+
+    let discovered = watched.apply(&update);
+    if discovered.is_empty() {
+        logger.debug(format!("Read {} line(s), nothing new", update.lines.len()));
+        return Ok(());
+    }
+    logger.debug(format!(
+        "Read {} line(s), {} new state(s) and {} new edge(s)",
+        update.lines.len(),
+        discovered.new_state_count(),
+        discovered.new_edge_count()
+    ));
+    for (node_id, states) in &discovered.new_states {
+        for state in states {
+            logger.success(format!("Node {}: new state {}", node_id, state));
+        }
+    }
+    for (node_id, edges) in &discovered.new_edges {
+        for edge in edges {
+            logger.success(format!(
+                "Node {}: new edge {} --{}--> {}",
+                node_id, edge.from, edge.transition, edge.to
+            ));
+        }
+    }
+
+    // Promela and TLA+ compose every node into the system model.
+    let graph = watched.graph();
+    let changed = match args.format {
+        OutputFormat::Promela | OutputFormat::Tla => graph.clone(),
+        _ => EventGraph {
+            nodes: graph
+                .nodes
+                .iter()
+                .filter(|(id, _)| discovered.touches(**id))
+                .map(|(id, node)| (*id, node.clone()))
+                .collect(),
+        },
+    };
+    write_graphs(&changed, out_dir, args, sources, logger, sh)
+}
+
+fn export_log(args: &ExportGraphsArgs, logger: &Logger, sh: &Shell) -> Result<()> {
     logger.log("Exporting event graphs");
     logger.debug(format!("Log file: {}", args.log_path));
     logger.debug(format!("Output directory: {}", args.output_dir));
@@ -39,11 +383,38 @@ pub fn run(args: ExportGraphsArgs, logger: &Logger, sh: &Shell) -> Result<()> {
     logger.log(format!("Reading events from {}", args.log_path));
     let log_content = fs::read_to_string(&args.log_path)
         .with_context(|| format!("reading log from {}", &args.log_path))?;
+    if let Some(warning) = batch_time_warning(&parse_event_log(&log_content)) {
+        logger.warning(warning);
+    }
+
+    let thresholds = DwellThresholds::parse(&args.max_dwell)?;
+
+    let refinement = match &args.refinement {
+        Some(path) => {
+            logger.log(format!("Applying abstraction refinement from {}", path));
+            Some(RefinementConfig::from_file(path)?.compile()?)
+        }
+        None => None,
+    };
 
     logger.debug("Building event graph from log entries");
-    let graph = build_event_graph(&log_content);
+    let graph = match &refinement {
+        Some(refinement) => build_event_graph_with(&log_content, refinement),
+        None => build_event_graph(&log_content),
+    };
     logger.log(format!("Found {} node(s) to process", graph.nodes.len()));
 
+    let sources = match &args.manifest {
+        Some(path) => {
+            logger.log(format!("Linking transitions to sources from {}", path));
+            Some(SourceLinks {
+                manifest: Manifest::from_file(path)?,
+                url_template: args.source_url.clone(),
+            })
+        }
+        None => None,
+    };
+
     let out_dir = Path::new(&args.output_dir);
     if !out_dir.exists() {
         logger.log(format!("Creating output directory: {}", args.output_dir));
@@ -61,10 +432,271 @@ pub fn run(args: ExportGraphsArgs, logger: &Logger, sh: &Shell) -> Result<()> {
         graph.nodes.len(),
         args.format
     ));
+    write_graphs(&graph, out_dir, args, sources.as_ref(), logger, sh)?;
+
+    let identity = Refinement::identity();
+    let refinement = refinement.as_ref().unwrap_or(&identity);
+    report_nondeterminism(&log_content, refinement, out_dir, logger)?;
+    report_dwell(&log_content, refinement, &thresholds, out_dir, logger)?;
+    if args.timeline {
+        write_timeline(&log_content, refinement, out_dir, args, logger)?;
+    }
+
+    if args.split_by != SplitBy::None {
+        export_split(
+            &log_content,
+            refinement,
+            out_dir,
+            args,
+            sources.as_ref(),
+            logger,
+            sh,
+        )?;
+    }
+
+    logger.success(format!(
+        "Exported {} graph(s) to {}",
+        graph.nodes.len(),
+        args.output_dir
+    ));
+    Ok(())
+}
+
+fn report_nondeterminism(
+    log_content: &str,
+    refinement: &Refinement,
+    out_dir: &Path,
+    logger: &Logger,
+) -> Result<()> {
+    let branches = find_nondeterminism(log_content, refinement);
+    if branches.is_empty() {
+        logger.debug("Abstraction is deterministic");
+        return Ok(());
+    }
+
+    logger.warning(format!(
+        "Found {} nondeterministic branch(es) - the abstraction may be too coarse",
+        branches.len()
+    ));
+    for branch in &branches {
+        let outcomes: Vec<String> = branch
+            .outcomes
+            .iter()
+            .map(|o| {
+                let lines: Vec<String> = o
+                    .witnesses
+                    .iter()
+                    .map(|w| w.source_line.to_string())
+                    .collect();
+                format!("{} (x{}, line {})", o.to, o.count, lines.join(", "))
+            })
+            .collect();
+        logger.warning(format!(
+            "Node {}: {} --{}--> {}",
+            branch.node_id,
+            branch.from,
+            branch.transition,
+            outcomes.join(" | ")
+        ));
+        match branch.hints.as_deref() {
+            Some([hint, ..]) => logger.log(format!(
+                "Consider capturing '{}' in the state annotation ({:.0}% of outcomes separated)",
+                hint.variable,
+                hint.separation * 100.0
+            )),
+            Some([]) => logger.debug("No captured variable separates these outcomes"),
+            None => {}
+        }
+    }
+
+    let report_path = out_dir.join("nondeterminism.json");
+    let json =
+        serde_json::to_string_pretty(&branches).context("serializing nondeterminism report")?;
+    fs::write(&report_path, json).with_context(|| format!("writing {}", report_path.display()))?;
+    logger.success(format!("Wrote {}", report_path.display()));
+    Ok(())
+}
+
+fn report_dwell(
+    log_content: &str,
+    refinement: &Refinement,
+    thresholds: &DwellThresholds,
+    out_dir: &Path,
+    logger: &Logger,
+) -> Result<()> {
+    let report = dwell_report(log_content, refinement, thresholds);
+    if report.states.is_empty() {
+        logger.debug("No timestamped state changes - skipping dwell report");
+        return Ok(());
+    }
+
+    for state in &report.states {
+        logger.debug(format!(
+            "Node {} {}: min {}, median {}, p99 {}, max {} over {} stay(s)",
+            state.node_id,
+            state.state,
+            format_duration(state.stats.min_us),
+            format_duration(state.stats.median_us),
+            format_duration(state.stats.p99_us),
+            format_duration(state.stats.max_us),
+            state.stats.samples
+        ));
+    }
+    if !report.outliers.is_empty() {
+        logger.warning(format!(
+            "Found {} stay(s) longer than the configured dwell thresholds",
+            report.outliers.len()
+        ));
+        for outlier in &report.outliers {
+            logger.warning(format!(
+                "Node {} stayed in {} for {} (limit {}), lines {}-{}",
+                outlier.stay.node_id,
+                outlier.stay.state,
+                format_duration(outlier.stay.duration_us),
+                format_duration(outlier.threshold_us),
+                outlier.stay.entered_line,
+                outlier.stay.left_line
+            ));
+        }
+    } else if !thresholds.is_empty() {
+        logger.debug("No stay exceeded the dwell thresholds");
+    }
+
+    let report_path = out_dir.join("dwell.json");
+    let json = serde_json::to_string_pretty(&report).context("serializing dwell report")?;
+    fs::write(&report_path, json).with_context(|| format!("writing {}", report_path.display()))?;
+    logger.success(format!("Wrote {}", report_path.display()));
+    Ok(())
+}
+
+fn write_timeline(
+    log_content: &str,
+    refinement: &Refinement,
+    out_dir: &Path,
+    args: &ExportGraphsArgs,
+    logger: &Logger,
+) -> Result<()> {
+    let Some(mut timeline) = build_timeline(log_content, refinement) else {
+        logger.warning("No timestamped events in the log - skipping timeline");
+        return Ok(());
+    };
+
+    if let Some(path) = &args.history {
+        logger.log(format!("Adding client operations from {}", path));
+        let content =
+            fs::read_to_string(path).with_context(|| format!("reading history from {}", path))?;
+        let ops = parse_jepsen_history(&content);
+        if ops.is_empty() {
+            logger.warning(format!("No operations found in {}", path));
+        }
+        timeline.add_history(&ops, args.history_offset_ms * 1_000);
+    }
+
+    for (name, content) in [
+        ("timeline.svg", svg_for_timeline(&timeline)),
+        ("timeline.html", html_for_timeline(&timeline)),
+    ] {
+        let file_path = out_dir.join(name);
+        fs::write(&file_path, content)
+            .with_context(|| format!("writing {}", file_path.display()))?;
+        logger.success(format!("Wrote {}", file_path.display()));
+    }
+    Ok(())
+}
+
+fn export_split(
+    log_content: &str,
+    refinement: &Refinement,
+    out_dir: &Path,
+    args: &ExportGraphsArgs,
+    sources: Option<&SourceLinks>,
+    logger: &Logger,
+    sh: &Shell,
+) -> Result<()> {
+    let schedules = build_schedule_graphs(log_content, refinement);
+    if schedules.is_empty() {
+        logger.warning("No schedule window markers found in event log - skipping split export");
+        return Ok(());
+    }
+
+    match args.split_by {
+        SplitBy::Schedule => {
+            logger.log(format!(
+                "Splitting graphs across {} schedule(s)",
+                schedules.len()
+            ));
+            for (schedule_id, graph) in &schedules {
+                let dir = out_dir.join(format!("schedule_{}", schedule_id));
+                create_dir(&dir)?;
+                write_graphs(graph, &dir, args, sources, logger, sh)?;
+            }
+        }
+        SplitBy::Window => {
+            let windows = build_window_graphs(log_content, refinement);
+            logger.log(format!(
+                "Splitting graphs across {} window(s)",
+                windows.len()
+            ));
+            for (window, graph) in &windows {
+                let dir = out_dir.join(format!(
+                    "schedule_{}_step_{}",
+                    window.schedule_id, window.step_id
+                ));
+                create_dir(&dir)?;
+                write_graphs(graph, &dir, args, sources, logger, sh)?;
+            }
+        }
+        SplitBy::None => unreachable!(),
+    }
+
+    let summary = summarize_schedules(&schedules);
+    for contribution in &summary {
+        let message = format!(
+            "Schedule {}: {} new state(s), {} new edge(s)",
+            contribution.schedule_id,
+            contribution.new_state_count(),
+            contribution.new_edge_count()
+        );
+        if contribution.is_productive() {
+            logger.success(message);
+        } else {
+            logger.debug(message);
+        }
+    }
+    let productive = summary.iter().filter(|c| c.is_productive()).count();
+    logger.log(format!(
+        "{} of {} schedule(s) discovered new states or edges",
+        productive,
+        summary.len()
+    ));
+
+    let summary_path = out_dir.join("schedule_summary.json");
+    let json = serde_json::to_string_pretty(&summary).context("serializing schedule summary")?;
+    fs::write(&summary_path, json)
+        .with_context(|| format!("writing {}", summary_path.display()))?;
+    logger.success(format!("Wrote {}", summary_path.display()));
+    Ok(())
+}
+
+fn create_dir(dir: &Path) -> Result<()> {
+    fs::create_dir_all(dir).with_context(|| format!("creating output directory {}", dir.display()))
+}
+
+fn write_graphs(
+    graph: &EventGraph,
+    out_dir: &Path,
+    args: &ExportGraphsArgs,
+    sources: Option<&SourceLinks>,
+    logger: &Logger,
+    sh: &Shell,
+) -> Result<()> {
+    if let OutputFormat::Promela | OutputFormat::Tla = args.format {
+        return write_models(graph, out_dir, args.format, logger);
+    }
 
     for (node_id, node_graph) in &graph.nodes {
         logger.debug(format!("Processing node {}", node_id));
-        let dot = dot_for_node_graph(node_graph);
+        let dot = dot_for_node_graph(node_graph, sources);
         match args.format {
             OutputFormat::Dot => {
                 let file_path = out_dir.join(format!("node_{}.dot", node_id));
@@ -73,13 +705,29 @@ pub fn run(args: ExportGraphsArgs, logger: &Logger, sh: &Shell) -> Result<()> {
                     .with_context(|| format!("writing {}", file_path.display()))?;
                 logger.success(format!("Wrote {}", file_path.display()));
             }
-            OutputFormat::Png | OutputFormat::Pdf => {
+            OutputFormat::Svg | OutputFormat::Pdf if args.renderer == Renderer::Builtin => {
+                let (file_path, content) = match args.format {
+                    OutputFormat::Svg => (
+                        out_dir.join(format!("node_{}.svg", node_id)),
+                        svg_for_node_graph(node_graph, sources).into_bytes(),
+                    ),
+                    _ => (
+                        out_dir.join(format!("node_{}.pdf", node_id)),
+                        pdf_for_node_graph(node_graph),
+                    ),
+                };
+                fs::write(&file_path, content)
+                    .with_context(|| format!("writing {}", file_path.display()))?;
+                logger.success(format!("Wrote {}", file_path.display()));
+            }
+            OutputFormat::Png | OutputFormat::Svg | OutputFormat::Pdf => {
                 let tmp_dot = out_dir.join(format!("node_{}.dot", node_id));
                 logger.debug(format!("Writing temporary DOT file: {}", tmp_dot.display()));
                 fs::write(&tmp_dot, &dot)
                     .with_context(|| format!("writing {}", tmp_dot.display()))?;
                 let ext = match args.format {
                     OutputFormat::Png => "png",
+                    OutputFormat::Svg => "svg",
                     OutputFormat::Pdf => "pdf",
                     _ => unreachable!(),
                 };
@@ -102,14 +750,66 @@ pub fn run(args: ExportGraphsArgs, logger: &Logger, sh: &Shell) -> Result<()> {
                 }
                 logger.success(format!("Wrote {}", out_path.display()));
             }
+            OutputFormat::Json => {
+                let file_path = out_dir.join(format!("node_{}.json", node_id));
+                let json = serde_json::to_string_pretty(&sourced_node_graph(
+                    *node_id, node_graph, sources,
+                ))
+                .context("serializing node graph")?;
+                fs::write(&file_path, json)
+                    .with_context(|| format!("writing {}", file_path.display()))?;
+                logger.success(format!("Wrote {}", file_path.display()));
+            }
+            OutputFormat::Html => {
+                let file_path = out_dir.join(format!("node_{}.html", node_id));
+                fs::write(
+                    &file_path,
+                    html_for_node_graph(*node_id, node_graph, sources),
+                )
+                .with_context(|| format!("writing {}", file_path.display()))?;
+                logger.success(format!("Wrote {}", file_path.display()));
+            }
+            OutputFormat::Promela | OutputFormat::Tla => unreachable!(),
         }
     }
+    Ok(())
+}
 
-    logger.success(format!(
-        "Exported {} graph(s) to {}",
-        graph.nodes.len(),
-        args.output_dir
-    ));
+/// Writes one model per node plus a `system` model composing all of them.
+fn write_models(
+    graph: &EventGraph,
+    out_dir: &Path,
+    format: OutputFormat,
+    logger: &Logger,
+) -> Result<()> {
+    let mut nodes: Vec<(u32, &NodeGraph)> = graph.nodes.iter().map(|(id, n)| (*id, n)).collect();
+    nodes.sort_unstable_by_key(|(id, _)| *id);
+
+    let render = |module: &str, nodes: &[(u32, &NodeGraph)]| match format {
+        OutputFormat::Promela => promela_for_nodes(nodes),
+        _ => tla_for_nodes(module, nodes),
+    };
+    let ext = match format {
+        OutputFormat::Promela => "pml",
+        _ => "tla",
+    };
+
+    let mut files: Vec<(String, String)> = nodes
+        .iter()
+        .map(|node| {
+            let module = format!("node_{}", node.0);
+            let source = render(&module, std::slice::from_ref(node));
+            (module, source)
+        })
+        .collect();
+    files.push(("system".to_string(), render("system", &nodes)));
+
+    for (module, source) in files {
+        let file_path = out_dir.join(format!("{}.{}", module, ext));
+        fs::write(&file_path, source)
+            .with_context(|| format!("writing {}", file_path.display()))?;
+        logger.success(format!("Wrote {}", file_path.display()));
+    }
     Ok(())
 }
 
@@ -123,17 +823,39 @@ mod tests {
         Logger::new(crate::logger::LogLevel::Quiet)
     }
 
+    fn test_args(log_path: &str, output_dir: &Path, format: OutputFormat) -> ExportGraphsArgs {
+        ExportGraphsArgs {
+            log_path: log_path.to_string(),
+            output_dir: output_dir.to_str().unwrap().to_string(),
+            format,
+            keep_dot: false,
+            renderer: Renderer::Auto,
+            refinement: None,
+            split_by: SplitBy::None,
+            manifest: None,
+            source_url: None,
+            max_dwell: vec![],
+            timeline: false,
+            history: None,
+            history_offset_ms: 0,
+            store: None,
+            list_runs: false,
+            runs: vec![],
+            watch: false,
+            watch_interval_ms: 1000,
+        }
+    }
+
     #[test]
     fn test_export_dot_format_single_node() {
         let temp_dir = TempDir::new().unwrap();
         let output_dir = temp_dir.path().join("output");
 
-        let args = ExportGraphsArgs {
-            log_path: "tests/export_graphs_test/simple_events.log".to_string(),
-            output_dir: output_dir.to_str().unwrap().to_string(),
-            format: OutputFormat::Dot,
-            keep_dot: false,
-        };
+        let args = test_args(
+            "tests/export_graphs_test/simple_events.log",
+            &output_dir,
+            OutputFormat::Dot,
+        );
 
         let logger = create_test_logger();
         let sh = Shell::new().unwrap();
@@ -154,12 +876,11 @@ mod tests {
         let temp_dir = TempDir::new().unwrap();
         let output_dir = temp_dir.path().join("output");
 
-        let args = ExportGraphsArgs {
-            log_path: "tests/export_graphs_test/multi_node_events.log".to_string(),
-            output_dir: output_dir.to_str().unwrap().to_string(),
-            format: OutputFormat::Dot,
-            keep_dot: false,
-        };
+        let args = test_args(
+            "tests/export_graphs_test/multi_node_events.log",
+            &output_dir,
+            OutputFormat::Dot,
+        );
 
         let logger = create_test_logger();
         let sh = Shell::new().unwrap();
@@ -185,12 +906,11 @@ mod tests {
         let temp_dir = TempDir::new().unwrap();
         let output_dir = temp_dir.path().join("output");
 
-        let args = ExportGraphsArgs {
-            log_path: "tests/export_graphs_test/simple_events.log".to_string(),
-            output_dir: output_dir.to_str().unwrap().to_string(),
-            format: OutputFormat::Png,
-            keep_dot: false,
-        };
+        let args = test_args(
+            "tests/export_graphs_test/simple_events.log",
+            &output_dir,
+            OutputFormat::Png,
+        );
 
         let logger = create_test_logger();
         let sh = Shell::new().unwrap();
@@ -209,10 +929,9 @@ mod tests {
             let dot_file = output_dir.join("node_1.dot");
             assert!(!dot_file.exists(), "Temporary .dot file should be removed");
         } else {
-            assert!(
-                result.is_err(),
-                "Export should fail when graphviz is not installed"
-            );
+            let err = result.unwrap_err().to_string();
+            assert!(err.contains("PNG needs Graphviz"), "{}", err);
+            assert!(!output_dir.join("node_1.svg").exists());
         }
     }
 
@@ -221,12 +940,11 @@ mod tests {
         let temp_dir = TempDir::new().unwrap();
         let output_dir = temp_dir.path().join("output");
 
-        let args = ExportGraphsArgs {
-            log_path: "tests/export_graphs_test/simple_events.log".to_string(),
-            output_dir: output_dir.to_str().unwrap().to_string(),
-            format: OutputFormat::Pdf,
-            keep_dot: false,
-        };
+        let args = test_args(
+            "tests/export_graphs_test/simple_events.log",
+            &output_dir,
+            OutputFormat::Pdf,
+        );
 
         let logger = create_test_logger();
         let sh = Shell::new().unwrap();
@@ -246,10 +964,44 @@ mod tests {
             assert!(!dot_file.exists(), "Temporary .dot file should be removed");
         } else {
             assert!(
-                result.is_err(),
-                "Export should fail when graphviz is not installed"
+                result.is_ok(),
+                "Export should use the built-in renderer when graphviz is not installed"
+            );
+            assert!(output_dir.join("node_1.pdf").exists());
+        }
+    }
+
+    #[test]
+    fn test_export_builtin_renderer() {
+        let temp_dir = TempDir::new().unwrap();
+        let output_dir = temp_dir.path().join("output");
+        let logger = create_test_logger();
+        let sh = Shell::new().unwrap();
+
+        for format in [OutputFormat::Svg, OutputFormat::Pdf] {
+            let mut args = test_args(
+                "tests/export_graphs_test/multi_node_events.log",
+                &output_dir,
+                format,
             );
+            args.renderer = Renderer::Builtin;
+            run(args, &logger, &sh).unwrap();
         }
+
+        let svg = fs::read_to_string(output_dir.join("node_1.svg")).unwrap();
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.contains("<circle"));
+        let pdf = fs::read(output_dir.join("node_2.pdf")).unwrap();
+        assert!(pdf.starts_with(b"%PDF-"));
+        assert!(!output_dir.join("node_1.dot").exists());
+
+        let mut args = test_args(
+            "tests/export_graphs_test/multi_node_events.log",
+            &output_dir,
+            OutputFormat::Png,
+        );
+        args.renderer = Renderer::Builtin;
+        assert!(run(args, &logger, &sh).is_err());
     }
 
     #[test]
@@ -257,12 +1009,11 @@ mod tests {
         let temp_dir = TempDir::new().unwrap();
         let output_dir = temp_dir.path().join("nested").join("output").join("dir");
 
-        let args = ExportGraphsArgs {
-            log_path: "tests/export_graphs_test/simple_events.log".to_string(),
-            output_dir: output_dir.to_str().unwrap().to_string(),
-            format: OutputFormat::Dot,
-            keep_dot: false,
-        };
+        let args = test_args(
+            "tests/export_graphs_test/simple_events.log",
+            &output_dir,
+            OutputFormat::Dot,
+        );
 
         let logger = create_test_logger();
         let sh = Shell::new().unwrap();
@@ -278,12 +1029,11 @@ mod tests {
         let temp_dir = TempDir::new().unwrap();
         let output_dir = temp_dir.path().join("output");
 
-        let args = ExportGraphsArgs {
-            log_path: "tests/export_graphs_test/empty_events.log".to_string(),
-            output_dir: output_dir.to_str().unwrap().to_string(),
-            format: OutputFormat::Dot,
-            keep_dot: false,
-        };
+        let args = test_args(
+            "tests/export_graphs_test/empty_events.log",
+            &output_dir,
+            OutputFormat::Dot,
+        );
 
         let logger = create_test_logger();
         let sh = Shell::new().unwrap();
@@ -302,12 +1052,11 @@ mod tests {
         let temp_dir = TempDir::new().unwrap();
         let output_dir = temp_dir.path().join("output");
 
-        let args = ExportGraphsArgs {
-            log_path: "tests/export_graphs_test/nonexistent.log".to_string(),
-            output_dir: output_dir.to_str().unwrap().to_string(),
-            format: OutputFormat::Dot,
-            keep_dot: false,
-        };
+        let args = test_args(
+            "tests/export_graphs_test/nonexistent.log",
+            &output_dir,
+            OutputFormat::Dot,
+        );
 
         let logger = create_test_logger();
         let sh = Shell::new().unwrap();
@@ -322,12 +1071,11 @@ mod tests {
         let temp_dir = TempDir::new().unwrap();
         let output_dir = temp_dir.path().join("output");
 
-        let args = ExportGraphsArgs {
-            log_path: "tests/export_graphs_test/complex_events.log".to_string(),
-            output_dir: output_dir.to_str().unwrap().to_string(),
-            format: OutputFormat::Dot,
-            keep_dot: false,
-        };
+        let args = test_args(
+            "tests/export_graphs_test/complex_events.log",
+            &output_dir,
+            OutputFormat::Dot,
+        );
 
         let logger = create_test_logger();
         let sh = Shell::new().unwrap();
@@ -346,6 +1094,57 @@ mod tests {
         assert!(node1_content.contains("authenticated"));
     }
 
+    #[test]
+    fn test_export_with_refinement() {
+        let temp_dir = TempDir::new().unwrap();
+        let output_dir = temp_dir.path().join("output");
+
+        let mut args = test_args(
+            "tests/export_graphs_test/raft_events.log",
+            &output_dir,
+            OutputFormat::Dot,
+        );
+        args.refinement = Some("tests/export_graphs_test/raft_refinement.json".to_string());
+
+        let logger = create_test_logger();
+        let sh = Shell::new().unwrap();
+
+        run(args, &logger, &sh).unwrap();
+
+        let content = fs::read_to_string(output_dir.join("node_1.dot")).unwrap();
+        assert!(content.contains("\"Leader\";"));
+        assert!(content.contains("\"Candidate\";"));
+        assert!(content.contains("\"Down\";"));
+        assert!(content.contains("\"Leader\" -> \"Leader\" [label=<replicationQuorum>];"));
+        assert!(!content.contains("LeaderMatchingQuorum"));
+        assert!(!content.contains("timeout"));
+    }
+
+    #[test]
+    fn test_export_with_missing_refinement_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let output_dir = temp_dir.path().join("output");
+
+        let mut args = test_args(
+            "tests/export_graphs_test/simple_events.log",
+            &output_dir,
+            OutputFormat::Dot,
+        );
+        args.refinement = Some("tests/export_graphs_test/nonexistent.json".to_string());
+
+        let logger = create_test_logger();
+        let sh = Shell::new().unwrap();
+
+        let result = run(args, &logger, &sh);
+        assert!(result.is_err());
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("reading refinement from")
+        );
+    }
+
     #[test]
     fn test_output_format_display() {
         assert_eq!(format!("{:?}", OutputFormat::Dot), "Dot");
@@ -358,12 +1157,11 @@ mod tests {
         let temp_dir = TempDir::new().unwrap();
         let output_dir = temp_dir.path().join("output");
 
-        let args = ExportGraphsArgs {
-            log_path: "tests/export_graphs_test/simple_events.log".to_string(),
-            output_dir: output_dir.to_str().unwrap().to_string(),
-            format: OutputFormat::Dot,
-            keep_dot: false,
-        };
+        let args = test_args(
+            "tests/export_graphs_test/simple_events.log",
+            &output_dir,
+            OutputFormat::Dot,
+        );
 
         let logger = create_test_logger();
         let sh = Shell::new().unwrap();
@@ -377,4 +1175,398 @@ mod tests {
         assert!(content.contains("node [shape=circle"));
         assert!(content.contains("edge [fontsize=9]"));
     }
+
+    #[test]
+    fn test_export_split_by_schedule() {
+        let temp_dir = TempDir::new().unwrap();
+        let output_dir = temp_dir.path().join("output");
+
+        let mut args = test_args(
+            "tests/export_graphs_test/windowed_events.log",
+            &output_dir,
+            OutputFormat::Dot,
+        );
+        args.split_by = SplitBy::Schedule;
+
+        let logger = create_test_logger();
+        let sh = Shell::new().unwrap();
+
+        run(args, &logger, &sh).unwrap();
+
+        assert!(output_dir.join("node_1.dot").exists());
+        assert!(output_dir.join("schedule_0").join("node_1.dot").exists());
+        assert!(output_dir.join("schedule_0").join("node_2.dot").exists());
+        assert!(output_dir.join("schedule_1").join("node_2.dot").exists());
+        assert!(!output_dir.join("schedule_1").join("node_1.dot").exists());
+
+        let content = fs::read_to_string(output_dir.join("schedule_2").join("node_1.dot")).unwrap();
+        assert!(content.contains("\"Leader\" -> \"Follower\" [label=<winElection>];"));
+
+        let summary: serde_json::Value = serde_json::from_str(
+            &fs::read_to_string(output_dir.join("schedule_summary.json")).unwrap(),
+        )
+        .unwrap();
+        let summary = summary.as_array().unwrap();
+        assert_eq!(summary.len(), 3);
+        assert_eq!(summary[0]["schedule_id"], 0);
+        assert_eq!(summary[1]["new_states"], serde_json::json!({}));
+        assert_eq!(summary[1]["new_edges"], serde_json::json!({}));
+        assert_eq!(summary[2]["new_edges"]["1"][0]["to"], "Follower");
+    }
+
+    #[test]
+    fn test_export_json_with_sources() {
+        let temp_dir = TempDir::new().unwrap();
+        let output_dir = temp_dir.path().join("output");
+        let sh = Shell::new().unwrap();
+
+        let mut args = test_args(
+            "tests/coverage_test/events.log",
+            &output_dir,
+            OutputFormat::Json,
+        );
+        args.manifest = Some("tests/coverage_test/manifest.jsonl".to_string());
+        args.source_url = Some("vscode://file/{file}:{line}".to_string());
+        run(args, &create_test_logger(), &sh).unwrap();
+
+        let node: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(output_dir.join("node_1.json")).unwrap())
+                .unwrap();
+        assert_eq!(node["edges"][0]["transition"], "raft_tick");
+        assert_eq!(node["edges"][0]["event_ids"], serde_json::json!([0]));
+        assert_eq!(node["edges"][0]["sources"][0]["line"], 31);
+        assert_eq!(
+            node["edges"][0]["sources"][0]["url"],
+            "vscode://file/src/raft.c:31"
+        );
+    }
+
+    #[test]
+    fn test_export_split_by_window() {
+        let temp_dir = TempDir::new().unwrap();
+        let output_dir = temp_dir.path().join("output");
+
+        let mut args = test_args(
+            "tests/export_graphs_test/windowed_events.log",
+            &output_dir,
+            OutputFormat::Dot,
+        );
+        args.split_by = SplitBy::Window;
+
+        let logger = create_test_logger();
+        let sh = Shell::new().unwrap();
+
+        run(args, &logger, &sh).unwrap();
+
+        let step0 = output_dir.join("schedule_0_step_0");
+        let step1 = output_dir.join("schedule_0_step_1");
+        assert!(step0.join("node_1.dot").exists());
+        assert!(!step0.join("node_2.dot").exists());
+        assert!(step1.join("node_2.dot").exists());
+        assert!(output_dir.join("schedule_2_step_0").exists());
+        assert!(output_dir.join("schedule_summary.json").exists());
+
+        let content = fs::read_to_string(step0.join("node_1.dot")).unwrap();
+        assert!(content.contains("\"Follower\" -> \"Candidate\" [label=<boot>];"));
+        assert!(!content.contains("stepDown"));
+    }
+
+    #[test]
+    fn test_export_split_without_window_markers() {
+        let temp_dir = TempDir::new().unwrap();
+        let output_dir = temp_dir.path().join("output");
+
+        let mut args = test_args(
+            "tests/export_graphs_test/simple_events.log",
+            &output_dir,
+            OutputFormat::Dot,
+        );
+        args.split_by = SplitBy::Schedule;
+
+        let logger = create_test_logger();
+        let sh = Shell::new().unwrap();
+
+        run(args, &logger, &sh).unwrap();
+
+        assert!(output_dir.join("node_1.dot").exists());
+        assert!(!output_dir.join("schedule_summary.json").exists());
+    }
+
+    #[test]
+    fn test_export_reports_nondeterminism() {
+        let temp_dir = TempDir::new().unwrap();
+        let output_dir = temp_dir.path().join("output");
+
+        let args = test_args(
+            "tests/export_graphs_test/nondeterministic_events.log",
+            &output_dir,
+            OutputFormat::Dot,
+        );
+
+        let logger = create_test_logger();
+        let sh = Shell::new().unwrap();
+
+        run(args, &logger, &sh).unwrap();
+
+        let report: serde_json::Value = serde_json::from_str(
+            &fs::read_to_string(output_dir.join("nondeterminism.json")).unwrap(),
+        )
+        .unwrap();
+        let branch = report
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|b| b["from"] == "Candidate" && b["transition"] == "requestVote")
+            .unwrap();
+        assert_eq!(branch["outcomes"].as_array().unwrap().len(), 2);
+        assert_eq!(branch["hints"][0]["variable"], "votes");
+    }
+
+    #[test]
+    fn test_export_reports_dwell_outliers() {
+        let temp_dir = TempDir::new().unwrap();
+        let output_dir = temp_dir.path().join("output");
+
+        let mut args = test_args(
+            "tests/export_graphs_test/windowed_events.log",
+            &output_dir,
+            OutputFormat::Json,
+        );
+        args.max_dwell = vec!["Leader=100ms".to_string()];
+
+        let logger = create_test_logger();
+        let sh = Shell::new().unwrap();
+
+        run(args, &logger, &sh).unwrap();
+
+        let report: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(output_dir.join("dwell.json")).unwrap())
+                .unwrap();
+        let outliers = report["outliers"].as_array().unwrap();
+        assert_eq!(outliers.len(), 1);
+        assert_eq!(outliers[0]["state"], "Leader");
+        assert_eq!(outliers[0]["threshold_us"], 100_000);
+        assert_eq!(outliers[0]["entered_line"], 5);
+
+        let node: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(output_dir.join("node_1.json")).unwrap())
+                .unwrap();
+        assert_eq!(node["dwell"]["Leader"]["max_us"], 299_999);
+    }
+
+    #[test]
+    fn test_export_timeline_with_history() {
+        let temp_dir = TempDir::new().unwrap();
+        let output_dir = temp_dir.path().join("output");
+        let history = temp_dir.path().join("history.edn");
+        fs::write(
+            &history,
+            "{:type :invoke, :f :write, :value 1, :time 1000000, :process 0, :index 0}\n\
+             {:type :ok, :f :write, :value 1, :time 90000000, :process 0, :index 1}\n",
+        )
+        .unwrap();
+
+        let mut args = test_args(
+            "tests/export_graphs_test/windowed_events.log",
+            &output_dir,
+            OutputFormat::Dot,
+        );
+        args.timeline = true;
+        args.history = Some(history.to_string_lossy().into_owned());
+
+        let logger = create_test_logger();
+        let sh = Shell::new().unwrap();
+
+        run(args, &logger, &sh).unwrap();
+
+        let svg = fs::read_to_string(output_dir.join("timeline.svg")).unwrap();
+        assert!(svg.contains(">Node 1</text>"));
+        assert!(svg.contains(">Node 2</text>"));
+        assert!(svg.contains(">Process 0</text>"));
+        assert_eq!(svg.matches("stroke-dasharray").count(), 4);
+        assert!(output_dir.join("timeline.html").exists());
+    }
+
+    #[test]
+    fn test_export_runs_from_jepsen_store() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = temp_dir.path().join("store");
+        let output_dir = temp_dir.path().join("output");
+        for timestamp in ["20251110T195655.000-0300", "20251111T080000.000-0300"] {
+            let run_dir = store.join("dqlite-append").join(timestamp);
+            fs::create_dir_all(&run_dir).unwrap();
+            let fixture = Path::new("tests/jepsen_store_test/store/raft/20251110T195655.000-0300");
+            for name in ["mediator.log", "events-random-event_history.log"] {
+                fs::copy(fixture.join(name), run_dir.join(name)).unwrap();
+            }
+        }
+
+        let mut args = test_args("", &output_dir, OutputFormat::Dot);
+        args.store = Some(store.to_string_lossy().into_owned());
+
+        let logger = create_test_logger();
+        let sh = Shell::new().unwrap();
+
+        run(args.clone(), &logger, &sh).unwrap();
+        let run_dir = |timestamp: &str| {
+            output_dir
+                .join("dqlite-append")
+                .join(format!("{}-random-event_history", timestamp))
+        };
+        assert!(
+            run_dir("20251111T080000.000-0300")
+                .join("node_1.dot")
+                .exists()
+        );
+        assert!(!run_dir("20251110T195655.000-0300").exists());
+
+        args.runs = vec!["all".to_string()];
+        run(args.clone(), &logger, &sh).unwrap();
+        assert!(
+            run_dir("20251110T195655.000-0300")
+                .join("node_1.dot")
+                .exists()
+        );
+
+        args.runs = vec!["dqlite-append/20250101T000000.000-0300".to_string()];
+        let err = run(args, &logger, &sh).unwrap_err();
+        assert!(err.to_string().contains("no run matches"));
+    }
+
+    #[test]
+    fn test_watch_renders_nodes_as_lines_arrive() {
+        let temp_dir = TempDir::new().unwrap();
+        let log_path = temp_dir.path().join("events.log");
+        let output_dir = temp_dir.path().join("output");
+        fs::create_dir_all(&output_dir).unwrap();
+        let args = test_args(log_path.to_str().unwrap(), &output_dir, OutputFormat::Json);
+
+        let logger = create_test_logger();
+        let sh = Shell::new().unwrap();
+        let mut tail = LogTail::new(&log_path);
+        let mut watched = WatchedGraph::new(Refinement::identity());
+        let mut step = || {
+            watch_step(
+                &mut tail,
+                &mut watched,
+                &output_dir,
+                &args,
+                None,
+                &logger,
+                &sh,
+            )
+        };
+
+        step().unwrap();
+        assert!(!output_dir.join("node_1.json").exists());
+
+        fs::write(
+            &log_path,
+            "[2025-11-10 19:56:55.000001][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 1 / 1] FunctionExecute 1 @ FunctionName boot @ state Follower\n",
+        )
+        .unwrap();
+        step().unwrap();
+        let node: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(output_dir.join("node_1.json")).unwrap())
+                .unwrap();
+        assert_eq!(node["states"], serde_json::json!(["Follower"]));
+
+        fs::write(
+            &log_path,
+            "[2025-11-10 19:56:56.000001][INFO] [FUNC_EVENT_TYPE][Node 2 Batch 1 Entry 1 / 1] FunctionExecute 1 @ FunctionName boot @ state Leader\n",
+        )
+        .unwrap();
+        step().unwrap();
+        assert!(output_dir.join("node_2.json").exists());
+        let node: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(output_dir.join("node_1.json")).unwrap())
+                .unwrap();
+        assert_eq!(node["states"], serde_json::json!(["Follower"]));
+    }
+
+    #[test]
+    fn test_watch_rejects_whole_log_reports() {
+        for option in [
+            &["--split-by", "schedule"][..],
+            &["--timeline"],
+            &["--max-dwell", "Candidate=2s"],
+        ] {
+            let argv = [&["export-graphs", "--watch"][..], option].concat();
+            assert!(
+                ExportGraphsArgs::try_parse_from(argv).is_err(),
+                "{:?}",
+                option
+            );
+        }
+        assert!(ExportGraphsArgs::try_parse_from(["export-graphs", "--watch"]).is_ok());
+    }
+
+    #[test]
+    fn test_export_deterministic_log_writes_no_report() {
+        let temp_dir = TempDir::new().unwrap();
+        let output_dir = temp_dir.path().join("output");
+
+        let args = test_args(
+            "tests/export_graphs_test/simple_events.log",
+            &output_dir,
+            OutputFormat::Dot,
+        );
+
+        let logger = create_test_logger();
+        let sh = Shell::new().unwrap();
+
+        run(args, &logger, &sh).unwrap();
+
+        assert!(!output_dir.join("nondeterminism.json").exists());
+    }
+
+    #[test]
+    fn test_export_promela_format() {
+        let temp_dir = TempDir::new().unwrap();
+        let output_dir = temp_dir.path().join("output");
+
+        let args = test_args(
+            "tests/export_graphs_test/multi_node_events.log",
+            &output_dir,
+            OutputFormat::Promela,
+        );
+
+        let logger = create_test_logger();
+        let sh = Shell::new().unwrap();
+
+        run(args, &logger, &sh).unwrap();
+
+        assert!(output_dir.join("node_1.pml").exists());
+        assert!(output_dir.join("node_3.pml").exists());
+        assert!(!output_dir.join("node_1.dot").exists());
+
+        let system = fs::read_to_string(output_dir.join("system.pml")).unwrap();
+        assert!(system.contains("active proctype node_1() {"));
+        assert!(system.contains("active proctype node_2() {"));
+        assert!(system.contains("active proctype node_3() {"));
+    }
+
+    #[test]
+    fn test_export_tla_format() {
+        let temp_dir = TempDir::new().unwrap();
+        let output_dir = temp_dir.path().join("output");
+
+        let args = test_args(
+            "tests/export_graphs_test/multi_node_events.log",
+            &output_dir,
+            OutputFormat::Tla,
+        );
+
+        let logger = create_test_logger();
+        let sh = Shell::new().unwrap();
+
+        run(args, &logger, &sh).unwrap();
+
+        let node = fs::read_to_string(output_dir.join("node_2.tla")).unwrap();
+        assert!(node.starts_with("---- MODULE node_2 ----"));
+
+        let system = fs::read_to_string(output_dir.join("system.tla")).unwrap();
+        assert!(system.starts_with("---- MODULE system ----"));
+        assert!(system.contains("node_3_state"));
+    }
 }
@@ -1,7 +1,8 @@
+use crate::model::compile_db::{CompilationDatabase, PathFilter, normalize};
 use crate::{logger::Logger, model::instrumentor::Instrumentor};
 use anyhow::{Context, Result};
-use clap::Parser;
-use std::path::Path;
+use clap::{Args, Parser};
+use std::path::{Path, PathBuf};
 use std::{fs, path};
 
 #[derive(Parser, Debug)]
@@ -10,8 +11,39 @@ pub struct GetTargetsArgs {
     pub path: String,
     #[arg(short, long)]
     pub output: String,
+    #[command(flatten)]
+    pub sources: SourceOptions,
 }
 
+/// Which files are scanned for annotations. Shared by `get-targets` and
+/// `instrument`.
+#[derive(Args, Debug, Clone, Default)]
+pub struct SourceOptions {
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Only scan the files in this compile_commands.json and the headers they include"
+    )]
+    pub compile_commands: Option<String>,
+    #[arg(
+        long,
+        value_name = "GLOB",
+        help = "Only scan files matching this glob, relative to --path (repeatable)"
+    )]
+    pub include: Vec<String>,
+    #[arg(
+        long,
+        value_name = "GLOB",
+        help = "Skip files matching this glob, relative to --path (repeatable)"
+    )]
+    pub exclude: Vec<String>,
+}
+
+const ANNOTATION_PREFIX: &str = "ABSTRAKTOR_";
+
+/// Extensions of the files an annotation may be in, headers included.
+const ANNOTATED_EXTENSIONS: [&str; 8] = ["c", "cc", "cpp", "cxx", "h", "hh", "hpp", "hxx"];
+
 fn get_files_content(path: &str) -> Vec<(String, String)> {
     let mut files = Vec::new();
     let path = path::absolute(Path::new(path)).unwrap();
@@ -26,12 +58,11 @@ fn get_files_content(path: &str) -> Vec<(String, String)> {
                 for entry in entries.flatten() {
                     let path = entry.path();
                     if path.is_file() {
-                        if let Some(ext) = path.extension() {
-                            if ext == "c" || ext == "cpp" {
-                                if let Ok(content) = fs::read_to_string(&path) {
-                                    files.push((content, path.to_string_lossy().into_owned()));
-                                }
-                            }
+                        if let Some(ext) = path.extension()
+                            && (ext == "c" || ext == "cpp")
+                            && let Ok(content) = fs::read_to_string(&path)
+                        {
+                            files.push((content, path.to_string_lossy().into_owned()));
                         }
                     } else if path.is_dir() {
                         visit_dirs(&path, files);
@@ -45,19 +76,120 @@ fn get_files_content(path: &str) -> Vec<(String, String)> {
     files
 }
 
+fn read_files(paths: impl IntoIterator<Item = PathBuf>) -> Vec<(String, String)> {
+    paths
+        .into_iter()
+        .filter_map(|path| {
+            let content = fs::read_to_string(&path).ok()?;
+            Some((content, path.to_string_lossy().into_owned()))
+        })
+        .collect()
+}
+
+/// Files below `root` with an annotation in them.
+fn annotated_files(root: &Path) -> Vec<PathBuf> {
+    fn visit_dirs(dir: &Path, files: &mut Vec<PathBuf>) {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                visit_dirs(&path, files);
+            } else if path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| ANNOTATED_EXTENSIONS.contains(&ext))
+                && fs::read_to_string(&path).is_ok_and(|c| c.contains(ANNOTATION_PREFIX))
+            {
+                files.push(path);
+            }
+        }
+    }
+    let mut files = Vec::new();
+    visit_dirs(root, &mut files);
+    files.sort();
+    files
+}
+
+/// The build's files below `root` that pass `filter`. Annotated files the
+/// build does not use are reported, since their events will never fire.
+fn build_files_content(
+    database: &Path,
+    root: &Path,
+    filter: &PathFilter,
+    logger: &Logger,
+) -> Result<Vec<(String, String)>> {
+    let database = CompilationDatabase::from_file(database)?;
+    logger.debug(format!(
+        "Compilation database lists {} commands",
+        database.commands.len()
+    ));
+    let root = normalize(root);
+    let files = database.build_files();
+    for annotated in annotated_files(&root) {
+        let relative = annotated.strip_prefix(&root).unwrap_or(&annotated);
+        if filter.matches(relative) && !files.contains(&annotated) {
+            logger.warning(format!(
+                "{} is annotated but not part of the build",
+                annotated.display()
+            ));
+        }
+    }
+    Ok(read_files(files.into_iter().filter(|file| {
+        file.strip_prefix(&root)
+            .is_ok_and(|relative| filter.matches(relative))
+    })))
+}
+
 pub fn run(args: GetTargetsArgs, logger: &Logger) -> Result<()> {
     logger.log(format!("Getting targets from {}", args.path));
 
-    let files = get_files_content(&args.path);
+    let root = path::absolute(Path::new(&args.path)).context("Failed to absolutize path")?;
+    let root = if root.is_file() {
+        root.parent().map(Path::to_path_buf).unwrap_or(root)
+    } else {
+        root
+    };
+    let filter = PathFilter::new(&args.sources.include, &args.sources.exclude)?;
+
+    // A build directory's database only exists once the SUT was configured,
+    // which on a fresh SUT comes after the first scan.
+    let database = args.sources.compile_commands.as_deref().filter(|database| {
+        let exists = Path::new(database).exists();
+        if !exists {
+            logger.warning(format!(
+                "{} does not exist yet, scanning every file under {}",
+                database, args.path
+            ));
+        }
+        exists
+    });
+    let files = match database {
+        Some(database) => build_files_content(Path::new(database), &root, &filter, logger)?,
+        None => get_files_content(&args.path)
+            .into_iter()
+            .filter(|(_, file)| {
+                let file = Path::new(file);
+                filter.matches(file.strip_prefix(&root).unwrap_or(file))
+            })
+            .collect(),
+    };
     logger.debug(format!("Found {} C/C++ files to analyze", files.len()));
 
     if files.is_empty() {
         logger.warning("No C/C++ files found in the specified path");
     }
 
-    let instrumentor = Instrumentor::new();
+    let instrumentor = Instrumentor::new().with_root(root);
     logger.debug("Analyzing files for instrumentation targets");
-    let targets = instrumentor.get_targets(files);
+    let (targets, collisions) = instrumentor.get_targets(files);
+    for collision in &collisions {
+        logger.warning(format!(
+            "Event id {} of {} is already taken by {}, using {} instead",
+            collision.id, collision.moved, collision.kept, collision.assigned
+        ));
+    }
     logger.debug(format!(
         "Identified {} instrumentation targets",
         targets.len()
@@ -82,20 +214,17 @@ mod tests {
     fn normalize_and_sort(mut value: serde_json::Value, base_dir: &Path) -> serde_json::Value {
         if let Some(array) = value.as_array_mut() {
             for item in array.iter_mut() {
-                if let Some(obj) = item.as_object_mut() {
-                    if let Some(path_val) = obj.get_mut("path") {
-                        if let Some(p) = path_val.as_str() {
-                            let path = Path::new(p);
-                            let rel = if path.is_absolute() {
-                                pathdiff::diff_paths(path, base_dir)
-                                    .unwrap_or_else(|| path.to_path_buf())
-                            } else {
-                                path.to_path_buf()
-                            };
-                            *path_val =
-                                serde_json::Value::String(rel.to_string_lossy().into_owned());
-                        }
-                    }
+                if let Some(obj) = item.as_object_mut()
+                    && let Some(path_val) = obj.get_mut("path")
+                    && let Some(p) = path_val.as_str()
+                {
+                    let path = Path::new(p);
+                    let rel = if path.is_absolute() {
+                        pathdiff::diff_paths(path, base_dir).unwrap_or_else(|| path.to_path_buf())
+                    } else {
+                        path.to_path_buf()
+                    };
+                    *path_val = serde_json::Value::String(rel.to_string_lossy().into_owned());
                 }
             }
 
@@ -123,6 +252,7 @@ mod tests {
         let args = GetTargetsArgs {
             path: test_dir.to_string_lossy().into_owned(),
             output: output_file.to_string_lossy().into_owned(),
+            sources: SourceOptions::default(),
         };
         let _ = run(args, &logger);
 
@@ -140,4 +270,82 @@ mod tests {
 
         fs::remove_file(&output_file).unwrap();
     }
+
+    #[test]
+    fn test_get_targets_from_compile_commands() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join("tools")).unwrap();
+        let annotated = "// ABSTRAKTOR_FUNC: state END\nint step(void) {\n  return 0;\n}\n";
+        fs::write(root.join("src/raft.c"), "#include \"raft.h\"\n").unwrap();
+        fs::write(root.join("src/raft.h"), annotated).unwrap();
+        fs::write(root.join("src/log.c"), annotated).unwrap();
+        fs::write(root.join("tools/bench.c"), annotated).unwrap();
+        let database = root.join("compile_commands.json");
+        fs::write(
+            &database,
+            serde_json::json!([
+                {"directory": root, "file": "src/raft.c", "command": "cc -c src/raft.c"},
+                {"directory": root, "file": "src/log.c", "command": "cc -c src/log.c"},
+            ])
+            .to_string(),
+        )
+        .unwrap();
+
+        let output = root.join("targets.json");
+        let args = GetTargetsArgs {
+            path: root.to_string_lossy().into_owned(),
+            output: output.to_string_lossy().into_owned(),
+            sources: SourceOptions {
+                compile_commands: Some(database.to_string_lossy().into_owned()),
+                include: Vec::new(),
+                exclude: vec!["**/log.c".to_string()],
+            },
+        };
+        run(args, &Logger::new(LogLevel::Quiet)).unwrap();
+
+        let targets: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&output).unwrap()).unwrap();
+        let mut paths: Vec<_> = targets
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|target| target["path"].as_str().unwrap().to_string())
+            .collect();
+        paths.sort();
+        let expected: Vec<_> = ["src/raft.c", "src/raft.h"]
+            .iter()
+            .map(|file| root.join(file).to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(paths, expected);
+        assert_eq!(
+            annotated_files(root),
+            [
+                root.join("src/log.c"),
+                root.join("src/raft.h"),
+                root.join("tools/bench.c")
+            ]
+        );
+
+        // Before the first build there is no database yet.
+        let args = GetTargetsArgs {
+            path: root.to_string_lossy().into_owned(),
+            output: output.to_string_lossy().into_owned(),
+            sources: SourceOptions {
+                compile_commands: Some(
+                    root.join("build/compile_commands.json")
+                        .display()
+                        .to_string(),
+                ),
+                include: Vec::new(),
+                exclude: vec!["**/log.c".to_string()],
+            },
+        };
+        run(args, &Logger::new(LogLevel::Quiet)).unwrap();
+        let targets: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&output).unwrap()).unwrap();
+        assert_eq!(targets.as_array().unwrap().len(), 2);
+        assert!(targets.to_string().contains("bench.c"));
+    }
 }
@@ -5,6 +5,8 @@ use anyhow::{Context, Result};
 use clap::Parser;
 use xshell::Shell;
 
+use super::get_targets::SourceOptions;
+use super::llvm::BuildOptions;
 use super::{LlvmArgs, get_targets, llvm};
 
 const TEMP_TARGETS_PATH: &str = "temp_targets.json";
@@ -16,6 +18,21 @@ pub struct InstrumentArgs {
 
     #[arg(short, long)]
     llvm_path: Option<String>,
+
+    #[arg(short, long)]
+    manifest_path: Option<String>,
+
+    #[arg(long)]
+    report_path: Option<String>,
+
+    #[arg(long)]
+    allow_uninstrumented: bool,
+
+    #[command(flatten)]
+    sources: SourceOptions,
+
+    #[command(flatten)]
+    build: BuildOptions,
 }
 
 pub fn run(args: InstrumentArgs, logger: &Logger, sh: &Shell) -> Result<()> {
@@ -44,6 +61,7 @@ fn run_with_temp_path(
     let get_targets_args = GetTargetsArgs {
         path: args.path.clone(),
         output: temp_targets_path_str.clone(),
+        sources: args.sources,
     };
 
     logger.log("Step 1/2: Analyzing source code for targets");
@@ -54,6 +72,10 @@ fn run_with_temp_path(
         path: args.path.clone(),
         targets_path: temp_targets_path_str.clone(),
         llvm_path: args.llvm_path,
+        manifest_path: args.manifest_path,
+        report_path: args.report_path,
+        allow_uninstrumented: args.allow_uninstrumented,
+        build: args.build,
     };
     llvm::run(llvm_args, logger, sh)?;
 
@@ -76,6 +98,11 @@ mod tests {
         let args = InstrumentArgs {
             path: "/test/path".to_string(),
             llvm_path: Some("/custom/llvm".to_string()),
+            manifest_path: None,
+            report_path: None,
+            allow_uninstrumented: false,
+            sources: SourceOptions::default(),
+            build: BuildOptions::default(),
         };
         assert_eq!(args.path, "/test/path");
         assert_eq!(args.llvm_path, Some("/custom/llvm".to_string()));
@@ -86,6 +113,11 @@ mod tests {
         let args = InstrumentArgs {
             path: "/test/path".to_string(),
             llvm_path: None,
+            manifest_path: None,
+            report_path: None,
+            allow_uninstrumented: false,
+            sources: SourceOptions::default(),
+            build: BuildOptions::default(),
         };
         assert_eq!(args.path, "/test/path");
         assert!(args.llvm_path.is_none());
@@ -105,6 +137,7 @@ mod tests {
         let get_targets_args = GetTargetsArgs {
             path: test_dir.to_string_lossy().into_owned(),
             output: temp_targets.to_string_lossy().into_owned(),
+            sources: SourceOptions::default(),
         };
 
         let result = get_targets::run(get_targets_args, &logger);
@@ -139,6 +172,11 @@ mod tests {
         let args = InstrumentArgs {
             path: test_dir.to_string_lossy().into_owned(),
             llvm_path: Some("/fake/llvm/path".to_string()),
+            manifest_path: None,
+            report_path: None,
+            allow_uninstrumented: false,
+            sources: SourceOptions::default(),
+            build: BuildOptions::default(),
         };
 
         let result =
@@ -167,6 +205,7 @@ mod tests {
         let get_targets_args = GetTargetsArgs {
             path: test_dir.to_string_lossy().into_owned(),
             output: temp_targets.to_string_lossy().into_owned(),
+            sources: SourceOptions::default(),
         };
 
         let result = get_targets::run(get_targets_args, &logger);
@@ -194,6 +233,7 @@ mod tests {
         let get_targets_args = GetTargetsArgs {
             path: test_dir.to_string_lossy().into_owned(),
             output: temp_targets.to_string_lossy().into_owned(),
+            sources: SourceOptions::default(),
         };
 
         get_targets::run(get_targets_args, &logger).unwrap();
@@ -217,6 +257,11 @@ mod tests {
             let args = InstrumentArgs {
                 path: path.to_string(),
                 llvm_path: None,
+                manifest_path: None,
+                report_path: None,
+                allow_uninstrumented: false,
+                sources: SourceOptions::default(),
+                build: BuildOptions::default(),
             };
             assert_eq!(args.path, path);
         }
@@ -227,12 +272,22 @@ mod tests {
         let with_custom = InstrumentArgs {
             path: "./test".to_string(),
             llvm_path: Some("/custom/llvm".to_string()),
+            manifest_path: None,
+            report_path: None,
+            allow_uninstrumented: false,
+            sources: SourceOptions::default(),
+            build: BuildOptions::default(),
         };
         assert!(with_custom.llvm_path.is_some());
 
         let with_default = InstrumentArgs {
             path: "./test".to_string(),
             llvm_path: None,
+            manifest_path: None,
+            report_path: None,
+            allow_uninstrumented: false,
+            sources: SourceOptions::default(),
+            build: BuildOptions::default(),
         };
         assert!(with_default.llvm_path.is_none());
     }
@@ -1,13 +1,238 @@
-use std::path::{self, Path};
+use std::path::{self, Path, PathBuf};
+use std::time::SystemTime;
 
 use crate::logger::Logger;
+use crate::model::build_system::{BuildRecord, BuildSystem, build_plan, find_artifacts};
+use crate::model::doctor::llvm_major_version;
+use crate::model::instrumentation_report::{
+    AnnotationStatus, format_table, instrumentation_report, read_pass_records,
+};
+use crate::model::instrumentor::InstrumentationTargets;
+use crate::model::toolchain::{SUPPORTED_LLVM, Toolchain, cxx_for, detect_toolchains, select_pass};
 use anyhow::{Context, Result};
-use clap::Parser;
+use clap::{Args, Parser, ValueEnum};
 use xshell::Shell;
 
-const LLVM_INSTRUMENTOR_PATH: &str = "./llvm/afl-clang-fast";
+pub const LLVM_INSTRUMENTOR_PATH: &str = "./llvm/afl-clang-fast";
 
-const AFL_CC: &str = "clang-11";
+pub const DEFAULT_MANIFEST_PATH: &str = "abstraktor-manifest.jsonl";
+
+pub const DEFAULT_REPORT_PATH: &str = "abstraktor-instrumentation.jsonl";
+
+pub const BUILD_COMMAND: &str = "./install.sh";
+
+pub const DEFAULT_BUILD_RECORD_PATH: &str = "abstraktor-build.json";
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, ValueEnum)]
+pub enum BuildSystemArg {
+    /// Picked from the files in the SUT directory.
+    #[default]
+    Auto,
+    /// `./install.sh`
+    Script,
+    Make,
+    Cmake,
+    Autotools,
+    Meson,
+}
+
+/// How the SUT is built with the instrumenting compiler. Shared by `llvm`
+/// and `instrument`.
+#[derive(Args, Debug, Clone, Default)]
+pub struct BuildOptions {
+    #[arg(
+        long,
+        help = "Command that builds the SUT with the instrumenting compiler (overrides --build-system)"
+    )]
+    pub build_command: Option<String>,
+    #[arg(long, value_enum, default_value = "auto")]
+    pub build_system: BuildSystemArg,
+    #[arg(
+        long,
+        help = "Out-of-tree build directory for CMake, Meson and autotools"
+    )]
+    pub build_dir: Option<String>,
+    #[arg(
+        long,
+        allow_hyphen_values = true,
+        help = "Extra flags for CFLAGS and CXXFLAGS"
+    )]
+    pub cflags: Option<String>,
+    #[arg(
+        long,
+        help = "Where to record the instrumented artifacts (default: abstraktor-build.json)"
+    )]
+    pub build_record: Option<String>,
+    #[arg(
+        long,
+        help = "Clang the instrumentor compiles with (default: the newest clang with a built pass)"
+    )]
+    pub cc: Option<String>,
+    #[arg(long, help = "C++ driver to go with --cc (default: derived from --cc)")]
+    pub cxx: Option<String>,
+    #[arg(
+        long,
+        value_name = "MAJOR",
+        help = "LLVM release to use, e.g. 17 for clang-17 (default: detected)"
+    )]
+    pub llvm_version: Option<u32>,
+}
+
+impl BuildOptions {
+    pub fn build_system(&self, source: &Path) -> Result<BuildSystem> {
+        if let Some(command) = &self.build_command {
+            return Ok(BuildSystem::Custom(command.clone()));
+        }
+        let system = match self.build_system {
+            BuildSystemArg::Auto => BuildSystem::detect(source).with_context(|| {
+                format!(
+                    "could not tell how to build {}: no install.sh, meson.build, CMakeLists.txt, configure or Makefile; pass --build-system or --build-command",
+                    source.display()
+                )
+            })?,
+            BuildSystemArg::Script => BuildSystem::Script,
+            BuildSystemArg::Make => BuildSystem::Make,
+            BuildSystemArg::Cmake => BuildSystem::CMake,
+            BuildSystemArg::Autotools => BuildSystem::Autotools,
+            BuildSystemArg::Meson => BuildSystem::Meson,
+        };
+        Ok(system)
+    }
+
+    /// The clang `--cc` or `--llvm-version` names, or else every clang in
+    /// `PATH`, newest first.
+    pub fn toolchains(&self, sh: &Shell) -> Result<Vec<Toolchain>> {
+        let toolchains = match (&self.cc, self.llvm_version) {
+            (Some(cc), version) => {
+                let major = version
+                    .or_else(|| llvm_version_of(cc, sh))
+                    .with_context(|| {
+                        format!("could not tell which LLVM {} is; pass --llvm-version", cc)
+                    })?;
+                vec![Toolchain {
+                    cc: cc.clone(),
+                    cxx: self.cxx.clone().unwrap_or_else(|| cxx_for(cc)),
+                    major,
+                }]
+            }
+            (None, Some(major)) => {
+                let mut toolchain = Toolchain::versioned(major);
+                if let Some(cxx) = &self.cxx {
+                    toolchain.cxx = cxx.clone();
+                }
+                vec![toolchain]
+            }
+            (None, None) => detect_toolchains(|tool| llvm_version_of(tool, sh)),
+        };
+        Ok(toolchains)
+    }
+
+    /// The clang to compile with and the pass built for it. Without `--cc`
+    /// or `--llvm-version`, the newest supported clang in `PATH` that has a
+    /// pass in `llvm_dir` wins.
+    pub fn toolchain(&self, llvm_dir: &Path, sh: &Shell) -> Result<(Toolchain, PathBuf)> {
+        let candidates = self.toolchains(sh)?;
+        if candidates.is_empty() {
+            anyhow::bail!(
+                "no clang found in PATH; install clang {} to {} or pass --cc",
+                SUPPORTED_LLVM.start(),
+                SUPPORTED_LLVM.end()
+            );
+        }
+        let supported: Vec<_> = candidates.iter().filter(|t| t.supported()).collect();
+        if supported.is_empty() {
+            anyhow::bail!(
+                "{} is LLVM {}, but the pass supports LLVM {} to {}; pass --cc or --llvm-version",
+                candidates[0].cc,
+                candidates[0].major,
+                SUPPORTED_LLVM.start(),
+                SUPPORTED_LLVM.end()
+            );
+        }
+        let fallback = llvm_version_of("llvm-config", sh);
+        for toolchain in &supported {
+            if let Some(pass) = select_pass(llvm_dir, toolchain.major, fallback) {
+                return Ok(((*toolchain).clone(), pass));
+            }
+        }
+        anyhow::bail!(
+            "no LLVM pass in {} is built for {}; build one with make -C {} pass LLVM_CONFIG=llvm-config-{}",
+            llvm_dir.display(),
+            supported
+                .iter()
+                .map(|t| format!("{} (LLVM {})", t.cc, t.major))
+                .collect::<Vec<_>>()
+                .join(", "),
+            llvm_dir.display(),
+            supported[0].major
+        )
+    }
+}
+
+/// The LLVM major version `tool --version` reports, if it runs.
+pub fn llvm_version_of(tool: &str, sh: &Shell) -> Option<u32> {
+    let output = sh
+        .cmd(tool)
+        .arg("--version")
+        .quiet()
+        .ignore_stderr()
+        .read()
+        .ok()?;
+    llvm_major_version(&output)
+}
+
+/// Variables the build runs with. Build systems pick the compilers up from
+/// `CC` and `CXX`; the instrumentor hands the code to `AFL_CC`/`AFL_CXX`,
+/// loads `ABSTRAKTOR_PASS` the way `ABSTRAKTOR_LLVM_MAJOR` calls for and
+/// reads the rest.
+pub fn instrumentation_env(
+    instrumentor: &Path,
+    toolchain: &Toolchain,
+    pass: &Path,
+    targets: &Path,
+    manifest: &Path,
+    report: &Path,
+    cflags: Option<&str>,
+) -> Vec<(String, String)> {
+    let mut env = vec![("CC".to_string(), instrumentor.display().to_string())];
+    // afl-clang-fast switches to C++ when called as afl-clang-fast++.
+    let cxx = instrumentor.with_file_name(format!(
+        "{}++",
+        instrumentor
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default()
+    ));
+    if cxx.exists() {
+        env.push(("CXX".to_string(), cxx.display().to_string()));
+    }
+    env.push(("AFL_CC".to_string(), toolchain.cc.clone()));
+    env.push(("AFL_CXX".to_string(), toolchain.cxx.clone()));
+    env.push(("ABSTRAKTOR_PASS".to_string(), pass.display().to_string()));
+    env.push((
+        "ABSTRAKTOR_LLVM_MAJOR".to_string(),
+        toolchain.major.to_string(),
+    ));
+    env.push(("TARGETS_FILE".to_string(), targets.display().to_string()));
+    env.push((
+        "ABSTRAKTOR_MANIFEST_FILE".to_string(),
+        manifest.display().to_string(),
+    ));
+    env.push((
+        "ABSTRAKTOR_REPORT_FILE".to_string(),
+        report.display().to_string(),
+    ));
+    if let Some(cflags) = cflags {
+        for name in ["CFLAGS", "CXXFLAGS"] {
+            let value = match std::env::var(name) {
+                Ok(existing) if !existing.is_empty() => format!("{} {}", existing, cflags),
+                _ => cflags.to_string(),
+            };
+            env.push((name.to_string(), value));
+        }
+    }
+    env
+}
 
 #[derive(Parser, Debug)]
 pub struct LlvmArgs {
@@ -17,6 +242,17 @@ pub struct LlvmArgs {
     pub targets_path: String,
     #[arg(short, long)]
     pub llvm_path: Option<String>,
+    #[arg(short, long, help = "Where to write the event id manifest")]
+    pub manifest_path: Option<String>,
+    #[arg(
+        long,
+        help = "Where the LLVM pass reports what it did with each annotation"
+    )]
+    pub report_path: Option<String>,
+    #[arg(long, help = "Succeed even when annotations were not instrumented")]
+    pub allow_uninstrumented: bool,
+    #[command(flatten)]
+    pub build: BuildOptions,
 }
 
 pub fn run(args: LlvmArgs, logger: &Logger, sh: &Shell) -> Result<()> {
@@ -35,6 +271,16 @@ pub fn run(args: LlvmArgs, logger: &Logger, sh: &Shell) -> Result<()> {
     let path = Path::new(&args.path);
     let targets_path = path::absolute(Path::new(&args.targets_path))
         .context("Failed to absolutize targets path")?;
+    let manifest_path = path::absolute(Path::new(
+        args.manifest_path
+            .as_deref()
+            .unwrap_or(DEFAULT_MANIFEST_PATH),
+    ))
+    .context("Failed to absolutize manifest path")?;
+    let report_path = path::absolute(Path::new(
+        args.report_path.as_deref().unwrap_or(DEFAULT_REPORT_PATH),
+    ))
+    .context("Failed to absolutize report path")?;
 
     logger.debug(format!(
         "Instrumentor path: {}",
@@ -71,32 +317,179 @@ pub fn run(args: LlvmArgs, logger: &Logger, sh: &Shell) -> Result<()> {
         ));
     }
 
+    // The pass is built next to the instrumentor.
+    let llvm_dir = instrumentor_path.parent().unwrap_or(Path::new("."));
+    let (toolchain, pass) = args.build.toolchain(llvm_dir, sh)?;
+    logger.log(format!(
+        "Compiling with {} (LLVM {}) and {}",
+        toolchain.cc,
+        toolchain.major,
+        pass.display()
+    ));
+
+    // Entries for annotations removed since the last build would be misleading.
+    std::fs::write(&manifest_path, "")
+        .with_context(|| format!("Failed to reset manifest at {}", manifest_path.display()))?;
+    // The pass appends to the report from every compiler invocation.
+    std::fs::write(&report_path, "")
+        .with_context(|| format!("Failed to reset report at {}", report_path.display()))?;
+
+    let source = path::absolute(path).context("Failed to absolutize source path")?;
+    let system = args.build.build_system(&source)?;
+    let build_dir = args
+        .build
+        .build_dir
+        .as_deref()
+        .map(|dir| path::absolute(Path::new(dir)))
+        .transpose()
+        .context("Failed to absolutize build directory")?;
+    let plan = build_plan(&system, &source, build_dir.as_deref())?;
+    logger.log(format!(
+        "Building {} with {}",
+        source.display(),
+        system.name()
+    ));
+
     logger.log("Setting up environment variables for instrumentation");
-    let _dir = sh.push_dir(path);
-    let _cc = sh.push_env("CC", instrumentor_path.to_str().unwrap());
-    let _targets = sh.push_env("TARGETS_FILE", targets_path.to_str().unwrap());
-    let _afl_cc = sh.push_env("AFL_CC", AFL_CC);
-
-    logger.debug(format!("CC={}", instrumentor_path.display()));
-    logger.debug(format!("TARGETS_FILE={}", targets_path.display()));
-    logger.debug(format!("AFL_CC={}", AFL_CC));
-
-    logger.log("Running install.sh script");
-    sh.cmd("sh")
-        .arg("-c")
-        .arg("./install.sh")
-        .run()
-        .context("Failed to run instrumentor")?;
+    let env = instrumentation_env(
+        &instrumentor_path,
+        &toolchain,
+        &pass,
+        &targets_path,
+        &manifest_path,
+        &report_path,
+        args.build.cflags.as_deref(),
+    );
+    let mut _env = Vec::new();
+    for (name, value) in &env {
+        logger.debug(format!("{}={}", name, value));
+        _env.push(sh.push_env(name, value));
+    }
+
+    let started = SystemTime::now();
+    std::fs::create_dir_all(&plan.build_dir).with_context(|| {
+        format!(
+            "Failed to create build directory {}",
+            plan.build_dir.display()
+        )
+    })?;
+    for step in &plan.steps {
+        let _dir = sh.push_dir(&step.dir);
+        logger.log(format!("Running {}", step.command));
+        sh.cmd("sh")
+            .arg("-c")
+            .arg(&step.command)
+            .run()
+            .with_context(|| format!("Failed to run instrumentor build step {}", step.command))?;
+    }
+
+    let artifacts = find_artifacts(&plan.build_dir, started)?;
+    let record_path = PathBuf::from(
+        args.build
+            .build_record
+            .as_deref()
+            .unwrap_or(DEFAULT_BUILD_RECORD_PATH),
+    );
+    let record = BuildRecord {
+        build_system: system.name().to_string(),
+        source_dir: source.display().to_string(),
+        build_dir: plan.build_dir.display().to_string(),
+        steps: plan.steps,
+        env,
+        artifacts,
+    };
+    record.save(&record_path)?;
+    if record.artifacts.is_empty() {
+        logger.warning("The build wrote no executables or libraries");
+    }
+    logger.log(format!(
+        "Recorded {} instrumented artifact(s) in {}",
+        record.artifacts.len(),
+        record_path.display()
+    ));
+
+    report_annotations(
+        &targets_path,
+        &report_path,
+        &source,
+        system.rebuilds_everything(),
+        args.allow_uninstrumented,
+        logger,
+    )?;
 
     logger.success("Binary instrumented successfully");
     Ok(())
 }
 
+/// Prints what the pass did with each annotation and fails when any of them
+/// will never emit an event.
+fn report_annotations(
+    targets_path: &Path,
+    report_path: &Path,
+    source: &Path,
+    clean_build: bool,
+    allow_uninstrumented: bool,
+    logger: &Logger,
+) -> Result<()> {
+    let content = std::fs::read_to_string(targets_path)
+        .with_context(|| format!("Failed to read targets from {}", targets_path.display()))?;
+    let targets: Vec<InstrumentationTargets> = serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse targets {}", targets_path.display()))?;
+    let records = read_pass_records(report_path)?;
+    let reports = instrumentation_report(&targets, &records, source, clean_build);
+    if reports.is_empty() {
+        return Ok(());
+    }
+
+    for line in format_table(&reports, source) {
+        logger.log(line);
+    }
+    let count = |status| reports.iter().filter(|r| r.status == status).count();
+    logger.log(format!(
+        "{} annotation(s): {} instrumented, {} partial, {} without values, {} not found (details in {})",
+        reports.len(),
+        count(AnnotationStatus::Instrumented),
+        count(AnnotationStatus::Partial),
+        count(AnnotationStatus::NoValues),
+        count(AnnotationStatus::NotFound),
+        report_path.display()
+    ));
+    if count(AnnotationStatus::Unknown) > 0 {
+        logger.warning(format!(
+            "{} annotation(s) are in files this build did not recompile; clean the SUT and build again to check them",
+            count(AnnotationStatus::Unknown)
+        ));
+    }
+    if count(AnnotationStatus::Partial) > 0 {
+        logger.warning(
+            "Some variables or struct fields of instrumented annotations were not captured",
+        );
+    }
+
+    let failed = reports.iter().filter(|r| r.status.failed()).count();
+    if failed > 0 {
+        let message = format!(
+            "{} of {} annotations were not instrumented",
+            failed,
+            reports.len()
+        );
+        if !allow_uninstrumented {
+            anyhow::bail!(
+                "{}; pass --allow-uninstrumented to continue anyway",
+                message
+            );
+        }
+        logger.warning(message);
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::logger::{LogLevel, Logger};
     use std::fs;
+    use std::os::unix::fs::PermissionsExt;
     use tempfile::TempDir;
 
     #[test]
@@ -105,6 +498,10 @@ mod tests {
             path: "/test/path".to_string(),
             targets_path: "/test/targets.json".to_string(),
             llvm_path: Some("/custom/llvm".to_string()),
+            manifest_path: None,
+            report_path: None,
+            allow_uninstrumented: false,
+            build: BuildOptions::default(),
         };
         assert_eq!(args.path, "/test/path");
         assert_eq!(args.targets_path, "/test/targets.json");
@@ -117,6 +514,10 @@ mod tests {
             path: "/test/path".to_string(),
             targets_path: "/test/targets.json".to_string(),
             llvm_path: None,
+            manifest_path: None,
+            report_path: None,
+            allow_uninstrumented: false,
+            build: BuildOptions::default(),
         };
         assert_eq!(args.path, "/test/path");
         assert_eq!(args.targets_path, "/test/targets.json");
@@ -128,9 +529,45 @@ mod tests {
         assert_eq!(LLVM_INSTRUMENTOR_PATH, "./llvm/afl-clang-fast");
     }
 
+    /// A clang that only answers `--version`, and the pass built for it.
+    fn fake_toolchain(dir: &Path) -> String {
+        let cc = dir.join("clang-fake");
+        fs::write(&cc, "#!/bin/sh\necho 'clang version 14.0.6'\n").unwrap();
+        fs::set_permissions(&cc, fs::Permissions::from_mode(0o755)).unwrap();
+        fs::write(dir.join("afl-llvm-pass-14.so"), "").unwrap();
+        cc.to_string_lossy().to_string()
+    }
+
     #[test]
-    fn test_afl_cc_constant() {
-        assert_eq!(AFL_CC, "clang-11");
+    fn test_toolchain_selection() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        let sh = Shell::new().unwrap();
+        let cc = fake_toolchain(dir);
+
+        let options = BuildOptions {
+            cc: Some(cc.clone()),
+            ..BuildOptions::default()
+        };
+        let (toolchain, pass) = options.toolchain(dir, &sh).unwrap();
+        assert_eq!(toolchain.major, 14);
+        assert_eq!(toolchain.cxx, dir.join("clang++-fake").to_string_lossy());
+        assert_eq!(pass, dir.join("afl-llvm-pass-14.so"));
+
+        let newer = BuildOptions {
+            cc: Some(cc),
+            llvm_version: Some(17),
+            ..BuildOptions::default()
+        };
+        let error = newer.toolchain(dir, &sh).unwrap_err().to_string();
+        assert!(error.contains("pass LLVM_CONFIG=llvm-config-17"));
+
+        let unsupported = BuildOptions {
+            llvm_version: Some(9),
+            ..BuildOptions::default()
+        };
+        let error = unsupported.toolchain(dir, &sh).unwrap_err().to_string();
+        assert!(error.contains("clang-9 is LLVM 9"));
     }
 
     #[test]
@@ -139,6 +576,10 @@ mod tests {
             path: "./test".to_string(),
             targets_path: "./targets.json".to_string(),
             llvm_path: None,
+            manifest_path: None,
+            report_path: None,
+            allow_uninstrumented: false,
+            build: BuildOptions::default(),
         };
 
         let llvm_path = args
@@ -154,6 +595,10 @@ mod tests {
             path: "./test".to_string(),
             targets_path: "./targets.json".to_string(),
             llvm_path: Some(custom_path.to_string()),
+            manifest_path: None,
+            report_path: None,
+            allow_uninstrumented: false,
+            build: BuildOptions::default(),
         };
 
         let llvm_path = args
@@ -178,6 +623,10 @@ mod tests {
             path: test_path.to_string_lossy().to_string(),
             targets_path: targets_file.to_string_lossy().to_string(),
             llvm_path: Some("/nonexistent/instrumentor/path".to_string()),
+            manifest_path: None,
+            report_path: None,
+            allow_uninstrumented: false,
+            build: BuildOptions::default(),
         };
 
         let result = run(args, &logger, &sh);
@@ -206,6 +655,10 @@ mod tests {
             path: "/nonexistent/source/path".to_string(),
             targets_path: targets_file.to_string_lossy().to_string(),
             llvm_path: Some(fake_instrumentor.to_string_lossy().to_string()),
+            manifest_path: None,
+            report_path: None,
+            allow_uninstrumented: false,
+            build: BuildOptions::default(),
         };
 
         let result = run(args, &logger, &sh);
@@ -229,6 +682,10 @@ mod tests {
             path: test_path.to_string_lossy().to_string(),
             targets_path: "/nonexistent/targets.json".to_string(),
             llvm_path: Some(fake_instrumentor.to_string_lossy().to_string()),
+            manifest_path: None,
+            report_path: None,
+            allow_uninstrumented: false,
+            build: BuildOptions::default(),
         };
 
         let result = run(args, &logger, &sh);
@@ -255,12 +712,285 @@ mod tests {
                 path: path.to_string(),
                 targets_path: targets_path.to_string(),
                 llvm_path: None,
+                manifest_path: None,
+                report_path: None,
+                allow_uninstrumented: false,
+                build: BuildOptions::default(),
             };
             assert_eq!(args.path, path);
             assert_eq!(args.targets_path, targets_path);
         }
     }
 
+    #[test]
+    fn test_run_records_custom_build() {
+        let temp_dir = TempDir::new().unwrap();
+        let sut = temp_dir.path().join("sut");
+        fs::create_dir(&sut).unwrap();
+        let targets_file = temp_dir.path().join("targets.json");
+        fs::write(&targets_file, "[]").unwrap();
+        let instrumentor = temp_dir.path().join("afl-clang-fast");
+        fs::write(&instrumentor, "#!/bin/sh\n").unwrap();
+        fs::write(temp_dir.path().join("afl-clang-fast++"), "#!/bin/sh\n").unwrap();
+        let cc = fake_toolchain(temp_dir.path());
+        let record_path = temp_dir.path().join("build.json");
+
+        let logger = Logger::new(LogLevel::Quiet);
+        let sh = Shell::new().unwrap();
+        let args = LlvmArgs {
+            path: sut.to_string_lossy().to_string(),
+            targets_path: targets_file.to_string_lossy().to_string(),
+            llvm_path: Some(instrumentor.to_string_lossy().to_string()),
+            manifest_path: Some(
+                temp_dir
+                    .path()
+                    .join("manifest")
+                    .to_string_lossy()
+                    .to_string(),
+            ),
+            report_path: Some(
+                temp_dir
+                    .path()
+                    .join("report.jsonl")
+                    .to_string_lossy()
+                    .to_string(),
+            ),
+            allow_uninstrumented: false,
+            build: BuildOptions {
+                build_command: Some(
+                    "echo \"$CC|$CXX|$CFLAGS|$AFL_CC|$ABSTRAKTOR_PASS|$ABSTRAKTOR_LLVM_MAJOR\" > env.txt && cp /bin/true sut"
+                        .to_string(),
+                ),
+                cflags: Some("-O0 -g".to_string()),
+                build_record: Some(record_path.to_string_lossy().to_string()),
+                cc: Some(cc.clone()),
+                ..BuildOptions::default()
+            },
+        };
+        run(args, &logger, &sh).unwrap();
+
+        let env = fs::read_to_string(sut.join("env.txt")).unwrap();
+        let fields: Vec<_> = env.trim().split('|').collect();
+        assert_eq!(fields[0], instrumentor.to_str().unwrap());
+        assert!(fields[1].ends_with("afl-clang-fast++"));
+        assert!(fields[2].ends_with("-O0 -g"));
+        assert_eq!(fields[3], cc);
+        assert!(fields[4].ends_with("/afl-llvm-pass-14.so"));
+        assert_eq!(fields[5], "14");
+
+        let record = BuildRecord::from_file(&record_path).unwrap();
+        assert_eq!(record.build_system, "custom");
+        assert_eq!(record.artifacts.len(), 1);
+        assert!(record.artifacts[0].path.ends_with("/sut/sut"));
+        assert!(record.stale_artifacts().is_empty());
+    }
+
+    #[test]
+    fn test_run_reports_uninstrumented_annotations() {
+        let temp_dir = TempDir::new().unwrap();
+        let sut = temp_dir.path().join("sut");
+        fs::create_dir(&sut).unwrap();
+        let source = sut.join("raft.c");
+        let targets_file = temp_dir.path().join("targets.json");
+        fs::write(
+            &targets_file,
+            serde_json::json!([{
+                "path": source,
+                "targets_const": {},
+                "targets_block": {},
+                "targets_function": {
+                    "3": {"var_info": [], "group": {"end_mark": true, "id": 7}},
+                    "9": {"var_info": [], "group": {"end_mark": true, "id": 8}},
+                },
+                "group_transition_names": {},
+            }])
+            .to_string(),
+        )
+        .unwrap();
+        let instrumentor = temp_dir.path().join("afl-clang-fast");
+        fs::write(&instrumentor, "#!/bin/sh\n").unwrap();
+        let cc = fake_toolchain(temp_dir.path());
+        let record = serde_json::json!({
+            "file": source, "line": 3, "kind": "function", "status": "instrumented",
+            "id": 7, "ir_function": "raft_tick", "ir_block": 0,
+        });
+
+        let logger = Logger::new(LogLevel::Quiet);
+        let sh = Shell::new().unwrap();
+        let path = |name: &str| Some(temp_dir.path().join(name).to_string_lossy().to_string());
+        let args = |allow_uninstrumented| LlvmArgs {
+            path: sut.to_string_lossy().to_string(),
+            targets_path: targets_file.to_string_lossy().to_string(),
+            llvm_path: Some(instrumentor.to_string_lossy().to_string()),
+            manifest_path: path("manifest.jsonl"),
+            report_path: path("report.jsonl"),
+            allow_uninstrumented,
+            build: BuildOptions {
+                build_command: Some(format!("echo '{}' >> \"$ABSTRAKTOR_REPORT_FILE\"", record)),
+                build_record: path("build.json"),
+                cc: Some(cc.clone()),
+                ..BuildOptions::default()
+            },
+        };
+
+        let error = run(args(false), &logger, &sh).unwrap_err().to_string();
+        assert!(error.contains("1 of 2 annotations were not instrumented"));
+        run(args(true), &logger, &sh).unwrap();
+        let report = fs::read_to_string(temp_dir.path().join("report.jsonl")).unwrap();
+        assert_eq!(report.lines().count(), 1);
+    }
+
+    #[test]
+    fn test_rerun_rebuilds_an_existing_build_dir() {
+        let temp_dir = TempDir::new().unwrap();
+        let sut = temp_dir.path().join("sut");
+        fs::create_dir(&sut).unwrap();
+        let source = sut.join("raft.c");
+        fs::write(&source, "int main(void) { return 0; }\n").unwrap();
+        let record = serde_json::json!({
+            "file": source, "line": 3, "kind": "function", "status": "instrumented",
+            "id": 7, "ir_function": "raft_tick", "ir_block": 0,
+        });
+        // The pass only reports what it compiles, so a build that is already
+        // up to date reports nothing.
+        let makefile = format!(
+            "raft: {}\n\techo '{}' >> \"$$ABSTRAKTOR_REPORT_FILE\" && cp /bin/true raft\nclean:\n\trm -f raft\n",
+            source.display(),
+            record
+        );
+        let configure = sut.join("configure");
+        fs::write(
+            &configure,
+            format!("#!/bin/sh\ncat > Makefile <<'EOF'\n{}EOF\n", makefile),
+        )
+        .unwrap();
+        fs::set_permissions(&configure, fs::Permissions::from_mode(0o755)).unwrap();
+        let targets_file = temp_dir.path().join("targets.json");
+        fs::write(
+            &targets_file,
+            serde_json::json!([{
+                "path": source,
+                "targets_const": {},
+                "targets_block": {},
+                "targets_function": {
+                    "3": {"var_info": [], "group": {"end_mark": true, "id": 7}},
+                },
+                "group_transition_names": {},
+            }])
+            .to_string(),
+        )
+        .unwrap();
+        let instrumentor = temp_dir.path().join("afl-clang-fast");
+        fs::write(&instrumentor, "#!/bin/sh\n").unwrap();
+        let cc = fake_toolchain(temp_dir.path());
+
+        let logger = Logger::new(LogLevel::Quiet);
+        let sh = Shell::new().unwrap();
+        let path = |name: &str| Some(temp_dir.path().join(name).to_string_lossy().to_string());
+        let args = || LlvmArgs {
+            path: sut.to_string_lossy().to_string(),
+            targets_path: targets_file.to_string_lossy().to_string(),
+            llvm_path: Some(instrumentor.to_string_lossy().to_string()),
+            manifest_path: path("manifest.jsonl"),
+            report_path: path("report.jsonl"),
+            allow_uninstrumented: false,
+            build: BuildOptions {
+                build_system: BuildSystemArg::Autotools,
+                build_dir: path("build"),
+                build_record: path("build.json"),
+                cc: Some(cc.clone()),
+                ..BuildOptions::default()
+            },
+        };
+
+        run(args(), &logger, &sh).unwrap();
+        run(args(), &logger, &sh).unwrap();
+        let report = fs::read_to_string(temp_dir.path().join("report.jsonl")).unwrap();
+        assert_eq!(report.lines().count(), 1);
+        let record = BuildRecord::from_file(&temp_dir.path().join("build.json")).unwrap();
+        assert_eq!(record.artifacts.len(), 1);
+    }
+
+    #[test]
+    fn test_rerun_of_an_incremental_build() {
+        let temp_dir = TempDir::new().unwrap();
+        let sut = temp_dir.path().join("sut");
+        fs::create_dir(&sut).unwrap();
+        let source = sut.join("raft.c");
+        let record = serde_json::json!({
+            "file": source, "line": 3, "kind": "function", "status": "instrumented",
+            "id": 7, "ir_function": "raft_tick", "ir_block": 0,
+        });
+        let targets_file = temp_dir.path().join("targets.json");
+        fs::write(
+            &targets_file,
+            serde_json::json!([{
+                "path": source,
+                "targets_const": {},
+                "targets_block": {},
+                "targets_function": {
+                    "3": {"var_info": [], "group": {"end_mark": true, "id": 7}},
+                },
+                "group_transition_names": {},
+            }])
+            .to_string(),
+        )
+        .unwrap();
+        let instrumentor = temp_dir.path().join("afl-clang-fast");
+        fs::write(&instrumentor, "#!/bin/sh\n").unwrap();
+        let cc = fake_toolchain(temp_dir.path());
+
+        let logger = Logger::new(LogLevel::Quiet);
+        let sh = Shell::new().unwrap();
+        let path = |name: &str| Some(temp_dir.path().join(name).to_string_lossy().to_string());
+        let args = |build_system, build_command: Option<String>| LlvmArgs {
+            path: sut.to_string_lossy().to_string(),
+            targets_path: targets_file.to_string_lossy().to_string(),
+            llvm_path: Some(instrumentor.to_string_lossy().to_string()),
+            manifest_path: path("manifest.jsonl"),
+            report_path: path("report.jsonl"),
+            allow_uninstrumented: false,
+            build: BuildOptions {
+                build_system,
+                build_command,
+                build_record: path("build.json"),
+                cc: Some(cc.clone()),
+                ..BuildOptions::default()
+            },
+        };
+        let report = || fs::read_to_string(temp_dir.path().join("report.jsonl")).unwrap();
+
+        // A custom command that skips what is up to date reports nothing the
+        // second time, which leaves the annotation unknown, not missing.
+        let command = format!(
+            "test -f raft.o || {{ echo '{}' >> \"$ABSTRAKTOR_REPORT_FILE\" && touch raft.o; }}",
+            record
+        );
+        run(
+            args(BuildSystemArg::Auto, Some(command.clone())),
+            &logger,
+            &sh,
+        )
+        .unwrap();
+        assert_eq!(report().lines().count(), 1);
+        run(args(BuildSystemArg::Auto, Some(command)), &logger, &sh).unwrap();
+        assert_eq!(report(), "");
+
+        // Plain Make is made to rebuild what it considers up to date.
+        fs::write(
+            sut.join("Makefile"),
+            format!(
+                "raft.o: raft.c\n\techo '{}' >> \"$$ABSTRAKTOR_REPORT_FILE\" && touch raft.o\n",
+                record
+            ),
+        )
+        .unwrap();
+        fs::write(&source, "int x;\n").unwrap();
+        run(args(BuildSystemArg::Make, None), &logger, &sh).unwrap();
+        run(args(BuildSystemArg::Make, None), &logger, &sh).unwrap();
+        assert_eq!(report().lines().count(), 1);
+    }
+
     #[test]
     fn test_validation_order_checks_instrumentor_first() {
         let logger = Logger::new(LogLevel::Quiet);
@@ -270,6 +1000,10 @@ mod tests {
             path: "/nonexistent1".to_string(),
             targets_path: "/nonexistent2".to_string(),
             llvm_path: Some("/nonexistent_instrumentor".to_string()),
+            manifest_path: None,
+            report_path: None,
+            allow_uninstrumented: false,
+            build: BuildOptions::default(),
         };
 
         let result = run(args, &logger, &sh);
@@ -1,4 +1,4 @@
-use clap::{command, Parser, Subcommand};
+use clap::{Parser, Subcommand};
 
 pub mod get_targets;
 pub use get_targets::GetTargetsArgs;
@@ -18,6 +18,30 @@ pub use run::RunArgs;
 pub mod export_graphs;
 pub use export_graphs::ExportGraphsArgs;
 
+pub mod check_spec;
+pub use check_spec::CheckSpecArgs;
+
+pub mod plan_faults;
+pub use plan_faults::PlanFaultsArgs;
+
+pub mod coverage;
+pub use coverage::CoverageArgs;
+
+pub mod report;
+pub use report::ReportArgs;
+
+pub mod pipeline;
+pub use pipeline::PipelineArgs;
+
+pub mod experiment;
+pub use experiment::ExperimentArgs;
+
+pub mod runs;
+pub use runs::RunsArgs;
+
+pub mod doctor;
+pub use doctor::DoctorArgs;
+
 #[derive(Parser, Debug)]
 #[command(
     name = "abstraktor",
@@ -47,4 +71,12 @@ pub enum AbstraktorSubcommand {
     Setup(SetupArgs),
     Run(RunArgs),
     ExportGraphs(ExportGraphsArgs),
+    CheckSpec(CheckSpecArgs),
+    PlanFaults(PlanFaultsArgs),
+    Coverage(CoverageArgs),
+    Report(ReportArgs),
+    Pipeline(PipelineArgs),
+    Experiment(ExperimentArgs),
+    Runs(RunsArgs),
+    Doctor(DoctorArgs),
 }
@@ -0,0 +1,495 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use clap::Parser;
+use xshell::Shell;
+
+use crate::logger::Logger;
+use crate::model::build_system::DEFAULT_BUILD_DIR;
+use crate::model::pipeline::{Fingerprint, PipelineState, Stage, plan};
+use crate::model::project::ProjectConfig;
+
+use super::run::{RunMalloryArgs, mallory};
+use super::setup::{SetupAllArgs, SutArgs};
+use super::{ExportGraphsArgs, InstrumentArgs, export_graphs, instrument, setup};
+
+const STATE_PATH: &str = ".abstraktor/pipeline.json";
+
+#[derive(Parser, Debug)]
+pub struct PipelineArgs {
+    #[arg(short, long, default_value = "abstraktor.toml")]
+    pub config: String,
+
+    #[arg(
+        long,
+        value_parser = Stage::parse,
+        help = "Run this stage and every later one, skipping the earlier ones (llvm, instrument, setup, run, export)"
+    )]
+    pub from: Option<Stage>,
+
+    #[arg(
+        long,
+        default_value = "false",
+        help = "Run every stage even if its inputs are unchanged"
+    )]
+    pub force: bool,
+
+    #[arg(
+        long,
+        default_value = "false",
+        help = "Only print which stages would run"
+    )]
+    pub dry_run: bool,
+}
+
+/// Arguments of the commands each stage runs, built the way the CLI would
+/// parse them so that their defaults stay in one place.
+struct StageArgs {
+    instrument: InstrumentArgs,
+    sut: SutArgs,
+    setup: SetupAllArgs,
+    run: RunMalloryArgs,
+    export: ExportGraphsArgs,
+}
+
+pub fn run(args: PipelineArgs, logger: &Logger, sh: &Shell) -> Result<()> {
+    run_with(args, logger, |stage, config| {
+        run_stage(stage, config, logger, sh)
+    })
+}
+
+/// `run`, with `run_stage` doing the work of each stage that is not skipped.
+fn run_with(
+    args: PipelineArgs,
+    logger: &Logger,
+    mut run_stage: impl FnMut(Stage, &ProjectConfig) -> Result<()>,
+) -> Result<()> {
+    logger.log(format!("Running pipeline from {}", args.config));
+    let config = ProjectConfig::from_file(&args.config)?;
+    let state_path = Path::new(&args.config)
+        .parent()
+        .unwrap_or(Path::new(""))
+        .join(STATE_PATH);
+    let mut state = PipelineState::load(&state_path)?;
+
+    let fingerprints = fingerprints(&config)?;
+    let decisions = plan(&state, &fingerprints, args.from, args.force);
+    for decision in &decisions {
+        let verb = if decision.run { "run" } else { "skip" };
+        logger.log(format!(
+            "{:<10} {} ({})",
+            decision.stage.name(),
+            verb,
+            decision.reason.describe()
+        ));
+    }
+    if args.dry_run {
+        return Ok(());
+    }
+
+    for decision in decisions.iter().filter(|d| d.run) {
+        let stage = decision.stage;
+        // Earlier stages may have just written this one's inputs, as the run
+        // stage writes the events log the export reads.
+        let before = fingerprint(stage, &config)?;
+        logger.log(format!("Stage {}", stage.name()));
+        if let Err(err) = run_stage(stage, &config) {
+            state.record(stage, &before, false);
+            state.save(&state_path)?;
+            return Err(err).with_context(|| {
+                format!(
+                    "stage {} failed; run the pipeline again to resume from it",
+                    stage.name()
+                )
+            });
+        }
+        // Taken again because a stage may write into its own inputs, as an
+        // in-tree build configures the SUT.
+        state.record(stage, &fingerprint(stage, &config)?, true);
+        state.save(&state_path)?;
+    }
+
+    logger.success("Pipeline finished");
+    Ok(())
+}
+
+fn stage_args(config: &ProjectConfig) -> Result<StageArgs> {
+    let mut instrument = vec!["instrument", "--path", &config.sut.path];
+    push_option(&mut instrument, "--llvm-path", &config.llvm.instrumentor);
+    push_option(&mut instrument, "--manifest-path", &config.sut.manifest);
+    push_option(&mut instrument, "--build-command", &config.sut.build);
+    push_option(&mut instrument, "--build-system", &config.sut.build_system);
+    push_option(&mut instrument, "--build-dir", &config.sut.build_dir);
+    push_option(&mut instrument, "--cflags", &config.sut.cflags);
+    push_option(&mut instrument, "--build-record", &config.sut.build_record);
+    push_option(&mut instrument, "--cc", &config.llvm.cc);
+    push_option(&mut instrument, "--cxx", &config.llvm.cxx);
+    let llvm_version = config.llvm.version.map(|v| v.to_string());
+    push_option(&mut instrument, "--llvm-version", &llvm_version);
+    push_option(
+        &mut instrument,
+        "--compile-commands",
+        &config.sut.compile_commands,
+    );
+    for glob in &config.sut.include {
+        instrument.extend(["--include", glob]);
+    }
+    for glob in &config.sut.exclude {
+        instrument.extend(["--exclude", glob]);
+    }
+
+    let node_count = config.cluster.nodes.map(|n| n.to_string());
+    let mut setup = vec!["all"];
+    push_option(&mut setup, "--node-count", &node_count);
+
+    Ok(StageArgs {
+        instrument: InstrumentArgs::try_parse_from(instrument)
+            .context("invalid [sut] or [llvm] options")?,
+        sut: SutArgs::try_parse_from(["sut", "--path", &config.sut.path])
+            .context("invalid [sut] options")?,
+        setup: SetupAllArgs::try_parse_from(setup).context("invalid [cluster] options")?,
+        run: mallory_args(config)?,
+        export: export_args(config)?,
+    })
+}
+
+/// `run mallory` as the `[cluster]` and `[test]` sections describe it.
+pub fn mallory_args(config: &ProjectConfig) -> Result<RunMalloryArgs> {
+    let node_count = config.cluster.nodes.map(|n| n.to_string());
+    let time_limit = config.test.time_limit.map(|n| n.to_string());
+    let test_count = config.test.test_count.map(|n| n.to_string());
+    let threshold = config.test.similarity_threshold.map(|t| t.to_string());
+    let schedule_duration = config.test.schedule_duration_ms.map(|n| n.to_string());
+    let schedule_interval = config.test.schedule_interval_ms.map(|n| n.to_string());
+    let reset_duration = config.test.reset_duration_ms.map(|n| n.to_string());
+    let mut run = vec!["mallory"];
+    push_option(&mut run, "--node-count", &node_count);
+    push_option(&mut run, "--time-limit", &time_limit);
+    push_option(&mut run, "--suite", &config.test.suite);
+    push_option(&mut run, "--workload", &config.test.workload);
+    push_option(&mut run, "--nemesis", &config.test.nemesis);
+    push_option(&mut run, "--scheduler", &config.test.scheduler);
+    push_option(&mut run, "--feedback", &config.test.feedback);
+    push_option(&mut run, "--similarity-threshold", &threshold);
+    push_option(&mut run, "--schedule-duration-ms", &schedule_duration);
+    push_option(&mut run, "--schedule-interval-ms", &schedule_interval);
+    push_option(&mut run, "--reset-duration-ms", &reset_duration);
+    push_option(&mut run, "--plan", &config.test.plan);
+    push_option(&mut run, "--test-count", &test_count);
+    push_option(&mut run, "--artifacts-dir", &config.test.artifacts_dir);
+    for arg in &config.test.args {
+        run.push("--test-arg");
+        run.push(arg);
+    }
+
+    let run = RunMalloryArgs::try_parse_from(run).context("invalid [test] options")?;
+    // Checked up front so that a typo does not surface after the build.
+    mallory::validate(&run).context("invalid [test] options")?;
+    Ok(run)
+}
+
+pub fn export_args(config: &ProjectConfig) -> Result<ExportGraphsArgs> {
+    let mut export = vec!["export-graphs"];
+    push_option(&mut export, "--log-path", &config.export.log_path);
+    push_option(&mut export, "--out", &config.export.out);
+    push_option(&mut export, "--format", &config.export.format);
+    push_option(&mut export, "--refinement", &config.export.refinement);
+    export.extend(config.export.args.iter().map(String::as_str));
+
+    ExportGraphsArgs::try_parse_from(export).context("invalid [export] options")
+}
+
+fn push_option<'a>(argv: &mut Vec<&'a str>, flag: &'a str, value: &'a Option<String>) {
+    if let Some(value) = value {
+        argv.push(flag);
+        argv.push(value);
+    }
+}
+
+fn fingerprints(config: &ProjectConfig) -> Result<BTreeMap<Stage, String>> {
+    Stage::ALL
+        .into_iter()
+        .map(|stage| Ok((stage, fingerprint(stage, config)?)))
+        .collect()
+}
+
+/// A stage's inputs are the options of the command it runs plus the files
+/// that command reads but does not write.
+fn fingerprint(stage: Stage, config: &ProjectConfig) -> Result<String> {
+    let args = stage_args(config)?;
+    let mut fingerprint = Fingerprint::default();
+    match stage {
+        Stage::Llvm => {
+            fingerprint
+                .add(&config.llvm.build)
+                .add(&format!("{:?}", config.llvm.version))
+                .add_path(Path::new(&config.llvm.dir))?;
+        }
+        Stage::Instrument => {
+            fingerprint
+                .add(&format!("{:?}", args.instrument))
+                .add(&format!("{:?}", config.sut.clean));
+            // What the build writes below the sources is not an input.
+            let excluded = [config
+                .sut
+                .build_dir
+                .as_ref()
+                .map(PathBuf::from)
+                .unwrap_or_else(|| Path::new(&config.sut.path).join(DEFAULT_BUILD_DIR))];
+            // The compilation database is left out too: the build rewrites it.
+            for source in config.sources() {
+                fingerprint.add_path_excluding(Path::new(&source), &excluded)?;
+            }
+        }
+        Stage::Setup => {
+            fingerprint
+                .add(&format!("{:?}", args.sut))
+                .add(&format!("{:?}", args.setup));
+        }
+        Stage::Run => {
+            fingerprint.add(&format!("{:?}", args.run));
+            if let Some(plan) = &args.run.mediator.plan {
+                fingerprint.add_path(Path::new(plan))?;
+            }
+        }
+        Stage::Export => {
+            fingerprint
+                .add(&format!("{:?}", args.export))
+                .add_path(Path::new(&args.export.log_path))?;
+            if let Some(refinement) = &args.export.refinement {
+                fingerprint.add_path(Path::new(refinement))?;
+            }
+        }
+    }
+    Ok(fingerprint.finish())
+}
+
+fn run_stage(stage: Stage, config: &ProjectConfig, logger: &Logger, sh: &Shell) -> Result<()> {
+    let args = stage_args(config)?;
+    match stage {
+        Stage::Llvm => {
+            let _dir = sh.push_dir(&config.llvm.dir);
+            // The Makefile builds the pass for whichever LLVM this names.
+            let _llvm_config = config
+                .llvm
+                .version
+                .map(|v| sh.push_env("LLVM_CONFIG", format!("llvm-config-{}", v)));
+            logger.log(format!(
+                "Running {} in {}",
+                config.llvm.build, config.llvm.dir
+            ));
+            sh.cmd("sh")
+                .arg("-c")
+                .arg(&config.llvm.build)
+                .run()
+                .context("Failed to build the LLVM pass")?;
+        }
+        Stage::Instrument => {
+            if let Some(clean) = &config.sut.clean {
+                let _dir = sh.push_dir(&config.sut.path);
+                logger.log(format!("Running {} in {}", clean, config.sut.path));
+                sh.cmd("sh")
+                    .arg("-c")
+                    .arg(clean)
+                    .run()
+                    .context("Failed to clean the SUT")?;
+            }
+            instrument::run(args.instrument, logger, sh)?;
+        }
+        Stage::Setup => {
+            setup::sut::run(args.sut, logger, sh)?;
+            setup::all::run(args.setup, logger, sh)?;
+        }
+        Stage::Run => mallory::run_recorded(args.run, logger, sh)?,
+        Stage::Export => export_graphs::run_recorded(args.export, logger, sh)?,
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn create_test_logger() -> Logger {
+        Logger::new(crate::logger::LogLevel::Quiet)
+    }
+
+    /// The LLVM stage counts its runs and the instrument stage fails while
+    /// cleaning, before anything touches the working directory.
+    fn failing_project(temp_dir: &TempDir) -> PipelineArgs {
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("llvm")).unwrap();
+        fs::write(root.join("llvm/pass.cc"), "// pass").unwrap();
+        fs::create_dir_all(root.join("sut")).unwrap();
+        fs::write(root.join("sut/raft.c"), "int x;").unwrap();
+        let config = root.join("abstraktor.toml");
+        fs::write(
+            &config,
+            format!(
+                "[sut]\npath = \"{sut}\"\nclean = \"exit 1\"\n\n[llvm]\ndir = \"{llvm}\"\nbuild = \"echo built >> runs\"\n",
+                sut = root.join("sut").display(),
+                llvm = root.join("llvm").display(),
+            ),
+        )
+        .unwrap();
+        PipelineArgs {
+            config: config.to_str().unwrap().to_string(),
+            from: None,
+            force: false,
+            dry_run: false,
+        }
+    }
+
+    fn llvm_runs(temp_dir: &TempDir) -> usize {
+        fs::read_to_string(temp_dir.path().join("llvm/runs"))
+            .unwrap_or_default()
+            .lines()
+            .count()
+    }
+
+    #[test]
+    fn test_pipeline_records_failure_and_resumes() {
+        let temp_dir = TempDir::new().unwrap();
+        let logger = create_test_logger();
+        let sh = Shell::new().unwrap();
+
+        let err = run(failing_project(&temp_dir), &logger, &sh).unwrap_err();
+        assert!(err.to_string().contains("stage instrument failed"));
+        assert_eq!(llvm_runs(&temp_dir), 1);
+
+        let state_path = temp_dir.path().join(STATE_PATH);
+        let state = PipelineState::load(&state_path).unwrap();
+        assert!(state.stages[&Stage::Llvm].completed);
+        assert!(!state.stages[&Stage::Instrument].completed);
+        assert!(!state.stages.contains_key(&Stage::Setup));
+
+        let err = run(failing_project(&temp_dir), &logger, &sh).unwrap_err();
+        assert!(err.to_string().contains("stage instrument failed"));
+        assert_eq!(llvm_runs(&temp_dir), 1);
+    }
+
+    #[test]
+    fn test_pipeline_reruns_changed_stage() {
+        let temp_dir = TempDir::new().unwrap();
+        let logger = create_test_logger();
+        let sh = Shell::new().unwrap();
+
+        run(failing_project(&temp_dir), &logger, &sh).unwrap_err();
+        fs::write(temp_dir.path().join("llvm/pass.h"), "// new").unwrap();
+        run(failing_project(&temp_dir), &logger, &sh).unwrap_err();
+        assert_eq!(llvm_runs(&temp_dir), 2);
+
+        let mut args = failing_project(&temp_dir);
+        args.force = true;
+        args.dry_run = true;
+        run(args, &logger, &sh).unwrap();
+        assert_eq!(llvm_runs(&temp_dir), 2);
+    }
+
+    #[test]
+    fn test_pipeline_fingerprints_stage_inputs_written_by_earlier_stages() {
+        let temp_dir = TempDir::new().unwrap();
+        let args = failing_project(&temp_dir);
+        let generated = temp_dir.path().join("sut/generated.c");
+        let content = fs::read_to_string(&args.config).unwrap().replace(
+            "build = \"echo built >> runs\"",
+            &format!("build = \"echo 'int y;' > {}\"", generated.display()),
+        );
+        fs::write(&args.config, content).unwrap();
+
+        let config = ProjectConfig::from_file(&args.config).unwrap();
+        let before = fingerprint(Stage::Instrument, &config).unwrap();
+        run(args, &create_test_logger(), &Shell::new().unwrap()).unwrap_err();
+        assert!(generated.exists());
+
+        let state = PipelineState::load(&temp_dir.path().join(STATE_PATH)).unwrap();
+        let recorded = &state.stages[&Stage::Instrument].fingerprint;
+        assert_ne!(recorded, &before);
+        assert_eq!(recorded, &fingerprint(Stage::Instrument, &config).unwrap());
+    }
+
+    #[test]
+    fn test_pipeline_skips_instrument_after_an_in_tree_build() {
+        let temp_dir = TempDir::new().unwrap();
+        let sut = temp_dir.path().join("sut");
+        let logger = create_test_logger();
+        let instrumented = std::cell::Cell::new(0);
+        // Writes what a configure and CMake build leave next to the sources.
+        let pipeline = || {
+            run_with(failing_project(&temp_dir), &logger, |stage, _| {
+                if stage == Stage::Instrument {
+                    instrumented.set(instrumented.get() + 1);
+                    fs::write(sut.join("Makefile"), "all:\n").unwrap();
+                    fs::write(sut.join("config.h"), "#define HAVE_RAFT 1\n").unwrap();
+                    let build = sut.join(DEFAULT_BUILD_DIR).join("CMakeFiles");
+                    fs::create_dir_all(&build).unwrap();
+                    let id = format!("/* {} */", instrumented.get());
+                    fs::write(build.join("CMakeCCompilerId.c"), id).unwrap();
+                }
+                Ok(())
+            })
+            .unwrap();
+        };
+
+        pipeline();
+        pipeline();
+        assert_eq!(instrumented.get(), 1);
+
+        fs::write(sut.join("log.c"), "int y;").unwrap();
+        pipeline();
+        assert_eq!(instrumented.get(), 2);
+    }
+
+    #[test]
+    fn test_pipeline_builds_pass_for_llvm_version() {
+        let temp_dir = TempDir::new().unwrap();
+        let args = failing_project(&temp_dir);
+        let content = fs::read_to_string(&args.config).unwrap().replace(
+            "build = \"echo built >> runs\"",
+            "build = \"echo $LLVM_CONFIG > llvm-config\"\nversion = 17",
+        );
+        fs::write(&args.config, content).unwrap();
+
+        let err = run(args, &create_test_logger(), &Shell::new().unwrap()).unwrap_err();
+        assert!(err.to_string().contains("stage instrument failed"));
+        let llvm_config = fs::read_to_string(temp_dir.path().join("llvm/llvm-config")).unwrap();
+        assert_eq!(llvm_config.trim(), "llvm-config-17");
+    }
+
+    #[test]
+    fn test_pipeline_rejects_invalid_options() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = temp_dir.path().join("abstraktor.toml");
+        fs::write(
+            &config,
+            "[sut]\npath = \"sut\"\n\n[export]\nformat = \"gif\"\n",
+        )
+        .unwrap();
+        let args = PipelineArgs {
+            config: config.to_str().unwrap().to_string(),
+            from: None,
+            force: false,
+            dry_run: true,
+        };
+
+        let err = run(args, &create_test_logger(), &Shell::new().unwrap()).unwrap_err();
+        assert!(err.to_string().contains("invalid [export] options"));
+
+        fs::write(
+            &config,
+            "[sut]\npath = \"sut\"\n\n[test]\nsuite = \"braft\"\nnemesis = \"all\"\n",
+        )
+        .unwrap();
+        let args = PipelineArgs {
+            config: config.to_str().unwrap().to_string(),
+            from: None,
+            force: false,
+            dry_run: true,
+        };
+        let err = run(args, &create_test_logger(), &Shell::new().unwrap()).unwrap_err();
+        assert!(err.to_string().contains("invalid [test] options"));
+    }
+}
@@ -0,0 +1,256 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use clap::Parser;
+
+use crate::logger::Logger;
+use crate::model::event_log::{parse_nemesis_actions, parse_window_actions};
+use crate::model::fault_plan::{
+    PlanTarget, PlannerOptions, jepsen_schedules, mediator_schedules, plan_faults,
+};
+use crate::model::refinement::Refinement;
+use crate::model::spec::{SpecMapping, compare_with_spec};
+use crate::model::tlc::parse_tlc_dot;
+use crate::model::{RefinementConfig, build_event_graph_with};
+
+#[derive(Parser, Debug)]
+pub struct PlanFaultsArgs {
+    #[arg(
+        short = 'a',
+        long = "log-path",
+        default_value = "mediator-logs/mediator.log"
+    )]
+    pub log_path: String,
+
+    #[arg(short = 'r', long = "refinement")]
+    pub refinement: Option<String>,
+
+    #[arg(
+        short = 't',
+        long = "targets",
+        help = "JSON list of {from, transition, to, node} edges to reach"
+    )]
+    pub targets: Option<String>,
+
+    #[arg(
+        short = 's',
+        long = "spec-graph",
+        requires = "mapping",
+        help = "Target the spec edges never exercised (TLC -dump dot)"
+    )]
+    pub spec_graph: Option<String>,
+
+    #[arg(short = 'm', long = "mapping")]
+    pub mapping: Option<String>,
+
+    #[arg(
+        short = 'l',
+        long = "length",
+        help = "Steps per schedule; defaults to the longest schedule in the log"
+    )]
+    pub length: Option<usize>,
+
+    #[arg(long = "max-per-target", default_value = "3")]
+    pub max_per_target: usize,
+
+    #[arg(
+        long = "num-actions",
+        help = "Number of nemesis actions, including the no-op"
+    )]
+    pub num_actions: Option<usize>,
+
+    #[arg(short = 'o', long = "out", default_value = "fault_plans")]
+    pub output_dir: String,
+}
+
+pub fn run(args: PlanFaultsArgs, logger: &Logger) -> Result<()> {
+    logger.log("Planning fault schedules");
+
+    logger.log(format!("Reading events from {}", args.log_path));
+    let log_content = fs::read_to_string(&args.log_path)
+        .with_context(|| format!("reading log from {}", &args.log_path))?;
+    let refinement = match &args.refinement {
+        Some(path) => {
+            logger.log(format!("Applying abstraction refinement from {}", path));
+            RefinementConfig::from_file(path)?.compile()?
+        }
+        None => Refinement::identity(),
+    };
+
+    let mut targets = match &args.targets {
+        Some(path) => PlanTarget::from_file(path)?,
+        None => Vec::new(),
+    };
+    if let (Some(spec_graph), Some(mapping)) = (&args.spec_graph, &args.mapping) {
+        logger.log(format!("Reading spec graph from {}", spec_graph));
+        let spec_content = fs::read_to_string(spec_graph)
+            .with_context(|| format!("reading spec graph from {}", spec_graph))?;
+        let graph = build_event_graph_with(&log_content, &refinement);
+        let report = compare_with_spec(
+            &graph,
+            &parse_tlc_dot(&spec_content),
+            &SpecMapping::from_file(mapping)?,
+        );
+        targets.extend(report.unexercised_edges.into_iter().map(|e| PlanTarget {
+            node: None,
+            from: e.from,
+            transition: e.transition,
+            to: e.to,
+        }));
+    }
+    if targets.is_empty() {
+        anyhow::bail!("no targets to plan for: pass --targets or --spec-graph with --mapping");
+    }
+    logger.log(format!("Planning for {} target(s)", targets.len()));
+
+    let window_actions = parse_window_actions(&log_content);
+    if window_actions.is_empty() {
+        logger.warning("No schedule window markers found in log - cannot replay any prefix");
+    }
+    let length = args.length.unwrap_or_else(|| {
+        window_actions
+            .keys()
+            .map(|w| w.step_id + 1)
+            .max()
+            .unwrap_or(0)
+    });
+    let options = PlannerOptions {
+        length,
+        max_per_target: args.max_per_target,
+        num_actions: args.num_actions,
+    };
+    let plans = plan_faults(&log_content, &refinement, &targets, &options);
+
+    for plan in &plans {
+        let t = &plan.target;
+        if plan.observed {
+            logger.debug(format!("{} --> {} was already observed", t.from, t.to));
+        } else if plan.source_visits == 0 {
+            logger.warning(format!(
+                "{} --> {}: source state {} was never reached",
+                t.from, t.to, t.from
+            ));
+        } else {
+            logger.log(format!(
+                "{} --> {}: {} schedule(s) from {} visit(s) of {}",
+                t.from,
+                t.to,
+                plan.schedules.len(),
+                plan.source_visits,
+                t.from
+            ));
+        }
+    }
+
+    let edn = parse_nemesis_actions(&log_content);
+    let unknown = plans
+        .iter()
+        .flat_map(|p| &p.schedules)
+        .flat_map(|s| &s.actions)
+        .any(|a| !edn.contains_key(a));
+    if unknown {
+        logger.warning("Some actions have no EDN in the log and are exported as :pending");
+    }
+
+    let out_dir = Path::new(&args.output_dir);
+    fs::create_dir_all(out_dir)
+        .with_context(|| format!("creating output directory {}", args.output_dir))?;
+    let files = [
+        (
+            "plan.json",
+            serde_json::to_string_pretty(&plans).context("serializing fault plan")?,
+        ),
+        ("schedules.txt", mediator_schedules(&plans)),
+        ("schedules.edn", jepsen_schedules(&plans, &edn)),
+    ];
+    for (name, content) in files {
+        let path = out_dir.join(name);
+        fs::write(&path, content).with_context(|| format!("writing {}", path.display()))?;
+        logger.success(format!("Wrote {}", path.display()));
+    }
+
+    let total: usize = plans.iter().map(|p| p.schedules.len()).sum();
+    logger.success(format!("Proposed {} schedule(s)", total));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn create_test_logger() -> Logger {
+        Logger::new(crate::logger::LogLevel::Quiet)
+    }
+
+    fn test_args(output_dir: &Path) -> PlanFaultsArgs {
+        PlanFaultsArgs {
+            log_path: "tests/plan_faults_test/mediator.log".to_string(),
+            refinement: None,
+            targets: None,
+            spec_graph: None,
+            mapping: None,
+            length: None,
+            max_per_target: 3,
+            num_actions: None,
+            output_dir: output_dir.to_str().unwrap().to_string(),
+        }
+    }
+
+    #[test]
+    fn test_plan_faults_from_targets() {
+        let temp_dir = TempDir::new().unwrap();
+        let output_dir = temp_dir.path().join("plans");
+
+        let mut args = test_args(&output_dir);
+        args.targets = Some("tests/plan_faults_test/targets.json".to_string());
+        run(args, &create_test_logger()).unwrap();
+
+        let schedules = fs::read_to_string(output_dir.join("schedules.txt")).unwrap();
+        assert_eq!(
+            schedules,
+            "# Leader --*--> Down\n0 1 2 0\n0 1 3 0\n0 1 1 0\n\
+             # Candidate --stepDown--> Follower\n0 2 0 0\n0 3 0 0\n0 1 0 0\n"
+        );
+
+        let edn = fs::read_to_string(output_dir.join("schedules.edn")).unwrap();
+        assert!(edn.contains(" [:pending {:f :start-partition, :must-dispatch true} {:f :kill, :must-dispatch true} :pending]\n"));
+
+        let plan: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(output_dir.join("plan.json")).unwrap())
+                .unwrap();
+        let plan = plan.as_array().unwrap();
+        assert_eq!(plan.len(), 3);
+        // Node 2 already went from Follower to Candidate.
+        assert_eq!(plan[2]["observed"], true);
+    }
+
+    #[test]
+    fn test_plan_faults_from_spec() {
+        let temp_dir = TempDir::new().unwrap();
+        let output_dir = temp_dir.path().join("plans");
+
+        let mut args = test_args(&output_dir);
+        args.spec_graph = Some("tests/check_spec_test/raft.dot".to_string());
+        args.mapping = Some("tests/check_spec_test/mapping.json".to_string());
+        run(args, &create_test_logger()).unwrap();
+
+        let plan: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(output_dir.join("plan.json")).unwrap())
+                .unwrap();
+        assert!(
+            plan.as_array()
+                .unwrap()
+                .iter()
+                .any(|p| p["target"]["to"] == "Down" && p["target"]["transition"] == "Crash")
+        );
+    }
+
+    #[test]
+    fn test_plan_faults_without_targets() {
+        let temp_dir = TempDir::new().unwrap();
+        let err = run(test_args(temp_dir.path()), &create_test_logger()).unwrap_err();
+        assert!(err.to_string().contains("no targets to plan for"));
+    }
+}
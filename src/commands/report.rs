@@ -0,0 +1,295 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use clap::{Parser, ValueEnum};
+
+use crate::logger::Logger;
+use crate::model::dwell::DwellThresholds;
+use crate::model::event_log::{batch_time_warning, parse_event_log};
+use crate::model::jepsen_store::{discover_runs, select_runs};
+use crate::model::refinement::Refinement;
+use crate::model::report::{
+    Oracles, RunInput, campaign_report, html_for_report, markdown_for_report,
+};
+use crate::model::spec::SpecMapping;
+use crate::model::tlc::parse_tlc_dot;
+use crate::model::{RefinementConfig, build_event_graph_with};
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
+pub enum ReportFormat {
+    Markdown,
+    Html,
+}
+
+#[derive(Parser, Debug)]
+pub struct ReportArgs {
+    #[arg(
+        short = 'a',
+        long = "log-path",
+        default_value = "mediator-logs/events.log"
+    )]
+    pub log_path: String,
+
+    #[arg(
+        long = "store",
+        conflicts_with = "log_path",
+        help = "Jepsen store directory; reports on each selected run"
+    )]
+    pub store: Option<String>,
+
+    #[arg(
+        long = "run",
+        requires = "store",
+        value_name = "RUN",
+        help = "Run to include: <test>/<timestamp>, <timestamp>, latest or all (default: all)"
+    )]
+    pub runs: Vec<String>,
+
+    #[arg(
+        long = "results",
+        conflicts_with = "store",
+        help = "Jepsen results.edn of the run that wrote the log"
+    )]
+    pub results: Option<String>,
+
+    #[arg(short = 'r', long = "refinement")]
+    pub refinement: Option<String>,
+
+    #[arg(
+        short = 'b',
+        long = "baseline",
+        help = "Events log of an earlier campaign; edges it never reached are listed as new"
+    )]
+    pub baseline: Option<String>,
+
+    #[arg(
+        short = 's',
+        long = "spec-graph",
+        requires = "mapping",
+        help = "State graph dumped by TLC with -dump dot; edges it does not allow are oracle hits"
+    )]
+    pub spec_graph: Option<String>,
+
+    #[arg(long = "mapping", requires = "spec_graph")]
+    pub mapping: Option<String>,
+
+    #[arg(
+        long = "max-dwell",
+        value_name = "STATE=DURATION",
+        help = "Stays in STATE longer than DURATION are oracle hits (e.g. Candidate=2s)"
+    )]
+    pub max_dwell: Vec<String>,
+
+    #[arg(short = 'f', long = "format", value_enum, default_value = "markdown")]
+    pub format: ReportFormat,
+
+    #[arg(
+        short = 'o',
+        long = "out",
+        help = "Report path (default: report.md or report.html)"
+    )]
+    pub output_path: Option<String>,
+}
+
+pub fn run(args: ReportArgs, logger: &Logger) -> Result<()> {
+    logger.log("Writing campaign report");
+
+    let runs = match &args.store {
+        Some(store) => store_runs(store, &args.runs, logger)?,
+        None => {
+            logger.log(format!("Reading events from {}", args.log_path));
+            let results = match &args.results {
+                Some(path) => Some(
+                    fs::read_to_string(path)
+                        .with_context(|| format!("reading results from {}", path))?,
+                ),
+                None => None,
+            };
+            vec![RunInput {
+                name: args.log_path.clone(),
+                log: fs::read_to_string(&args.log_path)
+                    .with_context(|| format!("reading log from {}", &args.log_path))?,
+                metadata: BTreeMap::new(),
+                results,
+            }]
+        }
+    };
+    if runs.is_empty() {
+        anyhow::bail!("no runs to report on");
+    }
+    for run in &runs {
+        if let Some(warning) = batch_time_warning(&parse_event_log(&run.log)) {
+            logger.warning(format!("{}: {}", run.name, warning));
+        }
+    }
+
+    let refinement = match &args.refinement {
+        Some(path) => {
+            logger.log(format!("Applying abstraction refinement from {}", path));
+            RefinementConfig::from_file(path)?.compile()?
+        }
+        None => Refinement::identity(),
+    };
+
+    let baseline = match &args.baseline {
+        Some(path) => {
+            logger.log(format!("Comparing against baseline {}", path));
+            let content = fs::read_to_string(path)
+                .with_context(|| format!("reading baseline from {}", path))?;
+            Some(build_event_graph_with(&content, &refinement))
+        }
+        None => None,
+    };
+
+    let spec = match (&args.spec_graph, &args.mapping) {
+        (Some(spec_graph), Some(mapping)) => {
+            let content = fs::read_to_string(spec_graph)
+                .with_context(|| format!("reading spec graph from {}", spec_graph))?;
+            Some((parse_tlc_dot(&content), SpecMapping::from_file(mapping)?))
+        }
+        _ => None,
+    };
+    let oracles = Oracles {
+        dwell: DwellThresholds::parse(&args.max_dwell)?,
+        spec: spec.as_ref().map(|(graph, mapping)| (graph, mapping)),
+    };
+
+    let report = campaign_report(&runs, baseline.as_ref(), &refinement, &oracles);
+    if report.oracle_hits.is_empty() {
+        logger.log("No oracle flagged any run");
+    } else {
+        logger.warning(format!("{} oracle hit(s)", report.oracle_hits.len()));
+        for hit in &report.oracle_hits {
+            logger.warning(format!("[{}] {}: {}", hit.oracle, hit.run, hit.detail));
+        }
+    }
+    if let Some(baseline) = &report.baseline {
+        logger.log(format!(
+            "{} new state(s) and {} new edge(s) compared to the baseline",
+            baseline.new_state_count(),
+            baseline.new_edge_count()
+        ));
+    }
+
+    let (content, default_path) = match args.format {
+        ReportFormat::Markdown => (markdown_for_report(&report), "report.md"),
+        ReportFormat::Html => (html_for_report(&report), "report.html"),
+    };
+    let output_path = args.output_path.as_deref().unwrap_or(default_path);
+    if let Some(parent) = Path::new(output_path).parent()
+        && !parent.as_os_str().is_empty()
+    {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("creating output directory {}", parent.display()))?;
+    }
+    fs::write(output_path, content).with_context(|| format!("writing {}", output_path))?;
+    logger.success(format!(
+        "Wrote report on {} run(s) to {}",
+        report.runs.len(),
+        output_path
+    ));
+    Ok(())
+}
+
+/// A campaign is usually every run in the store, so that is the default.
+fn store_runs(store: &str, selectors: &[String], logger: &Logger) -> Result<Vec<RunInput>> {
+    logger.log(format!("Looking for runs in Jepsen store {}", store));
+    let runs = discover_runs(Path::new(store))?;
+    if runs.is_empty() {
+        return Ok(Vec::new());
+    }
+    let selectors = if selectors.is_empty() {
+        vec!["all".to_string()]
+    } else {
+        selectors.to_vec()
+    };
+
+    let mut inputs = Vec::new();
+    for run in select_runs(&runs, &selectors)? {
+        logger.debug(format!("Reading {}", run.mediator_log.display()));
+        inputs.push(RunInput {
+            name: run.id(),
+            log: fs::read_to_string(&run.mediator_log)
+                .with_context(|| format!("reading log from {}", run.mediator_log.display()))?,
+            metadata: [("schedule", &run.schedule), ("feedback", &run.feedback)]
+                .into_iter()
+                .filter_map(|(key, value)| Some((key.to_string(), value.clone()?)))
+                .collect(),
+            results: fs::read_to_string(run.dir.join("results.edn")).ok(),
+        });
+    }
+    Ok(inputs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn create_test_logger() -> Logger {
+        Logger::new(crate::logger::LogLevel::Quiet)
+    }
+
+    fn test_args(output_path: &Path, format: ReportFormat) -> ReportArgs {
+        ReportArgs {
+            log_path: "tests/export_graphs_test/windowed_events.log".to_string(),
+            store: None,
+            runs: vec![],
+            results: None,
+            refinement: None,
+            baseline: None,
+            spec_graph: None,
+            mapping: None,
+            max_dwell: vec![],
+            format,
+            output_path: Some(output_path.to_str().unwrap().to_string()),
+        }
+    }
+
+    #[test]
+    fn test_report_markdown_with_baseline() {
+        let temp_dir = TempDir::new().unwrap();
+        let output_path = temp_dir.path().join("out").join("report.md");
+
+        let mut args = test_args(&output_path, ReportFormat::Markdown);
+        args.baseline = Some("tests/export_graphs_test/simple_events.log".to_string());
+        run(args, &create_test_logger()).unwrap();
+
+        let report = fs::read_to_string(&output_path).unwrap();
+        assert!(report.starts_with("# Campaign report"));
+        assert!(report.contains("## Compared to the baseline"));
+        assert!(report.contains("## Nemesis schedules"));
+        assert!(report.contains("```mermaid\nstateDiagram-v2"));
+    }
+
+    #[test]
+    fn test_report_html_from_store_with_spec_oracle() {
+        let temp_dir = TempDir::new().unwrap();
+        let output_path = temp_dir.path().join("report.html");
+
+        let mut args = test_args(&output_path, ReportFormat::Html);
+        args.store = Some("tests/jepsen_store_test/store".to_string());
+        args.runs = vec!["raft/20251110T195655.000-0300".to_string()];
+        args.spec_graph = Some("tests/check_spec_test/raft.dot".to_string());
+        args.mapping = Some("tests/check_spec_test/mapping.json".to_string());
+        run(args, &create_test_logger()).unwrap();
+
+        let report = fs::read_to_string(&output_path).unwrap();
+        assert!(report.contains("<td>raft/20251110T195655.000-0300</td>"));
+        assert!(report.contains("feedback=event_history, schedule=random"));
+        assert!(report.contains("checker workload found the history invalid"));
+        assert!(report.contains("Leader --heartbeat--&gt; Candidate is not allowed by the spec"));
+    }
+
+    #[test]
+    fn test_report_missing_log() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut args = test_args(&temp_dir.path().join("report.md"), ReportFormat::Markdown);
+        args.log_path = "tests/export_graphs_test/nonexistent.log".to_string();
+
+        let err = run(args, &create_test_logger()).unwrap_err();
+        assert!(err.to_string().contains("reading log from"));
+    }
+}
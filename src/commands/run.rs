@@ -23,8 +23,8 @@ pub enum RunSubcommand {
 
 pub fn run(args: RunArgs, logger: &Logger, sh: &Shell) -> Result<()> {
 	match args.command {
-		RunSubcommand::Mallory(args) => mallory::run(args, logger, sh)?,
-		RunSubcommand::Mediator(args) => mediator::run(args, logger, sh)?,
+		RunSubcommand::Mallory(args) => mallory::run_recorded(args, logger, sh)?,
+		RunSubcommand::Mediator(args) => mediator::run_recorded(args, logger, sh)?,
 	}
 	Ok(())
 }
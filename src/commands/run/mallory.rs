@@ -1,21 +1,187 @@
-use std::path::{self, Path};
+use std::path::{self, Path, PathBuf};
 
 use anyhow::{Context, Result};
 use clap::Parser;
 use xshell::Shell;
 
+use crate::commands::runs::{self, Invocation};
 use crate::logger::Logger;
+use crate::model::build_system::shell_quote;
+use crate::model::mallory::{self, TestSuite};
 
-#[derive(Parser, Debug)]
+use super::mediator::{MEDIATOR_CONFIG, MediatorOptions, PLAN_ENV, Scheduler};
+
+const SUITES_DIR: &str = "mallory/tests/mallory";
+
+/// Where the control container's `/host` is mounted on the host, as
+/// `mallory/docker/docker-compose.yml` sets it up.
+pub const HOST_ARTIFACTS_DIR: &str = "/var/log/abstraktor";
+/// What a run copies out of the suite's Jepsen store into `--artifacts-dir`.
+pub const EXPORTED_LOG: &str = "mediator.log";
+pub const EXPORTED_RESULTS: &str = "results.edn";
+/// Where `--plan` is copied to in the control container.
+pub const CONTAINER_PLAN: &str = "/jepsen/mediator/planned-schedules.txt";
+
+#[derive(Parser, Debug, Clone)]
 pub struct RunMalloryArgs {
 	#[arg(long, default_value = "5")]
 	pub node_count: usize,
 	#[arg(long, default_value = "65")]
 	pub time_limit: usize,
+	#[arg(long, default_value = "dqlite", help = "Jepsen test suite under mallory/tests/mallory")]
+	pub suite: String,
+	#[arg(long, help = "Workload to run (default: the suite's usual workload)")]
+	pub workload: Option<String>,
+	#[arg(long, help = "Comma-separated nemesis faults (default: all, if the suite takes --nemesis)")]
+	pub nemesis: Option<String>,
+	#[command(flatten)]
+	pub mediator: MediatorOptions,
+	#[arg(long, default_value = "1")]
+	pub test_count: usize,
+	#[arg(long, default_value = "/host", help = "Directory in the control container the mediator log is copied to")]
+	pub artifacts_dir: String,
+	#[arg(long = "test-arg", allow_hyphen_values = true, help = "Extra option for lein run test; can be repeated")]
+	pub test_args: Vec<String>,
+}
+
+/// Rejects combinations the mediator or the suite would only reject once the
+/// containers are up.
+pub fn validate(args: &RunMalloryArgs) -> Result<&'static TestSuite> {
+	let suite = mallory::suite(&args.suite).with_context(|| {
+		format!(
+			"unknown test suite '{}' (expected one of {})",
+			args.suite,
+			mallory::suite_names().join(", ")
+		)
+	})?;
+	if let Some(workload) = &args.workload
+		&& !suite.workloads.contains(&workload.as_str())
+	{
+		anyhow::bail!(
+			"suite {} has no workload '{}' (expected one of {})",
+			suite.name,
+			workload,
+			suite.workloads.join(", ")
+		);
+	}
+	if let Some(nemesis) = &args.nemesis {
+		if !suite.takes_nemesis() {
+			anyhow::bail!("suite {} does not take --nemesis; its faults are fixed", suite.name);
+		}
+		if let Some(fault) = suite.unknown_fault(nemesis) {
+			anyhow::bail!(
+				"suite {} has no nemesis fault '{}' (expected one of {})",
+				suite.name,
+				fault,
+				suite.nemesis_choices().join(", ")
+			);
+		}
+	}
+	args.mediator.validate()?;
+	// The mediator config in the container names no plan of its own.
+	if args.mediator.scheduler == Scheduler::Planned && args.mediator.plan.is_none() {
+		anyhow::bail!("the planned scheduler needs --plan");
+	}
+	if args.test_count == 0 || args.time_limit == 0 {
+		anyhow::bail!("--test-count and --time-limit must be at least 1");
+	}
+	Ok(suite)
+}
+
+/// What the control container runs: the mediator in the background, then the
+/// suite's test, then copying the mediator log and the test results out of the
+/// Jepsen store. It exits with the test's status, not the copy's.
+pub fn console_command(args: &RunMalloryArgs, suite: &TestSuite) -> String {
+	let mut test = vec![
+		"lein run test".to_string(),
+		format!("--workload {}", args.workload.as_deref().unwrap_or(suite.default_workload)),
+	];
+	if suite.takes_nemesis() {
+		test.push(format!("--nemesis {}", args.nemesis.as_deref().unwrap_or("all")));
+	}
+	test.extend(suite.extra_args.iter().map(|arg| arg.to_string()));
+	test.extend(args.test_args.iter().map(|arg| shell_quote(arg)));
+	test.push(format!("--time-limit {}", args.time_limit));
+	test.push(format!("--test-count {}", args.test_count));
+
+	let mut mediator: Vec<String> = args
+		.mediator
+		.env()
+		.into_iter()
+		.map(|(name, value)| format!("{}={}", name, value))
+		.collect();
+	if args.mediator.plan.is_some() {
+		mediator.push(format!("{}={}", PLAN_ENV, CONTAINER_PLAN));
+	}
+	mediator.push("./target/x86_64-unknown-linux-musl/release/mediator".to_string());
+	mediator.extend(args.mediator.args());
+
+	let suite_dir = format!("/jepsen/tests/mallory/{}", suite.name);
+	let artifacts_dir = shell_quote(&args.artifacts_dir);
+	format!(
+		"cd /jepsen/mediator && {} & sleep 5 && cd {} && {} ; status=$? ; mkdir -p {} ; cp {}/store/latest/{{{},{}}} {} ; exit $status",
+		mediator.join(" "),
+		suite_dir,
+		test.join(" "),
+		artifacts_dir,
+		suite_dir,
+		EXPORTED_LOG,
+		EXPORTED_RESULTS,
+		artifacts_dir
+	)
+}
+
+/// Copies `--plan` to where the mediator in the control container reads it.
+fn copy_plan(args: &RunMalloryArgs, logger: &Logger, sh: &Shell) -> Result<()> {
+	let Some(plan) = &args.mediator.plan else {
+		return Ok(());
+	};
+	logger.log(format!("Copying plan {} into the control container", plan));
+	sh.cmd("sudo")
+		.args(["docker", "cp"])
+		.arg(plan)
+		.arg(format!("jepsen-control:{}", CONTAINER_PLAN))
+		.run()
+		.with_context(|| format!("Failed to copy plan {} into the control container", plan))
+}
+
+/// Where a path in the control container's `/host` is on the host, with the
+/// mount at `host_dir`. Other paths are not visible from the host.
+pub fn host_path(host_dir: &Path, container_path: &str) -> Option<PathBuf> {
+	let rest = Path::new(container_path).strip_prefix("/host").ok()?;
+	Some(host_dir.join(rest))
+}
+
+/// What a run exports, as seen on this machine. An artifacts directory that
+/// is not below `/host` stays in the container, so there is nothing to list.
+pub fn artifacts(args: &RunMalloryArgs) -> Vec<String> {
+	let Some(dir) = host_path(Path::new(HOST_ARTIFACTS_DIR), &args.artifacts_dir) else {
+		return Vec::new();
+	};
+	[EXPORTED_LOG, EXPORTED_RESULTS]
+		.iter()
+		.map(|file| dir.join(file).display().to_string())
+		.collect()
+}
+
+/// `run`, recorded in the run registry.
+pub fn run_recorded(args: RunMalloryArgs, logger: &Logger, sh: &Shell) -> Result<()> {
+	let invocation = Invocation {
+		command: "run mallory",
+		args: format!("{:?}", args),
+		mediator_config: Some(Path::new(MEDIATOR_CONFIG)),
+		artifacts: artifacts(&args),
+	};
+	runs::record(invocation, logger, sh, || run(args, logger, sh))
 }
 
-pub fn run(_args: RunMalloryArgs, logger: &Logger, sh: &Shell) -> Result<()> {
+pub fn run(args: RunMalloryArgs, logger: &Logger, sh: &Shell) -> Result<()> {
 	logger.log("Starting Mallory test environment");
+	let suite = validate(&args)?;
+	if !Path::new(SUITES_DIR).join(suite.name).exists() {
+		anyhow::bail!("Test suite not found at {}/{}", SUITES_DIR, suite.name);
+	}
+	logger.debug(format!("Test suite: {}", suite.name));
 	
 	let up_path = path::absolute(Path::new("mallory/docker/bin/up"))
 		.context("Failed to absolutize mallory up path")?;
@@ -53,20 +219,22 @@ pub fn run(_args: RunMalloryArgs, logger: &Logger, sh: &Shell) -> Result<()> {
 		.arg(up_path.to_string_lossy().as_ref())
 		.arg("--no-build")
 		.arg("--node-count")
-		.arg(_args.node_count.to_string())
+		.arg(args.node_count.to_string())
 		.run()
 		.context("Failed to run mallory up")?;
 
 	logger.success("Mallory environment is running");
 	
-	let jepsen_cmd = format!(
-		"cd /jepsen/mediator && ./target/x86_64-unknown-linux-musl/release/mediator qlearning event_history 0.7 & sleep 5 && cd /jepsen/tests/mallory/dqlite && lein run test --workload append --nemesis all --time-limit {} --test-count 1 ; cp /jepsen/tests/mallory/dqlite/store/latest/mediator.log /host",
-		_args.time_limit
-	);
-	let result = sh.cmd("sudo")
-		.arg(console_path.to_string_lossy().as_ref())
-		.arg(jepsen_cmd)
-		.run();
+	let jepsen_cmd = console_command(&args, suite);
+	logger.debug(format!("Executing in console: {}", jepsen_cmd));
+	// The containers are torn down even when the test fails.
+	let result = copy_plan(&args, logger, sh).and_then(|()| {
+		sh.cmd("sudo")
+			.arg(console_path.to_string_lossy().as_ref())
+			.arg(jepsen_cmd)
+			.run()
+			.context("Jepsen test failed in the control container")
+	});
 
 	sh.cmd("bash")
 		.arg("-c")
@@ -80,7 +248,86 @@ pub fn run(_args: RunMalloryArgs, logger: &Logger, sh: &Shell) -> Result<()> {
 		.run()
 		.context("Failed to stop Jepsen containers")?;
 	 
+	result?;
 	Ok(())
 	
 }
 
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn parse_args(argv: &[&str]) -> RunMalloryArgs {
+		RunMalloryArgs::try_parse_from([&["mallory"], argv].concat()).unwrap()
+	}
+
+	#[test]
+	fn test_console_command_defaults_to_dqlite() {
+		let args = parse_args(&[]);
+		let suite = validate(&args).unwrap();
+		assert_eq!(
+			console_command(&args, suite),
+			"cd /jepsen/mediator && ./target/x86_64-unknown-linux-musl/release/mediator qlearning event_history 0.7 & sleep 5 && cd /jepsen/tests/mallory/dqlite && lein run test --workload append --nemesis all --time-limit 65 --test-count 1 ; status=$? ; mkdir -p /host ; cp /jepsen/tests/mallory/dqlite/store/latest/{mediator.log,results.edn} /host ; exit $status"
+		);
+	}
+
+	#[test]
+	fn test_console_command_for_other_suites() {
+		let args = parse_args(&[
+			"--suite", "braft", "--scheduler", "power", "--feedback", "afl_branch",
+			"--similarity-threshold", "0.5", "--test-count", "3", "--artifacts-dir", "/host/runs",
+			"--schedule-duration-ms", "10000",
+		]);
+		let command = console_command(&args, validate(&args).unwrap());
+		assert!(command.contains("cd /jepsen/mediator && MED_NEMESIS_SCHEDULE_DURATION_MS=10000 ./target/x86_64-unknown-linux-musl/release/mediator power afl_branch 0.5 &"));
+		assert!(command.contains("lein run test --workload wr-register --time-limit 65 --test-count 3"));
+		assert!(command.ends_with("cp /jepsen/tests/mallory/braft/store/latest/{mediator.log,results.edn} /host/runs ; exit $status"));
+		assert_eq!(
+			host_path(Path::new(HOST_ARTIFACTS_DIR), &args.artifacts_dir),
+			Some(PathBuf::from("/var/log/abstraktor/runs"))
+		);
+		assert_eq!(host_path(Path::new(HOST_ARTIFACTS_DIR), "/tmp/runs"), None);
+		assert_eq!(
+			artifacts(&args),
+			[
+				"/var/log/abstraktor/runs/mediator.log",
+				"/var/log/abstraktor/runs/results.edn",
+			]
+		);
+
+		let args = parse_args(&["--suite", "mongodb", "--nemesis", "partition,kill", "--test-arg", "--nemesis-interval", "--test-arg", "10"]);
+		let command = console_command(&args, validate(&args).unwrap());
+		assert!(command.contains("--workload list-append --nemesis partition,kill --nodes-file ~/nodes --sharded --nemesis-interval 10"));
+
+		let args = parse_args(&["--test-arg", "--db-opts", "--test-arg", "a b; rm -rf /", "--artifacts-dir", "/host/it's here"]);
+		let command = console_command(&args, validate(&args).unwrap());
+		assert!(command.contains("--db-opts 'a b; rm -rf /' --time-limit"));
+		assert!(command.ends_with("results.edn} '/host/it'\\''s here' ; exit $status"));
+	}
+
+	#[test]
+	fn test_validate_rejects_bad_combinations() {
+		let cases: [(&[&str], &str); 8] = [
+			(&["--suite", "tikv"], "unknown test suite 'tikv'"),
+			(&["--workload", "list-append"], "suite dqlite has no workload 'list-append'"),
+			(&["--suite", "braft", "--nemesis", "all"], "does not take --nemesis"),
+			(&["--nemesis", "partition,island"], "no nemesis fault 'island'"),
+			(&["--similarity-threshold", "1.5"], "between 0 and 1"),
+			(&["--scheduler", "planned"], "needs --plan"),
+			(&["--plan", "Cargo.toml"], "only read by the planned scheduler"),
+			(&["--scheduler", "planned", "--plan", "missing.txt"], "plan missing.txt not found"),
+		];
+		for (argv, expected) in cases {
+			let err = validate(&parse_args(argv)).unwrap_err();
+			assert!(err.to_string().contains(expected), "{}: {}", expected, err);
+		}
+
+		let args = parse_args(&["--scheduler", "planned", "--plan", "Cargo.toml"]);
+		let command = console_command(&args, validate(&args).unwrap());
+		assert!(command.contains(
+			"MED_PLANNED_SCHEDULES_FILENAME=/jepsen/mediator/planned-schedules.txt ./target/"
+		));
+		assert!(RunMalloryArgs::try_parse_from(["mallory", "--feedback", "branch"]).is_err());
+	}
+}
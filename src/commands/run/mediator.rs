@@ -1,26 +1,260 @@
-use std::path::{self, Path};
+use std::fs;
+use std::path::{self, Path, PathBuf};
 
 use anyhow::{Context, Result};
-use clap::Parser;
+use clap::{Args, Parser, ValueEnum};
 use xshell::Shell;
 
+use crate::commands::runs::{self, Invocation};
 use crate::logger::Logger;
 
-const MEDIATOR_BIN_REL: &str = "mallory/mediator/target/x86_64-unknown-linux-musl/release/mediator";
-const ALGORITHM: &str = "qlearning";
-const TABLE: &str = "event_history";
-const REWARD: &str = "0.7";
+pub const MEDIATOR_BIN_REL: &str = "mallory/mediator/target/x86_64-unknown-linux-musl/release/mediator";
+pub const MEDIATOR_CONFIG: &str = "mallory/mediator/Mediator.toml";
+
+/// Keys the mediator reads from its config without a fallback.
+const REQUIRED_CONFIG_KEYS: [&str; 17] = [
+	"experiment_network",
+	"unfiltered_ports",
+	"node_name_format",
+	"bucket_interval_ms",
+	"clocks_synchronized_to_within_ms",
+	"statistics_print_interval_secs",
+	"nfqueue_queue_num",
+	"nfqueue_max_len",
+	"nfqueue_select_timeout_ns",
+	"nemesis_schedule_duration_ms",
+	"nemesis_schedule_interval_ms",
+	"nemesis_reset_duration_ms",
+	"mediator_log_filename",
+	"shiviz_log_filename",
+	"event_log_filename",
+	"iptables_rules_filename",
+	"mediator_store_folder",
+];
+
+/// Overrides `planned_schedules_filename` in the mediator config.
+pub const PLAN_ENV: &str = "MED_PLANNED_SCHEDULES_FILENAME";
+
+/// Config keys naming the logs the mediator writes.
+const LOG_KEYS: [&str; 4] = [
+	"mediator_log_filename",
+	"shiviz_log_filename",
+	"event_log_filename",
+	"iptables_rules_filename",
+];
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
+pub enum Scheduler {
+	Noop,
+	Power,
+	Qlearning,
+	Planned,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
+#[value(rename_all = "snake_case")]
+pub enum Feedback {
+	EventHistory,
+	AflBranch,
+	AflBranchAndEventHistory,
+}
+
+/// How the mediator picks and scores nemesis schedules. Shared by `run
+/// mediator` and `run mallory`, which starts the mediator in the control
+/// container.
+#[derive(Args, Debug, Clone)]
+pub struct MediatorOptions {
+	#[arg(long, value_enum, default_value = "qlearning")]
+	pub scheduler: Scheduler,
+	#[arg(long, value_enum, default_value = "event_history")]
+	pub feedback: Feedback,
+	#[arg(long, default_value = "0.7", help = "State similarity threshold, between 0 and 1")]
+	pub similarity_threshold: f64,
+	#[arg(long, help = "How long a nemesis schedule runs (default: from the mediator config)")]
+	pub schedule_duration_ms: Option<u64>,
+	#[arg(long, help = "Time between two nemesis choices (default: from the mediator config)")]
+	pub schedule_interval_ms: Option<u64>,
+	#[arg(long, help = "Pause after a reset before the next schedule (default: from the mediator config)")]
+	pub reset_duration_ms: Option<u64>,
+	#[arg(long, help = "Schedules for the planned scheduler, as written by plan-faults")]
+	pub plan: Option<String>,
+}
+
+impl Scheduler {
+	pub fn name(self) -> &'static str {
+		match self {
+			Scheduler::Noop => "noop",
+			Scheduler::Power => "power",
+			Scheduler::Qlearning => "qlearning",
+			Scheduler::Planned => "planned",
+		}
+	}
+}
+
+impl Feedback {
+	pub fn name(self) -> &'static str {
+		match self {
+			Feedback::EventHistory => "event_history",
+			Feedback::AflBranch => "afl_branch",
+			Feedback::AflBranchAndEventHistory => "afl_branch_and_event_history",
+		}
+	}
+}
+
+impl MediatorOptions {
+	pub fn validate(&self) -> Result<()> {
+		if !(0.0..=1.0).contains(&self.similarity_threshold) {
+			anyhow::bail!(
+				"similarity threshold must be between 0 and 1, got {}",
+				self.similarity_threshold
+			);
+		}
+		if self.schedule_interval_ms == Some(0) {
+			anyhow::bail!("--schedule-interval-ms must be at least 1");
+		}
+		if let (Some(duration), Some(interval)) = (self.schedule_duration_ms, self.schedule_interval_ms)
+			&& interval > duration
+		{
+			anyhow::bail!(
+				"--schedule-interval-ms ({}) is longer than --schedule-duration-ms ({})",
+				interval,
+				duration
+			);
+		}
+		if let Some(plan) = &self.plan {
+			if self.scheduler != Scheduler::Planned {
+				anyhow::bail!("--plan is only read by the planned scheduler");
+			}
+			if !Path::new(plan).is_file() {
+				anyhow::bail!("plan {} not found", plan);
+			}
+		}
+		Ok(())
+	}
+
+	/// Positional arguments of the mediator binary.
+	pub fn args(&self) -> [String; 3] {
+		[
+			self.scheduler.name().to_string(),
+			self.feedback.name().to_string(),
+			self.similarity_threshold.to_string(),
+		]
+	}
+
+	/// Overrides the mediator reads from `MED_`-prefixed environment variables.
+	pub fn env(&self) -> Vec<(&'static str, String)> {
+		[
+			("MED_NEMESIS_SCHEDULE_DURATION_MS", self.schedule_duration_ms),
+			("MED_NEMESIS_SCHEDULE_INTERVAL_MS", self.schedule_interval_ms),
+			("MED_NEMESIS_RESET_DURATION_MS", self.reset_duration_ms),
+		]
+		.into_iter()
+		.filter_map(|(name, value)| value.map(|value| (name, value.to_string())))
+		.collect()
+	}
+}
 
 #[derive(Parser, Debug)]
-pub struct RunMediatorArgs {}
+pub struct RunMediatorArgs {
+	#[command(flatten)]
+	pub options: MediatorOptions,
+	#[arg(long, default_value = MEDIATOR_CONFIG, help = "Mediator configuration file")]
+	pub config: String,
+}
+
+/// Checks the config the mediator will read, so that a missing key fails
+/// here rather than after the firewall rules are installed.
+pub fn validate_config(path: &Path, options: &MediatorOptions) -> Result<()> {
+	let content = fs::read_to_string(path)
+		.with_context(|| format!("reading mediator config from {}", path.display()))?;
+	let config: toml::Table = toml::from_str(&content)
+		.with_context(|| format!("parsing mediator config {}", path.display()))?;
+	let missing: Vec<_> = REQUIRED_CONFIG_KEYS
+		.iter()
+		.filter(|key| !config.contains_key(**key))
+		.copied()
+		.collect();
+	if !missing.is_empty() {
+		anyhow::bail!(
+			"mediator config {} is missing {}",
+			path.display(),
+			missing.join(", ")
+		);
+	}
+	if options.scheduler == Scheduler::Planned
+		&& options.plan.is_none()
+		&& !config.contains_key("planned_schedules_filename")
+	{
+		anyhow::bail!(
+			"the planned scheduler needs --plan or planned_schedules_filename in {}",
+			path.display()
+		);
+	}
+	Ok(())
+}
 
-pub fn run(_args: RunMediatorArgs, logger: &Logger, sh: &Shell) -> Result<()> {
+/// The mediator looks for `Mediator.toml` in its working directory, so a
+/// config with another name is copied to a scratch directory under that name.
+fn config_dir(config: &Path) -> Result<PathBuf> {
+	let dir = working_dir(config);
+	if config.file_name().is_some_and(|name| name == "Mediator.toml") {
+		return Ok(dir);
+	}
+	fs::create_dir_all(&dir).with_context(|| format!("creating {}", dir.display()))?;
+	fs::copy(config, dir.join("Mediator.toml"))
+		.with_context(|| format!("copying {} to {}", config.display(), dir.display()))?;
+	Ok(dir)
+}
+
+/// Where the mediator runs, and so what relative paths in its config are
+/// relative to.
+fn working_dir(config: &Path) -> PathBuf {
+	if config.file_name().is_some_and(|name| name == "Mediator.toml") {
+		config.parent().unwrap_or(Path::new("")).to_path_buf()
+	} else {
+		std::env::temp_dir().join("abstraktor-mediator")
+	}
+}
+
+/// Log files the mediator writes, as named in its config. An unreadable
+/// config has none; `run` reports why.
+pub fn artifacts(config_path: &Path) -> Vec<String> {
+	let Some(config) = fs::read_to_string(config_path)
+		.ok()
+		.and_then(|content| toml::from_str::<toml::Table>(&content).ok())
+	else {
+		return Vec::new();
+	};
+	LOG_KEYS
+		.iter()
+		.filter_map(|key| config.get(*key)?.as_str())
+		.map(|file| working_dir(config_path).join(file).display().to_string())
+		.collect()
+}
+
+/// `run`, recorded in the run registry.
+pub fn run_recorded(args: RunMediatorArgs, logger: &Logger, sh: &Shell) -> Result<()> {
+	let config = args.config.clone();
+	let invocation = Invocation {
+		command: "run mediator",
+		args: format!("{:?}", args),
+		mediator_config: Some(Path::new(&config)),
+		artifacts: artifacts(Path::new(&config)),
+	};
+	runs::record(invocation, logger, sh, || run(args, logger, sh))
+}
+
+pub fn run(args: RunMediatorArgs, logger: &Logger, sh: &Shell) -> Result<()> {
 	logger.log("Starting Mediator");
-	
+	args.options.validate()?;
+	let config_path = path::absolute(Path::new(&args.config))
+		.context("Failed to absolutize mediator config path")?;
+	validate_config(&config_path, &args.options)?;
+
 	let bin_path = path::absolute(Path::new(MEDIATOR_BIN_REL))
 		.context("Failed to absolutize mediator binary path")?;
 	logger.debug(format!("Mediator binary path: {}", bin_path.display()));
-	
+
 	if !bin_path.exists() {
 		logger.error(format!(
 			"Mediator binary not found at {}",
@@ -33,18 +267,30 @@ pub fn run(_args: RunMediatorArgs, logger: &Logger, sh: &Shell) -> Result<()> {
 		));
 	}
 
+	let mediator_args = args.options.args();
 	logger.log("Launching mediator with configuration:");
-	logger.log(format!("  Algorithm: {}", ALGORITHM));
-	logger.log(format!("  Table: {}", TABLE));
-	logger.log(format!("  Reward: {}", REWARD));
-	logger.debug(format!("Executing: sudo {} {} {} {}", 
-		bin_path.to_string_lossy(), ALGORITHM, TABLE, REWARD));
-	
+	logger.log(format!("  Scheduler: {}", mediator_args[0]));
+	logger.log(format!("  Feedback: {}", mediator_args[1]));
+	logger.log(format!("  Similarity threshold: {}", mediator_args[2]));
+	logger.log(format!("  Config: {}", config_path.display()));
+	let mut env = args.options.env();
+	if let Some(plan) = &args.options.plan {
+		let plan = path::absolute(plan).context("Failed to absolutize plan path")?;
+		env.push((PLAN_ENV, plan.display().to_string()));
+	}
+	for (name, value) in &env {
+		logger.log(format!("  {}={}", name, value));
+	}
+	logger.debug(format!("Executing: sudo {} {}",
+		bin_path.to_string_lossy(), mediator_args.join(" ")));
+
+	// sudo resets the environment, so the overrides go through env(1).
+	let _dir = sh.push_dir(config_dir(&config_path)?);
 	sh.cmd("sudo")
+		.arg("env")
+		.args(env.iter().map(|(name, value)| format!("{}={}", name, value)))
 		.arg(bin_path.to_string_lossy().as_ref())
-		.arg(ALGORITHM)
-		.arg(TABLE)
-		.arg(REWARD)
+		.args(mediator_args)
 		.run()
 		.context("Failed to run mediator")?;
 
@@ -52,3 +298,86 @@ pub fn run(_args: RunMediatorArgs, logger: &Logger, sh: &Shell) -> Result<()> {
 	Ok(())
 }
 
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use tempfile::TempDir;
+
+	fn parse_args(argv: &[&str]) -> RunMediatorArgs {
+		RunMediatorArgs::try_parse_from([&["mediator"], argv].concat()).unwrap()
+	}
+
+	#[test]
+	fn test_defaults_match_previous_invocation() {
+		let args = parse_args(&[]);
+		assert_eq!(args.options.args(), ["qlearning", "event_history", "0.7"]);
+		assert!(args.options.env().is_empty());
+		assert_eq!(args.config, MEDIATOR_CONFIG);
+	}
+
+	#[test]
+	fn test_options_are_typed_and_validated() {
+		let args = parse_args(&[
+			"--scheduler", "power", "--feedback", "afl_branch_and_event_history",
+			"--schedule-duration-ms", "10000", "--schedule-interval-ms", "500",
+		]);
+		assert_eq!(args.options.args(), ["power", "afl_branch_and_event_history", "0.7"]);
+		assert_eq!(
+			args.options.env(),
+			[
+				("MED_NEMESIS_SCHEDULE_DURATION_MS", "10000".to_string()),
+				("MED_NEMESIS_SCHEDULE_INTERVAL_MS", "500".to_string()),
+			]
+		);
+		args.options.validate().unwrap();
+
+		assert!(RunMediatorArgs::try_parse_from(["mediator", "--scheduler", "random"]).is_err());
+		let err = parse_args(&["--similarity-threshold", "1.5"]).options.validate().unwrap_err();
+		assert!(err.to_string().contains("between 0 and 1"));
+		let err = parse_args(&["--schedule-duration-ms", "100", "--schedule-interval-ms", "200"])
+			.options
+			.validate()
+			.unwrap_err();
+		assert!(err.to_string().contains("longer than"));
+	}
+
+	#[test]
+	fn test_validate_config() {
+		let shipped = Path::new(MEDIATOR_CONFIG);
+		if !shipped.exists() {
+			return;
+		}
+		let planned = parse_args(&["--scheduler", "planned"]).options;
+		validate_config(shipped, &planned).unwrap();
+
+		let temp_dir = TempDir::new().unwrap();
+		let config = temp_dir.path().join("mediator.toml");
+		let content = fs::read_to_string(shipped).unwrap();
+		let without_plans: String = content
+			.lines()
+			.filter(|line| !line.starts_with("planned_schedules_filename"))
+			.map(|line| format!("{}\n", line))
+			.collect();
+		fs::write(&config, &without_plans).unwrap();
+		validate_config(&config, &parse_args(&[]).options).unwrap();
+		let err = validate_config(&config, &planned).unwrap_err();
+		assert!(err.to_string().contains("planned_schedules_filename"));
+		let with_plan = parse_args(&["--scheduler", "planned", "--plan", "Mediator.toml"]).options;
+		validate_config(&config, &with_plan).unwrap();
+
+		assert_eq!(
+			artifacts(&config),
+			[
+				"/tmp/mediator-logs/mediator.log",
+				"/tmp/mediator-logs/shiviz.log",
+				"/tmp/mediator-logs/events.log",
+				"/tmp/mediator-logs/iptables-rules.txt",
+			]
+		);
+
+		fs::write(&config, "experiment_network = \"10.1.0.0/16\"\n").unwrap();
+		assert!(artifacts(&config).is_empty());
+		let err = validate_config(&config, &planned).unwrap_err();
+		assert!(err.to_string().contains("missing unfiltered_ports"));
+	}
+}
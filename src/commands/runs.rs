@@ -0,0 +1,557 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand};
+use xshell::Shell;
+
+use crate::logger::Logger;
+use crate::model::pipeline::Fingerprint;
+use crate::model::project::ProjectConfig;
+use crate::model::run_registry::{
+    ARTIFACTS_DIR, GcPolicy, REGISTRY_DIR, RunManifest, RunRegistry, RunStatus, SutInfo,
+    format_timestamp, gc_candidates, manifest_diff,
+};
+
+/// Read for the SUT and its annotated sources when it is there.
+const PROJECT_CONFIG: &str = "abstraktor.toml";
+
+#[derive(Parser, Debug)]
+pub struct RunsArgs {
+    #[arg(long, default_value = REGISTRY_DIR, help = "Run registry directory")]
+    pub registry: String,
+
+    #[command(subcommand)]
+    pub command: RunsSubcommand,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum RunsSubcommand {
+    /// List recorded runs, oldest first.
+    List(RunsListArgs),
+    /// Print a run's manifest, or how it differs from another run.
+    Show(RunsShowArgs),
+    /// Remove recorded runs.
+    Gc(RunsGcArgs),
+}
+
+#[derive(Parser, Debug)]
+pub struct RunsListArgs {
+    #[arg(long, help = "Only runs of this command, e.g. \"run mallory\"")]
+    pub command: Option<String>,
+
+    #[arg(long, default_value = "false", help = "Only failed runs")]
+    pub failed: bool,
+}
+
+#[derive(Parser, Debug)]
+pub struct RunsShowArgs {
+    #[arg(help = "Run id, unique prefix of one, or latest")]
+    pub run: String,
+
+    #[arg(
+        long,
+        value_name = "RUN",
+        help = "List the fields that differ from this run"
+    )]
+    pub compare: Option<String>,
+}
+
+#[derive(Parser, Debug)]
+pub struct RunsGcArgs {
+    #[arg(long, help = "Keep the newest N runs")]
+    pub keep: Option<usize>,
+
+    #[arg(
+        long,
+        value_name = "DAYS",
+        help = "Only runs started at least DAYS ago"
+    )]
+    pub older_than: Option<u64>,
+
+    #[arg(long, default_value = "false", help = "Only failed runs")]
+    pub failed: bool,
+
+    #[arg(
+        long,
+        default_value = "false",
+        help = "Only print which runs would be removed"
+    )]
+    pub dry_run: bool,
+}
+
+/// What `record` knows about an invocation before it starts.
+pub struct Invocation<'a> {
+    pub command: &'a str,
+    pub args: String,
+    pub mediator_config: Option<&'a Path>,
+    pub artifacts: Vec<String>,
+}
+
+pub fn run(args: RunsArgs, logger: &Logger) -> Result<()> {
+    let registry = RunRegistry::new(&args.registry);
+    match args.command {
+        RunsSubcommand::List(list) => list_runs(&registry, list, logger),
+        RunsSubcommand::Show(show) => show_run(&registry, show, logger),
+        RunsSubcommand::Gc(gc) => gc_runs(&registry, gc, logger),
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+/// Runs `execute` with a run directory in the registry, and records how it
+/// ended. A registry that cannot be written does not stop the run.
+pub fn record(
+    invocation: Invocation,
+    logger: &Logger,
+    sh: &Shell,
+    execute: impl FnOnce() -> Result<()>,
+) -> Result<()> {
+    record_in(
+        &RunRegistry::new(REGISTRY_DIR),
+        invocation,
+        logger,
+        sh,
+        execute,
+    )
+}
+
+fn record_in(
+    registry: &RunRegistry,
+    invocation: Invocation,
+    logger: &Logger,
+    sh: &Shell,
+    execute: impl FnOnce() -> Result<()>,
+) -> Result<()> {
+    let mut manifest = RunManifest::new(invocation.command, invocation.args, now());
+    manifest.artifacts = invocation.artifacts;
+    manifest.pid = Some(std::process::id());
+    manifest.seeds = std::env::vars()
+        .filter(|(name, _)| name.ends_with("SEED"))
+        .collect();
+    if Path::new(PROJECT_CONFIG).exists() {
+        match ProjectConfig::from_file(PROJECT_CONFIG) {
+            Ok(project) => {
+                manifest.sut = Some(SutInfo {
+                    revision: git_revision(&project.sut.path, sh),
+                    path: project.sut.path.clone(),
+                });
+                manifest.targets_hash = targets_hash(&project);
+            }
+            Err(err) => logger.debug(format!("Not recording the SUT: {:#}", err)),
+        }
+    }
+
+    let dir = match registry.create(&mut manifest) {
+        Ok(dir) => dir,
+        Err(err) => {
+            logger.warning(format!("Not recording this run: {:#}", err));
+            return execute();
+        }
+    };
+    if let Some(config) = invocation.mediator_config
+        && let Ok(content) = fs::read_to_string(config)
+    {
+        manifest.mediator_config = Some(config.display().to_string());
+        let mut hash = Fingerprint::default();
+        hash.add(&content);
+        manifest.mediator_config_hash = Some(hash.finish());
+        if let Err(err) = fs::write(dir.join("Mediator.toml"), content) {
+            logger.debug(format!("Not copying the mediator config: {}", err));
+        }
+    }
+    if let Err(err) = registry.save(&manifest) {
+        logger.warning(format!("Not recording this run: {:#}", err));
+    }
+    logger.debug(format!(
+        "Recording run {} in {}",
+        manifest.id,
+        dir.display()
+    ));
+
+    let result = execute();
+    manifest.copies = copy_artifacts(&manifest.artifacts, &dir, logger);
+    manifest.finish(now(), result.as_ref().err().map(|err| format!("{:#}", err)));
+    match registry.save(&manifest) {
+        Ok(()) => logger.log(format!("Recorded run {}", manifest.id)),
+        Err(err) => logger.warning(format!("Not recording how the run ended: {:#}", err)),
+    }
+    result
+}
+
+/// Copies the artifacts that exist into the run directory, so that a later
+/// run writing to the same paths does not overwrite them. Returns where each
+/// went, relative to the run directory; one that cannot be copied is skipped.
+fn copy_artifacts(artifacts: &[String], dir: &Path, logger: &Logger) -> BTreeMap<String, String> {
+    let mut copies = BTreeMap::new();
+    for artifact in artifacts {
+        let source = Path::new(artifact);
+        let Some(name) = source.file_name().filter(|_| source.exists()) else {
+            continue;
+        };
+        let mut copy = Path::new(ARTIFACTS_DIR).join(name);
+        let mut n = 2;
+        while dir.join(&copy).exists() {
+            copy = Path::new(ARTIFACTS_DIR).join(format!("{}-{}", n, name.to_string_lossy()));
+            n += 1;
+        }
+        match copy_path(source, &dir.join(&copy)) {
+            Ok(()) => {
+                copies.insert(artifact.clone(), copy.display().to_string());
+            }
+            Err(err) => logger.warning(format!("Not copying {}: {:#}", artifact, err)),
+        }
+    }
+    copies
+}
+
+fn copy_path(source: &Path, dest: &Path) -> Result<()> {
+    if source.is_dir() {
+        fs::create_dir_all(dest).with_context(|| format!("creating {}", dest.display()))?;
+        let entries =
+            fs::read_dir(source).with_context(|| format!("reading {}", source.display()))?;
+        for entry in entries {
+            let entry = entry?;
+            copy_path(&entry.path(), &dest.join(entry.file_name()))?;
+        }
+        return Ok(());
+    }
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent).with_context(|| format!("creating {}", parent.display()))?;
+    }
+    fs::copy(source, dest)
+        .with_context(|| format!("copying {} to {}", source.display(), dest.display()))?;
+    Ok(())
+}
+
+fn git_revision(path: &str, sh: &Shell) -> Option<String> {
+    let git = |args: &[&str]| {
+        sh.cmd("git")
+            .arg("-C")
+            .arg(path)
+            .args(args)
+            .quiet()
+            .ignore_stderr()
+            .read()
+            .ok()
+    };
+    // A SUT vendored inside another repository has no history of its own;
+    // `rev-parse HEAD` would give the enclosing repository's commit.
+    let toplevel = git(&["rev-parse", "--show-toplevel"])?;
+    if fs::canonicalize(&toplevel).ok()? != fs::canonicalize(path).ok()? {
+        return None;
+    }
+    let revision = git(&["rev-parse", "HEAD"])?;
+    let dirty = git(&["status", "--porcelain"]).is_some_and(|status| !status.is_empty());
+    Some(if dirty {
+        format!("{}-dirty", revision)
+    } else {
+        revision
+    })
+}
+
+fn targets_hash(project: &ProjectConfig) -> Option<String> {
+    let mut hash = Fingerprint::default();
+    for source in project.sources() {
+        hash.add_path(Path::new(&source)).ok()?;
+    }
+    Some(hash.finish())
+}
+
+fn list_runs(registry: &RunRegistry, args: RunsListArgs, logger: &Logger) -> Result<()> {
+    let runs: Vec<_> = registry
+        .list()?
+        .into_iter()
+        .filter(|run| args.command.as_ref().is_none_or(|c| &run.command == c))
+        .filter(|run| !args.failed || run.status == RunStatus::Failed)
+        .collect();
+    if runs.is_empty() {
+        logger.log(format!("No runs recorded in {}", registry.root.display()));
+        return Ok(());
+    }
+    for run in &runs {
+        let revision = run
+            .sut
+            .as_ref()
+            .and_then(|sut| sut.revision.as_deref())
+            .map_or("-".to_string(), short_revision);
+        let duration = run
+            .duration_secs()
+            .map_or("-".to_string(), |secs| format!("{}s", secs));
+        logger.log(format!(
+            "{}  {:<9}  {:>7}  {}",
+            run.id,
+            if run.is_stale() {
+                "stale"
+            } else {
+                run.status.name()
+            },
+            duration,
+            revision
+        ));
+    }
+    logger.success(format!("{} run(s)", runs.len()));
+    Ok(())
+}
+
+/// The first 12 characters of a revision, keeping its `-dirty` mark.
+fn short_revision(revision: &str) -> String {
+    match revision.strip_suffix("-dirty") {
+        Some(clean) => format!("{}-dirty", clean.get(..12).unwrap_or(clean)),
+        None => revision.get(..12).unwrap_or(revision).to_string(),
+    }
+}
+
+fn show_run(registry: &RunRegistry, args: RunsShowArgs, logger: &Logger) -> Result<()> {
+    let run = registry.find(&args.run)?;
+    if let Some(other) = &args.compare {
+        let other = registry.find(other)?;
+        let diff = manifest_diff(&run, &other);
+        if diff.is_empty() {
+            logger.log(format!("{} and {} match", run.id, other.id));
+        }
+        for (field, a, b) in diff {
+            logger.log(format!(
+                "{}:\n  {}: {}\n  {}: {}",
+                field, run.id, a, other.id, b
+            ));
+        }
+        return Ok(());
+    }
+
+    logger.log(format!(
+        "Run {} ({})",
+        run.id,
+        registry.dir(&run.id).display()
+    ));
+    logger.log(format!("Command: {}", run.command));
+    logger.log(format!("Options: {}", run.args));
+    logger.log(format!("Status: {}", run.status.name()));
+    if let Some(error) = &run.error {
+        logger.log(format!("Error: {}", error));
+    }
+    logger.log(format!("Started: {} UTC", format_timestamp(run.started)));
+    if let Some(finished) = run.finished {
+        logger.log(format!("Finished: {} UTC", format_timestamp(finished)));
+    }
+    if let Some(sut) = &run.sut {
+        logger.log(format!(
+            "SUT: {} at {}",
+            sut.path,
+            sut.revision.as_deref().unwrap_or("unknown revision")
+        ));
+    }
+    if let Some(hash) = &run.targets_hash {
+        logger.log(format!("Targets: {}", hash));
+    }
+    if let (Some(config), Some(hash)) = (&run.mediator_config, &run.mediator_config_hash) {
+        logger.log(format!("Mediator config: {} ({})", config, hash));
+    }
+    for (name, value) in &run.seeds {
+        logger.log(format!("Seed: {}={}", name, value));
+    }
+    for artifact in &run.artifacts {
+        match run.copies.get(artifact) {
+            Some(copy) => logger.log(format!(
+                "Artifact: {} (copied to {})",
+                artifact,
+                registry.dir(&run.id).join(copy).display()
+            )),
+            None => logger.log(format!("Artifact: {} (not copied)", artifact)),
+        }
+    }
+    Ok(())
+}
+
+fn gc_runs(registry: &RunRegistry, args: RunsGcArgs, logger: &Logger) -> Result<()> {
+    let runs = registry.list()?;
+    let policy = GcPolicy {
+        keep: args.keep,
+        older_than_secs: args.older_than.map(|days| days * 86_400),
+        failed_only: args.failed,
+    };
+    let candidates = gc_candidates(&runs, policy, now());
+    for run in &candidates {
+        if args.dry_run {
+            logger.log(format!("Would remove {}", run.id));
+        } else {
+            registry
+                .remove(&run.id)
+                .with_context(|| format!("removing run {}", run.id))?;
+            logger.debug(format!("Removed {}", run.id));
+        }
+    }
+    if !args.dry_run {
+        logger.success(format!(
+            "Removed {} of {} run(s)",
+            candidates.len(),
+            runs.len()
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn create_test_logger() -> Logger {
+        Logger::new(crate::logger::LogLevel::Quiet)
+    }
+
+    fn registry_with_runs(temp_dir: &TempDir) -> RunRegistry {
+        let registry = RunRegistry::new(temp_dir.path().join("runs"));
+        for (started, error) in [(100, None), (200, Some("boom")), (300, None)] {
+            let mut manifest = RunManifest::new("export-graphs", String::new(), started);
+            registry.create(&mut manifest).unwrap();
+            manifest.finish(started + 10, error.map(str::to_string));
+            registry.save(&manifest).unwrap();
+        }
+        registry
+    }
+
+    fn gc_args(keep: Option<usize>, failed: bool, dry_run: bool) -> RunsGcArgs {
+        RunsGcArgs {
+            keep,
+            older_than: None,
+            failed,
+            dry_run,
+        }
+    }
+
+    #[test]
+    fn test_gc_removes_run_directories() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry = registry_with_runs(&temp_dir);
+        let logger = create_test_logger();
+
+        gc_runs(&registry, gc_args(None, true, true), &logger).unwrap();
+        assert_eq!(registry.list().unwrap().len(), 3);
+        gc_runs(&registry, gc_args(None, true, false), &logger).unwrap();
+        let runs = registry.list().unwrap();
+        assert_eq!(runs.len(), 2);
+        assert!(runs.iter().all(|run| run.status == RunStatus::Succeeded));
+
+        gc_runs(&registry, gc_args(Some(1), false, false), &logger).unwrap();
+        let runs = registry.list().unwrap();
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].started, 300);
+    }
+
+    #[test]
+    fn test_show_and_compare() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry = registry_with_runs(&temp_dir);
+        let logger = create_test_logger();
+        let show = |run: &str, compare: Option<&str>| {
+            let args = RunsShowArgs {
+                run: run.to_string(),
+                compare: compare.map(str::to_string),
+            };
+            show_run(&registry, args, &logger)
+        };
+
+        show("latest", None).unwrap();
+        show("19700101-000140", Some("latest")).unwrap();
+        assert!(show("1970", None).is_err());
+        assert!(show("nope", None).is_err());
+    }
+
+    #[test]
+    fn test_git_revision_of_a_vendored_sut() {
+        let temp_dir = TempDir::new().unwrap();
+        let sh = Shell::new().unwrap();
+        let repo = temp_dir.path();
+        let vendored = repo.join("vendor").join("raft");
+        fs::create_dir_all(&vendored).unwrap();
+        fs::write(vendored.join("raft.c"), "int x;\n").unwrap();
+        let git = |args: &[&str]| {
+            sh.cmd("git")
+                .arg("-C")
+                .arg(repo)
+                .args(["-c", "user.name=t", "-c", "user.email=t@t"])
+                .args(args)
+                .quiet()
+                .ignore_stdout()
+                .ignore_stderr()
+                .run()
+                .unwrap()
+        };
+        git(&["init", "-q"]);
+        git(&["add", "."]);
+        git(&["commit", "-q", "-m", "init"]);
+
+        let revision = git_revision(repo.to_str().unwrap(), &sh).unwrap();
+        assert_eq!(revision.len(), 40);
+        assert_eq!(short_revision(&revision), revision[..12]);
+        assert_eq!(short_revision("abc1234-dirty"), "abc1234-dirty");
+        assert_eq!(short_revision("abc"), "abc");
+        assert_eq!(git_revision(vendored.to_str().unwrap(), &sh), None);
+    }
+
+    #[test]
+    fn test_record_marks_how_runs_end() {
+        let temp_dir = TempDir::new().unwrap();
+        let sh = Shell::new().unwrap();
+        let registry = RunRegistry::new(temp_dir.path().join("runs"));
+        let config = temp_dir.path().join("Mediator.toml");
+        fs::write(&config, "scheduler = \"power\"\n").unwrap();
+        let logger = create_test_logger();
+
+        let logs = temp_dir.path().join("mediator-logs");
+        let log = logs.join("mediator.log");
+        let missing = temp_dir.path().join("shiviz.log").display().to_string();
+        let invocation = Invocation {
+            command: "run mediator",
+            args: "RunMediatorArgs".to_string(),
+            mediator_config: Some(&config),
+            artifacts: vec![logs.display().to_string(), missing.clone()],
+        };
+        let err = record_in(&registry, invocation, &logger, &sh, || {
+            fs::create_dir_all(&logs).unwrap();
+            fs::write(&log, "run 1").unwrap();
+            anyhow::bail!("no mediator")
+        })
+        .unwrap_err();
+        assert_eq!(err.to_string(), "no mediator");
+
+        let run = registry.find("latest").unwrap();
+        assert_eq!(run.status, RunStatus::Failed);
+        assert_eq!(run.error.as_deref(), Some("no mediator"));
+        assert!(run.mediator_config_hash.is_some());
+        assert!(registry.dir(&run.id).join("Mediator.toml").is_file());
+        assert_eq!(run.pid, Some(std::process::id()));
+        assert_eq!(run.artifacts, [logs.display().to_string(), missing]);
+        // A later run writing the same log leaves this run's copy alone.
+        fs::write(&log, "run 2").unwrap();
+        let copy = registry.dir(&run.id).join(&run.copies[&run.artifacts[0]]);
+        assert_eq!(copy, registry.dir(&run.id).join("artifacts/mediator-logs"));
+        assert_eq!(
+            fs::read_to_string(copy.join("mediator.log")).unwrap(),
+            "run 1"
+        );
+        assert_eq!(run.copies.len(), 1);
+
+        let invocation = Invocation {
+            command: "export-graphs",
+            args: "ExportGraphsArgs".to_string(),
+            mediator_config: None,
+            artifacts: Vec::new(),
+        };
+        record_in(&registry, invocation, &logger, &sh, || Ok(())).unwrap();
+        let runs = registry.list().unwrap();
+        let export = runs
+            .iter()
+            .find(|run| run.command == "export-graphs")
+            .unwrap();
+        assert_eq!(export.status, RunStatus::Succeeded);
+        assert!(export.finished.is_some());
+    }
+}
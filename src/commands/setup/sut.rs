@@ -178,7 +178,7 @@ mod tests {
         };
         assert_eq!(args.path, "/test/path");
         assert_eq!(args.destination, Some("custom_dest".to_string()));
-        assert_eq!(args.rebuild, false);
+        assert!(!args.rebuild);
     }
 
     #[test]
@@ -190,7 +190,7 @@ mod tests {
         };
         assert_eq!(args.path, "/test/path");
         assert!(args.destination.is_none());
-        assert_eq!(args.rebuild, false);
+        assert!(!args.rebuild);
     }
 
     #[test]
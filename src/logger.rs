@@ -192,7 +192,7 @@ mod tests {
     fn test_display_trait_with_numbers() {
         let logger = Logger::new(LogLevel::Debug);
         logger.debug(42);
-        logger.log(3.14);
+        logger.log(2.5);
         logger.success(100u64);
     }
 }
@@ -19,8 +19,18 @@ fn main() -> Result<()> {
         AbstraktorSubcommand::Setup(args) => commands::setup::run(args, &logger, &sh)?,
         AbstraktorSubcommand::Run(args) => commands::run::run(args, &logger, &sh)?,
         AbstraktorSubcommand::ExportGraphs(args) => {
-            commands::export_graphs::run(args, &logger, &sh)?
+            commands::export_graphs::run_recorded(args, &logger, &sh)?
         }
+        AbstraktorSubcommand::CheckSpec(args) => commands::check_spec::run(args, &logger)?,
+        AbstraktorSubcommand::PlanFaults(args) => commands::plan_faults::run(args, &logger)?,
+        AbstraktorSubcommand::Coverage(args) => commands::coverage::run(args, &logger)?,
+        AbstraktorSubcommand::Report(args) => commands::report::run(args, &logger)?,
+        AbstraktorSubcommand::Pipeline(args) => commands::pipeline::run(args, &logger, &sh)?,
+        AbstraktorSubcommand::Experiment(args) => {
+            commands::experiment::run(args, &logger, &sh)?
+        }
+        AbstraktorSubcommand::Runs(args) => commands::runs::run(args, &logger)?,
+        AbstraktorSubcommand::Doctor(args) => commands::doctor::run(args, &logger, &sh)?,
     };
     logger.outro();
     Ok(())
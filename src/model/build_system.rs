@@ -0,0 +1,489 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use super::pipeline::Fingerprint;
+
+/// How a SUT is built. Every variant picks the compiler up from `CC` and
+/// `CXX`, so the instrumenting compiler only has to be in the environment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BuildSystem {
+    /// `./install.sh`, the layout the bundled SUTs use.
+    Script,
+    Make,
+    CMake,
+    Autotools,
+    Meson,
+    Custom(String),
+}
+
+impl BuildSystem {
+    /// Looks at the files in `source`; a build script wins over the build
+    /// files it may drive.
+    pub fn detect(source: &Path) -> Option<BuildSystem> {
+        let has = |name: &str| source.join(name).is_file();
+        if has("install.sh") {
+            Some(BuildSystem::Script)
+        } else if has("meson.build") {
+            Some(BuildSystem::Meson)
+        } else if has("CMakeLists.txt") {
+            Some(BuildSystem::CMake)
+        } else if has("configure") || has("configure.ac") {
+            Some(BuildSystem::Autotools)
+        } else if ["GNUmakefile", "makefile", "Makefile"]
+            .iter()
+            .any(|name| has(name))
+        {
+            Some(BuildSystem::Make)
+        } else {
+            None
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            BuildSystem::Script => "script",
+            BuildSystem::Make => "make",
+            BuildSystem::CMake => "cmake",
+            BuildSystem::Autotools => "autotools",
+            BuildSystem::Meson => "meson",
+            BuildSystem::Custom(_) => "custom",
+        }
+    }
+
+    /// Whether `build_plan` recompiles every source, so that the pass sees
+    /// every annotated file. A build script or custom command may skip what
+    /// an earlier build left up to date.
+    pub fn rebuilds_everything(&self) -> bool {
+        !matches!(self, BuildSystem::Script | BuildSystem::Custom(_))
+    }
+
+    /// Whether the build can go to a directory other than the sources.
+    pub fn out_of_tree(&self) -> bool {
+        matches!(
+            self,
+            BuildSystem::CMake | BuildSystem::Autotools | BuildSystem::Meson
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildStep {
+    pub dir: PathBuf,
+    /// Run with `sh -c`.
+    pub command: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildPlan {
+    pub build_dir: PathBuf,
+    pub steps: Vec<BuildStep>,
+}
+
+/// Where CMake and Meson build when no directory is given: both refuse to
+/// build in the source tree.
+pub const DEFAULT_BUILD_DIR: &str = "build-abstraktor";
+
+/// The commands that build `source` with `system`. `build_dir` only applies
+/// to build systems that support out-of-tree builds.
+///
+/// The pass only writes the manifest and report for what it compiles, and
+/// `instrument` resets both, so a build directory left by an earlier run is
+/// reconfigured and rebuilt from scratch: CMake gets a fresh cache, Meson
+/// `--wipe`, autotools `make clean` and plain Make `-B`.
+pub fn build_plan(
+    system: &BuildSystem,
+    source: &Path,
+    build_dir: Option<&Path>,
+) -> Result<BuildPlan> {
+    if build_dir.is_some() && !system.out_of_tree() {
+        anyhow::bail!(
+            "{} builds cannot use a separate build directory",
+            system.name()
+        );
+    }
+    let step = |dir: &Path, command: String| BuildStep {
+        dir: dir.to_path_buf(),
+        command,
+    };
+    let quote = |path: &Path| shell_quote(&path.to_string_lossy());
+    let plan = match system {
+        BuildSystem::Script => BuildPlan {
+            build_dir: source.to_path_buf(),
+            steps: vec![step(source, "./install.sh".to_string())],
+        },
+        BuildSystem::Make => BuildPlan {
+            build_dir: source.to_path_buf(),
+            steps: vec![step(source, "make -B".to_string())],
+        },
+        BuildSystem::Custom(command) => BuildPlan {
+            build_dir: source.to_path_buf(),
+            steps: vec![step(source, command.clone())],
+        },
+        BuildSystem::CMake => {
+            let build = build_dir
+                .map(Path::to_path_buf)
+                .unwrap_or_else(|| source.join(DEFAULT_BUILD_DIR));
+            let mut steps = Vec::new();
+            // The cache keeps the compiler of the run that created it.
+            if build.join("CMakeCache.txt").is_file() {
+                steps.push(step(
+                    source,
+                    format!(
+                        "rm -rf {} {}",
+                        quote(&build.join("CMakeCache.txt")),
+                        quote(&build.join("CMakeFiles"))
+                    ),
+                ));
+            }
+            steps.push(step(
+                source,
+                format!(
+                    "cmake -S {} -B {} -DCMAKE_EXPORT_COMPILE_COMMANDS=ON",
+                    quote(source),
+                    quote(&build)
+                ),
+            ));
+            steps.push(step(
+                source,
+                format!("cmake --build {} --clean-first", quote(&build)),
+            ));
+            BuildPlan {
+                build_dir: build,
+                steps,
+            }
+        }
+        BuildSystem::Meson => {
+            let build = build_dir
+                .map(Path::to_path_buf)
+                .unwrap_or_else(|| source.join(DEFAULT_BUILD_DIR));
+            // `meson setup` refuses a directory it has already configured.
+            let wipe = if build.join("meson-private").is_dir() {
+                "--wipe "
+            } else {
+                ""
+            };
+            BuildPlan {
+                steps: vec![
+                    step(
+                        source,
+                        format!("meson setup {}{} {}", wipe, quote(&build), quote(source)),
+                    ),
+                    step(source, format!("meson compile -C {}", quote(&build))),
+                ],
+                build_dir: build,
+            }
+        }
+        BuildSystem::Autotools => {
+            let build = build_dir.unwrap_or(source).to_path_buf();
+            let mut steps = Vec::new();
+            if !source.join("configure").is_file() {
+                steps.push(step(source, "autoreconf -fi".to_string()));
+            }
+            steps.push(step(&build, format!("{}/configure", quote(source))));
+            if build.join("Makefile").is_file() {
+                steps.push(step(&build, "make clean".to_string()));
+            }
+            steps.push(step(&build, "make".to_string()));
+            BuildPlan {
+                build_dir: build,
+                steps,
+            }
+        }
+    };
+    Ok(plan)
+}
+
+/// Quotes `value` as one word for `sh`, leaving plain words alone.
+pub fn shell_quote(value: &str) -> String {
+    if value
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || "/._-+".contains(c))
+    {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', "'\\''"))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArtifactKind {
+    Executable,
+    SharedLibrary,
+    StaticLibrary,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Artifact {
+    pub path: String,
+    pub kind: ArtifactKind,
+    pub size: u64,
+    pub hash: String,
+}
+
+/// What an instrumented build produced, written next to the event id
+/// manifest for the stages after it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildRecord {
+    pub build_system: String,
+    pub source_dir: String,
+    pub build_dir: String,
+    pub steps: Vec<BuildStep>,
+    /// The compiler-related variables the build ran with.
+    pub env: Vec<(String, String)>,
+    pub artifacts: Vec<Artifact>,
+}
+
+impl BuildRecord {
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("reading build record from {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("parsing build record {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = serde_json::to_string_pretty(self)?;
+        fs::write(path, content).with_context(|| format!("writing {}", path.display()))
+    }
+
+    /// Artifacts that are gone or have changed since the build.
+    pub fn stale_artifacts(&self) -> Vec<&Artifact> {
+        self.artifacts
+            .iter()
+            .filter(|artifact| {
+                fs::read(&artifact.path).map_or(true, |content| hash(&content) != artifact.hash)
+            })
+            .collect()
+    }
+}
+
+fn hash(content: &[u8]) -> String {
+    Fingerprint::default().add_bytes(content).finish()
+}
+
+/// What a build output is, from its first bytes: ELF executables and shared
+/// objects, and `ar` archives. Object files and everything else are left out.
+pub fn artifact_kind(path: &Path, content: &[u8]) -> Option<ArtifactKind> {
+    const ET_EXEC: u16 = 2;
+    const ET_DYN: u16 = 3;
+    if content.starts_with(b"!<arch>\n") {
+        return Some(ArtifactKind::StaticLibrary);
+    }
+    if content.len() < 18 || &content[..4] != b"\x7fELF" {
+        return None;
+    }
+    let e_type = if content[5] == 2 {
+        u16::from_be_bytes([content[16], content[17]])
+    } else {
+        u16::from_le_bytes([content[16], content[17]])
+    };
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    match e_type {
+        ET_EXEC => Some(ArtifactKind::Executable),
+        // Position-independent executables are shared objects too.
+        ET_DYN if name.contains(".so") => Some(ArtifactKind::SharedLibrary),
+        ET_DYN => Some(ArtifactKind::Executable),
+        _ => None,
+    }
+}
+
+/// Build outputs below `dir` written at or after `since`. Hidden directories
+/// are skipped.
+pub fn find_artifacts(dir: &Path, since: SystemTime) -> Result<Vec<Artifact>> {
+    let mut artifacts = Vec::new();
+    collect_artifacts(dir, since, &mut artifacts)?;
+    artifacts.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(artifacts)
+}
+
+fn collect_artifacts(dir: &Path, since: SystemTime, artifacts: &mut Vec<Artifact>) -> Result<()> {
+    let entries =
+        fs::read_dir(dir).with_context(|| format!("reading directory {}", dir.display()))?;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Ok(meta) = fs::symlink_metadata(&path) else {
+            continue;
+        };
+        if meta.is_dir() {
+            if !entry.file_name().to_string_lossy().starts_with('.') {
+                collect_artifacts(&path, since, artifacts)?;
+            }
+            continue;
+        }
+        if !meta.is_file() || meta.modified().map_or(true, |modified| modified < since) {
+            continue;
+        }
+        let content = fs::read(&path).with_context(|| format!("reading {}", path.display()))?;
+        if let Some(kind) = artifact_kind(&path, &content) {
+            artifacts.push(Artifact {
+                path: path.to_string_lossy().into_owned(),
+                kind,
+                size: meta.len(),
+                hash: hash(&content),
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    fn elf(e_type: u16) -> Vec<u8> {
+        let mut content = vec![0u8; 64];
+        content[..6].copy_from_slice(b"\x7fELF\x02\x01");
+        content[16..18].copy_from_slice(&e_type.to_le_bytes());
+        content
+    }
+
+    #[test]
+    fn test_detect_build_system() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path();
+        assert_eq!(BuildSystem::detect(source), None);
+
+        fs::write(source.join("Makefile"), "all:\n").unwrap();
+        assert_eq!(BuildSystem::detect(source), Some(BuildSystem::Make));
+        fs::write(source.join("configure.ac"), "AC_INIT\n").unwrap();
+        assert_eq!(BuildSystem::detect(source), Some(BuildSystem::Autotools));
+        fs::write(source.join("CMakeLists.txt"), "project(raft)\n").unwrap();
+        assert_eq!(BuildSystem::detect(source), Some(BuildSystem::CMake));
+        fs::write(source.join("meson.build"), "project('raft', 'c')\n").unwrap();
+        assert_eq!(BuildSystem::detect(source), Some(BuildSystem::Meson));
+        fs::write(source.join("install.sh"), "#!/bin/sh\n").unwrap();
+        assert_eq!(BuildSystem::detect(source), Some(BuildSystem::Script));
+    }
+
+    #[test]
+    fn test_build_plans() {
+        let source = Path::new("/src/raft");
+        let commands = |system: BuildSystem, build_dir: Option<&Path>| {
+            let plan = build_plan(&system, source, build_dir).unwrap();
+            let commands: Vec<_> = plan.steps.into_iter().map(|s| s.command).collect();
+            (plan.build_dir, commands)
+        };
+
+        assert_eq!(
+            commands(BuildSystem::CMake, None),
+            (
+                source.join(DEFAULT_BUILD_DIR),
+                vec![
+                    "cmake -S /src/raft -B /src/raft/build-abstraktor -DCMAKE_EXPORT_COMPILE_COMMANDS=ON"
+                        .to_string(),
+                    "cmake --build /src/raft/build-abstraktor --clean-first".to_string()
+                ]
+            )
+        );
+        assert_eq!(
+            commands(BuildSystem::Meson, Some(Path::new("/tmp/my build"))).1,
+            [
+                "meson setup '/tmp/my build' /src/raft",
+                "meson compile -C '/tmp/my build'"
+            ]
+        );
+        let (build_dir, autotools) = commands(BuildSystem::Autotools, Some(Path::new("/tmp/b")));
+        assert_eq!(build_dir, Path::new("/tmp/b"));
+        assert_eq!(autotools, ["autoreconf -fi", "/src/raft/configure", "make"]);
+        assert_eq!(
+            commands(BuildSystem::Custom("make -j8 raft".to_string()), None).1,
+            ["make -j8 raft"]
+        );
+        assert_eq!(commands(BuildSystem::Make, None).1, ["make -B"]);
+        assert!(build_plan(&BuildSystem::Make, source, Some(Path::new("/tmp/b"))).is_err());
+    }
+
+    #[test]
+    fn test_build_plans_redo_an_earlier_build() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("raft");
+        let build = temp_dir.path().join("build");
+        fs::create_dir_all(build.join("meson-private")).unwrap();
+        fs::write(
+            build.join("CMakeCache.txt"),
+            "CMAKE_C_COMPILER:FILEPATH=/usr/bin/cc\n",
+        )
+        .unwrap();
+        fs::write(build.join("Makefile"), "all:\n").unwrap();
+        let commands = |system: BuildSystem| -> Vec<String> {
+            build_plan(&system, &source, Some(&build))
+                .unwrap()
+                .steps
+                .into_iter()
+                .map(|s| s.command.replace(&*temp_dir.path().to_string_lossy(), ""))
+                .collect()
+        };
+
+        assert_eq!(
+            commands(BuildSystem::CMake),
+            [
+                "rm -rf /build/CMakeCache.txt /build/CMakeFiles",
+                "cmake -S /raft -B /build -DCMAKE_EXPORT_COMPILE_COMMANDS=ON",
+                "cmake --build /build --clean-first",
+            ]
+        );
+        assert_eq!(
+            commands(BuildSystem::Meson),
+            ["meson setup --wipe /build /raft", "meson compile -C /build"]
+        );
+        assert_eq!(
+            commands(BuildSystem::Autotools)[1..],
+            ["/raft/configure", "make clean", "make"]
+        );
+    }
+
+    #[test]
+    fn test_find_artifacts() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        fs::create_dir_all(dir.join("lib")).unwrap();
+        fs::create_dir_all(dir.join(".git")).unwrap();
+        fs::write(dir.join("old-raft"), elf(2)).unwrap();
+        let since = SystemTime::now() + Duration::from_millis(10);
+        std::thread::sleep(Duration::from_millis(20));
+
+        fs::write(dir.join("raft-server"), elf(3)).unwrap();
+        fs::write(dir.join("lib/libraft.so.0"), elf(3)).unwrap();
+        fs::write(dir.join("lib/libraft.a"), b"!<arch>\nmember").unwrap();
+        fs::write(dir.join("lib/raft.o"), elf(1)).unwrap();
+        fs::write(dir.join("README"), "raft").unwrap();
+        fs::write(dir.join(".git/raft"), elf(2)).unwrap();
+
+        let artifacts = find_artifacts(dir, since).unwrap();
+        let found: Vec<_> = artifacts
+            .iter()
+            .map(|a| {
+                let path = Path::new(&a.path).strip_prefix(dir).unwrap();
+                (path.to_string_lossy().into_owned(), a.kind)
+            })
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("lib/libraft.a".to_string(), ArtifactKind::StaticLibrary),
+                ("lib/libraft.so.0".to_string(), ArtifactKind::SharedLibrary),
+                ("raft-server".to_string(), ArtifactKind::Executable),
+            ]
+        );
+
+        let record = BuildRecord {
+            build_system: "make".to_string(),
+            source_dir: dir.to_string_lossy().into_owned(),
+            build_dir: dir.to_string_lossy().into_owned(),
+            steps: Vec::new(),
+            env: Vec::new(),
+            artifacts,
+        };
+        assert!(record.stale_artifacts().is_empty());
+        fs::write(dir.join("raft-server"), elf(2)).unwrap();
+        fs::remove_file(dir.join("lib/libraft.a")).unwrap();
+        assert_eq!(record.stale_artifacts().len(), 2);
+    }
+}
@@ -0,0 +1,306 @@
+use std::collections::{BTreeSet, VecDeque};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// One entry of a `compile_commands.json`, as written by CMake, Meson and
+/// Bear. Either `arguments` or `command` is set.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompileCommand {
+    pub directory: String,
+    pub file: String,
+    #[serde(default)]
+    pub arguments: Vec<String>,
+    pub command: Option<String>,
+    pub output: Option<String>,
+}
+
+impl CompileCommand {
+    /// The compiled file; relative paths are relative to `directory`.
+    pub fn source_path(&self) -> PathBuf {
+        normalize(&Path::new(&self.directory).join(&self.file))
+    }
+
+    pub fn args(&self) -> Vec<String> {
+        match &self.command {
+            Some(command) if self.arguments.is_empty() => split_command(command),
+            _ => self.arguments.clone(),
+        }
+    }
+
+    /// Directories searched for `#include`s, in command-line order.
+    pub fn include_dirs(&self) -> Vec<PathBuf> {
+        let mut dirs = Vec::new();
+        let mut args = self.args().into_iter();
+        while let Some(arg) = args.next() {
+            let dir = ["-I", "-iquote", "-isystem", "-idirafter"]
+                .iter()
+                .find_map(|flag| arg.strip_prefix(flag).map(str::to_string));
+            let dir = match dir {
+                Some(dir) if dir.is_empty() => args.next(),
+                Some(dir) => Some(dir),
+                None => None,
+            };
+            if let Some(dir) = dir {
+                dirs.push(normalize(&Path::new(&self.directory).join(dir)));
+            }
+        }
+        dirs
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CompilationDatabase {
+    pub commands: Vec<CompileCommand>,
+}
+
+impl CompilationDatabase {
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("reading compilation database {}", path.display()))?;
+        let commands = serde_json::from_str(&content)
+            .with_context(|| format!("parsing compilation database {}", path.display()))?;
+        Ok(CompilationDatabase { commands })
+    }
+
+    /// Every compiled file and the headers it includes, directly or not.
+    /// Includes that resolve to no file, such as system headers the
+    /// database gives no directory for, are left out.
+    pub fn build_files(&self) -> BTreeSet<PathBuf> {
+        let include_regex = Regex::new(r#"^\s*#\s*include\s*([<"])([^>"]+)[>"]"#).unwrap();
+        let mut files = BTreeSet::new();
+        for command in &self.commands {
+            let include_dirs = command.include_dirs();
+            let mut queue = VecDeque::from([command.source_path()]);
+            while let Some(file) = queue.pop_front() {
+                if !files.insert(file.clone()) {
+                    continue;
+                }
+                let Ok(content) = fs::read_to_string(&file) else {
+                    continue;
+                };
+                for captures in content.lines().filter_map(|l| include_regex.captures(l)) {
+                    // Quoted includes look next to the including file first.
+                    let local = (&captures[1] == "\"")
+                        .then(|| file.parent().map(Path::to_path_buf))
+                        .flatten();
+                    let found = local
+                        .iter()
+                        .chain(&include_dirs)
+                        .map(|dir| normalize(&dir.join(&captures[2])))
+                        .find(|path| path.is_file());
+                    if let Some(header) = found
+                        && !files.contains(&header)
+                    {
+                        queue.push_back(header);
+                    }
+                }
+            }
+        }
+        files
+    }
+}
+
+/// Splits a `command` string the way a POSIX shell would split its words,
+/// honouring quotes and backslashes.
+fn split_command(command: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quote = None;
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('"'), '\\') | (None, '\\') => {
+                word.extend(chars.next());
+                in_word = true;
+            }
+            (Some(_), c) => word.push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                in_word = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            (None, c) => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if in_word {
+        words.push(word);
+    }
+    words
+}
+
+/// Removes `.` and `..` components without touching the file system, so
+/// paths from the database compare equal to paths found by walking.
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    normalized.push("..");
+                }
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+/// Include and exclude globs over paths relative to the scanned root. `*`
+/// and `?` stay within a path component, `**` spans any number of them.
+#[derive(Debug, Clone, Default)]
+pub struct PathFilter {
+    include: Vec<Regex>,
+    exclude: Vec<Regex>,
+}
+
+impl PathFilter {
+    pub fn new(include: &[String], exclude: &[String]) -> Result<Self> {
+        let compile = |globs: &[String]| -> Result<Vec<Regex>> {
+            globs
+                .iter()
+                .map(|glob| {
+                    Regex::new(&glob_regex(glob)).with_context(|| format!("invalid glob {}", glob))
+                })
+                .collect()
+        };
+        Ok(PathFilter {
+            include: compile(include)?,
+            exclude: compile(exclude)?,
+        })
+    }
+
+    /// No include globs means everything is included.
+    pub fn matches(&self, relative: &Path) -> bool {
+        let path = relative.to_string_lossy();
+        (self.include.is_empty() || self.include.iter().any(|r| r.is_match(&path)))
+            && !self.exclude.iter().any(|r| r.is_match(&path))
+    }
+}
+
+fn glob_regex(glob: &str) -> String {
+    let mut regex = String::from("^");
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    regex.push_str("(?:.*/)?");
+                } else {
+                    regex.push_str(".*");
+                }
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    regex
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_path_filter() {
+        let filter = PathFilter::new(
+            &["src/**".to_string(), "*.c".to_string()],
+            &["**/test?/**".to_string(), "src/*_test.c".to_string()],
+        )
+        .unwrap();
+        assert!(filter.matches(Path::new("src/raft.c")));
+        assert!(filter.matches(Path::new("src/lib/log.h")));
+        assert!(filter.matches(Path::new("main.c")));
+        assert!(!filter.matches(Path::new("tools/main.c")));
+        assert!(!filter.matches(Path::new("src/tests/raft.c")));
+        assert!(!filter.matches(Path::new("src/raft_test.c")));
+        assert!(filter.matches(Path::new("src/lib/raft_test.c")));
+
+        let everything = PathFilter::new(&[], &[]).unwrap();
+        assert!(everything.matches(Path::new("a/b/c.cpp")));
+    }
+
+    #[test]
+    fn test_command_include_dirs() {
+        let command = CompileCommand {
+            directory: "/sut/build".to_string(),
+            file: "../src/raft.c".to_string(),
+            arguments: Vec::new(),
+            command: Some(
+                r#"cc -I../include -isystem /opt/uv/include -I "gen dir" -DNAME=\"raft\" -c ../src/raft.c"#
+                    .to_string(),
+            ),
+            output: None,
+        };
+        assert_eq!(command.source_path(), Path::new("/sut/src/raft.c"));
+        assert_eq!(command.args()[6], r#"-DNAME="raft""#);
+        assert_eq!(
+            command.include_dirs(),
+            [
+                PathBuf::from("/sut/include"),
+                PathBuf::from("/opt/uv/include"),
+                PathBuf::from("/sut/build/gen dir"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_build_files_follow_includes() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join("include")).unwrap();
+        fs::write(
+            root.join("src/raft.c"),
+            "#include <stdio.h>\n#include \"log.h\"\n#include <raft.h>\n",
+        )
+        .unwrap();
+        fs::write(root.join("src/log.h"), "# include \"raft.h\"\n").unwrap();
+        fs::write(root.join("include/raft.h"), "#include \"log.h\"\n").unwrap();
+        fs::write(root.join("src/unused.c"), "").unwrap();
+        fs::write(
+            root.join("compile_commands.json"),
+            serde_json::json!([{
+                "directory": root.join("src"),
+                "file": "raft.c",
+                "arguments": ["cc", "-I", "../include", "-c", "raft.c"],
+            }])
+            .to_string(),
+        )
+        .unwrap();
+
+        let db = CompilationDatabase::from_file(&root.join("compile_commands.json")).unwrap();
+        let files: Vec<_> = db
+            .build_files()
+            .into_iter()
+            .map(|path| path.strip_prefix(root).unwrap().to_path_buf())
+            .collect();
+        assert_eq!(
+            files,
+            [
+                PathBuf::from("include/raft.h"),
+                PathBuf::from("src/log.h"),
+                PathBuf::from("src/raft.c"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,271 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use super::event_log::parse_event_log;
+use super::instrumentor::InstrumentationTargets;
+
+/// Where an event id was assigned, as recorded by the LLVM pass.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub id: u32,
+    pub kind: String,
+    pub file: String,
+    pub line: usize,
+    #[serde(default)]
+    pub function: String,
+    #[serde(default)]
+    pub transition: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Manifest {
+    pub entries: BTreeMap<u32, ManifestEntry>,
+}
+
+impl Manifest {
+    /// One JSON object per line. A later entry for the same id wins.
+    pub fn parse(content: &str) -> Result<Self> {
+        let mut entries = BTreeMap::new();
+        for (index, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry: ManifestEntry = serde_json::from_str(line)
+                .with_context(|| format!("parsing manifest line {}", index + 1))?;
+            entries.insert(entry.id, entry);
+        }
+        Ok(Self { entries })
+    }
+
+    pub fn from_file(path: &str) -> Result<Self> {
+        let content =
+            fs::read_to_string(path).with_context(|| format!("reading manifest from {}", path))?;
+        Self::parse(&content).with_context(|| format!("parsing manifest {}", path))
+    }
+
+    pub fn get(&self, id: u32) -> Option<&ManifestEntry> {
+        self.entries.get(&id)
+    }
+
+    fn contains_location(&self, file: &str, line: usize, root: &Path) -> bool {
+        self.entries
+            .values()
+            .any(|e| e.line == line && same_file(&e.file, file, root))
+    }
+}
+
+/// Debug locations may be relative to the compilation directory while the
+/// targets file holds absolute paths, so relative paths are taken to be
+/// relative to the SUT at `root` and both are compared as absolute paths.
+pub fn same_file(a: &str, b: &str, root: &Path) -> bool {
+    absolute(a, root) == absolute(b, root)
+}
+
+/// `path` resolved against `root`, with `.` and `..` taken out.
+fn absolute(path: &str, root: &Path) -> PathBuf {
+    let mut resolved = PathBuf::new();
+    for component in root.join(path).components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            component => resolved.push(component),
+        }
+    }
+    resolved
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnnotationHit {
+    #[serde(flatten)]
+    pub entry: ManifestEntry,
+    pub hits: usize,
+    pub nodes: Vec<u32>,
+}
+
+/// An annotation found in the sources that the pass never assigned an id to.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Annotation {
+    pub file: String,
+    pub line: usize,
+    pub kind: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub struct CoverageReport {
+    /// Annotations that fired, most frequent first.
+    pub hit: Vec<AnnotationHit>,
+    pub never_hit: Vec<ManifestEntry>,
+    pub not_instrumented: Vec<Annotation>,
+    /// Ids seen in the log that the manifest does not know about, with
+    /// their hit counts. Usually a manifest from a different build.
+    pub unknown_ids: BTreeMap<u32, usize>,
+}
+
+impl CoverageReport {
+    pub fn total(&self) -> usize {
+        self.hit.len() + self.never_hit.len() + self.not_instrumented.len()
+    }
+}
+
+pub fn annotation_coverage(
+    log: &str,
+    manifest: &Manifest,
+    targets: Option<&[InstrumentationTargets]>,
+    root: &Path,
+) -> CoverageReport {
+    let mut hits: BTreeMap<u32, (usize, BTreeSet<u32>)> = BTreeMap::new();
+    for rec in parse_event_log(log) {
+        if let Some(id) = rec.event_id {
+            let (count, nodes) = hits.entry(id).or_default();
+            *count += 1;
+            nodes.insert(rec.node_id);
+        }
+    }
+
+    let mut report = CoverageReport::default();
+    for (id, entry) in &manifest.entries {
+        match hits.remove(id) {
+            Some((count, nodes)) => report.hit.push(AnnotationHit {
+                entry: entry.clone(),
+                hits: count,
+                nodes: nodes.into_iter().collect(),
+            }),
+            None => report.never_hit.push(entry.clone()),
+        }
+    }
+    report
+        .hit
+        .sort_by(|a, b| b.hits.cmp(&a.hits).then(a.entry.id.cmp(&b.entry.id)));
+    report.unknown_ids = hits
+        .into_iter()
+        .map(|(id, (count, _))| (id, count))
+        .collect();
+
+    // Only the annotation closing a group (`END`, or a lone one) gets an id.
+    for file in targets.unwrap_or_default() {
+        let kinds = [
+            ("function", &file.targets_function),
+            ("block", &file.targets_block),
+        ];
+        for (kind, targets) in kinds {
+            for (line, target) in targets {
+                let closes_group = target.group.end_mark
+                    || !targets
+                        .values()
+                        .any(|t| t.group.id == target.group.id && t.group.end_mark);
+                if closes_group && !manifest.contains_location(&file.path, *line, root) {
+                    report.not_instrumented.push(Annotation {
+                        file: file.path.clone(),
+                        line: *line,
+                        kind: kind.to_string(),
+                    });
+                }
+            }
+        }
+    }
+    report.not_instrumented.sort();
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::instrumentor::{GroupInfo, TargetInfo};
+
+    const MANIFEST: &str = r#"{"id":1,"kind":"function","file":"src/raft.c","line":10,"function":"raft_tick","transition":"raft_tick"}
+{"id":2,"kind":"block","file":"src/raft.c","line":42,"function":"raft_step","transition":"becomeLeader"}
+
+{"id":3,"kind":"block","file":"src/log.c","line":7,"function":"log_append","transition":"log_append"}
+"#;
+
+    const LOG: &str = "[2025-11-10 19:56:55.000001][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 1 / 1] FunctionExecute 1 @ FunctionName raft_tick @ state Follower
+[2025-11-10 19:56:55.000002][INFO] [BLOCK_EVENT_TYPE][Node 2 Batch 1 Entry 1 / 1] BlockExecute 2 @ FunctionName becomeLeader @ state Leader
+[2025-11-10 19:56:55.000003][INFO] [FUNC_EVENT_TYPE][Node 2 Batch 2 Entry 1 / 1] FunctionExecute 1 @ FunctionName raft_tick @ state Leader
+[2025-11-10 19:56:55.000004][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 2 Entry 1 / 1] FunctionExecute 1 @ FunctionName raft_tick @ state Follower
+[2025-11-10 19:56:55.000005][INFO] [BLOCK_EVENT_TYPE][Node 3 Batch 1 Entry 1 / 1] BlockExecute 9 @ FunctionName stale @ state Follower";
+
+    fn target(id: u32, end_mark: bool) -> TargetInfo {
+        TargetInfo {
+            var_info: vec![],
+            group: GroupInfo { end_mark, id },
+        }
+    }
+
+    #[test]
+    fn parses_manifest_lines() {
+        let manifest = Manifest::parse(MANIFEST).unwrap();
+        assert_eq!(manifest.entries.len(), 3);
+        assert_eq!(manifest.entries[&2].transition, "becomeLeader");
+
+        let err = Manifest::parse("{\"id\": 1}\nnot json").unwrap_err();
+        assert!(err.to_string().contains("line 1"));
+    }
+
+    #[test]
+    fn counts_hits_and_never_hit_annotations() {
+        let manifest = Manifest::parse(MANIFEST).unwrap();
+        let report = annotation_coverage(LOG, &manifest, None, Path::new("/work/SUT"));
+
+        let hit: Vec<(u32, usize, Vec<u32>)> = report
+            .hit
+            .iter()
+            .map(|h| (h.entry.id, h.hits, h.nodes.clone()))
+            .collect();
+        assert_eq!(hit, vec![(1, 3, vec![1, 2]), (2, 1, vec![2])]);
+        assert_eq!(report.never_hit.len(), 1);
+        assert_eq!(report.never_hit[0].function, "log_append");
+        assert_eq!(report.unknown_ids, BTreeMap::from([(9, 1)]));
+        assert_eq!(report.total(), 3);
+    }
+
+    #[test]
+    fn matches_files_by_their_whole_path() {
+        let root = Path::new("/work/SUT");
+        assert!(same_file("src/raft.c", "/work/SUT/src/raft.c", root));
+        assert!(same_file(
+            "/work/SUT/src/../src/./raft.c",
+            "src/raft.c",
+            root
+        ));
+        assert!(!same_file("a/raft.c", "b/raft.c", root));
+        assert!(!same_file("raft.c", "/work/SUT/src/raft.c", root));
+        assert!(!same_file(
+            "/other/SUT/src/raft.c",
+            "/work/SUT/src/raft.c",
+            root
+        ));
+    }
+
+    #[test]
+    fn lists_annotations_the_pass_never_instrumented() {
+        let manifest = Manifest::parse(MANIFEST).unwrap();
+        let targets = vec![InstrumentationTargets {
+            path: "/work/SUT/src/raft.c".to_string(),
+            targets_function: BTreeMap::from([(10, target(0, false))]),
+            targets_block: BTreeMap::from([
+                // A grouped annotation that does not close its group.
+                (40, target(1, false)),
+                (42, target(1, true)),
+                (90, target(2, true)),
+            ]),
+            ..Default::default()
+        }];
+
+        let report = annotation_coverage(LOG, &manifest, Some(&targets), Path::new("/work/SUT"));
+        assert_eq!(
+            report.not_instrumented,
+            vec![Annotation {
+                file: "/work/SUT/src/raft.c".to_string(),
+                line: 90,
+                kind: "block".to_string(),
+            }]
+        );
+    }
+}
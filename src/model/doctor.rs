@@ -0,0 +1,187 @@
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Ok,
+    /// Some commands work without it, or work worse.
+    Warning,
+    /// A pipeline stage will fail.
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Check {
+    pub name: String,
+    pub status: CheckStatus,
+    pub detail: String,
+    /// What to run or change when the check does not pass.
+    pub fix: Option<String>,
+}
+
+impl Check {
+    pub fn ok(name: &str, detail: impl Into<String>) -> Self {
+        Check {
+            name: name.to_string(),
+            status: CheckStatus::Ok,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    pub fn warning(name: &str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Check {
+            name: name.to_string(),
+            status: CheckStatus::Warning,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    pub fn error(name: &str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Check {
+            name: name.to_string(),
+            status: CheckStatus::Error,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+}
+
+/// What `doctor --json` writes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DoctorReport {
+    pub status: CheckStatus,
+    pub checks: Vec<Check>,
+}
+
+impl DoctorReport {
+    pub fn new(checks: Vec<Check>) -> Self {
+        let status = checks
+            .iter()
+            .map(|check| check.status)
+            .max()
+            .unwrap_or(CheckStatus::Ok);
+        DoctorReport { status, checks }
+    }
+
+    pub fn count(&self, status: CheckStatus) -> usize {
+        self.checks
+            .iter()
+            .filter(|check| check.status == status)
+            .count()
+    }
+}
+
+/// Major version in the output of `clang --version` or `llvm-config
+/// --version`, e.g. 11 from "Ubuntu clang version 11.1.0-6".
+pub fn llvm_major_version(output: &str) -> Option<u32> {
+    let line = output.lines().next()?;
+    let version = match line.find("version ") {
+        Some(start) => &line[start + "version ".len()..],
+        None => line.trim(),
+    };
+    version
+        .split(|c: char| !c.is_ascii_digit())
+        .next()?
+        .parse()
+        .ok()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ElfInfo {
+    pub machine: &'static str,
+    /// No `PT_INTERP` header: the binary needs no dynamic loader.
+    pub static_binary: bool,
+}
+
+/// The parts of a 64-bit little-endian ELF header the mediator's
+/// `x86_64-unknown-linux-musl` target shows in.
+pub fn elf_info(binary: &[u8]) -> Option<ElfInfo> {
+    const PT_INTERP: u32 = 3;
+    if binary.len() < 64 || &binary[..4] != b"\x7fELF" || binary[4] != 2 || binary[5] != 1 {
+        return None;
+    }
+    let u16_at = |at: usize| u16::from_le_bytes([binary[at], binary[at + 1]]);
+    let machine = match u16_at(18) {
+        62 => "x86_64",
+        183 => "aarch64",
+        _ => "unknown",
+    };
+    let phoff = u64::from_le_bytes(binary[32..40].try_into().ok()?) as usize;
+    let phentsize = u16_at(54) as usize;
+    let phnum = u16_at(56) as usize;
+    let mut static_binary = true;
+    for i in 0..phnum {
+        let at = phoff + i * phentsize;
+        let p_type = u32::from_le_bytes(binary.get(at..at + 4)?.try_into().ok()?);
+        if p_type == PT_INTERP {
+            static_binary = false;
+        }
+    }
+    Some(ElfInfo {
+        machine,
+        static_binary,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 64-bit little-endian ELF header followed by `headers` program
+    /// header types.
+    fn elf(machine: u16, headers: &[u32]) -> Vec<u8> {
+        let mut binary = vec![0u8; 64];
+        binary[..6].copy_from_slice(b"\x7fELF\x02\x01");
+        binary[18..20].copy_from_slice(&machine.to_le_bytes());
+        binary[32..40].copy_from_slice(&64u64.to_le_bytes());
+        binary[54..56].copy_from_slice(&56u16.to_le_bytes());
+        binary[56..58].copy_from_slice(&(headers.len() as u16).to_le_bytes());
+        for p_type in headers {
+            let mut header = vec![0u8; 56];
+            header[..4].copy_from_slice(&p_type.to_le_bytes());
+            binary.extend(header);
+        }
+        binary
+    }
+
+    #[test]
+    fn test_llvm_versions() {
+        assert_eq!(
+            llvm_major_version("Ubuntu clang version 11.1.0-6\nTarget: x86_64-pc-linux-gnu\n"),
+            Some(11)
+        );
+        assert_eq!(llvm_major_version("14.0.0\n"), Some(14));
+        assert_eq!(llvm_major_version(""), None);
+    }
+
+    #[test]
+    fn test_elf_info() {
+        let musl = elf_info(&elf(62, &[1, 1])).unwrap();
+        assert_eq!(musl.machine, "x86_64");
+        assert!(musl.static_binary);
+        let glibc = elf_info(&elf(62, &[6, 3, 1])).unwrap();
+        assert!(!glibc.static_binary);
+        assert_eq!(elf_info(&elf(183, &[])).unwrap().machine, "aarch64");
+        assert!(elf_info(b"#!/bin/sh\n").is_none());
+    }
+
+    #[test]
+    fn test_report_status_is_the_worst_check() {
+        let report = DoctorReport::new(vec![
+            Check::ok("dot", "found"),
+            Check::warning("ag", "not found", "apt-get install silversearcher-ag"),
+        ]);
+        assert_eq!(report.status, CheckStatus::Warning);
+        assert_eq!(report.count(CheckStatus::Ok), 1);
+        assert_eq!(DoctorReport::new(Vec::new()).status, CheckStatus::Ok);
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["status"], "warning");
+        assert_eq!(
+            json["checks"][1]["fix"],
+            "apt-get install silversearcher-ag"
+        );
+    }
+}
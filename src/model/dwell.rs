@@ -0,0 +1,294 @@
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+
+use super::event_log::{EventRecord, parse_event_log};
+use super::refinement::Refinement;
+
+/// One visit of a node to an abstract state, from the first record in the
+/// state to the first record in a different one.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Stay {
+    pub node_id: u32,
+    pub state: String,
+    pub duration_us: u64,
+    pub entered_line: usize,
+    pub left_line: usize,
+    /// Index of the record that entered the state.
+    #[serde(skip)]
+    pub entered: usize,
+}
+
+/// Stays whose records lack a timestamp are skipped, as is the last state of
+/// every node since it was never left.
+pub fn collect_stays(records: &[EventRecord], refinement: &Refinement) -> Vec<Stay> {
+    let mut stays = Vec::new();
+    let mut current: HashMap<u32, (String, usize)> = HashMap::new();
+
+    for (index, rec) in records.iter().enumerate() {
+        let Some(state) = refinement.state(&rec.state, rec.constant) else {
+            continue;
+        };
+        match current.get(&rec.node_id) {
+            Some((entered_state, _)) if *entered_state == state => continue,
+            Some((entered_state, entered)) => {
+                let start = &records[*entered];
+                if let (Some(from), Some(to)) = (start.timestamp_us, rec.timestamp_us) {
+                    stays.push(Stay {
+                        node_id: rec.node_id,
+                        state: entered_state.clone(),
+                        duration_us: to.saturating_sub(from).max(0) as u64,
+                        entered_line: start.line,
+                        left_line: rec.line,
+                        entered: *entered,
+                    });
+                }
+            }
+            None => {}
+        }
+        current.insert(rec.node_id, (state, index));
+    }
+
+    stays
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DwellStats {
+    pub samples: usize,
+    pub min_us: u64,
+    pub median_us: u64,
+    pub p99_us: u64,
+    pub max_us: u64,
+}
+
+impl DwellStats {
+    pub fn from_durations(mut durations: Vec<u64>) -> Option<Self> {
+        if durations.is_empty() {
+            return None;
+        }
+        durations.sort_unstable();
+        Some(Self {
+            samples: durations.len(),
+            min_us: durations[0],
+            median_us: percentile(&durations, 50),
+            p99_us: percentile(&durations, 99),
+            max_us: durations[durations.len() - 1],
+        })
+    }
+}
+
+/// Nearest-rank percentile of sorted durations.
+fn percentile(sorted: &[u64], p: usize) -> u64 {
+    let rank = (p * sorted.len()).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+/// Longest acceptable stay per state, with `*` applying to every state
+/// without its own entry.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct DwellThresholds {
+    pub states: BTreeMap<String, u64>,
+}
+
+impl DwellThresholds {
+    /// Parses `STATE=DURATION` specs, where the duration is a number with an
+    /// optional `us`, `ms` (the default) or `s` suffix.
+    pub fn parse(specs: &[String]) -> Result<Self> {
+        let mut states = BTreeMap::new();
+        for spec in specs {
+            let (state, duration) = spec
+                .split_once('=')
+                .with_context(|| format!("expected STATE=DURATION, got '{}'", spec))?;
+            states.insert(
+                state.trim().to_string(),
+                parse_duration_us(duration.trim())?,
+            );
+        }
+        Ok(Self { states })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+
+    pub fn limit(&self, state: &str) -> Option<u64> {
+        self.states
+            .get(state)
+            .or_else(|| self.states.get("*"))
+            .copied()
+    }
+}
+
+fn parse_duration_us(duration: &str) -> Result<u64> {
+    let (number, scale) = if let Some(n) = duration.strip_suffix("us") {
+        (n, 1)
+    } else if let Some(n) = duration.strip_suffix("ms") {
+        (n, 1_000)
+    } else if let Some(n) = duration.strip_suffix('s') {
+        (n, 1_000_000)
+    } else {
+        (duration, 1_000)
+    };
+    let value: f64 = number
+        .trim()
+        .parse()
+        .with_context(|| format!("invalid duration '{}'", duration))?;
+    if !value.is_finite() || value < 0.0 {
+        bail!(
+            "invalid duration '{}': expected a non-negative number",
+            duration
+        );
+    }
+    Ok((value * scale as f64).round() as u64)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DwellOutlier {
+    #[serde(flatten)]
+    pub stay: Stay,
+    pub threshold_us: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateDwell {
+    pub node_id: u32,
+    pub state: String,
+    #[serde(flatten)]
+    pub stats: DwellStats,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub threshold_us: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DwellReport {
+    pub states: Vec<StateDwell>,
+    pub outliers: Vec<DwellOutlier>,
+}
+
+pub fn dwell_report(
+    log: &str,
+    refinement: &Refinement,
+    thresholds: &DwellThresholds,
+) -> DwellReport {
+    let stays = collect_stays(&parse_event_log(log), refinement);
+
+    let mut durations: BTreeMap<(u32, String), Vec<u64>> = BTreeMap::new();
+    for stay in &stays {
+        durations
+            .entry((stay.node_id, stay.state.clone()))
+            .or_default()
+            .push(stay.duration_us);
+    }
+
+    let states = durations
+        .into_iter()
+        .filter_map(|((node_id, state), durations)| {
+            Some(StateDwell {
+                threshold_us: thresholds.limit(&state),
+                stats: DwellStats::from_durations(durations)?,
+                node_id,
+                state,
+            })
+        })
+        .collect();
+
+    let outliers = stays
+        .into_iter()
+        .filter_map(|stay| {
+            let threshold_us = thresholds.limit(&stay.state)?;
+            (stay.duration_us > threshold_us).then_some(DwellOutlier { stay, threshold_us })
+        })
+        .collect();
+
+    DwellReport { states, outliers }
+}
+
+/// Renders microseconds with the coarsest unit that keeps three digits.
+pub fn format_duration(us: u64) -> String {
+    if us >= 1_000_000 {
+        format!("{:.2}s", us as f64 / 1_000_000.0)
+    } else if us >= 1_000 {
+        format!("{:.2}ms", us as f64 / 1_000.0)
+    } else {
+        format!("{}us", us)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOG: &str = "[2025-11-10 19:56:55.000000][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 1 / 6] FunctionExecute 1 @ FunctionName boot @ state Follower
+[2025-11-10 19:56:55.100000][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 2 / 6] FunctionExecute 2 @ FunctionName electionTimeout @ state Candidate
+[2025-11-10 19:56:55.150000][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 3 / 6] FunctionExecute 2 @ FunctionName electionTimeout @ state Candidate
+[2025-11-10 19:56:55.300000][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 4 / 6] FunctionExecute 3 @ FunctionName stepDown @ state Follower
+[2025-11-10 19:56:55.400000][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 5 / 6] FunctionExecute 2 @ FunctionName electionTimeout @ state Candidate
+[2025-11-10 19:56:57.400000][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 6 / 6] FunctionExecute 4 @ FunctionName winElection @ state Leader";
+
+    #[test]
+    fn measures_stays_until_the_state_changes() {
+        let stays = collect_stays(&parse_event_log(LOG), &Refinement::identity());
+        let summary: Vec<(&str, u64, usize, usize)> = stays
+            .iter()
+            .map(|s| (s.state.as_str(), s.duration_us, s.entered_line, s.left_line))
+            .collect();
+
+        assert_eq!(
+            summary,
+            vec![
+                ("Follower", 100_000, 1, 2),
+                ("Candidate", 200_000, 2, 4),
+                ("Follower", 100_000, 4, 5),
+                ("Candidate", 2_000_000, 5, 6),
+            ]
+        );
+    }
+
+    #[test]
+    fn summarizes_and_flags_stays_over_threshold() {
+        let thresholds = DwellThresholds::parse(&["Candidate=1s".to_string()]).unwrap();
+        let report = dwell_report(LOG, &Refinement::identity(), &thresholds);
+
+        let candidate = report
+            .states
+            .iter()
+            .find(|s| s.state == "Candidate")
+            .unwrap();
+        assert_eq!(candidate.stats.samples, 2);
+        assert_eq!(candidate.stats.min_us, 200_000);
+        assert_eq!(candidate.stats.median_us, 200_000);
+        assert_eq!(candidate.stats.p99_us, 2_000_000);
+        assert_eq!(candidate.stats.max_us, 2_000_000);
+        assert_eq!(candidate.threshold_us, Some(1_000_000));
+
+        assert_eq!(report.outliers.len(), 1);
+        assert_eq!(report.outliers[0].stay.entered_line, 5);
+        assert_eq!(report.outliers[0].stay.duration_us, 2_000_000);
+    }
+
+    #[test]
+    fn parses_threshold_specs() {
+        let thresholds = DwellThresholds::parse(&[
+            "*=500".to_string(),
+            "Candidate=1.5s".to_string(),
+            "Leader=250us".to_string(),
+        ])
+        .unwrap();
+        assert_eq!(thresholds.limit("Candidate"), Some(1_500_000));
+        assert_eq!(thresholds.limit("Leader"), Some(250));
+        assert_eq!(thresholds.limit("Follower"), Some(500_000));
+
+        assert!(DwellThresholds::parse(&["Candidate".to_string()]).is_err());
+        assert!(DwellThresholds::parse(&["Candidate=soon".to_string()]).is_err());
+        assert!(DwellThresholds::parse(&["Candidate=-5ms".to_string()]).is_err());
+        assert!(DwellThresholds::parse(&["Candidate=infs".to_string()]).is_err());
+    }
+
+    #[test]
+    fn formats_durations() {
+        assert_eq!(format_duration(12), "12us");
+        assert_eq!(format_duration(1_500), "1.50ms");
+        assert_eq!(format_duration(2_000_000), "2.00s");
+    }
+}
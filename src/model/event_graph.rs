@@ -1,8 +1,14 @@
-use std::collections::{BTreeMap, HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+use super::coverage::ManifestEntry;
+use super::dwell::{DwellStats, collect_stays};
+use super::event_log::{EventRecord, Window, parse_event_log};
+use super::refinement::Refinement;
+use super::sources::{SourceLinks, describe_location};
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct Edge {
     pub from: String,
     pub transition: String,
@@ -13,6 +19,14 @@ pub struct Edge {
 pub struct NodeGraph {
     pub states: Vec<String>,
     pub edges: Vec<Edge>,
+    /// First state observed for the node.
+    pub initial: Option<String>,
+    /// Event ids of the annotations that produced each transition label.
+    #[serde(default)]
+    pub transition_events: BTreeMap<String, Vec<u32>>,
+    /// Time spent in each state before leaving it.
+    #[serde(default)]
+    pub dwell: BTreeMap<String, DwellStats>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
@@ -20,77 +34,131 @@ pub struct EventGraph {
     pub nodes: HashMap<u32, NodeGraph>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-struct EventRecord {
-    node_id: u32,
-    transition: String,
-    state: String,
+pub fn build_event_graph(log: &str) -> EventGraph {
+    build_event_graph_with(log, &Refinement::identity())
 }
 
-fn parse_mediator_log_line(line: &str) -> Option<EventRecord> {
-    let relevant_part = line.find("[Node ")?;
-    let relevant_part = &line[relevant_part..];
-
-    let node_start = "[Node ".len();
-    let node_end = relevant_part[node_start..].find(' ')?;
-    let node_id: u32 = relevant_part[node_start..node_start + node_end]
-        .parse()
-        .ok()?;
-
-    let function_marker = "@ FunctionName ";
-    let function_start = relevant_part.find(function_marker)? + function_marker.len();
-    let function_end = relevant_part[function_start..].find(" @ ")?;
-    let transition = relevant_part[function_start..function_start + function_end].to_string();
-
-    let state = if let Some(state_marker_pos) = relevant_part.find("@ state ") {
-        let state_start = state_marker_pos + "@ state ".len();
-        relevant_part[state_start..].trim().to_string()
-    } else if let Some(const_marker_pos) = relevant_part.find("@ constant ") {
-        let const_start = const_marker_pos + "@ constant ".len();
-        relevant_part[const_start..].trim().to_string()
-    } else {
-        return None;
-    };
-
-    Some(EventRecord {
-        node_id,
-        transition,
-        state,
-    })
+pub fn build_event_graph_with(log: &str, refinement: &Refinement) -> EventGraph {
+    build_graphs_by(&parse_event_log(log), refinement, |_| Some(()))
+        .remove(&())
+        .unwrap_or_default()
 }
 
-pub fn build_event_graph(log: &str) -> EventGraph {
-    let mut records: Vec<EventRecord> = Vec::new();
-    let mut states_by_node: HashMap<u32, HashSet<String>> = HashMap::new();
+/// Builds one graph per window, following each node across window boundaries so
+/// the edge entering a window's first state is attributed to that window.
+pub fn build_window_graphs(log: &str, refinement: &Refinement) -> BTreeMap<Window, EventGraph> {
+    build_graphs_by(&parse_event_log(log), refinement, |rec| rec.window)
+}
 
-    for line in log.lines() {
-        let trimmed = line.trim();
-        if trimmed.is_empty() {
+pub fn build_schedule_graphs(log: &str, refinement: &Refinement) -> BTreeMap<usize, EventGraph> {
+    build_graphs_by(&parse_event_log(log), refinement, |rec| {
+        rec.window.map(|w| w.schedule_id)
+    })
+}
+
+/// States are attributed to the key of their record and edges to the key of
+/// their target record; records without a key only contribute as edge sources.
+/// Stays in a state count towards the key of the record that entered it.
+fn build_graphs_by<K: Ord + Copy>(
+    records: &[EventRecord],
+    refinement: &Refinement,
+    key: impl Fn(&EventRecord) -> Option<K>,
+) -> BTreeMap<K, EventGraph> {
+    let mut states: BTreeMap<K, HashMap<u32, HashSet<String>>> = BTreeMap::new();
+    let mut edges: BTreeMap<K, HashMap<u32, HashSet<Edge>>> = BTreeMap::new();
+    let mut initial: BTreeMap<K, HashMap<u32, String>> = BTreeMap::new();
+    let mut events: BTreeMap<K, HashMap<u32, BTreeMap<String, BTreeSet<u32>>>> = BTreeMap::new();
+    let mut last_for_node: HashMap<u32, (String, String, Option<u32>)> = HashMap::new();
+
+    for rec in records {
+        let Some(state) = refinement.state(&rec.state, rec.constant) else {
             continue;
-        }
+        };
 
-        if let Some(record) = parse_mediator_log_line(trimmed) {
-            states_by_node
-                .entry(record.node_id)
+        if let Some(k) = key(rec) {
+            initial
+                .entry(k)
                 .or_default()
-                .insert(record.state.clone());
-            records.push(record);
+                .entry(rec.node_id)
+                .or_insert_with(|| state.clone());
+            states
+                .entry(k)
+                .or_default()
+                .entry(rec.node_id)
+                .or_default()
+                .insert(state.clone());
+
+            if let Some((prev_state, prev_transition, prev_event)) = last_for_node.get(&rec.node_id)
+                && refinement.keeps_transition(prev_transition)
+            {
+                if let Some(event_id) = prev_event {
+                    events
+                        .entry(k)
+                        .or_default()
+                        .entry(rec.node_id)
+                        .or_default()
+                        .entry(prev_transition.clone())
+                        .or_default()
+                        .insert(*event_id);
+                }
+                edges
+                    .entry(k)
+                    .or_default()
+                    .entry(rec.node_id)
+                    .or_default()
+                    .insert(Edge {
+                        from: prev_state.clone(),
+                        transition: prev_transition.clone(),
+                        to: state.clone(),
+                    });
+            }
         }
+
+        last_for_node.insert(rec.node_id, (state, rec.transition.clone(), rec.event_id));
     }
 
-    let mut edges_by_node: HashMap<u32, HashSet<Edge>> = HashMap::new();
-    let mut last_for_node: HashMap<u32, (String, String)> = HashMap::new();
-    for rec in &records {
-        if let Some((prev_state, prev_transition)) = last_for_node.get(&rec.node_id) {
-            edges_by_node.entry(rec.node_id).or_default().insert(Edge {
-                from: prev_state.clone(),
-                transition: prev_transition.clone(),
-                to: rec.state.clone(),
-            });
+    let mut dwell: BTreeMap<K, HashMap<u32, BTreeMap<String, Vec<u64>>>> = BTreeMap::new();
+    for stay in collect_stays(records, refinement) {
+        if let Some(k) = key(&records[stay.entered]) {
+            dwell
+                .entry(k)
+                .or_default()
+                .entry(stay.node_id)
+                .or_default()
+                .entry(stay.state)
+                .or_default()
+                .push(stay.duration_us);
         }
-        last_for_node.insert(rec.node_id, (rec.state.clone(), rec.transition.clone()));
     }
 
+    states
+        .into_iter()
+        .map(|(k, states_by_node)| {
+            let edges_by_node = edges.remove(&k).unwrap_or_default();
+            let initial_by_node = initial.remove(&k).unwrap_or_default();
+            let events_by_node = events.remove(&k).unwrap_or_default();
+            let dwell_by_node = dwell.remove(&k).unwrap_or_default();
+            (
+                k,
+                assemble_graph(
+                    states_by_node,
+                    edges_by_node,
+                    initial_by_node,
+                    events_by_node,
+                    dwell_by_node,
+                ),
+            )
+        })
+        .collect()
+}
+
+fn assemble_graph(
+    states_by_node: HashMap<u32, HashSet<String>>,
+    edges_by_node: HashMap<u32, HashSet<Edge>>,
+    mut initial_by_node: HashMap<u32, String>,
+    mut events_by_node: HashMap<u32, BTreeMap<String, BTreeSet<u32>>>,
+    mut dwell_by_node: HashMap<u32, BTreeMap<String, Vec<u64>>>,
+) -> EventGraph {
     let mut nodes: HashMap<u32, NodeGraph> = HashMap::new();
     let all_node_ids: HashSet<u32> = states_by_node
         .keys()
@@ -117,13 +185,73 @@ pub fn build_event_graph(log: &str) -> EventGraph {
             o => o,
         });
 
-        nodes.insert(node_id, NodeGraph { states, edges });
+        nodes.insert(
+            node_id,
+            NodeGraph {
+                states,
+                edges,
+                initial: initial_by_node.remove(&node_id),
+                transition_events: events_by_node
+                    .remove(&node_id)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|(transition, ids)| (transition, ids.into_iter().collect()))
+                    .collect(),
+                dwell: dwell_by_node
+                    .remove(&node_id)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter_map(|(state, durations)| {
+                        Some((state, DwellStats::from_durations(durations)?))
+                    })
+                    .collect(),
+            },
+        );
     }
 
     EventGraph { nodes }
 }
 
-pub fn dot_for_node_graph(node: &NodeGraph) -> String {
+impl EventGraph {
+    /// Adds the states and edges of `other`. The initial state of a node is
+    /// kept from `self`; dwell statistics cannot be combined exactly, so the
+    /// ones with more samples win.
+    pub fn merge(&mut self, other: EventGraph) {
+        for (node_id, theirs) in other.nodes {
+            let ours = self.nodes.entry(node_id).or_default();
+
+            let states: BTreeSet<String> = ours.states.drain(..).chain(theirs.states).collect();
+            ours.states = states.into_iter().collect();
+            let edges: BTreeSet<Edge> = ours.edges.drain(..).chain(theirs.edges).collect();
+            ours.edges = edges.into_iter().collect();
+            ours.edges.sort_unstable_by(|l, r| {
+                (&l.from, &l.to, &l.transition).cmp(&(&r.from, &r.to, &r.transition))
+            });
+
+            if ours.initial.is_none() {
+                ours.initial = theirs.initial;
+            }
+            for (transition, ids) in theirs.transition_events {
+                let merged = ours.transition_events.entry(transition).or_default();
+                merged.extend(ids);
+                merged.sort_unstable();
+                merged.dedup();
+            }
+            for (state, stats) in theirs.dwell {
+                match ours.dwell.get(&state) {
+                    Some(existing) if existing.samples >= stats.samples => {}
+                    _ => {
+                        ours.dwell.insert(state, stats);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// With `sources`, every edge gets a tooltip listing where its transitions
+/// are annotated and, given a URL template, a link to the first of them.
+pub fn dot_for_node_graph(node: &NodeGraph, sources: Option<&SourceLinks>) -> String {
     let mut grouped: BTreeMap<(String, String), Vec<String>> = BTreeMap::new();
     for e in &node.edges {
         grouped
@@ -156,16 +284,38 @@ pub fn dot_for_node_graph(node: &NodeGraph) -> String {
         out.push_str(&to);
         out.push_str("\" [label=<");
         out.push_str(&label_str);
-        out.push_str(">];\n");
+        out.push('>');
+        if let Some(sources) = sources {
+            let locations: Vec<(&String, &ManifestEntry)> = labels
+                .iter()
+                .flat_map(|l| sources.locations(node, l).into_iter().map(move |e| (l, e)))
+                .collect();
+            if !locations.is_empty() {
+                let tooltip: Vec<String> = locations
+                    .iter()
+                    .map(|(label, e)| dot_escape(&format!("{}: {}", label, describe_location(e))))
+                    .collect();
+                out.push_str(&format!(", tooltip=\"{}\"", tooltip.join("\\n")));
+                if let Some(url) = sources.url(locations[0].1) {
+                    out.push_str(&format!(", URL=\"{}\"", dot_escape(&url)));
+                }
+            }
+        }
+        out.push_str("];\n");
     }
 
     out.push_str("}\n");
     out
 }
 
+fn dot_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::model::refinement::{MergeRule, RefinementConfig};
 
     #[test]
     fn builds_cyclic_graph_with_four_states() {
@@ -303,6 +453,7 @@ mod tests {
                 .iter()
                 .any(|e| e.from == "idle" && e.transition == "start" && e.to == "active")
         );
+        assert_eq!(n1.initial.as_deref(), Some("idle"));
         assert!(
             n1.edges
                 .iter()
@@ -364,13 +515,137 @@ too many parts here now
         );
     }
 
+    #[test]
+    fn refinement_merges_states_before_building_edges() {
+        let log = "[2025-11-10 19:56:55.000001][INFO] [BLOCK_EVENT_TYPE][Node 1 Batch 1 Entry 1 / 4] BlockExecute 1 @ FunctionName elect @ state CandidateVotesInQuorum
+[2025-11-10 19:56:55.000002][INFO] [BLOCK_EVENT_TYPE][Node 1 Batch 1 Entry 2 / 4] BlockExecute 2 @ FunctionName replicate @ state LeaderMatchingQuorumLogUpdated
+[2025-11-10 19:56:55.000003][INFO] [BLOCK_EVENT_TYPE][Node 1 Batch 1 Entry 3 / 4] BlockExecute 3 @ FunctionName replicate @ state LeaderNotMatchingQuorumLogUpdated
+[2025-11-10 19:56:55.000004][INFO] [BLOCK_EVENT_TYPE][Node 1 Batch 1 Entry 4 / 4] BlockExecute 4 @ FunctionName stepDown @ state Follower";
+        let config = RefinementConfig {
+            merge: vec![
+                MergeRule {
+                    into: "Leader".to_string(),
+                    pattern: Some("^Leader".to_string()),
+                    states: vec![],
+                },
+                MergeRule {
+                    into: "Candidate".to_string(),
+                    pattern: Some("^Candidate".to_string()),
+                    states: vec![],
+                },
+            ],
+            ..Default::default()
+        };
+        let g = build_event_graph_with(log, &config.compile().unwrap());
+        let node = g.nodes.get(&1).unwrap();
+
+        assert_eq!(node.states, vec!["Candidate", "Follower", "Leader"]);
+        assert_eq!(node.edges.len(), 3);
+        assert!(
+            node.edges
+                .iter()
+                .any(|e| e.from == "Leader" && e.transition == "replicate" && e.to == "Leader")
+        );
+    }
+
+    #[test]
+    fn refinement_drops_states_and_bridges_edges() {
+        let log = "[2025-11-10 19:56:55.000001][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 1 / 3] FunctionExecute 1 @ FunctionName a @ state Follower
+[2025-11-10 19:56:55.000002][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 2 / 3] FunctionExecute 2 @ FunctionName b @ state Unknown
+[2025-11-10 19:56:55.000003][INFO] [CONST_EVENT_TYPE][Node 1 Batch 1 Entry 3 / 3] ConstantExecute 3 @ FunctionName c @ constant timeout
+[2025-11-10 19:56:55.000004][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 2 Entry 1 / 1] FunctionExecute 4 @ FunctionName d @ state Candidate";
+        let config = RefinementConfig {
+            drop_states: vec!["^Unknown$".to_string()],
+            drop_constants: true,
+            ..Default::default()
+        };
+        let g = build_event_graph_with(log, &config.compile().unwrap());
+        let node = g.nodes.get(&1).unwrap();
+
+        assert_eq!(node.states, vec!["Candidate", "Follower"]);
+        assert_eq!(node.edges.len(), 1);
+        assert_eq!(
+            node.edges[0],
+            Edge {
+                from: "Follower".to_string(),
+                transition: "a".to_string(),
+                to: "Candidate".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn refinement_drops_transitions() {
+        let log = "[2025-11-10 19:56:55.000001][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 1 / 3] FunctionExecute 1 @ FunctionName tick @ state s0
+[2025-11-10 19:56:55.000002][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 2 / 3] FunctionExecute 2 @ FunctionName work @ state s1
+[2025-11-10 19:56:55.000003][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 3 / 3] FunctionExecute 3 @ FunctionName tick @ state s0";
+        let config = RefinementConfig {
+            drop_transitions: vec!["^tick$".to_string()],
+            ..Default::default()
+        };
+        let g = build_event_graph_with(log, &config.compile().unwrap());
+        let node = g.nodes.get(&1).unwrap();
+
+        assert_eq!(node.states.len(), 2);
+        assert_eq!(node.edges.len(), 1);
+        assert_eq!(node.edges[0].transition, "work");
+    }
+
+    #[test]
+    fn window_graphs_attribute_boundary_edges_to_target_window() {
+        let log = "[2025-11-10 19:56:55.000001][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 1 / 1] FunctionExecute 1 @ FunctionName boot @ state Follower
+[2025-11-10 19:56:55.000002][INFO] [WINDOW][Schedule 0 Step 0] Action 1
+[2025-11-10 19:56:55.000003][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 2 Entry 1 / 2] FunctionExecute 2 @ FunctionName elect @ state Candidate
+[2025-11-10 19:56:55.000004][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 2 Entry 2 / 2] FunctionExecute 3 @ FunctionName win @ state Leader
+[2025-11-10 19:56:55.000005][INFO] [WINDOW][Schedule 0 Step 1] Action 0
+[2025-11-10 19:56:55.000006][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 3 Entry 1 / 1] FunctionExecute 4 @ FunctionName stepDown @ state Follower";
+        let windows = build_window_graphs(log, &Refinement::identity());
+
+        assert_eq!(windows.len(), 2);
+        let first = &windows[&Window {
+            schedule_id: 0,
+            step_id: 0,
+        }]
+            .nodes[&1];
+        assert_eq!(first.states, vec!["Candidate", "Leader"]);
+        assert_eq!(first.edges.len(), 2);
+        assert!(
+            first
+                .edges
+                .iter()
+                .any(|e| e.from == "Follower" && e.transition == "boot" && e.to == "Candidate")
+        );
+
+        let second = &windows[&Window {
+            schedule_id: 0,
+            step_id: 1,
+        }]
+            .nodes[&1];
+        assert_eq!(second.states, vec!["Follower"]);
+        assert_eq!(
+            second.edges,
+            vec![Edge {
+                from: "Leader".to_string(),
+                transition: "win".to_string(),
+                to: "Follower".to_string(),
+            }]
+        );
+
+        assert_eq!(first.initial.as_deref(), Some("Candidate"));
+        assert_eq!(second.initial.as_deref(), Some("Follower"));
+
+        let schedules = build_schedule_graphs(log, &Refinement::identity());
+        assert_eq!(schedules.len(), 1);
+        assert_eq!(schedules[&0].nodes[&1].edges.len(), 3);
+    }
+
     #[test]
     fn renders_dot_for_node_graph() {
         let log = "[2025-11-10 19:56:55.000001][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 1 / 3] FunctionExecute 1 @ FunctionName A @ state s0
 [2025-11-10 19:56:55.000002][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 2 / 3] FunctionExecute 2 @ FunctionName B @ state s1
 [2025-11-10 19:56:55.000003][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 3 / 3] FunctionExecute 3 @ FunctionName C @ state s0";
         let g = build_event_graph(log);
-        let dot = dot_for_node_graph(g.nodes.get(&1).unwrap());
+        let dot = dot_for_node_graph(g.nodes.get(&1).unwrap(), None);
         assert!(dot.contains("digraph G{"));
         assert!(dot.contains("rankdir=LR;"));
         assert!(dot.contains("\"s0\";"));
@@ -387,7 +662,7 @@ too many parts here now
 [2025-11-10 19:56:55.000004][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 4 / 5] FunctionExecute 4 @ FunctionName D @ state s1
 [2025-11-10 19:56:55.000005][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 5 / 5] FunctionExecute 5 @ FunctionName E @ state s0";
         let g = build_event_graph(log);
-        let dot = dot_for_node_graph(g.nodes.get(&1).unwrap());
+        let dot = dot_for_node_graph(g.nodes.get(&1).unwrap(), None);
         assert!(dot.contains("\"s0\" -> \"s1\" [label=<A<BR/>C>];"));
         assert!(dot.contains("\"s1\" -> \"s0\" [label=<B<BR/>D>];"));
     }
@@ -471,4 +746,25 @@ Random log line without brackets
 
         assert_eq!(g.nodes.len(), 2);
     }
+
+    #[test]
+    fn attributes_dwell_to_the_window_that_entered_the_state() {
+        let log = std::fs::read_to_string("tests/export_graphs_test/windowed_events.log").unwrap();
+
+        let full = build_event_graph(&log);
+        let node = &full.nodes[&1];
+        assert_eq!(node.dwell["Follower"].max_us, 100_000);
+        assert_eq!(node.dwell["Candidate"].samples, 1);
+        assert_eq!(node.dwell["Leader"].median_us, 299_999);
+
+        let windows = build_window_graphs(&log, &Refinement::identity());
+        let first = &windows[&Window {
+            schedule_id: 0,
+            step_id: 0,
+        }]
+            .nodes[&1];
+        assert!(!first.dwell.contains_key("Follower"));
+        assert_eq!(first.dwell["Candidate"].max_us, 1);
+        assert_eq!(first.dwell["Leader"].max_us, 299_999);
+    }
 }
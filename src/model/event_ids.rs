@@ -0,0 +1,171 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Event ids travel through the LLVM pass and the runtime as `u16`.
+pub const EVENT_ID_SPACE: u32 = 1 << 16;
+
+/// What identifies an annotation independently of where other annotations
+/// are: line numbers are left out so that edits elsewhere in the file do not
+/// change the id.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct AnnotationKey {
+    pub path: String,
+    pub function: String,
+    pub annotation: String,
+}
+
+impl AnnotationKey {
+    pub fn describe(&self) -> String {
+        if self.function.is_empty() {
+            format!("{}: {}", self.path, self.annotation)
+        } else {
+            format!("{} ({}): {}", self.path, self.function, self.annotation)
+        }
+    }
+}
+
+/// Two groups whose annotations hashed to the same id. The group that sorts
+/// first keeps the id and the other one is moved to the next free salt.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdCollision {
+    pub id: u32,
+    pub kept: String,
+    pub moved: String,
+    pub assigned: u32,
+}
+
+pub fn fnv1a(bytes: impl IntoIterator<Item = u8>, mut hash: u64) -> u64 {
+    for byte in bytes {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+/// Hash of a group's annotations, folded into the event id space.
+pub fn stable_id(keys: &[AnnotationKey], salt: u32) -> u32 {
+    let mut hash = 0xcbf29ce484222325;
+    for key in keys {
+        for field in [&key.path, &key.function, &key.annotation] {
+            hash = fnv1a(field.bytes().chain([0]), hash);
+        }
+    }
+    if salt > 0 {
+        hash = fnv1a(salt.to_le_bytes(), hash);
+    }
+    let folded = hash ^ (hash >> 16) ^ (hash >> 32) ^ (hash >> 48);
+    (folded % u64::from(EVENT_ID_SPACE)) as u32
+}
+
+/// Maps provisional group ids, handed out in parse order, to ids derived from
+/// the annotations in each group. Groups are visited in the order of their
+/// contents, so the result does not depend on the order files were read in.
+pub fn assign_stable_ids(
+    groups: &BTreeMap<u32, Vec<AnnotationKey>>,
+) -> (BTreeMap<u32, u32>, Vec<IdCollision>) {
+    let mut ordered: Vec<(Vec<AnnotationKey>, u32)> = groups
+        .iter()
+        .map(|(provisional, keys)| {
+            let mut keys = keys.clone();
+            keys.sort();
+            (keys, *provisional)
+        })
+        .collect();
+    ordered.sort();
+
+    let mut ids = BTreeMap::new();
+    let mut owners: BTreeMap<u32, String> = BTreeMap::new();
+    let mut used = BTreeSet::new();
+    let mut collisions = Vec::new();
+
+    for (keys, provisional) in ordered {
+        let base = stable_id(&keys, 0);
+        let mut id = base;
+        let mut salt = 0;
+        while used.contains(&id) {
+            salt += 1;
+            id = stable_id(&keys, salt);
+        }
+        let description = describe_group(&keys);
+        if salt > 0 {
+            collisions.push(IdCollision {
+                id: base,
+                kept: owners[&base].clone(),
+                moved: description.clone(),
+                assigned: id,
+            });
+        }
+        used.insert(id);
+        owners.insert(id, description);
+        ids.insert(provisional, id);
+    }
+
+    (ids, collisions)
+}
+
+fn describe_group(keys: &[AnnotationKey]) -> String {
+    keys.iter()
+        .map(AnnotationKey::describe)
+        .collect::<Vec<_>>()
+        .join(" + ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(path: &str, function: &str, annotation: &str) -> AnnotationKey {
+        AnnotationKey {
+            path: path.to_string(),
+            function: function.to_string(),
+            annotation: annotation.to_string(),
+        }
+    }
+
+    #[test]
+    fn id_depends_on_path_function_and_annotation() {
+        let base = stable_id(&[key("src/raft.c", "step", "ABSTRAKTOR_FUNC: r END")], 0);
+        assert_eq!(
+            base,
+            stable_id(&[key("src/raft.c", "step", "ABSTRAKTOR_FUNC: r END")], 0)
+        );
+        assert_ne!(
+            base,
+            stable_id(&[key("src/log.c", "step", "ABSTRAKTOR_FUNC: r END")], 0)
+        );
+        assert_ne!(
+            base,
+            stable_id(&[key("src/raft.c", "tick", "ABSTRAKTOR_FUNC: r END")], 0)
+        );
+        assert_ne!(
+            base,
+            stable_id(&[key("src/raft.c", "step", "ABSTRAKTOR_FUNC: s END")], 0)
+        );
+        assert!(base < EVENT_ID_SPACE);
+    }
+
+    #[test]
+    fn assignment_ignores_provisional_order() {
+        let a = vec![key("a.c", "f", "ABSTRAKTOR_FUNC: x END")];
+        let b = vec![key("b.c", "g", "ABSTRAKTOR_FUNC: y END")];
+
+        let (first, _) = assign_stable_ids(&BTreeMap::from([(0, a.clone()), (1, b.clone())]));
+        let (second, _) = assign_stable_ids(&BTreeMap::from([(0, b), (1, a)]));
+
+        assert_eq!(first[&0], second[&1]);
+        assert_eq!(first[&1], second[&0]);
+    }
+
+    #[test]
+    fn colliding_groups_get_distinct_ids_and_are_reported() {
+        let same = vec![key("a.c", "f", "ABSTRAKTOR_BLOCK_EVENT END")];
+        let (ids, collisions) =
+            assign_stable_ids(&BTreeMap::from([(0, same.clone()), (1, same.clone())]));
+
+        assert_ne!(ids[&0], ids[&1]);
+        assert_eq!(ids[&0], stable_id(&same, 0));
+        assert_eq!(collisions.len(), 1);
+        assert_eq!(collisions[0].id, ids[&0]);
+        assert_eq!(collisions[0].assigned, ids[&1]);
+        assert_eq!(collisions[0].moved, "a.c (f): ABSTRAKTOR_BLOCK_EVENT END");
+    }
+}
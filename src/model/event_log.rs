@@ -0,0 +1,402 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Window {
+    pub schedule_id: usize,
+    pub step_id: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventRecord {
+    pub node_id: u32,
+    /// Id assigned to the annotation at instrumentation time.
+    pub event_id: Option<u32>,
+    pub transition: String,
+    pub state: String,
+    pub constant: bool,
+    pub window: Option<Window>,
+    /// 1-based line number in the event log.
+    pub line: usize,
+    /// When the event happened, in microseconds since the Unix epoch. See
+    /// `parse_event_log` for where it comes from.
+    pub timestamp_us: Option<i64>,
+    /// The runtime's `CLOCK_MONOTONIC` reading for the event, in nanoseconds,
+    /// from the trailing `@ ts` segment.
+    pub event_time_ns: Option<u64>,
+    /// Raw values of the captured variables, from an optional
+    /// `@ values name=value,...` segment before `@ ts`.
+    pub values: BTreeMap<String, String>,
+}
+
+/// Event times come from the `@ ts` the mediator logs with every event,
+/// placed on the wall clock by the smallest gap between an event and the
+/// line that logged it, per node. Records without `@ ts` fall back to the
+/// `[YYYY-MM-DD HH:MM:SS.ffffff]` line prefix, which is when the mediator
+/// logged the whole batch; see `batch_time_warning`.
+pub fn parse_event_log(log: &str) -> Vec<EventRecord> {
+    let mut records = Vec::new();
+    let mut window = None;
+
+    for (index, line) in log.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+
+        if let Some(marker) = parse_window_marker(trimmed) {
+            window = Some(marker);
+            continue;
+        }
+
+        if let Some(mut record) = parse_mediator_log_line(trimmed) {
+            record.window = window;
+            record.line = index + 1;
+            record.timestamp_us = parse_timestamp(trimmed);
+            records.push(record);
+        }
+    }
+
+    let mut offsets: HashMap<u32, i64> = HashMap::new();
+    for rec in &records {
+        if let (Some(logged), Some(event)) = (rec.timestamp_us, rec.event_time_ns) {
+            let offset = logged - (event / 1_000) as i64;
+            offsets
+                .entry(rec.node_id)
+                .and_modify(|o| *o = (*o).min(offset))
+                .or_insert(offset);
+        }
+    }
+    for rec in &mut records {
+        if let (Some(offset), Some(event)) = (offsets.get(&rec.node_id), rec.event_time_ns) {
+            rec.timestamp_us = Some(offset + (event / 1_000) as i64);
+        }
+    }
+
+    records
+}
+
+/// A warning when some timed records carry no `@ ts`, so that their times are
+/// the batch's log time: every event of a batch gets the same time and stays
+/// shorter than a batch do not show.
+pub fn batch_time_warning(records: &[EventRecord]) -> Option<String> {
+    let timed = records.iter().filter(|r| r.timestamp_us.is_some()).count();
+    let batch_timed = records
+        .iter()
+        .filter(|r| r.timestamp_us.is_some() && r.event_time_ns.is_none())
+        .count();
+    (batch_timed > 0).then(|| {
+        format!(
+            "{} of {} events have no per-event timestamp (`@ ts`), using the mediator's batch log time for them; rebuild the mediator for exact times",
+            batch_timed, timed
+        )
+    })
+}
+
+pub fn parse_window_marker(line: &str) -> Option<Window> {
+    let marker = "[WINDOW][Schedule ";
+    let start = line.find(marker)? + marker.len();
+    let rest = &line[start..];
+
+    let schedule_end = rest.find(' ')?;
+    let schedule_id = rest[..schedule_end].parse().ok()?;
+
+    let rest = rest[schedule_end..].strip_prefix(" Step ")?;
+    let step_end = rest.find(']')?;
+    let step_id = rest[..step_end].parse().ok()?;
+
+    Some(Window {
+        schedule_id,
+        step_id,
+    })
+}
+
+/// Parses the mediator's `[YYYY-MM-DD HH:MM:SS.ffffff]` line prefix, read as
+/// UTC.
+pub fn parse_timestamp(line: &str) -> Option<i64> {
+    let rest = line.strip_prefix('[')?;
+    let stamp = &rest[..rest.find(']')?];
+    let (date, time) = stamp.split_once(' ')?;
+
+    let mut date_parts = date.splitn(3, '-').map(|p| p.parse::<i64>().ok());
+    let (year, month, day) = (
+        date_parts.next()??,
+        date_parts.next()??,
+        date_parts.next()??,
+    );
+
+    let (clock, fraction) = time.split_once('.').unwrap_or((time, ""));
+    let mut clock_parts = clock.splitn(3, ':').map(|p| p.parse::<i64>().ok());
+    let (hour, minute, second) = (
+        clock_parts.next()??,
+        clock_parts.next()??,
+        clock_parts.next()??,
+    );
+    let micros = if fraction.is_empty() {
+        0
+    } else {
+        let digits: String = fraction.chars().chain("000000".chars()).take(6).collect();
+        digits.parse::<i64>().ok()?
+    };
+
+    let seconds = days_from_civil(year, month, day) * 86_400 + hour * 3_600 + minute * 60 + second;
+    Some(seconds * 1_000_000 + micros)
+}
+
+/// Days since 1970-01-01 in the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_index = (month + 9) % 12;
+    let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Nemesis action chosen at every window, from the `Action A` suffix of the
+/// window markers.
+pub fn parse_window_actions(log: &str) -> BTreeMap<Window, usize> {
+    log.lines()
+        .filter_map(|line| Some((parse_window_marker(line)?, parse_window_action(line)?)))
+        .collect()
+}
+
+pub fn parse_window_action(line: &str) -> Option<usize> {
+    line[line.find("] Action ")? + "] Action ".len()..]
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// EDN of every nemesis action id, from the mediator's
+/// `[NEMESIS] Generated action id <edn> (<id>) at step <step>.` lines.
+pub fn parse_nemesis_actions(log: &str) -> BTreeMap<usize, String> {
+    let marker = "Generated action id ";
+    log.lines()
+        .filter(|line| line.contains("[NEMESIS]"))
+        .filter_map(|line| {
+            let rest = &line[line.find(marker)? + marker.len()..];
+            let rest = &rest[..rest.rfind(") at step ")?];
+            let open = rest.rfind(" (")?;
+            let id = rest[open + 2..].parse().ok()?;
+            Some((id, rest[..open].trim().to_string()))
+        })
+        .collect()
+}
+
+fn parse_mediator_log_line(line: &str) -> Option<EventRecord> {
+    let relevant_part = line.find("[Node ")?;
+    let relevant_part = &line[relevant_part..];
+
+    let node_start = "[Node ".len();
+    let node_end = relevant_part[node_start..].find(' ')?;
+    let node_id: u32 = relevant_part[node_start..node_start + node_end]
+        .parse()
+        .ok()?;
+
+    let event_id = relevant_part.find("Execute ").and_then(|pos| {
+        let rest = &relevant_part[pos + "Execute ".len()..];
+        rest[..rest.find(' ')?].parse().ok()
+    });
+
+    let function_marker = "@ FunctionName ";
+    let function_start = relevant_part.find(function_marker)? + function_marker.len();
+    let function_end = relevant_part[function_start..].find(" @ ")?;
+    let transition = relevant_part[function_start..function_start + function_end].to_string();
+
+    let (relevant_part, event_time_ns) = match relevant_part.rfind(" @ ts ") {
+        Some(pos) => (
+            &relevant_part[..pos],
+            relevant_part[pos + " @ ts ".len()..].trim().parse().ok(),
+        ),
+        None => (relevant_part, None),
+    };
+
+    let (relevant_part, values) = match relevant_part.find(" @ values ") {
+        Some(pos) => (
+            &relevant_part[..pos],
+            parse_values(&relevant_part[pos + " @ values ".len()..]),
+        ),
+        None => (relevant_part, BTreeMap::new()),
+    };
+
+    let (state, constant) = if let Some(state_marker_pos) = relevant_part.find("@ state ") {
+        let state_start = state_marker_pos + "@ state ".len();
+        (relevant_part[state_start..].trim().to_string(), false)
+    } else if let Some(const_marker_pos) = relevant_part.find("@ constant ") {
+        let const_start = const_marker_pos + "@ constant ".len();
+        (relevant_part[const_start..].trim().to_string(), true)
+    } else {
+        return None;
+    };
+
+    Some(EventRecord {
+        node_id,
+        event_id,
+        transition,
+        state,
+        constant,
+        window: None,
+        line: 0,
+        timestamp_us: None,
+        event_time_ns,
+        values,
+    })
+}
+
+fn parse_values(segment: &str) -> BTreeMap<String, String> {
+    segment
+        .split(',')
+        .filter_map(|pair| {
+            let (name, value) = pair.split_once('=')?;
+            Some((name.trim().to_string(), value.trim().to_string()))
+        })
+        .filter(|(name, _)| !name.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_window_markers() {
+        assert_eq!(
+            parse_window_marker(
+                "[2025-11-10 19:56:55.000001][INFO] [WINDOW][Schedule 3 Step 12] Action 4"
+            ),
+            Some(Window {
+                schedule_id: 3,
+                step_id: 12
+            })
+        );
+        assert_eq!(
+            parse_window_marker("[WINDOW][Schedule x Step 1] Action 0"),
+            None
+        );
+        assert_eq!(parse_window_marker("[NEMESIS] Generated action"), None);
+    }
+
+    #[test]
+    fn parses_window_actions_and_nemesis_edn() {
+        let log = "[2025-11-10 19:56:55.000001][INFO] [WINDOW][Schedule 0 Step 0] Action 2
+[2025-11-10 19:56:55.000002][INFO] [NEMESIS] (Start schedule)  Generated action id {:f :start-partition, :value (:majority), :must-dispatch true} (2) at step 0.
+[2025-11-10 19:56:55.000003][INFO] [WINDOW][Schedule 0 Step 1] Action 0
+[2025-11-10 19:56:55.000004][INFO] [NEMESIS]  Generated action id :pending (0) at step 1.
+[2025-11-10 19:56:55.000005][INFO] [WINDOW][Schedule 1 Step 0]";
+        let actions = parse_window_actions(log);
+
+        assert_eq!(actions.len(), 2);
+        assert_eq!(
+            actions[&Window {
+                schedule_id: 0,
+                step_id: 0
+            }],
+            2
+        );
+
+        let edn = parse_nemesis_actions(log);
+        assert_eq!(edn.len(), 2);
+        assert_eq!(
+            edn[&2],
+            "{:f :start-partition, :value (:majority), :must-dispatch true}"
+        );
+        assert_eq!(edn[&0], ":pending");
+    }
+
+    #[test]
+    fn tags_events_with_latest_window() {
+        let log = "[2025-11-10 19:56:55.000001][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 1 / 1] FunctionExecute 1 @ FunctionName boot @ state idle
+[2025-11-10 19:56:55.000002][INFO] [WINDOW][Schedule 0 Step 0] Action 1
+[2025-11-10 19:56:55.000003][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 2 Entry 1 / 1] FunctionExecute 2 @ FunctionName start @ state active
+[2025-11-10 19:56:55.000004][INFO] [WINDOW][Schedule 1 Step 2] Action 0
+[2025-11-10 19:56:55.000005][INFO] [FUNC_EVENT_TYPE][Node 2 Batch 1 Entry 1 / 1] FunctionExecute 3 @ FunctionName stop @ state idle";
+        let records = parse_event_log(log);
+
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].window, None);
+        assert_eq!(
+            records[1].window,
+            Some(Window {
+                schedule_id: 0,
+                step_id: 0
+            })
+        );
+        assert_eq!(
+            records[2].window,
+            Some(Window {
+                schedule_id: 1,
+                step_id: 2
+            })
+        );
+        assert_eq!(records[2].node_id, 2);
+        assert_eq!(records[2].line, 5);
+    }
+
+    #[test]
+    fn parses_optional_values_segment() {
+        let log = "[2025-11-10 19:56:55.000001][INFO] [BLOCK_EVENT_TYPE][Node 1 Batch 1 Entry 1 / 1] BlockExecute 7 @ FunctionName replicate @ state Leader @ values commit_index=4, last_term = 2,bogus @ ts 1200";
+        let records = parse_event_log(log);
+
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].event_id, Some(7));
+        assert_eq!(records[0].state, "Leader");
+        assert_eq!(records[0].values.len(), 2);
+        assert_eq!(records[0].values["commit_index"], "4");
+        assert_eq!(records[0].values["last_term"], "2");
+        assert_eq!(records[0].event_time_ns, Some(1200));
+    }
+
+    #[test]
+    fn parses_line_timestamps() {
+        assert_eq!(
+            parse_timestamp("[1970-01-01 00:00:01.5][INFO] [WINDOW]"),
+            Some(1_500_000)
+        );
+        assert_eq!(
+            parse_timestamp("[2025-11-10 19:56:55.000001][INFO]"),
+            Some(1_762_804_615_000_001)
+        );
+        assert_eq!(parse_timestamp("[INFO] no timestamp"), None);
+
+        let log = "[2025-11-10 19:56:55.000001][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 1 / 1] FunctionExecute 1 @ FunctionName boot @ state idle";
+        assert_eq!(
+            parse_event_log(log)[0].timestamp_us,
+            Some(1_762_804_615_000_001)
+        );
+    }
+
+    #[test]
+    fn prefers_per_event_timestamps() {
+        // One batch logged at .000900 whose events happened 300us apart, and
+        // an older mediator line without `@ ts`.
+        let log = "[2025-11-10 19:56:55.000900][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 1 / 2] FunctionExecute 1 @ FunctionName boot @ state idle @ ts 5000000
+[2025-11-10 19:56:55.000900][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 2 / 2] FunctionExecute 2 @ FunctionName start @ state active @ ts 5300000
+[2025-11-10 19:56:55.001000][INFO] [FUNC_EVENT_TYPE][Node 2 Batch 1 Entry 1 / 1] FunctionExecute 1 @ FunctionName boot @ state idle";
+        let records = parse_event_log(log);
+
+        assert_eq!(records[0].state, "idle");
+        assert_eq!(records[1].event_time_ns, Some(5_300_000));
+        assert_eq!(
+            records[1].timestamp_us.unwrap() - records[0].timestamp_us.unwrap(),
+            300
+        );
+        assert_eq!(records[1].timestamp_us, Some(1_762_804_615_000_900));
+        assert_eq!(records[2].timestamp_us, Some(1_762_804_615_001_000));
+        assert!(batch_time_warning(&records).unwrap().starts_with("1 of 3"));
+        assert_eq!(batch_time_warning(&records[..2]), None);
+    }
+
+    #[test]
+    fn marks_constant_events() {
+        let log = "[2025-11-10 19:56:55.000001][INFO] [CONST_EVENT_TYPE][Node 1 Batch 1 Entry 1 / 1] ConstantExecute 1 @ FunctionName tick @ constant timeout";
+        let records = parse_event_log(log);
+
+        assert_eq!(records.len(), 1);
+        assert!(records[0].constant);
+        assert_eq!(records[0].state, "timeout");
+        assert_eq!(records[0].transition, "tick");
+    }
+}
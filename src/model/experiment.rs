@@ -0,0 +1,575 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use super::build_event_graph_with;
+use super::dwell::format_duration;
+use super::event_graph::{Edge, EventGraph};
+use super::event_log::parse_event_log;
+use super::project::TestConfig;
+use super::refinement::Refinement;
+use super::report::{Oracles, RunInput, campaign_report};
+use super::stats::{MannWhitney, Summary, mann_whitney, summarize};
+
+/// `experiment.toml`: the `[test]` options of a project to vary, and how
+/// often to run each combination.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExperimentConfig {
+    pub project: String,
+    pub repetitions: usize,
+    pub out: String,
+    /// Files copied into each run's directory after the run, besides the
+    /// mediator log and results `run mallory` exports.
+    pub collect: Vec<String>,
+    /// Events log whose edges do not count as discoveries.
+    pub baseline: Option<String>,
+    pub refinement: Option<String>,
+    pub max_dwell: Vec<String>,
+    /// `[test]` option name to the values it takes.
+    pub matrix: BTreeMap<String, Vec<toml::Value>>,
+}
+
+impl Default for ExperimentConfig {
+    fn default() -> Self {
+        ExperimentConfig {
+            project: "abstraktor.toml".to_string(),
+            repetitions: 5,
+            out: "experiments".to_string(),
+            collect: Vec::new(),
+            baseline: None,
+            refinement: None,
+            max_dwell: Vec::new(),
+            matrix: BTreeMap::new(),
+        }
+    }
+}
+
+/// One combination of matrix values.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Configuration {
+    pub name: String,
+    pub overrides: Vec<(String, toml::Value)>,
+}
+
+impl ExperimentConfig {
+    pub fn from_file(path: &str) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("reading experiment from {}", path))?;
+        Self::parse(&content).with_context(|| format!("parsing experiment {}", path))
+    }
+
+    pub fn parse(content: &str) -> Result<Self> {
+        let config: Self = toml::from_str(content)?;
+        if config.repetitions == 0 {
+            anyhow::bail!("repetitions must be at least 1");
+        }
+        if let Some((name, _)) = config.matrix.iter().find(|(_, values)| values.is_empty()) {
+            anyhow::bail!("matrix option {} has no values", name);
+        }
+        Ok(config)
+    }
+
+    /// Every combination of matrix values, the last option varying fastest.
+    pub fn configurations(&self) -> Vec<Configuration> {
+        let mut combinations: Vec<Vec<(String, toml::Value)>> = vec![Vec::new()];
+        for (name, values) in &self.matrix {
+            combinations = combinations
+                .into_iter()
+                .flat_map(|combination| {
+                    values.iter().map(move |value| {
+                        let mut combination = combination.clone();
+                        combination.push((name.clone(), value.clone()));
+                        combination
+                    })
+                })
+                .collect();
+        }
+        combinations
+            .into_iter()
+            .map(|overrides| Configuration {
+                name: if overrides.is_empty() {
+                    "default".to_string()
+                } else {
+                    overrides
+                        .iter()
+                        .map(|(name, value)| format!("{}={}", name, display_value(value)))
+                        .collect::<Vec<_>>()
+                        .join(",")
+                },
+                overrides,
+            })
+            .collect()
+    }
+}
+
+fn display_value(value: &toml::Value) -> String {
+    match value {
+        toml::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+impl Configuration {
+    /// The project's `[test]` section with this configuration's values; an
+    /// option `[test]` does not have is an error.
+    pub fn apply(&self, test: &TestConfig) -> Result<TestConfig> {
+        let mut table = toml::Table::try_from(test)?;
+        for (name, value) in &self.overrides {
+            table.insert(name.clone(), value.clone());
+        }
+        table
+            .try_into()
+            .with_context(|| format!("applying configuration {}", self.name))
+    }
+}
+
+/// The artifacts of one finished run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunOutcome {
+    pub configuration: String,
+    pub repetition: usize,
+    pub log: String,
+    pub results: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunMetrics {
+    pub configuration: String,
+    pub repetition: usize,
+    pub states: usize,
+    pub edges: usize,
+    /// Oracle hits, as in `report`.
+    pub bugs: usize,
+    /// Time from the run's first event to the first edge outside the
+    /// reference set.
+    pub first_discovery_us: Option<u64>,
+    pub duration_us: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigurationSummary {
+    pub name: String,
+    pub runs: usize,
+    pub states: Option<Summary>,
+    pub edges: Option<Summary>,
+    pub bugs: Option<Summary>,
+    /// In seconds, over the runs that discovered anything.
+    pub first_discovery: Option<Summary>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comparison {
+    pub metric: &'static str,
+    pub a: String,
+    pub b: String,
+    pub test: MannWhitney,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExperimentReport {
+    pub runs: Vec<RunMetrics>,
+    pub configurations: Vec<ConfigurationSummary>,
+    pub comparisons: Vec<Comparison>,
+    /// Edges that are not discoveries: the baseline's, or else the ones
+    /// every run reached.
+    pub reference_edges: usize,
+    pub baseline: bool,
+}
+
+const METRICS: [&str; 4] = ["states", "edges", "bugs", "time to first discovery"];
+
+fn metric(run: &RunMetrics, name: &str) -> Option<f64> {
+    match name {
+        "states" => Some(run.states as f64),
+        "edges" => Some(run.edges as f64),
+        "bugs" => Some(run.bugs as f64),
+        _ => run.first_discovery_us.map(|us| us as f64 / 1_000_000.0),
+    }
+}
+
+type NodeEdge = (u32, Edge);
+
+fn edge_set(graph: &EventGraph) -> HashSet<NodeEdge> {
+    graph
+        .nodes
+        .iter()
+        .flat_map(|(node_id, node)| node.edges.iter().map(|e| (*node_id, e.clone())))
+        .collect()
+}
+
+/// Walks the run like `build_event_graph_with` and stops at the first edge
+/// outside `reference`, timed from the first event by the events' own times
+/// rather than the batches they were logged in.
+fn first_discovery_us(
+    log: &str,
+    refinement: &Refinement,
+    reference: &HashSet<NodeEdge>,
+) -> Option<u64> {
+    let records = parse_event_log(log);
+    let start = records.iter().find_map(|r| r.timestamp_us)?;
+    let mut last_for_node: HashMap<u32, (String, String)> = HashMap::new();
+    for rec in records {
+        let Some(state) = refinement.state(&rec.state, rec.constant) else {
+            continue;
+        };
+        if let Some((from, transition)) = last_for_node.get(&rec.node_id)
+            && refinement.keeps_transition(transition)
+        {
+            let edge = Edge {
+                from: from.clone(),
+                transition: transition.clone(),
+                to: state.clone(),
+            };
+            if !reference.contains(&(rec.node_id, edge)) {
+                return rec.timestamp_us.map(|ts| (ts - start).max(0) as u64);
+            }
+        }
+        last_for_node.insert(rec.node_id, (state, rec.transition));
+    }
+    None
+}
+
+/// Aggregates the runs per configuration, in the order of `configurations`,
+/// and compares every pair of configurations on every metric.
+pub fn experiment_report(
+    outcomes: &[RunOutcome],
+    configurations: &[String],
+    baseline: Option<&EventGraph>,
+    refinement: &Refinement,
+    oracles: &Oracles,
+) -> ExperimentReport {
+    let inputs: Vec<RunInput> = outcomes
+        .iter()
+        .map(|outcome| RunInput {
+            name: format!("{}/run-{}", outcome.configuration, outcome.repetition),
+            log: outcome.log.clone(),
+            metadata: BTreeMap::new(),
+            results: outcome.results.clone(),
+        })
+        .collect();
+    let campaign = campaign_report(&inputs, None, refinement, oracles);
+
+    let edge_sets: Vec<HashSet<NodeEdge>> = outcomes
+        .iter()
+        .map(|outcome| edge_set(&build_event_graph_with(&outcome.log, refinement)))
+        .collect();
+    let reference = match baseline {
+        Some(baseline) => edge_set(baseline),
+        None => edge_sets.iter().skip(1).fold(
+            edge_sets.first().cloned().unwrap_or_default(),
+            |acc, set| acc.intersection(set).cloned().collect(),
+        ),
+    };
+
+    let runs: Vec<RunMetrics> = outcomes
+        .iter()
+        .zip(&inputs)
+        .zip(&campaign.runs)
+        .map(|((outcome, input), summary)| RunMetrics {
+            configuration: outcome.configuration.clone(),
+            repetition: outcome.repetition,
+            states: summary.states,
+            edges: summary.edges,
+            bugs: campaign
+                .oracle_hits
+                .iter()
+                .filter(|hit| hit.run == input.name)
+                .count(),
+            first_discovery_us: first_discovery_us(&outcome.log, refinement, &reference),
+            duration_us: summary.duration_us,
+        })
+        .collect();
+
+    let samples = |name: &str, metric_name: &str| -> Vec<f64> {
+        runs.iter()
+            .filter(|run| run.configuration == name)
+            .filter_map(|run| metric(run, metric_name))
+            .collect()
+    };
+
+    let summaries = configurations
+        .iter()
+        .map(|name| ConfigurationSummary {
+            name: name.clone(),
+            runs: runs.iter().filter(|run| &run.configuration == name).count(),
+            states: summarize(&samples(name, METRICS[0])),
+            edges: summarize(&samples(name, METRICS[1])),
+            bugs: summarize(&samples(name, METRICS[2])),
+            first_discovery: summarize(&samples(name, METRICS[3])),
+        })
+        .collect();
+
+    let mut comparisons = Vec::new();
+    for metric_name in METRICS {
+        for (i, a) in configurations.iter().enumerate() {
+            for b in &configurations[i + 1..] {
+                if let Some(test) = mann_whitney(&samples(a, metric_name), &samples(b, metric_name))
+                {
+                    comparisons.push(Comparison {
+                        metric: metric_name,
+                        a: a.clone(),
+                        b: b.clone(),
+                        test,
+                    });
+                }
+            }
+        }
+    }
+
+    ExperimentReport {
+        runs,
+        configurations: summaries,
+        comparisons,
+        reference_edges: reference.len(),
+        baseline: baseline.is_some(),
+    }
+}
+
+fn format_summary(summary: Option<Summary>, unit: &str) -> String {
+    match summary {
+        None => "-".to_string(),
+        Some(Summary {
+            mean, ci95: None, ..
+        }) => format!("{:.1}{}", mean, unit),
+        Some(Summary {
+            mean,
+            ci95: Some(ci),
+            ..
+        }) => format!("{:.1}{} ± {:.1}", mean, unit, ci),
+    }
+}
+
+pub fn markdown_for_experiment(report: &ExperimentReport) -> String {
+    let mut out = String::from("# Experiment\n\n");
+    out.push_str(&format!(
+        "Means with 95% confidence intervals. A discovery is an edge outside {} ({} edges).\n\n",
+        if report.baseline {
+            "the baseline"
+        } else {
+            "the ones every run reached"
+        },
+        report.reference_edges
+    ));
+
+    out.push_str("## Configurations\n\n");
+    out.push_str("| Configuration | Runs | States | Edges | Bugs | Time to first discovery |\n");
+    out.push_str("|---|---|---|---|---|---|\n");
+    for c in &report.configurations {
+        let discovered = c.first_discovery.map_or(0, |s| s.n);
+        out.push_str(&format!(
+            "| {} | {} | {} | {} | {} | {} ({}/{} runs) |\n",
+            c.name,
+            c.runs,
+            format_summary(c.states, ""),
+            format_summary(c.edges, ""),
+            format_summary(c.bugs, ""),
+            format_summary(c.first_discovery, " s"),
+            discovered,
+            c.runs
+        ));
+    }
+
+    out.push_str("\n## Comparisons\n\n");
+    if report.comparisons.is_empty() {
+        out.push_str("Nothing to compare; an experiment needs two configurations with runs.\n");
+    } else {
+        out.push_str(
+            "Mann-Whitney U test, two-sided. A12 is the probability that a run of A scores higher than a run of B.\n\n",
+        );
+        out.push_str("| Metric | A | B | U | p | A12 |\n|---|---|---|---|---|---|\n");
+        for c in &report.comparisons {
+            out.push_str(&format!(
+                "| {} | {} | {} | {} | {:.4}{} | {:.2} |\n",
+                c.metric,
+                c.a,
+                c.b,
+                c.test.u,
+                c.test.p_value,
+                if c.test.p_value < 0.05 { " *" } else { "" },
+                c.test.a12
+            ));
+        }
+    }
+
+    out.push_str("\n## Runs\n\n");
+    out.push_str("| Configuration | Run | States | Edges | Bugs | First discovery | Duration |\n");
+    out.push_str("|---|---|---|---|---|---|---|\n");
+    for run in &report.runs {
+        out.push_str(&format!(
+            "| {} | {} | {} | {} | {} | {} | {} |\n",
+            run.configuration,
+            run.repetition,
+            run.states,
+            run.edges,
+            run.bugs,
+            run.first_discovery_us
+                .map(format_duration)
+                .unwrap_or_default(),
+            run.duration_us.map(format_duration).unwrap_or_default()
+        ));
+    }
+    out
+}
+
+pub fn csv_for_runs(runs: &[RunMetrics]) -> String {
+    let seconds = |us: Option<u64>| {
+        us.map(|us| (us as f64 / 1e6).to_string())
+            .unwrap_or_default()
+    };
+    let mut out =
+        String::from("configuration,repetition,states,edges,bugs,first_discovery_s,duration_s\n");
+    for run in runs {
+        out.push_str(&format!(
+            "\"{}\",{},{},{},{},{},{}\n",
+            run.configuration.replace('"', "\"\""),
+            run.repetition,
+            run.states,
+            run.edges,
+            run.bugs,
+            seconds(run.first_discovery_us),
+            seconds(run.duration_us)
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::dwell::DwellThresholds;
+
+    fn oracles() -> Oracles<'static> {
+        Oracles {
+            dwell: DwellThresholds::default(),
+            spec: None,
+        }
+    }
+
+    fn outcome(configuration: &str, repetition: usize, log: &str) -> RunOutcome {
+        RunOutcome {
+            configuration: configuration.to_string(),
+            repetition,
+            log: log.to_string(),
+            results: None,
+        }
+    }
+
+    #[test]
+    fn test_configurations_are_the_matrix_product() {
+        let config = ExperimentConfig::parse(
+            "repetitions = 3\n[matrix]\nscheduler = [\"qlearning\", \"power\"]\ntime_limit = [60, 120]\n",
+        )
+        .unwrap();
+        let names: Vec<_> = config
+            .configurations()
+            .into_iter()
+            .map(|c| c.name)
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "scheduler=qlearning,time_limit=60",
+                "scheduler=qlearning,time_limit=120",
+                "scheduler=power,time_limit=60",
+                "scheduler=power,time_limit=120",
+            ]
+        );
+        assert_eq!(config.project, "abstraktor.toml");
+
+        let empty = ExperimentConfig::parse("").unwrap();
+        assert_eq!(empty.configurations()[0].name, "default");
+        assert!(ExperimentConfig::parse("[matrix]\nscheduler = []\n").is_err());
+        assert!(ExperimentConfig::parse("repetitions = 0\n").is_err());
+    }
+
+    #[test]
+    fn test_apply_overrides_test_section() {
+        let config = ExperimentConfig::parse(
+            "[matrix]\nscheduler = [\"power\"]\nsimilarity_threshold = [0.5]\n",
+        )
+        .unwrap();
+        let base = TestConfig {
+            scheduler: Some("qlearning".to_string()),
+            time_limit: Some(60),
+            ..TestConfig::default()
+        };
+        let applied = config.configurations()[0].apply(&base).unwrap();
+        assert_eq!(applied.scheduler.as_deref(), Some("power"));
+        assert_eq!(applied.similarity_threshold, Some(0.5));
+        assert_eq!(applied.time_limit, Some(60));
+
+        let typo = ExperimentConfig::parse("[matrix]\nschedular = [\"power\"]\n").unwrap();
+        assert!(typo.configurations()[0].apply(&base).is_err());
+    }
+
+    #[test]
+    fn test_experiment_report() {
+        let simple = include_str!("../../tests/export_graphs_test/simple_events.log");
+        let multi = include_str!("../../tests/export_graphs_test/multi_node_events.log");
+        let outcomes = [
+            outcome("scheduler=qlearning", 1, simple),
+            outcome("scheduler=qlearning", 2, multi),
+            outcome("scheduler=noop", 1, simple),
+            outcome("scheduler=noop", 2, simple),
+        ];
+        let configurations = [
+            "scheduler=qlearning".to_string(),
+            "scheduler=noop".to_string(),
+        ];
+        let baseline = build_event_graph_with(simple, &Refinement::identity());
+        let report = experiment_report(
+            &outcomes,
+            &configurations,
+            Some(&baseline),
+            &Refinement::identity(),
+            &oracles(),
+        );
+
+        assert_eq!(report.runs.len(), 4);
+        assert_eq!(report.configurations[0].runs, 2);
+        assert_eq!(report.configurations[1].states.unwrap().ci95, Some(0.0));
+        // The noop runs only reach the baseline's edges.
+        assert!(report.runs[2].first_discovery_us.is_none());
+        assert!(report.configurations[1].first_discovery.is_none());
+        assert!(report.runs[1].first_discovery_us.is_some());
+        assert_eq!(
+            report
+                .comparisons
+                .iter()
+                .map(|c| c.metric)
+                .collect::<Vec<_>>(),
+            ["states", "edges", "bugs"]
+        );
+
+        let markdown = markdown_for_experiment(&report);
+        assert!(markdown.contains("| scheduler=noop | 2 |"));
+        assert!(markdown.contains("## Comparisons"));
+        let csv = csv_for_runs(&report.runs);
+        assert!(csv.starts_with("configuration,repetition,"));
+        assert_eq!(csv.lines().count(), 5);
+    }
+
+    #[test]
+    fn test_first_discovery_uses_event_times() {
+        // Both events arrive in one batch, 200ms after the first.
+        let log = "[2025-11-10 19:56:55.000000][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 1 / 1] FunctionExecute 1 @ FunctionName boot @ state Follower @ ts 1000000000
+[2025-11-10 19:56:55.300000][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 2 Entry 1 / 2] FunctionExecute 2 @ FunctionName electionTimeout @ state Candidate @ ts 1050000000
+[2025-11-10 19:56:55.300000][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 2 Entry 2 / 2] FunctionExecute 3 @ FunctionName winElection @ state Leader @ ts 1200000000";
+        let refinement = Refinement::identity();
+        assert_eq!(
+            first_discovery_us(log, &refinement, &HashSet::new()),
+            Some(50_000)
+        );
+
+        let graph = build_event_graph_with(log, &refinement);
+        let mut reference = edge_set(&graph);
+        reference.retain(|(_, edge)| edge.to != "Leader");
+        assert_eq!(
+            first_discovery_us(log, &refinement, &reference),
+            Some(200_000)
+        );
+    }
+}
@@ -0,0 +1,332 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use super::event_graph::build_event_graph_with;
+use super::event_log::{Window, parse_event_log, parse_window_actions};
+use super::refinement::Refinement;
+
+/// The no-op nemesis action always has index 0 in the mediator.
+pub const NOOP_ACTION: usize = 0;
+
+/// An abstract edge to drive the SUT towards. An empty transition matches any.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct PlanTarget {
+    pub node: Option<u32>,
+    pub from: String,
+    pub transition: String,
+    pub to: String,
+}
+
+impl PlanTarget {
+    pub fn from_file(path: &str) -> Result<Vec<Self>> {
+        let content =
+            fs::read_to_string(path).with_context(|| format!("reading targets from {}", path))?;
+        serde_json::from_str(&content).with_context(|| format!("parsing targets {}", path))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProposedSchedule {
+    pub actions: Vec<usize>,
+    /// Window whose schedule prefix reached the target's source state.
+    pub prefix_from: Window,
+    pub follow_up: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TargetPlan {
+    pub target: PlanTarget,
+    pub observed: bool,
+    pub source_visits: usize,
+    pub schedules: Vec<ProposedSchedule>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlannerOptions {
+    pub length: usize,
+    pub max_per_target: usize,
+    /// Number of nemesis actions, including the no-op. When unknown, only
+    /// actions that appear in the log are proposed.
+    pub num_actions: Option<usize>,
+}
+
+/// Replays the shortest schedule prefix that reached each target's source
+/// state, then follows it with the actions least often tried from there.
+pub fn plan_faults(
+    log: &str,
+    refinement: &Refinement,
+    targets: &[PlanTarget],
+    options: &PlannerOptions,
+) -> Vec<TargetPlan> {
+    let window_actions = parse_window_actions(log);
+    let mut schedules: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for (window, action) in &window_actions {
+        let sched = schedules.entry(window.schedule_id).or_default();
+        if sched.len() <= window.step_id {
+            sched.resize(window.step_id + 1, NOOP_ACTION);
+        }
+        sched[window.step_id] = *action;
+    }
+
+    let mut visits: BTreeSet<(u32, String, Window)> = BTreeSet::new();
+    for rec in parse_event_log(log) {
+        if let (Some(window), Some(state)) =
+            (rec.window, refinement.state(&rec.state, rec.constant))
+        {
+            visits.insert((rec.node_id, state, window));
+        }
+    }
+
+    let universe: BTreeSet<usize> = match options.num_actions {
+        Some(n) => (0..n).collect(),
+        None => window_actions.values().copied().collect(),
+    };
+    let graph = build_event_graph_with(log, refinement);
+
+    targets
+        .iter()
+        .map(|target| {
+            let matches_node = |node: u32| target.node.is_none_or(|n| n == node);
+            let observed = graph.nodes.iter().any(|(node_id, node)| {
+                matches_node(*node_id)
+                    && node.edges.iter().any(|e| {
+                        e.from == target.from
+                            && e.to == target.to
+                            && (target.transition.is_empty() || e.transition == target.transition)
+                    })
+            });
+
+            let source_windows: Vec<Window> = visits
+                .iter()
+                .filter(|(node, state, _)| matches_node(*node) && *state == target.from)
+                .map(|(_, _, window)| *window)
+                .collect();
+
+            let mut plan = TargetPlan {
+                target: target.clone(),
+                observed,
+                source_visits: source_windows.len(),
+                schedules: Vec::new(),
+            };
+            if observed {
+                return plan;
+            }
+
+            let Some(best) = source_windows
+                .iter()
+                .min_by_key(|w| (w.step_id, w.schedule_id))
+            else {
+                return plan;
+            };
+
+            let mut tried: BTreeMap<usize, usize> = universe
+                .iter()
+                .filter(|a| **a != NOOP_ACTION)
+                .map(|a| (*a, 0))
+                .collect();
+            for window in &source_windows {
+                let next = schedules
+                    .get(&window.schedule_id)
+                    .and_then(|s| s.get(window.step_id + 1));
+                if let Some(count) = next.and_then(|a| tried.get_mut(a)) {
+                    *count += 1;
+                }
+            }
+            let mut candidates: Vec<(usize, usize)> =
+                tried.into_iter().map(|(a, count)| (count, a)).collect();
+            candidates.sort_unstable();
+
+            let prefix: Vec<usize> = (0..=best.step_id)
+                .map(|step| {
+                    schedules
+                        .get(&best.schedule_id)
+                        .and_then(|s| s.get(step))
+                        .copied()
+                        .unwrap_or(NOOP_ACTION)
+                })
+                .collect();
+
+            plan.schedules = candidates
+                .into_iter()
+                .take(options.max_per_target)
+                .map(|(_, follow_up)| {
+                    let mut actions = prefix.clone();
+                    actions.push(follow_up);
+                    if actions.len() < options.length {
+                        actions.resize(options.length, NOOP_ACTION);
+                    }
+                    ProposedSchedule {
+                        actions,
+                        prefix_from: *best,
+                        follow_up,
+                    }
+                })
+                .collect();
+            plan
+        })
+        .collect()
+}
+
+fn describe(target: &PlanTarget) -> String {
+    let node = target
+        .node
+        .map(|n| format!("node {} ", n))
+        .unwrap_or_default();
+    let transition = if target.transition.is_empty() {
+        "*"
+    } else {
+        &target.transition
+    };
+    format!("{}{} --{}--> {}", node, target.from, transition, target.to)
+}
+
+/// One schedule per line as space-separated action ids, the format read by the
+/// mediator's `planned` scheduler.
+pub fn mediator_schedules(plans: &[TargetPlan]) -> String {
+    let mut out = String::new();
+    for plan in plans.iter().filter(|p| !p.schedules.is_empty()) {
+        out.push_str(&format!("# {}\n", describe(&plan.target)));
+        for schedule in &plan.schedules {
+            let ids: Vec<String> = schedule.actions.iter().map(|a| a.to_string()).collect();
+            out.push_str(&ids.join(" "));
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// A vector of schedules, each a vector of nemesis operations. Actions whose
+/// EDN is unknown are rendered as `:pending`.
+pub fn jepsen_schedules(plans: &[TargetPlan], edn: &BTreeMap<usize, String>) -> String {
+    let mut out = String::from("[\n");
+    for plan in plans.iter().filter(|p| !p.schedules.is_empty()) {
+        out.push_str(&format!(" ; {}\n", describe(&plan.target)));
+        for schedule in &plan.schedules {
+            let ops: Vec<&str> = schedule
+                .actions
+                .iter()
+                .map(|a| edn.get(a).map(|s| s.as_str()).unwrap_or(":pending"))
+                .collect();
+            out.push_str(&format!(" [{}]\n", ops.join(" ")));
+        }
+    }
+    out.push_str("]\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOG: &str = "[2025-11-10 19:56:55.000001][INFO] [WINDOW][Schedule 0 Step 0] Action 1
+[2025-11-10 19:56:55.000002][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 1 / 1] FunctionExecute 1 @ FunctionName electionTimeout @ state Follower
+[2025-11-10 19:56:55.000003][INFO] [WINDOW][Schedule 0 Step 1] Action 2
+[2025-11-10 19:56:55.000004][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 2 Entry 1 / 1] FunctionExecute 2 @ FunctionName winElection @ state Candidate
+[2025-11-10 19:56:55.000005][INFO] [WINDOW][Schedule 0 Step 2] Action 1
+[2025-11-10 19:56:55.000006][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 3 Entry 1 / 1] FunctionExecute 3 @ FunctionName heartbeat @ state Leader
+[2025-11-10 19:56:55.000007][INFO] [WINDOW][Schedule 1 Step 0] Action 0
+[2025-11-10 19:56:55.000008][INFO] [WINDOW][Schedule 1 Step 1] Action 0
+[2025-11-10 19:56:55.000009][INFO] [FUNC_EVENT_TYPE][Node 2 Batch 1 Entry 1 / 1] FunctionExecute 4 @ FunctionName heartbeat @ state Leader";
+
+    fn target(from: &str, to: &str) -> PlanTarget {
+        PlanTarget {
+            from: from.to_string(),
+            to: to.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn options() -> PlannerOptions {
+        PlannerOptions {
+            length: 4,
+            max_per_target: 2,
+            num_actions: Some(4),
+        }
+    }
+
+    #[test]
+    fn replays_shortest_prefix_then_untried_actions() {
+        let plans = plan_faults(
+            LOG,
+            &Refinement::identity(),
+            &[target("Leader", "Down")],
+            &options(),
+        );
+
+        assert_eq!(plans.len(), 1);
+        let plan = &plans[0];
+        assert!(!plan.observed);
+        assert_eq!(plan.source_visits, 2);
+        // Schedule 1 reached Leader at step 1, earlier than schedule 0.
+        assert_eq!(
+            plan.schedules[0].prefix_from,
+            Window {
+                schedule_id: 1,
+                step_id: 1
+            }
+        );
+        assert_eq!(plan.schedules.len(), 2);
+        assert_eq!(plan.schedules[0].actions, vec![0, 0, 1, 0]);
+        assert_eq!(plan.schedules[1].actions, vec![0, 0, 2, 0]);
+    }
+
+    #[test]
+    fn prefers_actions_not_yet_tried_from_the_source() {
+        let plans = plan_faults(
+            LOG,
+            &Refinement::identity(),
+            &[PlanTarget {
+                node: Some(1),
+                ..target("Follower", "Down")
+            }],
+            &options(),
+        );
+
+        // Action 2 already followed Follower at step 1.
+        let follow_ups: Vec<usize> = plans[0].schedules.iter().map(|s| s.follow_up).collect();
+        assert_eq!(follow_ups, vec![1, 3]);
+        assert_eq!(plans[0].schedules[0].actions, vec![1, 1, 0, 0]);
+    }
+
+    #[test]
+    fn skips_observed_and_unreachable_targets() {
+        let plans = plan_faults(
+            LOG,
+            &Refinement::identity(),
+            &[target("Follower", "Candidate"), target("Down", "Follower")],
+            &options(),
+        );
+
+        assert!(plans[0].observed);
+        assert!(plans[0].schedules.is_empty());
+        assert!(!plans[1].observed);
+        assert_eq!(plans[1].source_visits, 0);
+        assert!(plans[1].schedules.is_empty());
+    }
+
+    #[test]
+    fn renders_mediator_and_jepsen_schedules() {
+        let plans = plan_faults(
+            LOG,
+            &Refinement::identity(),
+            &[target("Leader", "Down")],
+            &options(),
+        );
+
+        let mediator = mediator_schedules(&plans);
+        assert_eq!(mediator, "# Leader --*--> Down\n0 0 1 0\n0 0 2 0\n");
+
+        let edn = BTreeMap::from([
+            (0, ":pending".to_string()),
+            (1, "{:f :kill, :must-dispatch true}".to_string()),
+        ]);
+        let jepsen = jepsen_schedules(&plans, &edn);
+        assert!(jepsen.contains(" [:pending :pending {:f :kill, :must-dispatch true} :pending]\n"));
+        assert!(jepsen.contains(" [:pending :pending :pending :pending]\n"));
+        assert!(jepsen.starts_with("[\n ; Leader --*--> Down\n"));
+    }
+}
@@ -0,0 +1,119 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+/// Keywords and LTL operators of Promela.
+const PROMELA_RESERVED: &str = "\
+    active assert atomic bit bool break byte chan d_step D_proctype do else \
+    empty enabled eval false fi for full goto hidden if in init inline int \
+    len local ltl mtype nempty never nfull notrace np_ od of pc_value pid \
+    printf printm priority proctype provided run select short skip timeout \
+    true typedef unless unsigned xr xs c_code c_decl c_expr c_state c_track \
+    always eventually until implies equivalent stronguntil weakuntil release \
+    X U V W";
+
+/// Reserved words of TLA+.
+const TLA_RESERVED: &str = "\
+    ACTION ASSUME ASSUMPTION AXIOM BOOLEAN BY CASE CHOOSE CONSTANT CONSTANTS \
+    COROLLARY DEF DEFINE DEFS DOMAIN ELSE ENABLED EXCEPT EXTENDS FALSE HAVE \
+    HIDE IF IN INSTANCE LET LEMMA LOCAL MODULE NEW OBVIOUS OMITTED ONLY \
+    OTHER PICK PROOF PROPOSITION PROVE QED RECURSIVE SF_ STATE STRING SUBSET \
+    SUFFICES TAKE TEMPORAL THEN THEOREM TRUE UNCHANGED UNION USE VARIABLE \
+    VARIABLES WF_ WITH WITNESS";
+
+/// Turns a state or transition name into an identifier accepted by Promela
+/// and TLA+. Reserved words, and names that could be taken for the
+/// `node_`/`act_` identifiers the models declare (`none` becomes `act_none`),
+/// get the same `s_` prefix as names that do not start with a letter.
+pub fn identifier(name: &str) -> String {
+    let mut id: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if !id.starts_with(|c: char| c.is_ascii_alphabetic())
+        || PROMELA_RESERVED
+            .split_whitespace()
+            .chain(TLA_RESERVED.split_whitespace())
+            .any(|word| word == id)
+        || id.starts_with("node_")
+        || id.starts_with("act_")
+        || id == "none"
+    {
+        id.insert_str(0, "s_");
+    }
+    id
+}
+
+/// Distinct identifiers for a set of names. Names that sanitize to the same
+/// identifier, like `a-b` and `a_b`, are told apart by a numeric suffix, given
+/// in name order so that the same names always get the same identifiers.
+#[derive(Debug, Clone, Default)]
+pub struct Identifiers {
+    ids: BTreeMap<String, String>,
+}
+
+impl Identifiers {
+    pub fn new<'a>(names: impl IntoIterator<Item = &'a str>) -> Self {
+        let names: BTreeSet<&str> = names.into_iter().collect();
+        let mut taken = HashSet::new();
+        let mut ids = BTreeMap::new();
+        for name in names {
+            let base = identifier(name);
+            let mut id = base.clone();
+            let mut n = 2;
+            while !taken.insert(id.clone()) {
+                id = format!("{}_{}", base, n);
+                n += 1;
+            }
+            ids.insert(name.to_string(), id);
+        }
+        Identifiers { ids }
+    }
+
+    /// The identifier of one of the names the table was built from, or `None`
+    /// for any other name.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.ids.get(name).map(String::as_str)
+    }
+
+    /// Like [`Identifiers::get`], but a name the table was not built from
+    /// gets its plain [`identifier`] instead.
+    pub fn id(&self, name: &str) -> String {
+        self.get(name)
+            .map(str::to_string)
+            .unwrap_or_else(|| identifier(name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanitizes_identifiers() {
+        assert_eq!(identifier("Leader"), "Leader");
+        assert_eq!(identifier("raft_io.send"), "raft_io_send");
+        assert_eq!(identifier("2PC"), "s_2PC");
+        assert_eq!(identifier(""), "s_");
+        assert_eq!(identifier("active"), "s_active");
+        assert_eq!(identifier("VARIABLE"), "s_VARIABLE");
+        assert_eq!(identifier("node_1_state"), "s_node_1_state");
+        assert_eq!(identifier("Active"), "Active");
+    }
+
+    #[test]
+    fn tells_colliding_identifiers_apart() {
+        let ids = Identifiers::new(["a_b", "a-b", "a.b", "a_b_2", "Leader", "a-b"]);
+        assert_eq!(ids.get("a-b"), Some("a_b"));
+        assert_eq!(ids.get("a.b"), Some("a_b_2"));
+        assert_eq!(ids.get("a_b"), Some("a_b_3"));
+        assert_eq!(ids.get("a_b_2"), Some("a_b_2_2"));
+        assert_eq!(ids.get("Leader"), Some("Leader"));
+    }
+
+    #[test]
+    fn names_outside_the_table() {
+        let ids = Identifiers::new(["Leader"]);
+        assert_eq!(ids.get("do"), None);
+        assert_eq!(ids.id("do"), "s_do");
+        assert_eq!(ids.id("Leader"), "Leader");
+    }
+}
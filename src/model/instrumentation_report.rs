@@ -0,0 +1,347 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use super::coverage::same_file;
+use super::instrumentor::InstrumentationTargets;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PassStatus {
+    /// The annotated line was found in the IR and its values are passed to
+    /// the event, or to the group it belongs to.
+    Instrumented,
+    /// The line was found but none of its variables could be captured, so
+    /// no event is emitted for it.
+    NoValues,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CapturedVariable {
+    pub name: String,
+    /// The LLVM type of the captured value, empty when nothing was captured.
+    #[serde(rename = "type", default)]
+    pub ty: String,
+    /// Struct field indexes followed from the variable.
+    #[serde(default)]
+    pub fields: Vec<u32>,
+    /// Whether the variable exists and its field path ends in a value.
+    pub resolved: bool,
+}
+
+/// What the LLVM pass did with one annotated line, one JSON object per line
+/// in `$ABSTRAKTOR_REPORT_FILE`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PassRecord {
+    pub file: String,
+    pub line: usize,
+    pub kind: String,
+    pub status: PassStatus,
+    pub id: u32,
+    #[serde(default)]
+    pub function: String,
+    /// The mangled function and the index of the basic block the event call
+    /// was inserted in.
+    pub ir_function: String,
+    pub ir_block: u32,
+    #[serde(default)]
+    pub variables: Vec<CapturedVariable>,
+}
+
+pub fn parse_pass_records(content: &str) -> Result<Vec<PassRecord>> {
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            serde_json::from_str(line)
+                .with_context(|| format!("parsing instrumentation report line {}", index + 1))
+        })
+        .collect()
+}
+
+/// A missing report is an empty one: the pass writes nothing when it
+/// instruments nothing.
+pub fn read_pass_records(path: &Path) -> Result<Vec<PassRecord>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("reading instrumentation report {}", path.display()))?;
+    parse_pass_records(&content)
+        .with_context(|| format!("parsing instrumentation report {}", path.display()))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnnotationStatus {
+    Instrumented,
+    /// Instrumented, but some variables or struct paths did not resolve.
+    Partial,
+    NoValues,
+    /// The pass never saw the line: the file was not compiled, the line
+    /// holds no instruction, or the code was optimized away.
+    NotFound,
+    /// The pass said nothing about the file, and the build may have left it
+    /// as an earlier build compiled it.
+    Unknown,
+}
+
+impl AnnotationStatus {
+    pub fn name(self) -> &'static str {
+        match self {
+            AnnotationStatus::Instrumented => "instrumented",
+            AnnotationStatus::Partial => "partial",
+            AnnotationStatus::NoValues => "no values",
+            AnnotationStatus::NotFound => "not found",
+            AnnotationStatus::Unknown => "unknown",
+        }
+    }
+
+    /// No event will carry this annotation's values.
+    pub fn failed(self) -> bool {
+        matches!(
+            self,
+            AnnotationStatus::NoValues | AnnotationStatus::NotFound
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnnotationReport {
+    pub file: String,
+    pub line: usize,
+    pub kind: String,
+    pub status: AnnotationStatus,
+    pub record: Option<PassRecord>,
+}
+
+fn record_status(record: &PassRecord) -> AnnotationStatus {
+    match record.status {
+        PassStatus::NoValues => AnnotationStatus::NoValues,
+        PassStatus::Instrumented if record.variables.iter().all(|v| v.resolved) => {
+            AnnotationStatus::Instrumented
+        }
+        PassStatus::Instrumented => AnnotationStatus::Partial,
+    }
+}
+
+/// Every function and block annotation in `targets` with what the pass did
+/// with it. A header compiled into several objects gets several records;
+/// the best one counts.
+///
+/// Unless the build recompiled everything, a file the pass said nothing
+/// about may just not have been recompiled, so its annotations are unknown
+/// rather than not found. Relative paths are relative to the SUT at `root`.
+pub fn instrumentation_report(
+    targets: &[InstrumentationTargets],
+    records: &[PassRecord],
+    root: &Path,
+    clean_build: bool,
+) -> Vec<AnnotationReport> {
+    let mut reports = Vec::new();
+    for file in targets {
+        let missing = if clean_build || records.iter().any(|r| same_file(&r.file, &file.path, root))
+        {
+            AnnotationStatus::NotFound
+        } else {
+            AnnotationStatus::Unknown
+        };
+        let kinds = [
+            ("function", &file.targets_function),
+            ("block", &file.targets_block),
+        ];
+        for (kind, lines) in kinds {
+            for line in lines.keys() {
+                let best = records
+                    .iter()
+                    .filter(|r| {
+                        r.line == *line && r.kind == kind && same_file(&r.file, &file.path, root)
+                    })
+                    .min_by_key(|r| record_status(r));
+                reports.push(AnnotationReport {
+                    file: file.path.clone(),
+                    line: *line,
+                    kind: kind.to_string(),
+                    status: best.map_or(missing, record_status),
+                    record: best.cloned(),
+                });
+            }
+        }
+    }
+    reports.sort_by(|a, b| a.file.cmp(&b.file).then(a.line.cmp(&b.line)));
+    reports
+}
+
+/// One row per annotation, with paths relative to `root`.
+pub fn format_table(reports: &[AnnotationReport], root: &Path) -> Vec<String> {
+    reports
+        .iter()
+        .map(|report| {
+            let file = pathdiff::diff_paths(&report.file, root)
+                .filter(|path| !path.starts_with(".."))
+                .map_or(report.file.clone(), |path| path.display().to_string());
+            let location = format!("{}:{}", file, report.line);
+            let (id, ir, variables) = match &report.record {
+                Some(record) => (
+                    record.id.to_string(),
+                    format!("{}#{}", record.function_name(), record.ir_block),
+                    record
+                        .variables
+                        .iter()
+                        .map(CapturedVariable::describe)
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                ),
+                None => ("-".to_string(), "-".to_string(), String::new()),
+            };
+            format!(
+                "{:<32}  {:<8}  {:<12}  {:>5}  {}  {}",
+                location,
+                report.kind,
+                report.status.name(),
+                id,
+                ir,
+                variables
+            )
+            .trim_end()
+            .to_string()
+        })
+        .collect()
+}
+
+impl PassRecord {
+    /// The demangled name when the pass had one.
+    fn function_name(&self) -> &str {
+        if self.function.is_empty() {
+            &self.ir_function
+        } else {
+            &self.function
+        }
+    }
+}
+
+impl CapturedVariable {
+    /// `state->1->0: i32`, or `state->1: ?` when the path did not resolve.
+    fn describe(&self) -> String {
+        let path: String = std::iter::once(self.name.clone())
+            .chain(self.fields.iter().map(|f| f.to_string()))
+            .collect::<Vec<_>>()
+            .join("->");
+        match (self.resolved, self.ty.is_empty()) {
+            (true, _) => format!("{}: {}", path, self.ty),
+            (false, true) => format!("{}: ?", path),
+            (false, false) => format!("{}: ? ({})", path, self.ty),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::instrumentor::{GroupInfo, TargetInfo};
+    use std::collections::BTreeMap;
+
+    const REPORT: &str = r#"{"file":"/sut/src/raft.c","line":10,"kind":"function","status":"instrumented","id":7,"function":"raft_tick","ir_function":"raft_tick","ir_block":0,"variables":[{"name":"r","type":"i32","fields":[1,0],"resolved":true}]}
+{"file":"/sut/src/raft.c","line":42,"kind":"block","status":"no_values","id":9,"function":"raft_step","ir_function":"raft_step","ir_block":3,"variables":[{"name":"state","type":"%struct.raft*","fields":[2],"resolved":false}]}
+
+{"file":"/sut/src/raft.h","line":5,"kind":"block","status":"no_values","id":4,"function":"inline_a","ir_function":"inline_a","ir_block":1,"variables":[]}
+{"file":"/sut/src/raft.h","line":5,"kind":"block","status":"instrumented","id":4,"function":"inline_b","ir_function":"inline_b","ir_block":2,"variables":[{"name":"x","fields":[0],"resolved":false}]}
+"#;
+
+    fn target() -> TargetInfo {
+        TargetInfo {
+            var_info: vec![],
+            group: GroupInfo {
+                end_mark: true,
+                id: 0,
+            },
+        }
+    }
+
+    fn targets() -> Vec<InstrumentationTargets> {
+        vec![
+            InstrumentationTargets {
+                path: "/sut/src/raft.c".to_string(),
+                targets_function: BTreeMap::from([(10, target())]),
+                targets_block: BTreeMap::from([(42, target()), (50, target())]),
+                ..Default::default()
+            },
+            InstrumentationTargets {
+                path: "/sut/src/raft.h".to_string(),
+                targets_block: BTreeMap::from([(5, target())]),
+                ..Default::default()
+            },
+        ]
+    }
+
+    #[test]
+    fn test_parse_pass_records() {
+        let records = parse_pass_records(REPORT).unwrap();
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[0].variables[0].ty, "i32");
+        assert_eq!(records[1].status, PassStatus::NoValues);
+        assert!(records[3].variables[0].ty.is_empty());
+
+        let err = parse_pass_records("{}\n").unwrap_err();
+        assert!(err.to_string().contains("line 1"));
+    }
+
+    #[test]
+    fn test_instrumentation_report() {
+        let records = parse_pass_records(REPORT).unwrap();
+        let reports = instrumentation_report(&targets(), &records, Path::new("/sut"), true);
+        let statuses: Vec<_> = reports
+            .iter()
+            .map(|r| (r.line, r.status, r.record.as_ref().map(|r| r.id)))
+            .collect();
+        assert_eq!(
+            statuses,
+            [
+                (10, AnnotationStatus::Instrumented, Some(7)),
+                (42, AnnotationStatus::NoValues, Some(9)),
+                (50, AnnotationStatus::NotFound, None),
+                (5, AnnotationStatus::Partial, Some(4)),
+            ]
+        );
+        assert_eq!(reports.iter().filter(|r| r.status.failed()).count(), 2);
+
+        let table = format_table(&reports, Path::new("/sut"));
+        assert!(table[0].starts_with("src/raft.c:10"));
+        assert!(table[0].ends_with("raft_tick#0  r->1->0: i32"));
+        assert!(table[1].contains("no values"));
+        assert!(table[1].ends_with("state->2: ? (%struct.raft*)"));
+        assert!(table[2].contains("not found"));
+        assert!(table[2].ends_with("    -  -"));
+        assert!(table[3].ends_with("inline_b#2  x->0: ?"));
+    }
+
+    #[test]
+    fn test_files_left_alone_by_an_incremental_build() {
+        let records = parse_pass_records(REPORT).unwrap();
+        let raft_h: Vec<_> = records
+            .into_iter()
+            .filter(|r| r.file.ends_with("raft.h"))
+            .collect();
+        let statuses: Vec<_> =
+            instrumentation_report(&targets(), &raft_h, Path::new("/sut"), false)
+                .iter()
+                .map(|r| (r.line, r.status))
+                .collect();
+        assert_eq!(
+            statuses,
+            [
+                (10, AnnotationStatus::Unknown),
+                (42, AnnotationStatus::Unknown),
+                (50, AnnotationStatus::Unknown),
+                (5, AnnotationStatus::Partial),
+            ]
+        );
+        assert!(!AnnotationStatus::Unknown.failed());
+
+        let clean = instrumentation_report(&targets(), &raft_h, Path::new("/sut"), true);
+        assert_eq!(clean[0].status, AnnotationStatus::NotFound);
+    }
+}
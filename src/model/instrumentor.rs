@@ -1,8 +1,11 @@
 use std::collections::{BTreeMap};
+use std::path::{Path, PathBuf};
 
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 
+use super::event_ids::{AnnotationKey, IdCollision, assign_stable_ids};
+
 #[derive(Debug, Serialize, Deserialize, Default, PartialEq, Eq)]
 pub struct TargetInfo {
     pub var_info: Vec<VarInfo>,
@@ -30,7 +33,32 @@ pub struct InstrumentationTargets {
     pub group_transition_names: BTreeMap<u32, String>,
 }
 
+/// Provisional group ids handed out while parsing, and the annotations that
+/// make up each group.
+#[derive(Debug, Default)]
+struct GroupIds {
+    next: u32,
+    members: BTreeMap<u32, Vec<AnnotationKey>>,
+}
+
+impl InstrumentationTargets {
+    fn remap_group_ids(&mut self, ids: &BTreeMap<u32, u32>) {
+        for target in self
+            .targets_block
+            .values_mut()
+            .chain(self.targets_function.values_mut())
+        {
+            target.group.id = ids[&target.group.id];
+        }
+        self.group_transition_names = std::mem::take(&mut self.group_transition_names)
+            .into_iter()
+            .map(|(id, name)| (ids[&id], name))
+            .collect();
+    }
+}
+
 pub struct Instrumentor {
+    root: Option<PathBuf>,
     target_const_regex: Regex,
     target_block_regex: Regex,
     block_start_regex: Regex,
@@ -38,11 +66,13 @@ pub struct Instrumentor {
     target_override_transition_name_regex: Regex,
     target_override_func_regex: Regex,
     target_override_block_regex: Regex,
+    function_definition_regex: Regex,
 }
 
 impl Instrumentor {
     pub fn new() -> Self {
         Self {
+            root: None,
             target_const_regex: Regex::new(r"ABSTRAKTOR_CONST: (\w+)").unwrap(),
             target_block_regex: Regex::new(r"ABSTRAKTOR_BLOCK_EVENT(?:\s*:\s*(\w+(?:->\d+)*(?:\s*,\s*\w+(?:->\d+)*)*))?(?:\s+END)?\s*$").unwrap(),
             block_start_regex: Regex::new(r"^(([a-zA-z]{1}.*)|\})").unwrap(),
@@ -56,7 +86,53 @@ impl Instrumentor {
             target_override_block_regex: Regex::new(
                 r"ABSTRAKTOR_OVERRADE_TRANSITION_NAME:\s*\w+\s*,\s*ABSTRAKTOR_BLOCK_EVENT(?:\s*:\s*(\w+(?:->\d+)*(?:\s*,\s*\w+(?:->\d+)*)*))?(?:\s+END)?\s*$"
             ).unwrap(),
+            function_definition_regex: Regex::new(r"^(?:[A-Za-z_][\w\s\*]*?[\s\*])?([A-Za-z_]\w*)\s*\(").unwrap(),
+        }
+    }
+
+    /// Paths are hashed into event ids relative to `root`, so the same tree
+    /// checked out elsewhere gets the same ids.
+    pub fn with_root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.root = Some(root.into());
+        self
+    }
+
+    fn key_path(&self, path: &str) -> String {
+        self.root
+            .as_ref()
+            .and_then(|root| pathdiff::diff_paths(path, root))
+            .unwrap_or_else(|| Path::new(path).to_path_buf())
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    /// Name of the function a definition line introduces, if it is one.
+    fn function_name(&self, line: &str) -> Option<String> {
+        if line.trim_end().ends_with(';') {
+            return None;
+        }
+        let captures = self.function_definition_regex.captures(line)?;
+        let name = captures[1].to_string();
+        match name.as_str() {
+            "if" | "for" | "while" | "switch" | "return" | "sizeof" => None,
+            _ => Some(name),
+        }
+    }
+
+    /// Function annotations precede the definition they annotate, block
+    /// annotations live inside it.
+    fn enclosing_function(&self, lines: &[&str], line_num: usize, is_function: bool) -> String {
+        if is_function {
+            return self
+                .find_next_block_start(lines, line_num)
+                .and_then(|block_line| self.function_name(lines[block_line - 1]))
+                .unwrap_or_default();
         }
+        lines[..line_num]
+            .iter()
+            .rev()
+            .find_map(|line| self.function_name(line))
+            .unwrap_or_default()
     }
 
     fn is_block_start(&self, line: &str) -> bool {
@@ -79,7 +155,8 @@ impl Instrumentor {
         &self,
         line: &str,
         regex: &Regex,
-        id: &mut u32,
+        ids: &mut GroupIds,
+        key: AnnotationKey,
     ) -> Option<TargetInfo> {
         let captures = regex.captures(line)?;
      
@@ -109,11 +186,11 @@ impl Instrumentor {
 
             if !numbers.is_empty() {
                 map.entry(var_name)
-                .or_insert_with(Vec::new)
+                .or_default()
                 .push(numbers);
             } else {
                 map.entry(var_name)
-                .or_insert_with(Vec::new);
+                .or_default();
             }
         }
 
@@ -129,11 +206,12 @@ impl Instrumentor {
 
         let group = GroupInfo {
             end_mark: has_end,
-            id: *id,
+            id: ids.next,
         };
 
+        ids.members.entry(ids.next).or_default().push(key);
         if has_end {
-            *id += 1;
+            ids.next += 1;
         }
 
         Some(TargetInfo {
@@ -143,7 +221,30 @@ impl Instrumentor {
     }
 
 
-    fn get_targets_single(&self, content: &str, path: &str, id: &mut u32) -> InstrumentationTargets {
+    fn annotation_key(
+        &self,
+        lines: &[&str],
+        line_num: usize,
+        path: &str,
+        is_function: bool,
+    ) -> AnnotationKey {
+        let line = lines[line_num - 1];
+        let annotation = line
+            .find("ABSTRAKTOR_")
+            .map_or(line, |start| &line[start..]);
+        AnnotationKey {
+            path: self.key_path(path),
+            function: self.enclosing_function(lines, line_num, is_function),
+            annotation: annotation.trim_end().to_string(),
+        }
+    }
+
+    fn get_targets_single(
+        &self,
+        content: &str,
+        path: &str,
+        ids: &mut GroupIds,
+    ) -> InstrumentationTargets {
         let mut targets = InstrumentationTargets {
             path: path.to_string(),
             ..Default::default()
@@ -165,7 +266,8 @@ impl Instrumentor {
                     _ => &self.target_override_block_regex,
                 };
 
-                if let Some(target_info) = self.parse_target_line(line, parse_regex, id) {
+                let key = self.annotation_key(&lines, line_num, path, event_type == "ABSTRAKTOR_FUNC");
+                if let Some(target_info) = self.parse_target_line(line, parse_regex, ids, key) {
                     targets.group_transition_names.insert(target_info.group.id, transition_name);
                     if let Some(block_line) = self.find_next_block_start(&lines, line_num) {
                         match event_type.as_str() {
@@ -175,19 +277,27 @@ impl Instrumentor {
                     }
                 }
             } else {
-                if self.target_function_regex.is_match(line) {
-                    if let Some(target_info) = self.parse_target_line(line, &self.target_function_regex, id) {
-                        if let Some(block_line) = self.find_next_block_start(&lines, line_num) {
-                            targets.targets_function.insert(block_line, target_info);
-                        }
-                    }
+                if self.target_function_regex.is_match(line)
+                    && let Some(target_info) = self.parse_target_line(
+                        line,
+                        &self.target_function_regex,
+                        ids,
+                        self.annotation_key(&lines, line_num, path, true),
+                    )
+                    && let Some(block_line) = self.find_next_block_start(&lines, line_num)
+                {
+                    targets.targets_function.insert(block_line, target_info);
                 }
-                if self.target_block_regex.is_match(line) {
-                    if let Some(target_info) = self.parse_target_line(line, &self.target_block_regex, id) {
-                        if let Some(block_line) = self.find_next_block_start(&lines, line_num) {
-                            targets.targets_block.insert(block_line, target_info);
-                        }
-                    }
+                if self.target_block_regex.is_match(line)
+                    && let Some(target_info) = self.parse_target_line(
+                        line,
+                        &self.target_block_regex,
+                        ids,
+                        self.annotation_key(&lines, line_num, path, false),
+                    )
+                    && let Some(block_line) = self.find_next_block_start(&lines, line_num)
+                {
+                    targets.targets_block.insert(block_line, target_info);
                 }
             }
             if self.target_const_regex.is_match(line) {
@@ -204,12 +314,23 @@ impl Instrumentor {
         targets
     }
 
-    pub fn get_targets(&self, files: Vec<(String, String)>) -> Vec<InstrumentationTargets> {
-        let mut id = 0;
-        files
+    /// Group ids are derived from the annotations in each group rather than
+    /// from the order they were found in, see [`assign_stable_ids`].
+    pub fn get_targets(
+        &self,
+        files: Vec<(String, String)>,
+    ) -> (Vec<InstrumentationTargets>, Vec<IdCollision>) {
+        let mut ids = GroupIds::default();
+        let mut targets: Vec<InstrumentationTargets> = files
             .into_iter()
-            .map(|(content, path)| self.get_targets_single(&content, &path, &mut id))
-            .collect()
+            .map(|(content, path)| self.get_targets_single(&content, &path, &mut ids))
+            .collect();
+
+        let (stable, collisions) = assign_stable_ids(&ids.members);
+        for target in &mut targets {
+            target.remap_group_ids(&stable);
+        }
+        (targets, collisions)
     }
 }
 
@@ -284,7 +405,7 @@ mod tests {
         let x = 1;
         ";
         let path = "test.c";
-        let targets = instrumentor.get_targets_single(content, path, &mut 0);
+        let targets = instrumentor.get_targets_single(content, path, &mut GroupIds::default());
         assert!(targets.targets_block.is_empty());
         assert!(targets.targets_const.is_empty());
         assert_eq!(targets.path, path);
@@ -302,7 +423,7 @@ mod tests {
         let z = 3;
         ";
         let path = "test.c";
-        let targets = instrumentor.get_targets_single(content, path, &mut 0);
+        let targets = instrumentor.get_targets_single(content, path, &mut GroupIds::default());
 
         let expected = BTreeMap::from([
             (3, "x".to_string()),
@@ -326,7 +447,7 @@ mod tests {
         let z = 3;
         ";
         let path = "test.c";
-        let targets = instrumentor.get_targets_single(content, path, &mut 0);
+        let targets = instrumentor.get_targets_single(content, path, &mut GroupIds::default());
 
         let expected_block: BTreeMap<usize, TargetInfo> = BTreeMap::from([
             (
@@ -369,7 +490,7 @@ mod tests {
         let x = 1;
         ";
         let path = "test.c";
-        let targets = instrumentor.get_targets_single(content, path, &mut 0);
+        let targets = instrumentor.get_targets_single(content, path, &mut GroupIds::default());
 
         let expected_block: BTreeMap<usize, TargetInfo> = BTreeMap::from([
             (
@@ -403,7 +524,7 @@ mod tests {
         let x = 1;
         ";
         let path = "test.c";
-        let targets = instrumentor.get_targets_single(content, path, &mut 0);
+        let targets = instrumentor.get_targets_single(content, path, &mut GroupIds::default());
 
         let expected_block: BTreeMap<usize, TargetInfo> = BTreeMap::from([
             (
@@ -440,7 +561,7 @@ mod tests {
         let z = 3;
         ";
         let path = "test.c";
-        let targets = instrumentor.get_targets_single(content, path, &mut 0);
+        let targets = instrumentor.get_targets_single(content, path, &mut GroupIds::default());
 
         let expected_block: BTreeMap<usize, TargetInfo> = BTreeMap::from([
             (
@@ -494,7 +615,7 @@ mod tests {
         let y = 2;
         ";
         let path = "test.c";
-        let targets = instrumentor.get_targets_single(content, path, &mut 0);
+        let targets = instrumentor.get_targets_single(content, path, &mut GroupIds::default());
 
         let expected_block: BTreeMap<usize, TargetInfo> = BTreeMap::from([
             (
@@ -538,7 +659,7 @@ mod tests {
         let y = 2;
         ";
         let path = "test.c";
-        let targets = instrumentor.get_targets_single(content, path, &mut 0);
+        let targets = instrumentor.get_targets_single(content, path, &mut GroupIds::default());
 
         let expected_block: BTreeMap<usize, TargetInfo> = BTreeMap::from([
             (
@@ -579,7 +700,7 @@ mod tests {
         // ABSTRAKTOR_BLOCK_EVENT
         ";
         let path = "test.c";
-        let targets = instrumentor.get_targets_single(content, path, &mut 0);
+        let targets = instrumentor.get_targets_single(content, path, &mut GroupIds::default());
 
         let expected_block: BTreeMap<usize, TargetInfo> = BTreeMap::from([
             (
@@ -609,7 +730,7 @@ mod tests {
         // No actual code
         ";
         let path = "test.c";
-        let targets = instrumentor.get_targets_single(content, path, &mut 0);
+        let targets = instrumentor.get_targets_single(content, path, &mut GroupIds::default());
 
         assert!(targets.targets_block.is_empty());
         assert!(targets.targets_const.is_empty());
@@ -625,7 +746,7 @@ mod tests {
         // No actual code
         ";
         let path = "test.c";
-        let targets = instrumentor.get_targets_single(content, path, &mut 0);
+        let targets = instrumentor.get_targets_single(content, path, &mut GroupIds::default());
 
         assert!(targets.targets_block.is_empty());
         assert!(targets.targets_const.is_empty());
@@ -642,7 +763,7 @@ mod tests {
         { // start of new block
         ";
         let path = "test.c";
-        let targets = instrumentor.get_targets_single(content, path, &mut 0);
+        let targets = instrumentor.get_targets_single(content, path, &mut GroupIds::default());
 
         let expected_block: BTreeMap<usize, TargetInfo> = BTreeMap::from([
             (
@@ -679,8 +800,9 @@ mod tests {
             ),
         ];
 
-        let targets = instrumentor.get_targets(files);
+        let (targets, _) = instrumentor.get_targets(files);
         assert_eq!(targets.len(), 1);
+        let id = targets[0].targets_function[&3].group.id;
 
         // Check first file
         assert_eq!(targets[0].path, "file1.c");
@@ -700,7 +822,7 @@ mod tests {
                     ],
                     group: GroupInfo {
                         end_mark: false,
-                        id: 0,
+                        id,
                     }
                 }
             )
@@ -725,8 +847,9 @@ mod tests {
             ),
         ];
 
-        let targets = instrumentor.get_targets(files);
+        let (targets, _) = instrumentor.get_targets(files);
         assert_eq!(targets.len(), 1);
+        let id = targets[0].targets_function[&3].group.id;
 
         // Check first file
         assert_eq!(targets[0].path, "file1.c");
@@ -746,7 +869,7 @@ mod tests {
                     ],
                     group: GroupInfo {
                         end_mark: false,
-                        id: 0,
+                        id,
                     }
                 }
             )
@@ -772,8 +895,9 @@ mod tests {
             ),
         ];
 
-        let targets = instrumentor.get_targets(files);
+        let (targets, _) = instrumentor.get_targets(files);
         assert_eq!(targets.len(), 1);
+        let id = targets[0].targets_function[&3].group.id;
 
         // Check first file
         assert_eq!(targets[0].path, "file1.c");
@@ -793,7 +917,7 @@ mod tests {
                     ],
                     group: GroupInfo {
                         end_mark: false,
-                        id: 0,
+                        id,
                     }
                 }
             )
@@ -820,8 +944,11 @@ mod tests {
             ),
         ];
 
-        let targets = instrumentor.get_targets(files);
+        let (targets, _) = instrumentor.get_targets(files);
         assert_eq!(targets.len(), 1);
+        let first = targets[0].targets_block[&5].group.id;
+        let second = targets[0].targets_block[&7].group.id;
+        assert_ne!(first, second);
 
         let expected_block: BTreeMap<usize, TargetInfo> = BTreeMap::from([
             (
@@ -835,7 +962,7 @@ mod tests {
                     ],
                     group: GroupInfo {
                         end_mark: true,
-                        id: 0,
+                        id: first,
                     }
                 }
             ),
@@ -850,7 +977,7 @@ mod tests {
                     ],
                     group: GroupInfo {
                         end_mark: true,
-                        id: 1,
+                        id: second,
                     }
                 }
             ),
@@ -874,7 +1001,7 @@ mod tests {
                     ],
                     group: GroupInfo {
                         end_mark: false,
-                        id: 0,
+                        id: first,
                     }
                 }
             )
@@ -899,8 +1026,9 @@ mod tests {
             ),
         ];
 
-        let targets = instrumentor.get_targets(files);
+        let (targets, _) = instrumentor.get_targets(files);
         assert_eq!(targets.len(), 1);
+        let id = targets[0].targets_function[&3].group.id;
 
         // Check first file
         assert_eq!(targets[0].path, "file1.c");
@@ -921,7 +1049,7 @@ mod tests {
                     ],
                     group: GroupInfo {
                         end_mark: false,
-                        id: 0,
+                        id,
                     }
                 }
             ),
@@ -936,7 +1064,7 @@ mod tests {
                     ],
                     group: GroupInfo {
                         end_mark: false,
-                        id: 0,
+                        id,
                     }
                 }
             )
@@ -961,8 +1089,9 @@ mod tests {
             ),
         ];
 
-        let targets = instrumentor.get_targets(files);
+        let (targets, _) = instrumentor.get_targets(files);
         assert_eq!(targets.len(), 1);
+        let id = targets[0].targets_function[&3].group.id;
 
         // Check first file
         assert_eq!(targets[0].path, "file1.c");
@@ -983,7 +1112,7 @@ mod tests {
                     ],
                     group: GroupInfo {
                         end_mark: true,
-                        id: 0,
+                        id,
                     }
                 }
             ),
@@ -1020,7 +1149,7 @@ mod tests {
             ),
         ];
 
-        let targets = instrumentor.get_targets(files);
+        let (targets, _) = instrumentor.get_targets(files);
         assert_eq!(targets.len(), 2);
 
         // Check first file
@@ -1047,7 +1176,7 @@ mod tests {
         // ABSTRAKTOR_OVERRADE_TRANSITION_NAME: sending, ABSTRAKTOR_FUNC: r->19->4
         do_something();
         ";
-        let targets = instrumentor.get_targets_single(content, "test.c", &mut 0);
+        let targets = instrumentor.get_targets_single(content, "test.c", &mut GroupIds::default());
 
         let expected_function: BTreeMap<usize, TargetInfo> = BTreeMap::from([
             (
@@ -1075,7 +1204,7 @@ mod tests {
         // ABSTRAKTOR_OVERRADE_TRANSITION_NAME: receiving, ABSTRAKTOR_BLOCK_EVENT: x->4->5
         some_var = 5;
         ";
-        let targets = instrumentor.get_targets_single(content, "test.c", &mut 0);
+        let targets = instrumentor.get_targets_single(content, "test.c", &mut GroupIds::default());
 
         let expected_block: BTreeMap<usize, TargetInfo> = BTreeMap::from([
             (
@@ -1105,7 +1234,7 @@ mod tests {
         // ABSTRAKTOR_FUNC: s END
         do_other();
         ";
-        let targets = instrumentor.get_targets_single(content, "test.c", &mut 0);
+        let targets = instrumentor.get_targets_single(content, "test.c", &mut GroupIds::default());
 
         assert_eq!(
             targets.targets_function[&3].group,
@@ -1130,7 +1259,7 @@ mod tests {
         // ABSTRAKTOR_BLOCK_EVENT: x->4 END
         some_var = 5;
         ";
-        let targets = instrumentor.get_targets_single(content, "test.c", &mut 0);
+        let targets = instrumentor.get_targets_single(content, "test.c", &mut GroupIds::default());
 
         // Ambos eventos pertenecen al grupo 0
         assert_eq!(targets.targets_function[&3].group.id, 0);
@@ -1150,7 +1279,7 @@ mod tests {
         // ABSTRAKTOR_OVERRADE_TRANSITION_NAME: beta, ABSTRAKTOR_BLOCK_EVENT: x->4 END
         some_var = 5;
         ";
-        let targets = instrumentor.get_targets_single(content, "test.c", &mut 0);
+        let targets = instrumentor.get_targets_single(content, "test.c", &mut GroupIds::default());
 
         assert_eq!(
             targets.group_transition_names,
@@ -1170,7 +1299,7 @@ mod tests {
         // ABSTRAKTOR_OVERRADE_TRANSITION_NAME: sending, ABSTRAKTOR_FUNC: r->19, s->3
         do_something();
         ";
-        let targets = instrumentor.get_targets_single(content, "test.c", &mut 0);
+        let targets = instrumentor.get_targets_single(content, "test.c", &mut GroupIds::default());
 
         let target = &targets.targets_function[&3];
         assert_eq!(target.var_info.len(), 2);
@@ -1193,8 +1322,82 @@ mod tests {
         // ABSTRAKTOR_BLOCK_EVENT: x->4
         some_var = 5;
         ";
-        let targets = instrumentor.get_targets_single(content, "test.c", &mut 0);
+        let targets = instrumentor.get_targets_single(content, "test.c", &mut GroupIds::default());
 
         assert!(targets.group_transition_names.is_empty());
     }
+    #[test]
+    fn test_adding_an_annotation_keeps_existing_ids() {
+        let instrumentor = Instrumentor::new();
+        let before = r"
+int step(struct raft *r) {
+    // ABSTRAKTOR_BLOCK_EVENT: state END
+    r->state = 1;
+}
+";
+        let after = r"
+int tick(struct raft *r) {
+    // ABSTRAKTOR_BLOCK_EVENT: timer END
+    r->timer = 0;
+}
+
+int step(struct raft *r) {
+    // ABSTRAKTOR_BLOCK_EVENT: state END
+    r->state = 1;
+}
+";
+        let (before, _) =
+            instrumentor.get_targets(vec![(before.to_string(), "raft.c".to_string())]);
+        let (after, _) = instrumentor.get_targets(vec![(after.to_string(), "raft.c".to_string())]);
+
+        assert_eq!(
+            before[0].targets_block[&4].group.id,
+            after[0].targets_block[&9].group.id
+        );
+        assert_ne!(
+            after[0].targets_block[&4].group.id,
+            after[0].targets_block[&9].group.id
+        );
+    }
+
+    #[test]
+    fn test_same_annotation_in_different_functions_gets_different_ids() {
+        let instrumentor = Instrumentor::new().with_root("/work/SUT");
+        let content = r"
+static void
+become_leader(struct raft *r)
+{
+    // ABSTRAKTOR_BLOCK_EVENT END
+    r->state = LEADER;
+}
+
+// ABSTRAKTOR_FUNC: r END
+void become_follower(struct raft *r)
+{
+    // ABSTRAKTOR_BLOCK_EVENT END
+    r->state = FOLLOWER;
+}
+";
+        let mut ids = GroupIds::default();
+        instrumentor.get_targets_single(content, "/work/SUT/src/raft.c", &mut ids);
+
+        let keys: Vec<&AnnotationKey> = ids.members.values().flatten().collect();
+        assert_eq!(keys.len(), 3);
+        assert_eq!(keys[0].path, "src/raft.c");
+        assert_eq!(keys[0].function, "become_leader");
+        assert_eq!(keys[0].annotation, "ABSTRAKTOR_BLOCK_EVENT END");
+        assert_eq!(keys[1].function, "become_follower");
+        assert_eq!(keys[1].annotation, "ABSTRAKTOR_FUNC: r END");
+        assert_eq!(keys[2].function, "become_follower");
+
+        let (targets, collisions) = instrumentor.get_targets(vec![(
+            content.to_string(),
+            "/work/SUT/src/raft.c".to_string(),
+        )]);
+        assert!(collisions.is_empty());
+        assert_ne!(
+            targets[0].targets_block[&6].group.id,
+            targets[0].targets_block[&13].group.id
+        );
+    }
 }
@@ -0,0 +1,359 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+/// What `dump_run` in the mediator names its log in the run directory.
+pub const MEDIATOR_LOG: &str = "mediator.log";
+
+/// The mediator log the mediator copied into a Jepsen run directory, as
+/// `store/<test>/<timestamp>/mediator.log`. Its event lines are what the
+/// graphs are built from; the `events-<schedule>-<feedback>.log` next to it
+/// is the mediator's summary log and only names the schedule and feedback.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct JepsenRun {
+    pub test: String,
+    pub timestamp: String,
+    pub schedule: Option<String>,
+    pub feedback: Option<String>,
+    /// Top-level `:valid?` of `results.edn`; `None` when the run has no
+    /// results or the checker could not decide.
+    pub valid: Option<bool>,
+    pub dir: PathBuf,
+    pub mediator_log: PathBuf,
+}
+
+impl JepsenRun {
+    /// `<test>/<timestamp>`, as shown by `--list-runs` and accepted by `--run`.
+    pub fn id(&self) -> String {
+        format!("{}/{}", self.test, self.timestamp)
+    }
+
+    pub fn history(&self) -> Option<PathBuf> {
+        let path = self.dir.join("history.edn");
+        path.exists().then_some(path)
+    }
+
+    /// Output directory for the run, relative to the export root.
+    pub fn output_subdir(&self) -> PathBuf {
+        let name = match (&self.schedule, &self.feedback) {
+            (Some(schedule), Some(feedback)) => {
+                format!("{}-{}-{}", self.timestamp, schedule, feedback)
+            }
+            _ => self.timestamp.clone(),
+        };
+        Path::new(&self.test).join(name)
+    }
+
+    pub fn matches(&self, selector: &str) -> bool {
+        selector == self.id() || selector == self.timestamp
+    }
+}
+
+/// Lists every run with a mediator log, oldest first. The `latest` symlinks
+/// Jepsen maintains are skipped so runs are not listed twice.
+pub fn discover_runs(store: &Path) -> Result<Vec<JepsenRun>> {
+    let mut runs = Vec::new();
+    for test_dir in sorted_dirs(store)? {
+        let test = file_name(&test_dir);
+        for run_dir in sorted_dirs(&test_dir)? {
+            let mediator_log = run_dir.join(MEDIATOR_LOG);
+            if !mediator_log.is_file() {
+                continue;
+            }
+            let valid = fs::read_to_string(run_dir.join("results.edn"))
+                .ok()
+                .and_then(|results| top_level_validity(&results));
+            let mut names: Vec<String> = fs::read_dir(&run_dir)
+                .with_context(|| format!("reading {}", run_dir.display()))?
+                .flatten()
+                .map(|entry| file_name(&entry.path()))
+                .collect();
+            names.sort();
+            let (schedule, feedback) = names
+                .iter()
+                .find_map(|name| parse_summary_log_name(name))
+                .unzip();
+            runs.push(JepsenRun {
+                test: test.clone(),
+                timestamp: file_name(&run_dir),
+                schedule,
+                feedback,
+                valid,
+                dir: run_dir,
+                mediator_log,
+            });
+        }
+    }
+    runs.sort_by(|l, r| l.timestamp.cmp(&r.timestamp).then(l.test.cmp(&r.test)));
+    Ok(runs)
+}
+
+fn sorted_dirs(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut dirs: Vec<PathBuf> = fs::read_dir(dir)
+        .with_context(|| format!("reading {}", dir.display()))?
+        .flatten()
+        .filter(|entry| entry.file_type().is_ok_and(|t| t.is_dir()))
+        .map(|entry| entry.path())
+        .filter(|path| file_name(path) != "latest")
+        .collect();
+    dirs.sort();
+    Ok(dirs)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// `events-<schedule>-<feedback>.log`; schedule types have no dashes.
+fn parse_summary_log_name(name: &str) -> Option<(String, String)> {
+    let stem = name.strip_prefix("events-")?.strip_suffix(".log")?;
+    let (schedule, feedback) = stem.split_once('-')?;
+    Some((schedule.to_string(), feedback.to_string()))
+}
+
+/// Runs are oldest first, so `latest` is the last one. Selectors are
+/// `<test>/<timestamp>`, a bare timestamp, `latest` or `all`; without any,
+/// only the latest run is selected.
+pub fn select_runs<'a>(runs: &'a [JepsenRun], selectors: &[String]) -> Result<Vec<&'a JepsenRun>> {
+    if selectors.is_empty() {
+        return Ok(runs.last().into_iter().collect());
+    }
+    let mut selected: Vec<&JepsenRun> = Vec::new();
+    for selector in selectors {
+        let matching: Vec<&JepsenRun> = match selector.as_str() {
+            "all" => runs.iter().collect(),
+            "latest" => runs.last().into_iter().collect(),
+            _ => runs.iter().filter(|r| r.matches(selector)).collect(),
+        };
+        if matching.is_empty() {
+            anyhow::bail!("no run matches '{}'; use --list-runs to see them", selector);
+        }
+        for run in matching {
+            if !selected.contains(&run) {
+                selected.push(run);
+            }
+        }
+    }
+    Ok(selected)
+}
+
+/// Calls `visit` with the depth and byte index of every keyword outside a
+/// string, until it returns `false`.
+fn visit_keywords(results: &str, mut visit: impl FnMut(usize, usize) -> bool) {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (index, c) in results.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' | '[' | '(' => depth += 1,
+            '}' | ']' | ')' => depth = depth.saturating_sub(1),
+            ':' if !visit(depth, index) => return,
+            _ => {}
+        }
+    }
+}
+
+/// The boolean after the `:valid?` keyword at `index`, if it is one.
+fn validity_at(results: &str, index: usize) -> Option<bool> {
+    let value = results[index..].strip_prefix(":valid?")?.trim_start();
+    if value.starts_with("true") {
+        Some(true)
+    } else if value.starts_with("false") {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/// Finds `:valid?` in the outermost map of `results.edn`, ignoring the ones
+/// nested in per-checker results.
+pub fn top_level_validity(results: &str) -> Option<bool> {
+    let mut valid = None;
+    visit_keywords(results, |depth, index| {
+        if depth == 1 && results[index..].starts_with(":valid?") {
+            valid = validity_at(results, index);
+            return false;
+        }
+        true
+    });
+    valid
+}
+
+/// `:valid?` of every checker whose results are a map in the outermost map
+/// of `results.edn`, keyed by the checker name without its colon.
+pub fn checker_validity(results: &str) -> BTreeMap<String, Option<bool>> {
+    let mut checkers = BTreeMap::new();
+    let mut key = None;
+    visit_keywords(results, |depth, index| {
+        match depth {
+            1 => {
+                let name: String = results[index + 1..]
+                    .chars()
+                    .take_while(|c| !c.is_whitespace() && !matches!(c, ',' | '{' | '}'))
+                    .collect();
+                key = Some(name);
+            }
+            2 if results[index..].starts_with(":valid?") => {
+                if let Some(key) = &key {
+                    checkers
+                        .entry(key.clone())
+                        .or_insert_with(|| validity_at(results, index));
+                }
+            }
+            _ => {}
+        }
+        true
+    });
+    checkers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::event_log::parse_event_log;
+    use tempfile::TempDir;
+
+    fn write_run(store: &Path, test: &str, timestamp: &str, files: &[(&str, &str)]) {
+        let dir = store.join(test).join(timestamp);
+        fs::create_dir_all(&dir).unwrap();
+        for (name, content) in files {
+            fs::write(dir.join(name), content).unwrap();
+        }
+    }
+
+    #[test]
+    fn discovers_runs_by_their_mediator_log() {
+        let temp = TempDir::new().unwrap();
+        let store = temp.path();
+        write_run(
+            store,
+            "dqlite-append",
+            "20251110T195655.000-0300",
+            &[
+                ("mediator.log", ""),
+                ("events-qlearning-event_history.log", ""),
+                (
+                    "results.edn",
+                    "{:workload {:valid? true}, :stats {:valid? true}, :valid? false}",
+                ),
+                ("history.edn", ""),
+            ],
+        );
+        write_run(
+            store,
+            "dqlite-append",
+            "20251111T080000.000-0300",
+            &[("mediator.log", ""), ("results.edn", "{:valid? :unknown}")],
+        );
+        write_run(
+            store,
+            "dqlite-append",
+            "20251112T080000.000-0300",
+            &[("events-random-event_history.log", ""), ("jepsen.log", "")],
+        );
+        fs::create_dir_all(store.join("latest")).unwrap();
+
+        let runs = discover_runs(store).unwrap();
+        assert_eq!(runs.len(), 2);
+
+        assert_eq!(runs[0].id(), "dqlite-append/20251110T195655.000-0300");
+        assert_eq!(runs[0].schedule.as_deref(), Some("qlearning"));
+        assert_eq!(runs[0].feedback.as_deref(), Some("event_history"));
+        assert_eq!(runs[0].valid, Some(false));
+        assert!(runs[0].history().is_some());
+        assert!(runs[0].mediator_log.ends_with("mediator.log"));
+        assert_eq!(
+            runs[0].output_subdir(),
+            Path::new("dqlite-append/20251110T195655.000-0300-qlearning-event_history")
+        );
+
+        assert_eq!(runs[1].schedule, None);
+        assert_eq!(
+            runs[1].output_subdir(),
+            Path::new("dqlite-append/20251111T080000.000-0300")
+        );
+        assert_eq!(runs[1].valid, None);
+        assert!(runs[1].history().is_none());
+        assert!(runs[1].matches("20251111T080000.000-0300"));
+    }
+
+    #[test]
+    fn reads_events_from_the_mediator_log_not_the_summary_log() {
+        let runs = discover_runs(Path::new("tests/jepsen_store_test/store")).unwrap();
+        assert_eq!(runs.len(), 2);
+
+        let run = &runs[0];
+        assert_eq!(run.schedule.as_deref(), Some("random"));
+        let summary = fs::read_to_string(run.dir.join("events-random-event_history.log")).unwrap();
+        assert!(parse_event_log(&summary).is_empty());
+
+        let records = parse_event_log(&fs::read_to_string(&run.mediator_log).unwrap());
+        assert_eq!(records.len(), 6);
+        assert_eq!(records[0].state, "Follower");
+    }
+
+    #[test]
+    fn reads_top_level_validity_only() {
+        assert_eq!(
+            top_level_validity("{:a {:valid? false} :valid? true}"),
+            Some(true)
+        );
+        assert_eq!(
+            top_level_validity("{:msg \"{:valid? true\" :valid? false}"),
+            Some(false)
+        );
+        assert_eq!(top_level_validity("{:a {:valid? true}}"), None);
+    }
+
+    #[test]
+    fn reads_validity_of_each_checker() {
+        let checkers = checker_validity(
+            "{:perf {:latency-graph {:valid? true}, :valid? true}, :workload {:valid? false, :anomalies {:G1c [{:valid? true}]}}, :exceptions {:valid? :unknown}, :valid? false}",
+        );
+        assert_eq!(
+            checkers,
+            BTreeMap::from([
+                ("exceptions".to_string(), None),
+                ("perf".to_string(), Some(true)),
+                ("workload".to_string(), Some(false)),
+            ])
+        );
+    }
+
+    #[test]
+    fn selects_runs_by_id_timestamp_or_keyword() {
+        let run = |timestamp: &str| JepsenRun {
+            test: "t".to_string(),
+            timestamp: timestamp.to_string(),
+            schedule: None,
+            feedback: None,
+            valid: None,
+            dir: PathBuf::new(),
+            mediator_log: PathBuf::new(),
+        };
+        let runs = vec![run("1"), run("2")];
+
+        assert_eq!(select_runs(&runs, &[]).unwrap(), vec![&runs[1]]);
+        assert_eq!(select_runs(&runs, &["all".to_string()]).unwrap().len(), 2);
+        assert_eq!(
+            select_runs(&runs, &["t/1".to_string()]).unwrap(),
+            vec![&runs[0]]
+        );
+        assert!(select_runs(&runs, &["3".to_string()]).is_err());
+    }
+}
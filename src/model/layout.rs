@@ -0,0 +1,706 @@
+use std::collections::{BTreeMap, HashMap};
+
+use super::event_graph::NodeGraph;
+use super::sources::{SourceLinks, describe_location};
+use super::timeline::{state_color, xml_escape};
+
+const FONT_SIZE: f64 = 10.0;
+const LABEL_FONT_SIZE: f64 = 9.0;
+/// Average glyph width relative to the font size, close enough for Helvetica.
+const CHAR_WIDTH: f64 = 0.6;
+const MIN_RADIUS: f64 = 18.0;
+const LAYER_GAP: f64 = 110.0;
+const ROW_GAP: f64 = 50.0;
+const MARGIN: f64 = 20.0;
+const ARROW: f64 = 8.0;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlacedState {
+    pub name: String,
+    pub rank: usize,
+    pub x: f64,
+    pub y: f64,
+    pub radius: f64,
+}
+
+/// An edge drawn as a quadratic curve from `start` to `end` through
+/// `control`; all transitions between the same two states share one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlacedEdge {
+    pub from: String,
+    pub to: String,
+    pub labels: Vec<String>,
+    pub start: (f64, f64),
+    pub control: (f64, f64),
+    pub end: (f64, f64),
+    pub label_at: (f64, f64),
+    pub self_loop: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layout {
+    pub width: f64,
+    pub height: f64,
+    pub states: Vec<PlacedState>,
+    pub edges: Vec<PlacedEdge>,
+}
+
+fn text_width(text: &str, size: f64) -> f64 {
+    text.chars().count() as f64 * size * CHAR_WIDTH
+}
+
+/// Layered left-to-right layout, like `dot` with `rankdir=LR`: cycles are
+/// broken by reversing the edges that lead back to a state on the current
+/// depth-first path from the initial state, states are ranked by their
+/// longest path from a source, and each layer is reordered by the barycenter
+/// of its neighbours to reduce crossings.
+pub fn layout_node_graph(node: &NodeGraph) -> Layout {
+    let mut names: Vec<&str> = node.states.iter().map(String::as_str).collect();
+    for edge in &node.edges {
+        for name in [&edge.from, &edge.to] {
+            if !names.contains(&name.as_str()) {
+                names.push(name);
+            }
+        }
+    }
+    let index: HashMap<&str, usize> = names.iter().enumerate().map(|(i, n)| (*n, i)).collect();
+
+    let mut grouped: BTreeMap<(usize, usize), Vec<String>> = BTreeMap::new();
+    for edge in &node.edges {
+        grouped
+            .entry((index[edge.from.as_str()], index[edge.to.as_str()]))
+            .or_default()
+            .push(edge.transition.clone());
+    }
+    for labels in grouped.values_mut() {
+        labels.sort_unstable();
+        labels.dedup();
+    }
+
+    let mut successors: Vec<Vec<usize>> = vec![Vec::new(); names.len()];
+    for &(from, to) in grouped.keys() {
+        if from != to {
+            successors[from].push(to);
+        }
+    }
+
+    let dag = acyclic_edges(&names, &successors, node.initial.as_deref());
+    let ranks = longest_path_ranks(names.len(), &dag);
+    let layers = order_layers(&ranks, &dag);
+
+    let radii: Vec<f64> = names
+        .iter()
+        .map(|n| (text_width(n, FONT_SIZE) / 2.0 + 6.0).max(MIN_RADIUS))
+        .collect();
+
+    let mut layer_x = Vec::with_capacity(layers.len());
+    let mut x = 0.0;
+    for layer in &layers {
+        let widest = layer.iter().map(|&s| radii[s]).fold(0.0, f64::max);
+        layer_x.push(x + widest);
+        x += 2.0 * widest + LAYER_GAP;
+    }
+    let tallest = layers
+        .iter()
+        .map(|layer| layer_height(layer, &radii))
+        .fold(0.0, f64::max);
+
+    let mut states: Vec<PlacedState> = names
+        .iter()
+        .map(|n| PlacedState {
+            name: n.to_string(),
+            rank: 0,
+            x: 0.0,
+            y: 0.0,
+            radius: 0.0,
+        })
+        .collect();
+    for (rank, layer) in layers.iter().enumerate() {
+        let mut y = (tallest - layer_height(layer, &radii)) / 2.0;
+        for &s in layer {
+            states[s].rank = rank;
+            states[s].x = layer_x[rank];
+            states[s].y = y + radii[s];
+            states[s].radius = radii[s];
+            y += 2.0 * radii[s] + ROW_GAP;
+        }
+    }
+
+    let edges: Vec<PlacedEdge> = grouped
+        .iter()
+        .map(|(&(from, to), labels)| {
+            place_edge(
+                &states[from],
+                &states[to],
+                labels,
+                grouped.contains_key(&(to, from)),
+            )
+        })
+        .collect();
+
+    fit(states, edges)
+}
+
+fn layer_height(layer: &[usize], radii: &[f64]) -> f64 {
+    let diameters: f64 = layer.iter().map(|&s| 2.0 * radii[s]).sum();
+    diameters + ROW_GAP * layer.len().saturating_sub(1) as f64
+}
+
+/// Depth-first from the initial state, then from every state not reached
+/// yet, reversing edges into states still on the stack.
+fn acyclic_edges(
+    names: &[&str],
+    successors: &[Vec<usize>],
+    initial: Option<&str>,
+) -> Vec<(usize, usize)> {
+    #[derive(Clone, Copy, PartialEq)]
+    enum Mark {
+        New,
+        Active,
+        Done,
+    }
+
+    let mut marks = vec![Mark::New; names.len()];
+    let mut dag = Vec::new();
+    let roots = initial
+        .and_then(|i| names.iter().position(|n| *n == i))
+        .into_iter()
+        .chain(0..names.len());
+
+    for root in roots {
+        if marks[root] != Mark::New {
+            continue;
+        }
+        marks[root] = Mark::Active;
+        let mut stack = vec![(root, 0)];
+        while let Some((state, next)) = stack.pop() {
+            let Some(&succ) = successors[state].get(next) else {
+                marks[state] = Mark::Done;
+                continue;
+            };
+            stack.push((state, next + 1));
+            match marks[succ] {
+                Mark::Active => dag.push((succ, state)),
+                Mark::Done => dag.push((state, succ)),
+                Mark::New => {
+                    dag.push((state, succ));
+                    marks[succ] = Mark::Active;
+                    stack.push((succ, 0));
+                }
+            }
+        }
+    }
+    dag.sort_unstable();
+    dag.dedup();
+    dag
+}
+
+fn longest_path_ranks(count: usize, dag: &[(usize, usize)]) -> Vec<usize> {
+    let mut indegree = vec![0; count];
+    for &(_, to) in dag {
+        indegree[to] += 1;
+    }
+    let mut ranks = vec![0; count];
+    let mut ready: Vec<usize> = (0..count).filter(|&s| indegree[s] == 0).collect();
+    while let Some(state) = ready.pop() {
+        for &(from, to) in dag.iter().filter(|(from, _)| *from == state) {
+            ranks[to] = ranks[to].max(ranks[from] + 1);
+            indegree[to] -= 1;
+            if indegree[to] == 0 {
+                ready.push(to);
+            }
+        }
+    }
+    ranks
+}
+
+fn order_layers(ranks: &[usize], dag: &[(usize, usize)]) -> Vec<Vec<usize>> {
+    let depth = ranks.iter().max().map_or(0, |r| r + 1);
+    let mut layers: Vec<Vec<usize>> = vec![Vec::new(); depth];
+    for (state, &rank) in ranks.iter().enumerate() {
+        layers[rank].push(state);
+    }
+
+    let position = |layers: &[Vec<usize>]| {
+        let mut position = vec![0.0; ranks.len()];
+        for layer in layers {
+            for (i, &s) in layer.iter().enumerate() {
+                position[s] = i as f64;
+            }
+        }
+        position
+    };
+
+    for sweep in 0..4 {
+        let downward = sweep % 2 == 0;
+        let order: Vec<usize> = if downward {
+            (1..depth).collect()
+        } else {
+            (0..depth.saturating_sub(1)).rev().collect()
+        };
+        for rank in order {
+            let position = position(&layers);
+            let barycenter = |state: usize| {
+                let neighbours: Vec<f64> = dag
+                    .iter()
+                    .filter_map(|&(from, to)| match downward {
+                        true if to == state => Some(position[from]),
+                        false if from == state => Some(position[to]),
+                        _ => None,
+                    })
+                    .collect();
+                if neighbours.is_empty() {
+                    position[state]
+                } else {
+                    neighbours.iter().sum::<f64>() / neighbours.len() as f64
+                }
+            };
+            layers[rank].sort_by(|l, r| barycenter(*l).total_cmp(&barycenter(*r)));
+        }
+    }
+    layers
+}
+
+/// Edges between adjacent layers are straight; the others, and pairs of
+/// opposite edges, bend so they do not overlap or cross the states between.
+fn place_edge(
+    from: &PlacedState,
+    to: &PlacedState,
+    labels: &[String],
+    has_reverse: bool,
+) -> PlacedEdge {
+    let label_lines = labels.len() as f64 * LABEL_FONT_SIZE * 1.2;
+
+    if from.name == to.name {
+        let top = from.y - from.radius;
+        let spread = from.radius * 0.6;
+        return PlacedEdge {
+            from: from.name.clone(),
+            to: to.name.clone(),
+            labels: labels.to_vec(),
+            start: (from.x - spread, top + from.radius * 0.2),
+            control: (from.x, top - 2.5 * from.radius),
+            end: (from.x + spread, top + from.radius * 0.2),
+            label_at: (from.x, top - 1.25 * from.radius - label_lines),
+            self_loop: true,
+        };
+    }
+
+    let (dx, dy) = (to.x - from.x, to.y - from.y);
+    let distance = dx.hypot(dy).max(1.0);
+    let normal = (-dy / distance, dx / distance);
+    let bend = if has_reverse || from.rank.abs_diff(to.rank) != 1 {
+        25.0 + 0.15 * distance
+    } else {
+        0.0
+    };
+    let middle = ((from.x + to.x) / 2.0, (from.y + to.y) / 2.0);
+    let control = (
+        middle.0 + normal.0 * bend * 2.0,
+        middle.1 + normal.1 * bend * 2.0,
+    );
+
+    let toward = |state: &PlacedState, extra: f64| {
+        let (cx, cy) = (control.0 - state.x, control.1 - state.y);
+        let length = cx.hypot(cy).max(1.0);
+        let reach = state.radius + extra;
+        (state.x + cx / length * reach, state.y + cy / length * reach)
+    };
+
+    let label_at = (
+        0.25 * from.x + 0.5 * control.0 + 0.25 * to.x,
+        0.25 * from.y + 0.5 * control.1 + 0.25 * to.y - label_lines - 2.0,
+    );
+    PlacedEdge {
+        from: from.name.clone(),
+        to: to.name.clone(),
+        labels: labels.to_vec(),
+        start: toward(from, 0.0),
+        control,
+        end: toward(to, 1.0),
+        label_at,
+        self_loop: false,
+    }
+}
+
+/// Moves everything so the bounding box, labels included, starts at the margin.
+fn fit(mut states: Vec<PlacedState>, mut edges: Vec<PlacedEdge>) -> Layout {
+    let (mut min_x, mut min_y) = (f64::MAX, f64::MAX);
+    let (mut max_x, mut max_y) = (f64::MIN, f64::MIN);
+    let mut extend = |x0: f64, y0: f64, x1: f64, y1: f64| {
+        min_x = min_x.min(x0);
+        min_y = min_y.min(y0);
+        max_x = max_x.max(x1);
+        max_y = max_y.max(y1);
+    };
+    for s in &states {
+        extend(
+            s.x - s.radius,
+            s.y - s.radius,
+            s.x + s.radius,
+            s.y + s.radius,
+        );
+    }
+    for e in &edges {
+        let widest = e
+            .labels
+            .iter()
+            .map(|l| text_width(l, LABEL_FONT_SIZE))
+            .fold(0.0, f64::max);
+        let height = e.labels.len() as f64 * LABEL_FONT_SIZE * 1.2;
+        extend(
+            e.label_at.0 - widest / 2.0,
+            e.label_at.1,
+            e.label_at.0 + widest / 2.0,
+            e.label_at.1 + height,
+        );
+        let midpoint = (
+            0.25 * e.start.0 + 0.5 * e.control.0 + 0.25 * e.end.0,
+            0.25 * e.start.1 + 0.5 * e.control.1 + 0.25 * e.end.1,
+        );
+        extend(midpoint.0, midpoint.1, midpoint.0, midpoint.1);
+    }
+    if states.is_empty() {
+        (min_x, min_y, max_x, max_y) = (0.0, 0.0, 0.0, 0.0);
+    }
+
+    let shift = |p: &mut (f64, f64)| {
+        p.0 += MARGIN - min_x;
+        p.1 += MARGIN - min_y;
+    };
+    for s in &mut states {
+        s.x += MARGIN - min_x;
+        s.y += MARGIN - min_y;
+    }
+    for e in &mut edges {
+        for p in [&mut e.start, &mut e.control, &mut e.end, &mut e.label_at] {
+            shift(p);
+        }
+    }
+
+    Layout {
+        width: max_x - min_x + 2.0 * MARGIN,
+        height: max_y - min_y + 2.0 * MARGIN,
+        states,
+        edges,
+    }
+}
+
+/// The two back corners of the arrowhead at `end`, pointing away from `control`.
+fn arrowhead(control: (f64, f64), end: (f64, f64)) -> [(f64, f64); 2] {
+    let (dx, dy) = (end.0 - control.0, end.1 - control.1);
+    let length = dx.hypot(dy).max(1.0);
+    let (ux, uy) = (dx / length, dy / length);
+    let base = (end.0 - ux * ARROW, end.1 - uy * ARROW);
+    [
+        (base.0 - uy * ARROW / 2.5, base.1 + ux * ARROW / 2.5),
+        (base.0 + uy * ARROW / 2.5, base.1 - ux * ARROW / 2.5),
+    ]
+}
+
+/// Renders the built-in layout; transitions carry their source locations as
+/// tooltips and link to the first one when a URL template is configured.
+pub fn svg_for_node_graph(node: &NodeGraph, sources: Option<&SourceLinks>) -> String {
+    let layout = layout_node_graph(node);
+    let mut out = String::new();
+    out.push_str(&format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.0}\" height=\"{:.0}\" viewBox=\"0 0 {:.0} {:.0}\" font-family=\"Helvetica, Arial, sans-serif\">\n",
+        layout.width, layout.height, layout.width, layout.height
+    ));
+
+    for edge in &layout.edges {
+        let mut titles = Vec::new();
+        let mut url = None;
+        if let Some(sources) = sources {
+            for label in &edge.labels {
+                for entry in sources.locations(node, label) {
+                    titles.push(format!("{}: {}", label, describe_location(entry)));
+                    url = url.or_else(|| sources.url(entry));
+                }
+            }
+        }
+        if let Some(url) = &url {
+            out.push_str(&format!("  <a href=\"{}\">\n", xml_escape(url)));
+        }
+        out.push_str("  <g class=\"edge\">");
+        if !titles.is_empty() {
+            out.push_str(&format!(
+                "<title>{}</title>",
+                xml_escape(&titles.join("\n"))
+            ));
+        }
+        out.push('\n');
+
+        let path = if edge.self_loop {
+            format!(
+                "M{:.1},{:.1} C{:.1},{:.1} {:.1},{:.1} {:.1},{:.1}",
+                edge.start.0,
+                edge.start.1,
+                edge.start.0 - 10.0,
+                edge.control.1,
+                edge.end.0 + 10.0,
+                edge.control.1,
+                edge.end.0,
+                edge.end.1
+            )
+        } else {
+            format!(
+                "M{:.1},{:.1} Q{:.1},{:.1} {:.1},{:.1}",
+                edge.start.0, edge.start.1, edge.control.0, edge.control.1, edge.end.0, edge.end.1
+            )
+        };
+        out.push_str(&format!(
+            "    <path d=\"{}\" fill=\"none\" stroke=\"#333\"/>\n",
+            path
+        ));
+        let toward = if edge.self_loop {
+            (edge.end.0 + 10.0, edge.control.1)
+        } else {
+            edge.control
+        };
+        let [a, b] = arrowhead(toward, edge.end);
+        out.push_str(&format!(
+            "    <polygon points=\"{:.1},{:.1} {:.1},{:.1} {:.1},{:.1}\" fill=\"#333\"/>\n",
+            edge.end.0, edge.end.1, a.0, a.1, b.0, b.1
+        ));
+        for (i, label) in edge.labels.iter().enumerate() {
+            out.push_str(&format!(
+                "    <text x=\"{:.1}\" y=\"{:.1}\" font-size=\"{}\" text-anchor=\"middle\">{}</text>\n",
+                edge.label_at.0,
+                edge.label_at.1 + (i as f64 + 1.0) * LABEL_FONT_SIZE * 1.2,
+                LABEL_FONT_SIZE,
+                xml_escape(label)
+            ));
+        }
+        out.push_str("  </g>\n");
+        if url.is_some() {
+            out.push_str("  </a>\n");
+        }
+    }
+
+    for state in &layout.states {
+        let initial = node.initial.as_deref() == Some(state.name.as_str());
+        out.push_str(&format!(
+            "  <g class=\"state\"><circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"{:.1}\" fill=\"{}\" stroke=\"#333\" stroke-width=\"{}\"/>",
+            state.x,
+            state.y,
+            state.radius,
+            state_color(&state.name),
+            if initial { 2.5 } else { 1.0 }
+        ));
+        out.push_str(&format!(
+            "<text x=\"{:.1}\" y=\"{:.1}\" font-size=\"{}\" text-anchor=\"middle\">{}</text></g>\n",
+            state.x,
+            state.y + FONT_SIZE / 3.0,
+            FONT_SIZE,
+            xml_escape(&state.name)
+        ));
+    }
+
+    out.push_str("</svg>\n");
+    out
+}
+
+/// Renders the built-in layout as a single-page PDF using the standard
+/// Helvetica font, so no fonts need to be embedded.
+pub fn pdf_for_node_graph(node: &NodeGraph) -> Vec<u8> {
+    let layout = layout_node_graph(node);
+    let height = layout.height;
+    // PDF puts the origin at the bottom left.
+    let p = |(x, y): (f64, f64)| format!("{:.1} {:.1}", x, height - y);
+
+    let mut content = String::from("1 w 0.2 G 0.2 g\n");
+    for edge in &layout.edges {
+        let (c1, c2, toward) = if edge.self_loop {
+            let c1 = (edge.start.0 - 10.0, edge.control.1);
+            let c2 = (edge.end.0 + 10.0, edge.control.1);
+            (c1, c2, c2)
+        } else {
+            let lerp = |a: (f64, f64), b: (f64, f64)| {
+                (a.0 + 2.0 / 3.0 * (b.0 - a.0), a.1 + 2.0 / 3.0 * (b.1 - a.1))
+            };
+            (
+                lerp(edge.start, edge.control),
+                lerp(edge.end, edge.control),
+                edge.control,
+            )
+        };
+        content.push_str(&format!(
+            "{} m {} {} {} c S\n",
+            p(edge.start),
+            p(c1),
+            p(c2),
+            p(edge.end)
+        ));
+        let [a, b] = arrowhead(toward, edge.end);
+        content.push_str(&format!("{} m {} l {} l f\n", p(edge.end), p(a), p(b)));
+        for (i, label) in edge.labels.iter().enumerate() {
+            let y = edge.label_at.1 + (i as f64 + 1.0) * LABEL_FONT_SIZE * 1.2;
+            let x = edge.label_at.0 - text_width(label, LABEL_FONT_SIZE) / 2.0;
+            content.push_str(&pdf_text(&p((x, y)), LABEL_FONT_SIZE, label));
+        }
+    }
+
+    for state in &layout.states {
+        let initial = node.initial.as_deref() == Some(state.name.as_str());
+        content.push_str(&format!("{} w ", if initial { 2.5 } else { 1.0 }));
+        content.push_str(&pdf_circle(state.x, height - state.y, state.radius));
+        let x = state.x - text_width(&state.name, FONT_SIZE) / 2.0;
+        content.push_str(&pdf_text(
+            &p((x, state.y + FONT_SIZE / 3.0)),
+            FONT_SIZE,
+            &state.name,
+        ));
+    }
+
+    let objects = [
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        "<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_string(),
+        format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {:.0} {:.0}] /Contents 4 0 R /Resources << /Font << /F1 5 0 R >> >> >>",
+            layout.width, layout.height
+        ),
+        format!(
+            "<< /Length {} >>\nstream\n{}endstream",
+            content.len(),
+            content
+        ),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>".to_string(),
+    ];
+
+    let mut pdf = String::from("%PDF-1.4\n");
+    let mut offsets = Vec::new();
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.push_str(&format!("{} 0 obj\n{}\nendobj\n", i + 1, object));
+    }
+    let xref = pdf.len();
+    pdf.push_str(&format!(
+        "xref\n0 {}\n0000000000 65535 f \n",
+        objects.len() + 1
+    ));
+    for offset in offsets {
+        pdf.push_str(&format!("{:010} 00000 n \n", offset));
+    }
+    pdf.push_str(&format!(
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+        objects.len() + 1,
+        xref
+    ));
+    pdf.into_bytes()
+}
+
+/// A filled and stroked circle from four Bézier arcs.
+fn pdf_circle(cx: f64, cy: f64, r: f64) -> String {
+    let k = 0.5523 * r;
+    format!(
+        "0.93 g {:.1} {:.1} m {:.1} {:.1} {:.1} {:.1} {:.1} {:.1} c {:.1} {:.1} {:.1} {:.1} {:.1} {:.1} c {:.1} {:.1} {:.1} {:.1} {:.1} {:.1} c {:.1} {:.1} {:.1} {:.1} {:.1} {:.1} c B 0.2 g\n",
+        cx + r,
+        cy,
+        cx + r,
+        cy + k,
+        cx + k,
+        cy + r,
+        cx,
+        cy + r,
+        cx - k,
+        cy + r,
+        cx - r,
+        cy + k,
+        cx - r,
+        cy,
+        cx - r,
+        cy - k,
+        cx - k,
+        cy - r,
+        cx,
+        cy - r,
+        cx + k,
+        cy - r,
+        cx + r,
+        cy - k,
+        cx + r,
+        cy,
+    )
+}
+
+/// Helvetica only covers Latin-1 without an encoding, so anything else
+/// becomes `?`.
+fn pdf_text(at: &str, size: f64, text: &str) -> String {
+    let escaped: String = text
+        .chars()
+        .map(|c| match c {
+            '(' | ')' | '\\' => format!("\\{}", c),
+            ' '..='~' => c.to_string(),
+            _ => "?".to_string(),
+        })
+        .collect();
+    format!("BT /F1 {} Tf {} Td ({}) Tj ET\n", size, at, escaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::build_event_graph;
+
+    const LOG: &str = "[INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 1 / 1] FunctionExecute 1 @ FunctionName boot @ state Follower
+[INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 1 / 1] FunctionExecute 2 @ FunctionName electionTimeout @ state Candidate
+[INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 1 / 1] FunctionExecute 2 @ FunctionName electionTimeout @ state Candidate
+[INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 1 / 1] FunctionExecute 3 @ FunctionName winElection @ state Leader
+[INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 1 / 1] FunctionExecute 4 @ FunctionName stepDown @ state Follower";
+
+    fn graph() -> NodeGraph {
+        build_event_graph(LOG).nodes.remove(&1).unwrap()
+    }
+
+    fn state<'a>(layout: &'a Layout, name: &str) -> &'a PlacedState {
+        layout.states.iter().find(|s| s.name == name).unwrap()
+    }
+
+    #[test]
+    fn ranks_states_from_the_initial_one_despite_cycles() {
+        let layout = layout_node_graph(&graph());
+
+        assert_eq!(state(&layout, "Follower").rank, 0);
+        assert_eq!(state(&layout, "Candidate").rank, 1);
+        assert_eq!(state(&layout, "Leader").rank, 2);
+        assert!(state(&layout, "Follower").x < state(&layout, "Candidate").x);
+
+        let self_loop = layout.edges.iter().find(|e| e.self_loop).unwrap();
+        assert_eq!(self_loop.from, "Candidate");
+        assert_eq!(layout.edges.len(), 4);
+        for s in &layout.states {
+            assert!(s.x - s.radius >= 0.0 && s.x + s.radius <= layout.width);
+            assert!(s.y - s.radius >= 0.0 && s.y + s.radius <= layout.height);
+        }
+    }
+
+    #[test]
+    fn orders_layers_to_follow_their_predecessors() {
+        let ranks = vec![0, 0, 1, 1];
+        let layers = order_layers(&ranks, &[(0, 3), (1, 2)]);
+        assert_eq!(layers, vec![vec![0, 1], vec![3, 2]]);
+    }
+
+    #[test]
+    fn renders_svg_and_pdf() {
+        let svg = svg_for_node_graph(&graph(), None);
+        assert!(svg.starts_with("<svg "));
+        assert_eq!(svg.matches("<circle").count(), 3);
+        assert!(svg.contains(">winElection</text>"));
+
+        let pdf = String::from_utf8(pdf_for_node_graph(&graph())).unwrap();
+        assert!(pdf.starts_with("%PDF-1.4"));
+        assert!(pdf.contains("(boot) Tj"));
+        let xref: usize = pdf
+            .rsplit("startxref\n")
+            .next()
+            .unwrap()
+            .lines()
+            .next()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(pdf[xref..].starts_with("xref"));
+    }
+}
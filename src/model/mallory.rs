@@ -0,0 +1,154 @@
+/// A Jepsen test suite shipped under `mallory/tests/mallory`, with the
+/// options its `lein run test` accepts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestSuite {
+    pub name: &'static str,
+    pub workloads: &'static [&'static str],
+    pub default_workload: &'static str,
+    /// Faults `--nemesis` accepts. Suites whose faults are fixed in the test
+    /// have none and do not take the option.
+    pub faults: &'static [&'static str],
+    pub special_nemeses: &'static [&'static str],
+    /// Options the suite needs to run under Mallory.
+    pub extra_args: &'static [&'static str],
+}
+
+pub const SUITES: [TestSuite; 5] = [
+    TestSuite {
+        name: "dqlite",
+        workloads: &["append", "bank", "set"],
+        default_workload: "append",
+        faults: &[
+            "pause",
+            "kill",
+            "stop",
+            "disk",
+            "partition",
+            "member",
+            "clock",
+        ],
+        special_nemeses: &["none", "all"],
+        extra_args: &[],
+    },
+    TestSuite {
+        name: "braft",
+        workloads: &["wr-register"],
+        default_workload: "wr-register",
+        faults: &[],
+        special_nemeses: &[],
+        extra_args: &[],
+    },
+    TestSuite {
+        name: "redisraft",
+        workloads: &["append"],
+        default_workload: "append",
+        faults: &[
+            "pause",
+            "kill",
+            "partition",
+            "clock",
+            "member",
+            "island",
+            "mystery",
+        ],
+        special_nemeses: &["none", "standard", "all"],
+        extra_args: &["--follower-proxy"],
+    },
+    TestSuite {
+        name: "mongodb",
+        workloads: &["list-append", "none"],
+        default_workload: "list-append",
+        faults: &["pause", "kill", "partition", "clock", "member"],
+        special_nemeses: &["none", "all"],
+        extra_args: &["--nodes-file", "~/nodes", "--sharded"],
+    },
+    TestSuite {
+        name: "scylladb",
+        workloads: &[
+            "batch-set",
+            "batch-return",
+            "cas-register",
+            "counter",
+            "cmap",
+            "list-append",
+            "mv",
+            "none",
+            "cset",
+            "wr-register",
+            "write-isolation",
+            "write-isolation-single-row",
+            "write-isolation-single-write",
+        ],
+        default_workload: "list-append",
+        faults: &[
+            "pause",
+            "kill",
+            "partition",
+            "clock",
+            "decommission",
+            "remove",
+            "repair",
+        ],
+        special_nemeses: &["none", "membership", "all"],
+        extra_args: &[],
+    },
+];
+
+pub fn suite(name: &str) -> Option<&'static TestSuite> {
+    SUITES.iter().find(|suite| suite.name == name)
+}
+
+pub fn suite_names() -> Vec<&'static str> {
+    SUITES.iter().map(|suite| suite.name).collect()
+}
+
+impl TestSuite {
+    pub fn takes_nemesis(&self) -> bool {
+        !self.faults.is_empty()
+    }
+
+    /// Checks a comma-separated `--nemesis` value the way the suite's CLI
+    /// would, returning the first fault it does not know.
+    pub fn unknown_fault<'a>(&self, nemesis: &'a str) -> Option<&'a str> {
+        nemesis
+            .split(',')
+            .map(str::trim)
+            .find(|fault| !self.faults.contains(fault) && !self.special_nemeses.contains(fault))
+    }
+
+    pub fn nemesis_choices(&self) -> Vec<&'static str> {
+        self.faults
+            .iter()
+            .chain(self.special_nemeses)
+            .copied()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_suite_lookup() {
+        assert_eq!(suite("braft").unwrap().default_workload, "wr-register");
+        assert!(suite("tikv").is_none());
+        assert_eq!(
+            suite_names(),
+            ["dqlite", "braft", "redisraft", "mongodb", "scylladb"]
+        );
+        for suite in &SUITES {
+            assert!(suite.workloads.contains(&suite.default_workload));
+        }
+    }
+
+    #[test]
+    fn test_unknown_fault() {
+        let dqlite = suite("dqlite").unwrap();
+        assert_eq!(dqlite.unknown_fault("all"), None);
+        assert_eq!(dqlite.unknown_fault("partition,kill"), None);
+        assert_eq!(dqlite.unknown_fault("partition,island"), Some("island"));
+        assert!(!suite("braft").unwrap().takes_nemesis());
+        assert_eq!(suite("redisraft").unwrap().unknown_fault("island"), None);
+    }
+}
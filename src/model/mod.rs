@@ -1,4 +1,41 @@
+pub mod build_system;
+pub mod compile_db;
+pub mod coverage;
+pub mod doctor;
+pub mod dwell;
 pub mod event_graph;
+pub mod event_ids;
+pub mod event_log;
+pub mod experiment;
+pub mod fault_plan;
+pub mod identifiers;
+pub mod instrumentation_report;
 pub mod instrumentor;
+pub mod jepsen_store;
+pub mod layout;
+pub mod mallory;
+pub mod nondeterminism;
+pub mod pipeline;
+pub mod project;
+pub mod promela;
+pub mod refinement;
+pub mod report;
+pub mod run_registry;
+pub mod schedules;
+pub mod sources;
+pub mod spec;
+pub mod stats;
+pub mod timeline;
+pub mod tla;
+pub mod tlc;
+pub mod toolchain;
+pub mod watch;
 
-pub use event_graph::{build_event_graph, dot_for_node_graph};
+pub use dwell::dwell_report;
+pub use event_graph::{
+    build_event_graph, build_event_graph_with, build_schedule_graphs, build_window_graphs,
+    dot_for_node_graph,
+};
+pub use nondeterminism::find_nondeterminism;
+pub use refinement::RefinementConfig;
+pub use schedules::summarize_schedules;
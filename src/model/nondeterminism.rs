@@ -0,0 +1,248 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use super::event_log::{EventRecord, parse_event_log};
+use super::refinement::Refinement;
+
+const MAX_WITNESSES: usize = 3;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Witness {
+    pub source_line: usize,
+    pub target_line: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Outcome {
+    pub to: String,
+    pub count: usize,
+    pub witnesses: Vec<Witness>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VariableHint {
+    pub variable: String,
+    /// Fraction of observations whose outcome is predicted by the variable's
+    /// value alone; 1.0 means the variable fully separates the outcomes.
+    pub separation: f64,
+    pub distinct_values: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NondeterministicBranch {
+    pub node_id: u32,
+    pub from: String,
+    pub transition: String,
+    pub outcomes: Vec<Outcome>,
+    /// Captured variables that separate the outcomes, best first; absent
+    /// when the log carries no `@ values` segments to rank.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hints: Option<Vec<VariableHint>>,
+}
+
+#[derive(Default)]
+struct Observations<'a> {
+    outcomes: BTreeMap<String, Vec<(&'a EventRecord, &'a EventRecord)>>,
+}
+
+pub fn find_nondeterminism(log: &str, refinement: &Refinement) -> Vec<NondeterministicBranch> {
+    let records = parse_event_log(log);
+    let has_values = records.iter().any(|rec| !rec.values.is_empty());
+
+    let mut observed: BTreeMap<(u32, String, String), Observations> = BTreeMap::new();
+    let mut last_for_node: HashMap<u32, (String, &EventRecord)> = HashMap::new();
+    for rec in &records {
+        let Some(state) = refinement.state(&rec.state, rec.constant) else {
+            continue;
+        };
+        if let Some((prev_state, prev)) = last_for_node.get(&rec.node_id)
+            && refinement.keeps_transition(&prev.transition)
+        {
+            observed
+                .entry((rec.node_id, prev_state.clone(), prev.transition.clone()))
+                .or_default()
+                .outcomes
+                .entry(state.clone())
+                .or_default()
+                .push((prev, rec));
+        }
+        last_for_node.insert(rec.node_id, (state, rec));
+    }
+
+    observed
+        .into_iter()
+        .filter(|(_, obs)| obs.outcomes.len() > 1)
+        .map(
+            |((node_id, from, transition), obs)| NondeterministicBranch {
+                node_id,
+                from,
+                transition,
+                hints: has_values.then(|| rank_variables(&obs)),
+                outcomes: obs
+                    .outcomes
+                    .iter()
+                    .map(|(to, pairs)| Outcome {
+                        to: to.clone(),
+                        count: pairs.len(),
+                        witnesses: pairs
+                            .iter()
+                            .take(MAX_WITNESSES)
+                            .map(|(source, target)| Witness {
+                                source_line: source.line,
+                                target_line: target.line,
+                            })
+                            .collect(),
+                    })
+                    .collect(),
+            },
+        )
+        .collect()
+}
+
+/// Ranks the variables captured at the source event by how well their value
+/// predicts the outcome. Only variables present in every observation and
+/// doing better than always guessing the most common outcome are suggested.
+fn rank_variables(obs: &Observations) -> Vec<VariableHint> {
+    let pairs: Vec<(&str, &EventRecord)> = obs
+        .outcomes
+        .iter()
+        .flat_map(|(to, pairs)| pairs.iter().map(move |(source, _)| (to.as_str(), *source)))
+        .collect();
+    let total = pairs.len();
+    let Some((_, first)) = pairs.first() else {
+        return Vec::new();
+    };
+
+    let baseline = obs.outcomes.values().map(Vec::len).max().unwrap_or(0) as f64 / total as f64;
+
+    let mut hints: Vec<VariableHint> = first
+        .values
+        .keys()
+        .filter(|var| {
+            pairs
+                .iter()
+                .all(|(_, source)| source.values.contains_key(*var))
+        })
+        .filter_map(|var| {
+            let mut by_value: HashMap<&str, HashMap<&str, usize>> = HashMap::new();
+            for (to, source) in &pairs {
+                *by_value
+                    .entry(source.values[var].as_str())
+                    .or_default()
+                    .entry(to)
+                    .or_default() += 1;
+            }
+            let predicted: usize = by_value
+                .values()
+                .map(|counts| counts.values().copied().max().unwrap_or(0))
+                .sum();
+            let separation = predicted as f64 / total as f64;
+            (separation > baseline).then(|| VariableHint {
+                variable: var.clone(),
+                separation,
+                distinct_values: by_value.len(),
+            })
+        })
+        .collect();
+
+    // Between equally separating variables prefer the coarser one: a value
+    // unique to every observation separates trivially but abstracts nothing.
+    hints.sort_by(|l, r| {
+        r.separation
+            .total_cmp(&l.separation)
+            .then(l.distinct_values.cmp(&r.distinct_values))
+            .then(l.variable.cmp(&r.variable))
+    });
+    hints
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::refinement::{MergeRule, RefinementConfig};
+
+    #[test]
+    fn deterministic_log_has_no_branches() {
+        let log = "[2025-11-10 19:56:55.000001][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 1 / 3] FunctionExecute 1 @ FunctionName a @ state s0
+[2025-11-10 19:56:55.000002][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 2 / 3] FunctionExecute 2 @ FunctionName b @ state s1
+[2025-11-10 19:56:55.000003][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 3 / 3] FunctionExecute 3 @ FunctionName a @ state s0";
+        assert!(find_nondeterminism(log, &Refinement::identity()).is_empty());
+    }
+
+    #[test]
+    fn reports_branches_with_witnesses() {
+        let log = "[2025-11-10 19:56:55.000001][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 1 / 4] FunctionExecute 1 @ FunctionName tick @ state s0
+[2025-11-10 19:56:55.000002][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 2 / 4] FunctionExecute 2 @ FunctionName back @ state s1
+[2025-11-10 19:56:55.000003][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 3 / 4] FunctionExecute 3 @ FunctionName tick @ state s0
+[2025-11-10 19:56:55.000004][INFO] [FUNC_EVENT_TYPE][Node 2 Batch 1 Entry 1 / 1] FunctionExecute 4 @ FunctionName tick @ state s0
+[2025-11-10 19:56:55.000005][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 4 / 4] FunctionExecute 5 @ FunctionName tick @ state s2";
+        let branches = find_nondeterminism(log, &Refinement::identity());
+
+        assert_eq!(branches.len(), 1);
+        let branch = &branches[0];
+        assert_eq!(branch.node_id, 1);
+        assert_eq!(branch.from, "s0");
+        assert_eq!(branch.transition, "tick");
+        assert_eq!(branch.outcomes.len(), 2);
+        assert_eq!(branch.outcomes[0].to, "s1");
+        assert_eq!(
+            branch.outcomes[0].witnesses,
+            vec![Witness {
+                source_line: 1,
+                target_line: 2
+            }]
+        );
+        assert_eq!(branch.outcomes[1].to, "s2");
+        assert_eq!(branch.outcomes[1].witnesses[0].target_line, 5);
+        assert_eq!(branch.hints, None);
+    }
+
+    #[test]
+    fn coarse_refinement_introduces_nondeterminism() {
+        let log = "[2025-11-10 19:56:55.000001][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 1 / 4] FunctionExecute 1 @ FunctionName replicate @ state LeaderA
+[2025-11-10 19:56:55.000002][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 2 / 4] FunctionExecute 2 @ FunctionName replicate @ state LeaderB
+[2025-11-10 19:56:55.000003][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 3 / 4] FunctionExecute 3 @ FunctionName replicate @ state Follower";
+        assert!(find_nondeterminism(log, &Refinement::identity()).is_empty());
+
+        let config = RefinementConfig {
+            merge: vec![MergeRule {
+                into: "Leader".to_string(),
+                pattern: Some("^Leader".to_string()),
+                states: vec![],
+            }],
+            ..Default::default()
+        };
+        let branches = find_nondeterminism(log, &config.compile().unwrap());
+        assert_eq!(branches.len(), 1);
+        assert_eq!(branches[0].from, "Leader");
+        assert_eq!(branches[0].outcomes.len(), 2);
+    }
+
+    #[test]
+    fn ranks_variables_that_separate_outcomes() {
+        let log = "[2025-11-10 19:56:55.000001][INFO] [BLOCK_EVENT_TYPE][Node 1 Batch 1 Entry 1 / 8] BlockExecute 1 @ FunctionName vote @ state Candidate @ values votes=3,term=1,noise=a
+[2025-11-10 19:56:55.000002][INFO] [BLOCK_EVENT_TYPE][Node 1 Batch 1 Entry 2 / 8] BlockExecute 2 @ FunctionName reset @ state Leader @ values votes=0,term=1,noise=a
+[2025-11-10 19:56:55.000003][INFO] [BLOCK_EVENT_TYPE][Node 1 Batch 1 Entry 3 / 8] BlockExecute 1 @ FunctionName vote @ state Candidate @ values votes=1,term=2,noise=b
+[2025-11-10 19:56:55.000004][INFO] [BLOCK_EVENT_TYPE][Node 1 Batch 1 Entry 4 / 8] BlockExecute 2 @ FunctionName reset @ state Candidate @ values votes=0,term=2,noise=a
+[2025-11-10 19:56:55.000005][INFO] [BLOCK_EVENT_TYPE][Node 1 Batch 1 Entry 5 / 8] BlockExecute 1 @ FunctionName vote @ state Candidate @ values votes=3,term=3,noise=b
+[2025-11-10 19:56:55.000006][INFO] [BLOCK_EVENT_TYPE][Node 1 Batch 1 Entry 6 / 8] BlockExecute 2 @ FunctionName reset @ state Leader @ values votes=0,term=3,noise=b
+[2025-11-10 19:56:55.000007][INFO] [BLOCK_EVENT_TYPE][Node 1 Batch 1 Entry 7 / 8] BlockExecute 1 @ FunctionName vote @ state Candidate @ values votes=1,term=4
+[2025-11-10 19:56:55.000008][INFO] [BLOCK_EVENT_TYPE][Node 1 Batch 1 Entry 8 / 8] BlockExecute 2 @ FunctionName reset @ state Candidate @ values votes=0,term=4";
+        let branches = find_nondeterminism(log, &Refinement::identity());
+
+        let branch = branches
+            .iter()
+            .find(|b| b.from == "Candidate" && b.transition == "vote")
+            .unwrap();
+        assert_eq!(branch.outcomes.len(), 2);
+        // `term` separates trivially, being unique per observation; `noise`
+        // is missing from some observations and is never suggested.
+        let hints = branch.hints.as_ref().unwrap();
+        assert_eq!(hints.len(), 2);
+        assert_eq!(hints[0].variable, "votes");
+        assert_eq!(hints[0].separation, 1.0);
+        assert_eq!(hints[0].distinct_values, 2);
+        assert_eq!(hints[1].variable, "term");
+    }
+}
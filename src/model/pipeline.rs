@@ -0,0 +1,440 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use super::event_ids::fnv1a;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Stage {
+    Llvm,
+    Instrument,
+    Setup,
+    Run,
+    Export,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 5] = [
+        Stage::Llvm,
+        Stage::Instrument,
+        Stage::Setup,
+        Stage::Run,
+        Stage::Export,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Stage::Llvm => "llvm",
+            Stage::Instrument => "instrument",
+            Stage::Setup => "setup",
+            Stage::Run => "run",
+            Stage::Export => "export",
+        }
+    }
+
+    pub fn parse(name: &str) -> Result<Stage, String> {
+        Stage::ALL
+            .into_iter()
+            .find(|stage| stage.name() == name)
+            .ok_or_else(|| {
+                let names: Vec<_> = Stage::ALL.iter().map(|s| s.name()).collect();
+                format!(
+                    "unknown stage '{}' (expected one of {})",
+                    name,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StageRecord {
+    pub fingerprint: String,
+    pub completed: bool,
+}
+
+/// What the last invocations of `pipeline` did, kept between runs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct PipelineState {
+    pub stages: BTreeMap<Stage, StageRecord>,
+}
+
+impl PipelineState {
+    /// A missing file is a pipeline that never ran.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(PipelineState::default());
+        }
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("reading pipeline state from {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("parsing pipeline state {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            fs::create_dir_all(parent).with_context(|| format!("creating {}", parent.display()))?;
+        }
+        let content = serde_json::to_string_pretty(self)?;
+        fs::write(path, content)
+            .with_context(|| format!("writing pipeline state to {}", path.display()))
+    }
+
+    pub fn record(&mut self, stage: Stage, fingerprint: &str, completed: bool) {
+        self.stages.insert(
+            stage,
+            StageRecord {
+                fingerprint: fingerprint.to_string(),
+                completed,
+            },
+        );
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StageReason {
+    Unchanged,
+    BeforeFrom,
+    NeverRun,
+    Failed,
+    InputsChanged,
+    Forced,
+    EarlierStageRan,
+}
+
+impl StageReason {
+    pub fn describe(self) -> &'static str {
+        match self {
+            StageReason::Unchanged => "inputs unchanged",
+            StageReason::BeforeFrom => "before --from",
+            StageReason::NeverRun => "never ran",
+            StageReason::Failed => "failed last time",
+            StageReason::InputsChanged => "inputs changed",
+            StageReason::Forced => "forced",
+            StageReason::EarlierStageRan => "an earlier stage runs",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StageDecision {
+    pub stage: Stage,
+    pub run: bool,
+    pub reason: StageReason,
+}
+
+/// Decides which stages to run. A stage runs when it never completed with
+/// its current fingerprint; once one stage runs, every later stage runs too,
+/// since they consume its outputs. `from` runs everything from that stage on
+/// and skips what comes before it.
+pub fn plan(
+    state: &PipelineState,
+    fingerprints: &BTreeMap<Stage, String>,
+    from: Option<Stage>,
+    force: bool,
+) -> Vec<StageDecision> {
+    let mut upstream_ran = false;
+    Stage::ALL
+        .into_iter()
+        .map(|stage| {
+            let reason = match (from, state.stages.get(&stage)) {
+                (Some(from), _) if stage < from => StageReason::BeforeFrom,
+                (Some(from), _) if stage == from => StageReason::Forced,
+                _ if force => StageReason::Forced,
+                _ if upstream_ran => StageReason::EarlierStageRan,
+                (_, None) => StageReason::NeverRun,
+                (_, Some(record)) if Some(&record.fingerprint) != fingerprints.get(&stage) => {
+                    StageReason::InputsChanged
+                }
+                (_, Some(record)) if !record.completed => StageReason::Failed,
+                _ => StageReason::Unchanged,
+            };
+            let run = !matches!(reason, StageReason::Unchanged | StageReason::BeforeFrom);
+            upstream_ran |= run;
+            StageDecision { stage, run, reason }
+        })
+        .collect()
+}
+
+const SOURCE_EXTENSIONS: [&str; 14] = [
+    "c", "cc", "cpp", "cxx", "h", "hh", "hpp", "rs", "sh", "mk", "ac", "am", "in", "cmake",
+];
+
+const SOURCE_NAMES: [&str; 6] = [
+    "Makefile",
+    "GNUmakefile",
+    "makefile",
+    "CMakeLists.txt",
+    "meson.build",
+    "meson_options.txt",
+];
+
+/// Hashes the options and source files a stage depends on. Build outputs are
+/// left out by only looking at source-like files, so that building does not
+/// change the fingerprint of its own inputs.
+#[derive(Debug, Clone)]
+pub struct Fingerprint {
+    hash: u64,
+}
+
+impl Default for Fingerprint {
+    fn default() -> Self {
+        Fingerprint {
+            hash: 0xcbf29ce484222325,
+        }
+    }
+}
+
+impl Fingerprint {
+    pub fn add(&mut self, value: &str) -> &mut Self {
+        self.hash = fnv1a(value.bytes().chain([0]), self.hash);
+        self
+    }
+
+    pub fn add_bytes(&mut self, bytes: &[u8]) -> &mut Self {
+        self.hash = fnv1a(bytes.iter().copied(), self.hash);
+        self
+    }
+
+    /// Adds a file, or every source file below a directory. Hidden
+    /// directories and `target` are skipped; a missing path is hashed as such.
+    pub fn add_path(&mut self, path: &Path) -> Result<&mut Self> {
+        self.add_path_excluding(path, &[])
+    }
+
+    /// `add_path`, leaving out the files and directories in `excluded`, such
+    /// as a build directory below the sources.
+    pub fn add_path_excluding(&mut self, path: &Path, excluded: &[PathBuf]) -> Result<&mut Self> {
+        let excluded: Vec<PathBuf> = excluded
+            .iter()
+            .filter_map(|path| std::path::absolute(path).ok())
+            .collect();
+        self.add_path_except(path, &excluded)
+    }
+
+    fn add_path_except(&mut self, path: &Path, excluded: &[PathBuf]) -> Result<&mut Self> {
+        if !path.exists() {
+            self.add("missing").add(&path.to_string_lossy());
+        } else if path.is_dir() {
+            let mut entries: Vec<_> = fs::read_dir(path)
+                .with_context(|| format!("reading directory {}", path.display()))?
+                .collect::<std::io::Result<_>>()
+                .with_context(|| format!("reading directory {}", path.display()))?;
+            entries.sort_by_key(|entry| entry.file_name());
+            for entry in entries {
+                let entry_path = entry.path();
+                let name = entry.file_name().to_string_lossy().into_owned();
+                if std::path::absolute(&entry_path).is_ok_and(|p| excluded.contains(&p)) {
+                    continue;
+                }
+                if entry_path.is_dir() {
+                    if !name.starts_with('.') && name != "target" {
+                        self.add_path_except(&entry_path, excluded)?;
+                    }
+                } else if is_source(&entry_path) {
+                    self.add_file(&entry_path)?;
+                }
+            }
+        } else {
+            self.add_file(path)?;
+        }
+        Ok(self)
+    }
+
+    fn add_file(&mut self, path: &Path) -> Result<()> {
+        let content = fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+        self.add(&path.to_string_lossy());
+        self.hash = fnv1a(content, self.hash);
+        Ok(())
+    }
+
+    pub fn finish(&self) -> String {
+        format!("{:016x}", self.hash)
+    }
+}
+
+fn is_source(path: &Path) -> bool {
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    SOURCE_NAMES.contains(&name) || SOURCE_EXTENSIONS.contains(&extension)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn fingerprints(values: [&str; 5]) -> BTreeMap<Stage, String> {
+        Stage::ALL
+            .into_iter()
+            .zip(values.map(str::to_string))
+            .collect()
+    }
+
+    fn completed(values: [&str; 5]) -> PipelineState {
+        let mut state = PipelineState::default();
+        for (stage, fingerprint) in Stage::ALL.into_iter().zip(values) {
+            state.record(stage, fingerprint, true);
+        }
+        state
+    }
+
+    fn ran(decisions: &[StageDecision]) -> Vec<&'static str> {
+        decisions
+            .iter()
+            .filter(|d| d.run)
+            .map(|d| d.stage.name())
+            .collect()
+    }
+
+    #[test]
+    fn test_plan_runs_everything_the_first_time() {
+        let decisions = plan(
+            &PipelineState::default(),
+            &fingerprints(["a", "b", "c", "d", "e"]),
+            None,
+            false,
+        );
+        assert_eq!(
+            ran(&decisions),
+            ["llvm", "instrument", "setup", "run", "export"]
+        );
+        assert_eq!(decisions[0].reason, StageReason::NeverRun);
+        assert_eq!(decisions[1].reason, StageReason::EarlierStageRan);
+    }
+
+    #[test]
+    fn test_plan_skips_unchanged_and_reruns_downstream_of_changes() {
+        let state = completed(["a", "b", "c", "d", "e"]);
+        let unchanged = plan(
+            &state,
+            &fingerprints(["a", "b", "c", "d", "e"]),
+            None,
+            false,
+        );
+        assert!(ran(&unchanged).is_empty());
+
+        let decisions = plan(
+            &state,
+            &fingerprints(["a", "b", "c", "d", "E"]),
+            None,
+            false,
+        );
+        assert_eq!(ran(&decisions), ["export"]);
+        assert_eq!(decisions[4].reason, StageReason::InputsChanged);
+
+        let decisions = plan(
+            &state,
+            &fingerprints(["a", "B", "c", "d", "e"]),
+            None,
+            false,
+        );
+        assert_eq!(ran(&decisions), ["instrument", "setup", "run", "export"]);
+    }
+
+    #[test]
+    fn test_plan_resumes_from_failed_stage() {
+        let mut state = completed(["a", "b", "c", "d", "e"]);
+        state.record(Stage::Setup, "c", false);
+        state.stages.remove(&Stage::Run);
+        state.stages.remove(&Stage::Export);
+
+        let decisions = plan(
+            &state,
+            &fingerprints(["a", "b", "c", "d", "e"]),
+            None,
+            false,
+        );
+        assert_eq!(ran(&decisions), ["setup", "run", "export"]);
+        assert_eq!(decisions[2].reason, StageReason::Failed);
+    }
+
+    #[test]
+    fn test_plan_from_and_force() {
+        let state = completed(["a", "b", "c", "d", "e"]);
+        let decisions = plan(
+            &state,
+            &fingerprints(["A", "b", "c", "d", "e"]),
+            Some(Stage::Run),
+            false,
+        );
+        assert_eq!(ran(&decisions), ["run", "export"]);
+        assert_eq!(decisions[0].reason, StageReason::BeforeFrom);
+
+        let decisions = plan(&state, &fingerprints(["a", "b", "c", "d", "e"]), None, true);
+        assert_eq!(ran(&decisions).len(), 5);
+    }
+
+    #[test]
+    fn test_fingerprint_ignores_build_outputs() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("raft.c"), "int x;").unwrap();
+        fs::create_dir(temp_dir.path().join(".git")).unwrap();
+        fs::write(temp_dir.path().join(".git/HEAD"), "ref").unwrap();
+        let fingerprint = || {
+            Fingerprint::default()
+                .add_path(temp_dir.path())
+                .unwrap()
+                .finish()
+        };
+
+        let before = fingerprint();
+        fs::write(temp_dir.path().join("raft.o"), "object").unwrap();
+        fs::write(temp_dir.path().join(".git/HEAD"), "other").unwrap();
+        assert_eq!(fingerprint(), before);
+
+        fs::write(temp_dir.path().join("raft.c"), "int y;").unwrap();
+        assert_ne!(fingerprint(), before);
+
+        let build = temp_dir.path().join("build-abstraktor");
+        let without_build = || {
+            Fingerprint::default()
+                .add_path_excluding(temp_dir.path(), std::slice::from_ref(&build))
+                .unwrap()
+                .finish()
+        };
+        let before = without_build();
+        fs::create_dir(&build).unwrap();
+        fs::write(build.join("Makefile"), "all:").unwrap();
+        fs::write(build.join("CMakeCCompilerId.c"), "int id;").unwrap();
+        assert_eq!(without_build(), before);
+        assert_ne!(fingerprint(), without_build());
+    }
+
+    #[test]
+    fn test_state_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(".abstraktor/pipeline.json");
+        assert_eq!(
+            PipelineState::load(&path).unwrap(),
+            PipelineState::default()
+        );
+
+        let mut state = PipelineState::default();
+        state.record(Stage::Instrument, "b", false);
+        state.save(&path).unwrap();
+        assert_eq!(PipelineState::load(&path).unwrap(), state);
+        assert!(
+            fs::read_to_string(&path)
+                .unwrap()
+                .contains("\"instrument\"")
+        );
+    }
+
+    #[test]
+    fn test_stage_parse() {
+        assert_eq!(Stage::parse("run"), Ok(Stage::Run));
+        assert!(
+            Stage::parse("deploy")
+                .unwrap_err()
+                .contains("llvm, instrument")
+        );
+    }
+}
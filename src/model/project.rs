@@ -0,0 +1,187 @@
+use std::fs;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// `abstraktor.toml`. Options left out fall back to the defaults of the
+/// command the pipeline stage runs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectConfig {
+    pub sut: SutConfig,
+    #[serde(default)]
+    pub llvm: LlvmConfig,
+    #[serde(default)]
+    pub cluster: ClusterConfig,
+    #[serde(default)]
+    pub test: TestConfig,
+    #[serde(default)]
+    pub export: ExportConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SutConfig {
+    pub path: String,
+    /// Files and directories holding the annotated sources (default: `path`).
+    #[serde(default)]
+    pub sources: Vec<String>,
+    /// `compile_commands.json` limiting which sources are scanned.
+    pub compile_commands: Option<String>,
+    /// Globs over the scanned files, relative to `path`.
+    #[serde(default)]
+    pub include: Vec<String>,
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    pub clean: Option<String>,
+    pub build: Option<String>,
+    /// `script`, `make`, `cmake`, `autotools` or `meson` (default: detected).
+    pub build_system: Option<String>,
+    pub build_dir: Option<String>,
+    pub cflags: Option<String>,
+    pub build_record: Option<String>,
+    pub manifest: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LlvmConfig {
+    pub dir: String,
+    pub build: String,
+    pub instrumentor: Option<String>,
+    /// LLVM release to build the pass for and compile the SUT with; the
+    /// pass is built with `llvm-config-<version>` (default: detected).
+    pub version: Option<u32>,
+    pub cc: Option<String>,
+    pub cxx: Option<String>,
+}
+
+impl Default for LlvmConfig {
+    fn default() -> Self {
+        LlvmConfig {
+            dir: "llvm".to_string(),
+            build: "make".to_string(),
+            instrumentor: None,
+            version: None,
+            cc: None,
+            cxx: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClusterConfig {
+    pub nodes: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct TestConfig {
+    pub suite: Option<String>,
+    pub workload: Option<String>,
+    pub nemesis: Option<String>,
+    pub scheduler: Option<String>,
+    pub feedback: Option<String>,
+    pub similarity_threshold: Option<f64>,
+    pub schedule_duration_ms: Option<u64>,
+    pub schedule_interval_ms: Option<u64>,
+    pub reset_duration_ms: Option<u64>,
+    /// Schedules for the `planned` scheduler, copied into the control
+    /// container.
+    pub plan: Option<String>,
+    pub time_limit: Option<usize>,
+    pub test_count: Option<usize>,
+    pub artifacts_dir: Option<String>,
+    /// Passed to `lein run test` as they are.
+    pub args: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExportConfig {
+    pub log_path: Option<String>,
+    pub out: Option<String>,
+    pub format: Option<String>,
+    pub refinement: Option<String>,
+    /// Passed to `export-graphs` as they are, e.g. `["--timeline"]`.
+    pub args: Vec<String>,
+}
+
+impl ProjectConfig {
+    pub fn from_file(path: &str) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("reading project config from {}", path))?;
+        Self::parse(&content).with_context(|| format!("parsing project config {}", path))
+    }
+
+    pub fn parse(content: &str) -> Result<Self> {
+        Ok(toml::from_str(content)?)
+    }
+
+    pub fn sources(&self) -> Vec<String> {
+        if self.sut.sources.is_empty() {
+            vec![self.sut.path.clone()]
+        } else {
+            self.sut.sources.clone()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_full_config() {
+        let config = ProjectConfig::parse(
+            r#"
+[sut]
+path = "SUT/dqlite/dqlite-raft"
+sources = ["SUT/dqlite/dqlite-raft/src"]
+clean = "make clean"
+build = "./install.sh"
+
+[cluster]
+nodes = 3
+
+[test]
+suite = "redisraft"
+workload = "append"
+nemesis = "partition"
+scheduler = "power"
+similarity_threshold = 0.5
+time_limit = 120
+
+[export]
+format = "html"
+args = ["--timeline"]
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.sut.clean.as_deref(), Some("make clean"));
+        assert_eq!(config.sources(), vec!["SUT/dqlite/dqlite-raft/src"]);
+        assert_eq!(config.llvm, LlvmConfig::default());
+        assert_eq!(config.cluster.nodes, Some(3));
+        assert_eq!(config.test.suite.as_deref(), Some("redisraft"));
+        assert_eq!(config.test.similarity_threshold, Some(0.5));
+        assert_eq!(config.test.time_limit, Some(120));
+        assert_eq!(config.export.args, vec!["--timeline"]);
+        assert!(config.export.out.is_none());
+    }
+
+    #[test]
+    fn test_sources_default_to_sut_path() {
+        let config = ProjectConfig::parse("[sut]\npath = \"SUT/raft\"\n").unwrap();
+        assert_eq!(config.sources(), vec!["SUT/raft"]);
+        assert_eq!(config.test, TestConfig::default());
+    }
+
+    #[test]
+    fn test_unknown_keys_are_rejected() {
+        let err = ProjectConfig::parse("[sut]\npath = \"SUT\"\n[test]\nworklaod = \"append\"\n")
+            .unwrap_err();
+        assert!(format!("{:#}", err).contains("worklaod"));
+    }
+}
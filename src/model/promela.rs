@@ -0,0 +1,239 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::event_graph::{Edge, NodeGraph};
+use super::identifiers::Identifiers;
+
+/// Renders the nodes as one `active proctype` each, with an `inline` per
+/// transition label. The current state and the last action of every node are
+/// globals so that environment models and LTL properties can refer to them.
+pub fn promela_for_nodes(nodes: &[(u32, &NodeGraph)]) -> String {
+    // States and actions share the mtype namespace of the whole model.
+    let names = ModelIds {
+        states: Identifiers::new(
+            nodes
+                .iter()
+                .flat_map(|(_, n)| n.states.iter().map(String::as_str)),
+        ),
+        labels: Identifiers::new(
+            nodes
+                .iter()
+                .flat_map(|(_, n)| n.edges.iter().map(|e| e.transition.as_str())),
+        ),
+    };
+    let states: BTreeSet<String> = nodes
+        .iter()
+        .flat_map(|(_, n)| n.states.iter().map(|s| names.states.id(s)))
+        .collect();
+    let actions: BTreeSet<String> = nodes
+        .iter()
+        .flat_map(|(_, n)| n.edges.iter().map(|e| names.action(&e.transition)))
+        .chain(["act_none".to_string()])
+        .collect();
+
+    let ids: Vec<String> = nodes.iter().map(|(id, _)| id.to_string()).collect();
+    let mut out = String::new();
+    out.push_str(&format!(
+        "/* Abstraction recovered by abstraktor from node(s) {}. */\n\n",
+        ids.join(", ")
+    ));
+    out.push_str(&format!(
+        "mtype:state = {{ {} }};\n",
+        states.into_iter().collect::<Vec<_>>().join(", ")
+    ));
+    out.push_str(&format!(
+        "mtype:action = {{ {} }};\n",
+        actions.into_iter().collect::<Vec<_>>().join(", ")
+    ));
+
+    for (node_id, _) in nodes {
+        out.push('\n');
+        out.push_str(&format!("mtype:state node_{}_state;\n", node_id));
+        out.push_str(&format!("mtype:action node_{}_last = act_none;\n", node_id));
+    }
+
+    for (node_id, node) in nodes {
+        out.push('\n');
+        out.push_str(&proctype(*node_id, node, &names));
+    }
+
+    out
+}
+
+struct ModelIds {
+    states: Identifiers,
+    labels: Identifiers,
+}
+
+impl ModelIds {
+    fn action(&self, transition: &str) -> String {
+        format!("act_{}", self.labels.id(transition))
+    }
+}
+
+fn proctype(node_id: u32, node: &NodeGraph, ids: &ModelIds) -> String {
+    let var = format!("node_{}_state", node_id);
+    let mut by_label: BTreeMap<&str, Vec<&Edge>> = BTreeMap::new();
+    for e in &node.edges {
+        by_label.entry(&e.transition).or_default().push(e);
+    }
+
+    let mut out = String::new();
+    for (label, edges) in &by_label {
+        out.push_str(&format!(
+            "inline node_{}_{}() {{\n",
+            node_id,
+            ids.labels.id(label)
+        ));
+        out.push_str("    if\n");
+        for e in edges {
+            out.push_str(&format!(
+                "    :: {} == {} -> {} = {}\n",
+                var,
+                ids.states.id(&e.from),
+                var,
+                ids.states.id(&e.to)
+            ));
+        }
+        out.push_str("    fi;\n");
+        out.push_str(&format!(
+            "    node_{}_last = {}\n",
+            node_id,
+            ids.action(label)
+        ));
+        out.push_str("}\n\n");
+    }
+
+    out.push_str(&format!("active proctype node_{}() {{\n", node_id));
+    match &node.initial {
+        Some(initial) => out.push_str(&format!("    {} = {};\n", var, ids.states.id(initial))),
+        None => {
+            out.push_str("    if\n");
+            for s in &node.states {
+                out.push_str(&format!("    :: {} = {}\n", var, ids.states.id(s)));
+            }
+            out.push_str("    fi;\n");
+        }
+    }
+
+    if !by_label.is_empty() {
+        out.push_str("    do\n");
+        for (label, edges) in &by_label {
+            let sources: BTreeSet<String> = edges.iter().map(|e| ids.states.id(&e.from)).collect();
+            let guard: Vec<String> = sources
+                .iter()
+                .map(|s| format!("{} == {}", var, s))
+                .collect();
+            out.push_str(&format!(
+                "    :: atomic {{ ({}) -> node_{}_{}() }}\n",
+                guard.join(" || "),
+                node_id,
+                ids.labels.id(label)
+            ));
+        }
+        out.push_str("    od\n");
+    }
+    out.push_str("}\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::build_event_graph;
+
+    const LOG: &str = "[2025-11-10 19:56:55.000001][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 1 / 4] FunctionExecute 1 @ FunctionName electionTimeout @ state Follower
+[2025-11-10 19:56:55.000002][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 2 / 4] FunctionExecute 2 @ FunctionName winElection @ state Candidate
+[2025-11-10 19:56:55.000003][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 3 / 4] FunctionExecute 3 @ FunctionName electionTimeout @ state Leader
+[2025-11-10 19:56:55.000004][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 4 / 4] FunctionExecute 4 @ FunctionName stepDown @ state Follower
+[2025-11-10 19:56:55.000005][INFO] [FUNC_EVENT_TYPE][Node 2 Batch 1 Entry 1 / 1] FunctionExecute 5 @ FunctionName boot @ state Follower";
+
+    #[test]
+    fn renders_one_inline_per_transition_label() {
+        let g = build_event_graph(LOG);
+        let pml = promela_for_nodes(&[(1, &g.nodes[&1])]);
+
+        assert!(pml.contains("mtype:state = { Candidate, Follower, Leader };"));
+        assert!(pml.contains("mtype:action = { act_electionTimeout, act_none, act_winElection };"));
+        assert!(pml.contains("mtype:state node_1_state;"));
+        assert!(pml.contains("inline node_1_electionTimeout() {"));
+        assert!(pml.contains("    :: node_1_state == Follower -> node_1_state = Candidate\n"));
+        assert!(pml.contains("    :: node_1_state == Leader -> node_1_state = Follower\n"));
+        assert!(pml.contains("    node_1_last = act_electionTimeout\n"));
+        assert!(pml.contains("    node_1_state = Follower;\n"));
+        assert!(pml.contains(
+            "    :: atomic { (node_1_state == Follower || node_1_state == Leader) -> node_1_electionTimeout() }\n"
+        ));
+    }
+
+    #[test]
+    fn composes_all_nodes_in_one_model() {
+        let g = build_event_graph(LOG);
+        let pml = promela_for_nodes(&[(1, &g.nodes[&1]), (2, &g.nodes[&2])]);
+
+        assert!(pml.contains("node(s) 1, 2."));
+        assert!(pml.contains("active proctype node_1() {"));
+        assert!(pml.contains("active proctype node_2() {"));
+        assert!(pml.contains("mtype:action node_2_last = act_none;"));
+        // Node 2 never leaves its initial state, so it has no loop.
+        let node_2 = &pml[pml.find("active proctype node_2()").unwrap()..];
+        assert!(!node_2.contains("do\n"));
+    }
+
+    #[test]
+    fn generated_model_parses() {
+        let mut log =
+            std::fs::read_to_string("tests/export_graphs_test/simple_events.log").unwrap();
+        log.push_str("\n[2025-11-10 19:56:56.000001][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 2 Entry 1 / 4] FunctionExecute 6 @ FunctionName do @ state a-b
+[2025-11-10 19:56:56.000002][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 2 Entry 2 / 4] FunctionExecute 7 @ FunctionName none @ state a_b
+[2025-11-10 19:56:56.000003][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 2 Entry 3 / 4] FunctionExecute 8 @ FunctionName raft.do @ state timeout
+[2025-11-10 19:56:56.000004][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 2 Entry 4 / 4] FunctionExecute 9 @ FunctionName reset @ state idle\n");
+        let g = build_event_graph(&log);
+        let pml = promela_for_nodes(&[(1, &g.nodes[&1])]);
+
+        // Every mtype name is declared once and is an identifier Spin takes.
+        let mut declared = BTreeSet::new();
+        for line in pml
+            .lines()
+            .filter(|l| l.starts_with("mtype:") && l.contains('{'))
+        {
+            let names = &line[line.find('{').unwrap() + 1..line.find('}').unwrap()];
+            for name in names.split(',').map(str::trim) {
+                assert!(
+                    name.starts_with(|c: char| c.is_ascii_alphabetic())
+                        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'),
+                    "{}",
+                    name
+                );
+                assert!(!RESERVED_FOR_TEST.contains(&name), "{}", name);
+                assert!(declared.insert(name.to_string()), "{} declared twice", name);
+            }
+        }
+        assert!(declared.contains("s_active") && declared.contains("s_timeout"));
+        assert!(declared.contains("a_b") && declared.contains("a_b_2"));
+        assert!(declared.contains("act_s_do") && declared.contains("act_raft_do"));
+        assert!(declared.contains("act_s_none") && declared.contains("act_none"));
+        let inlines: Vec<&str> = pml.lines().filter(|l| l.starts_with("inline ")).collect();
+        let unique: BTreeSet<&&str> = inlines.iter().collect();
+        assert_eq!(unique.len(), inlines.len());
+
+        // Spin itself, when it is installed.
+        let dir = tempfile::TempDir::new().unwrap();
+        let model = dir.path().join("model.pml");
+        std::fs::write(&model, &pml).unwrap();
+        if let Ok(output) = std::process::Command::new("spin")
+            .arg("-a")
+            .arg(&model)
+            .current_dir(dir.path())
+            .output()
+        {
+            assert!(
+                output.status.success(),
+                "{}",
+                String::from_utf8_lossy(&output.stdout)
+            );
+        }
+    }
+
+    const RESERVED_FOR_TEST: &[&str] =
+        &["active", "init", "timeout", "if", "do", "od", "skip", "run"];
+}
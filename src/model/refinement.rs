@@ -0,0 +1,358 @@
+use std::collections::BTreeMap;
+use std::fs;
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct RefinementConfig {
+    pub rename: BTreeMap<String, String>,
+    pub merge: Vec<MergeRule>,
+    pub drop_states: Vec<String>,
+    pub drop_transitions: Vec<String>,
+    pub drop_constants: bool,
+    pub projection: Option<Projection>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct MergeRule {
+    pub into: String,
+    pub pattern: Option<String>,
+    pub states: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct Projection {
+    pub dimensions: Vec<Dimension>,
+    pub keep: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct Dimension {
+    pub name: String,
+    pub values: Vec<String>,
+}
+
+impl RefinementConfig {
+    pub fn from_file(path: &str) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("reading refinement from {}", path))?;
+        serde_json::from_str(&content).with_context(|| format!("parsing refinement {}", path))
+    }
+
+    pub fn compile(&self) -> Result<Refinement> {
+        let compile_all = |patterns: &[String]| -> Result<Vec<Regex>> {
+            patterns
+                .iter()
+                .map(|p| Regex::new(p).with_context(|| format!("invalid pattern '{}'", p)))
+                .collect()
+        };
+
+        let mut merge = Vec::new();
+        for rule in &self.merge {
+            let pattern = match &rule.pattern {
+                Some(p) => {
+                    Some(Regex::new(p).with_context(|| format!("invalid merge pattern '{}'", p))?)
+                }
+                None => None,
+            };
+            merge.push(CompiledMerge {
+                into: rule.into.clone(),
+                pattern,
+                states: rule.states.clone(),
+            });
+        }
+
+        let projection = match &self.projection {
+            Some(projection) => {
+                for name in &projection.keep {
+                    if !projection.dimensions.iter().any(|d| &d.name == name) {
+                        anyhow::bail!("projection keeps unknown dimension '{}'", name);
+                    }
+                }
+                let dimensions = projection
+                    .dimensions
+                    .iter()
+                    .map(|d| {
+                        let mut values = d.values.clone();
+                        // "NotMatchingQuorum" must win over "MatchingQuorum".
+                        values.sort_by(|l, r| r.len().cmp(&l.len()).then(l.cmp(r)));
+                        let kept = projection.keep.is_empty() || projection.keep.contains(&d.name);
+                        (values, kept)
+                    })
+                    .collect();
+                Some(dimensions)
+            }
+            None => None,
+        };
+
+        Ok(Refinement {
+            rename: self.rename.clone(),
+            merge,
+            drop_states: compile_all(&self.drop_states)?,
+            drop_transitions: compile_all(&self.drop_transitions)?,
+            drop_constants: self.drop_constants,
+            projection,
+        })
+    }
+}
+
+#[derive(Debug)]
+struct CompiledMerge {
+    into: String,
+    pattern: Option<Regex>,
+    states: Vec<String>,
+}
+
+impl CompiledMerge {
+    fn matches(&self, state: &str) -> bool {
+        self.states.iter().any(|s| s == state)
+            || self.pattern.as_ref().is_some_and(|p| p.is_match(state))
+    }
+}
+
+/// Rules are applied in a fixed order: drops on the raw name, then renames,
+/// projection onto the kept dimensions and finally merges.
+#[derive(Debug, Default)]
+pub struct Refinement {
+    rename: BTreeMap<String, String>,
+    merge: Vec<CompiledMerge>,
+    drop_states: Vec<Regex>,
+    drop_transitions: Vec<Regex>,
+    drop_constants: bool,
+    projection: Option<Vec<(Vec<String>, bool)>>,
+}
+
+impl Refinement {
+    pub fn identity() -> Self {
+        Self::default()
+    }
+
+    pub fn state(&self, raw: &str, constant: bool) -> Option<String> {
+        if constant && self.drop_constants {
+            return None;
+        }
+        if self.drop_states.iter().any(|r| r.is_match(raw)) {
+            return None;
+        }
+
+        let mut state = self
+            .rename
+            .get(raw)
+            .cloned()
+            .unwrap_or_else(|| raw.to_string());
+
+        if let Some(dimensions) = &self.projection {
+            state = project(&state, dimensions);
+        }
+
+        if let Some(rule) = self.merge.iter().find(|m| m.matches(&state)) {
+            state = rule.into.clone();
+        }
+
+        Some(state)
+    }
+
+    pub fn keeps_transition(&self, transition: &str) -> bool {
+        !self.drop_transitions.iter().any(|r| r.is_match(transition))
+    }
+}
+
+fn project(state: &str, dimensions: &[(Vec<String>, bool)]) -> String {
+    let mut matched_any = false;
+    let mut parts = Vec::new();
+    for (values, kept) in dimensions {
+        if let Some(value) = values.iter().find(|v| state.contains(v.as_str())) {
+            matched_any = true;
+            if *kept {
+                parts.push(value.as_str());
+            }
+        }
+    }
+
+    if !matched_any || parts.is_empty() {
+        return state.to_string();
+    }
+    parts.concat()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raft_projection(keep: &[&str]) -> Projection {
+        Projection {
+            dimensions: vec![
+                Dimension {
+                    name: "role".to_string(),
+                    values: vec!["Leader".to_string(), "Candidate".to_string()],
+                },
+                Dimension {
+                    name: "quorum".to_string(),
+                    values: vec![
+                        "MatchingQuorum".to_string(),
+                        "NotMatchingQuorum".to_string(),
+                    ],
+                },
+                Dimension {
+                    name: "log".to_string(),
+                    values: vec!["LogUpdated".to_string(), "NotLogUpdated".to_string()],
+                },
+            ],
+            keep: keep.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn identity_keeps_everything() {
+        let r = Refinement::identity();
+        assert_eq!(r.state("Leader", false), Some("Leader".to_string()));
+        assert_eq!(r.state("idle", true), Some("idle".to_string()));
+        assert!(r.keeps_transition("anything"));
+    }
+
+    #[test]
+    fn renames_states() {
+        let config = RefinementConfig {
+            rename: BTreeMap::from([("Unavailable".to_string(), "Down".to_string())]),
+            ..Default::default()
+        };
+        let r = config.compile().unwrap();
+        assert_eq!(r.state("Unavailable", false), Some("Down".to_string()));
+        assert_eq!(r.state("Follower", false), Some("Follower".to_string()));
+    }
+
+    #[test]
+    fn merges_by_pattern_and_explicit_group() {
+        let config = RefinementConfig {
+            merge: vec![
+                MergeRule {
+                    into: "Leader".to_string(),
+                    pattern: Some("^Leader".to_string()),
+                    states: vec![],
+                },
+                MergeRule {
+                    into: "Candidate".to_string(),
+                    pattern: None,
+                    states: vec![
+                        "CandidateVotesInQuorum".to_string(),
+                        "CandidateNotVotesInQuorum".to_string(),
+                    ],
+                },
+            ],
+            ..Default::default()
+        };
+        let r = config.compile().unwrap();
+        assert_eq!(
+            r.state("LeaderMatchingQuorumLogUpdated", false),
+            Some("Leader".to_string())
+        );
+        assert_eq!(
+            r.state("CandidateNotVotesInQuorum", false),
+            Some("Candidate".to_string())
+        );
+        assert_eq!(r.state("Candidate", false), Some("Candidate".to_string()));
+    }
+
+    #[test]
+    fn drops_states_transitions_and_constants() {
+        let config = RefinementConfig {
+            drop_states: vec!["^Unknown$".to_string()],
+            drop_transitions: vec!["^raft_io".to_string()],
+            drop_constants: true,
+            ..Default::default()
+        };
+        let r = config.compile().unwrap();
+        assert_eq!(r.state("Unknown", false), None);
+        assert_eq!(
+            r.state("UnknownPeer", false),
+            Some("UnknownPeer".to_string())
+        );
+        assert_eq!(r.state("Follower", true), None);
+        assert!(!r.keeps_transition("raft_io_send"));
+        assert!(r.keeps_transition("sendAppendEntries"));
+    }
+
+    #[test]
+    fn projects_composite_names_onto_kept_dimensions() {
+        let config = RefinementConfig {
+            projection: Some(raft_projection(&["role", "log"])),
+            ..Default::default()
+        };
+        let r = config.compile().unwrap();
+        assert_eq!(
+            r.state("LeaderNotMatchingQuorumNotLogUpdated", false),
+            Some("LeaderNotLogUpdated".to_string())
+        );
+        assert_eq!(
+            r.state("LeaderMatchingQuorumLogUpdated", false),
+            Some("LeaderLogUpdated".to_string())
+        );
+        assert_eq!(r.state("Follower", false), Some("Follower".to_string()));
+    }
+
+    #[test]
+    fn projection_prefers_longest_value() {
+        let config = RefinementConfig {
+            projection: Some(raft_projection(&["quorum"])),
+            ..Default::default()
+        };
+        let r = config.compile().unwrap();
+        assert_eq!(
+            r.state("LeaderNotMatchingQuorumLogUpdated", false),
+            Some("NotMatchingQuorum".to_string())
+        );
+    }
+
+    #[test]
+    fn rename_is_applied_before_merge() {
+        let config = RefinementConfig {
+            rename: BTreeMap::from([("Unavailable".to_string(), "Offline".to_string())]),
+            merge: vec![MergeRule {
+                into: "Down".to_string(),
+                pattern: Some("^Offline$".to_string()),
+                states: vec![],
+            }],
+            ..Default::default()
+        };
+        let r = config.compile().unwrap();
+        assert_eq!(r.state("Unavailable", false), Some("Down".to_string()));
+    }
+
+    #[test]
+    fn rejects_invalid_patterns_and_unknown_dimensions() {
+        let config = RefinementConfig {
+            drop_states: vec!["(".to_string()],
+            ..Default::default()
+        };
+        assert!(config.compile().is_err());
+
+        let config = RefinementConfig {
+            projection: Some(raft_projection(&["term"])),
+            ..Default::default()
+        };
+        let err = config.compile().unwrap_err();
+        assert!(err.to_string().contains("unknown dimension 'term'"));
+    }
+
+    #[test]
+    fn parses_config_from_json() {
+        let json = r#"{
+            "rename": {"Unavailable": "Down"},
+            "merge": [{"into": "Leader", "pattern": "^Leader"}],
+            "drop_constants": true
+        }"#;
+        let config: RefinementConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(config.rename.get("Unavailable"), Some(&"Down".to_string()));
+        assert_eq!(config.merge.len(), 1);
+        assert!(config.merge[0].states.is_empty());
+        assert!(config.drop_constants);
+        assert!(config.drop_states.is_empty());
+        assert!(config.projection.is_none());
+    }
+}
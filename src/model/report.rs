@@ -0,0 +1,817 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use serde::Serialize;
+
+use super::dwell::{DwellThresholds, dwell_report, format_duration};
+use super::event_graph::{
+    Edge, EventGraph, NodeGraph, build_event_graph_with, build_schedule_graphs,
+};
+use super::event_log::{parse_event_log, parse_nemesis_actions, parse_window_actions};
+use super::identifiers::Identifiers;
+use super::jepsen_store::{checker_validity, top_level_validity};
+use super::layout::svg_for_node_graph;
+use super::refinement::Refinement;
+use super::schedules::{ScheduleContribution, summarize_schedules};
+use super::spec::{SpecMapping, compare_with_spec};
+use super::timeline::xml_escape;
+use super::tlc::SpecGraph;
+
+/// One events log of the campaign, with what is known about the run that
+/// produced it.
+pub struct RunInput {
+    pub name: String,
+    pub log: String,
+    /// Shown as-is in the run table, e.g. schedule and feedback type.
+    pub metadata: BTreeMap<String, String>,
+    /// Content of the run's Jepsen `results.edn`.
+    pub results: Option<String>,
+}
+
+/// Checks that turn a run into a bug candidate.
+#[derive(Default)]
+pub struct Oracles<'a> {
+    pub dwell: DwellThresholds,
+    pub spec: Option<(&'a SpecGraph, &'a SpecMapping)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OracleHit {
+    pub run: String,
+    /// `jepsen`, `spec` or `dwell`.
+    pub oracle: String,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct JepsenVerdict {
+    pub valid: Option<bool>,
+    pub checkers: BTreeMap<String, Option<bool>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ScheduleSummary {
+    pub schedule_id: usize,
+    pub steps: usize,
+    /// Nemesis action of every step, as EDN when the mediator logged it.
+    pub actions: Vec<String>,
+    pub new_states: usize,
+    pub new_edges: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RunSummary {
+    pub name: String,
+    pub metadata: BTreeMap<String, String>,
+    pub events: usize,
+    pub duration_us: Option<u64>,
+    pub nodes: usize,
+    pub states: usize,
+    pub edges: usize,
+    pub jepsen: Option<JepsenVerdict>,
+    pub schedules: Vec<ScheduleSummary>,
+}
+
+/// Distinct states and edges, summed over nodes, after `events` events of
+/// the campaign. A point is recorded every time either count grows.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DiscoveryPoint {
+    pub events: usize,
+    pub states: usize,
+    pub edges: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CampaignReport {
+    pub runs: Vec<RunSummary>,
+    pub graph: EventGraph,
+    /// Union of every node's abstraction.
+    pub global: NodeGraph,
+    pub discovery: Vec<DiscoveryPoint>,
+    /// States and edges per node that the baseline never reached.
+    pub baseline: Option<BaselineDiff>,
+    pub oracle_hits: Vec<OracleHit>,
+}
+
+/// What the campaign reached that the baseline did not, per node.
+#[derive(Debug, Clone, PartialEq, Default, Serialize)]
+pub struct BaselineDiff {
+    pub new_states: BTreeMap<u32, Vec<String>>,
+    pub new_edges: BTreeMap<u32, Vec<Edge>>,
+}
+
+impl BaselineDiff {
+    pub fn new(baseline: &EventGraph, graph: &EventGraph) -> Self {
+        let mut diff = BaselineDiff::default();
+        for (node_id, node) in &graph.nodes {
+            let known = baseline.nodes.get(node_id);
+            let new_states: Vec<String> = node
+                .states
+                .iter()
+                .filter(|s| known.is_none_or(|k| !k.states.contains(s)))
+                .cloned()
+                .collect();
+            if !new_states.is_empty() {
+                diff.new_states.insert(*node_id, new_states);
+            }
+            let new_edges: Vec<Edge> = node
+                .edges
+                .iter()
+                .filter(|e| known.is_none_or(|k| !k.edges.contains(e)))
+                .cloned()
+                .collect();
+            if !new_edges.is_empty() {
+                diff.new_edges.insert(*node_id, new_edges);
+            }
+        }
+        diff
+    }
+
+    pub fn new_state_count(&self) -> usize {
+        self.new_states.values().map(Vec::len).sum()
+    }
+
+    pub fn new_edge_count(&self) -> usize {
+        self.new_edges.values().map(Vec::len).sum()
+    }
+}
+
+pub fn campaign_report(
+    runs: &[RunInput],
+    baseline: Option<&EventGraph>,
+    refinement: &Refinement,
+    oracles: &Oracles,
+) -> CampaignReport {
+    let mut graph = EventGraph::default();
+    let mut summaries = Vec::new();
+    let mut oracle_hits = Vec::new();
+
+    for run in runs {
+        let run_graph = build_event_graph_with(&run.log, refinement);
+        let records = parse_event_log(&run.log);
+        let timestamps: Vec<i64> = records.iter().filter_map(|r| r.timestamp_us).collect();
+        let duration_us = match (timestamps.iter().min(), timestamps.iter().max()) {
+            (Some(first), Some(last)) => Some((last - first) as u64),
+            _ => None,
+        };
+
+        let jepsen = run.results.as_deref().map(|results| JepsenVerdict {
+            valid: top_level_validity(results),
+            checkers: checker_validity(results),
+        });
+        if let Some(jepsen) = &jepsen {
+            for (checker, valid) in &jepsen.checkers {
+                if *valid == Some(false) {
+                    oracle_hits.push(OracleHit {
+                        run: run.name.clone(),
+                        oracle: "jepsen".to_string(),
+                        detail: format!("checker {} found the history invalid", checker),
+                    });
+                }
+            }
+            if jepsen.valid == Some(false) && !jepsen.checkers.values().any(|v| *v == Some(false)) {
+                oracle_hits.push(OracleHit {
+                    run: run.name.clone(),
+                    oracle: "jepsen".to_string(),
+                    detail: "history is invalid".to_string(),
+                });
+            }
+        }
+
+        if let Some((spec, mapping)) = oracles.spec {
+            for unexpected in compare_with_spec(&run_graph, spec, mapping).unexpected_edges {
+                let e = &unexpected.edge;
+                oracle_hits.push(OracleHit {
+                    run: run.name.clone(),
+                    oracle: "spec".to_string(),
+                    detail: format!(
+                        "{} --{}--> {} is not allowed by the spec (node(s) {:?})",
+                        e.from, e.transition, e.to, unexpected.nodes
+                    ),
+                });
+            }
+        }
+        if !oracles.dwell.is_empty() {
+            for outlier in dwell_report(&run.log, refinement, &oracles.dwell).outliers {
+                oracle_hits.push(OracleHit {
+                    run: run.name.clone(),
+                    oracle: "dwell".to_string(),
+                    detail: format!(
+                        "node {} stayed in {} for {} (limit {}), lines {}-{}",
+                        outlier.stay.node_id,
+                        outlier.stay.state,
+                        format_duration(outlier.stay.duration_us),
+                        format_duration(outlier.threshold_us),
+                        outlier.stay.entered_line,
+                        outlier.stay.left_line
+                    ),
+                });
+            }
+        }
+
+        summaries.push(RunSummary {
+            name: run.name.clone(),
+            metadata: run.metadata.clone(),
+            events: records.len(),
+            duration_us,
+            nodes: run_graph.nodes.len(),
+            states: run_graph.nodes.values().map(|n| n.states.len()).sum(),
+            edges: run_graph.nodes.values().map(|n| n.edges.len()).sum(),
+            jepsen,
+            schedules: schedule_summaries(&run.log, refinement),
+        });
+        graph.merge(run_graph);
+    }
+
+    let baseline = baseline.map(|baseline| BaselineDiff::new(baseline, &graph));
+
+    CampaignReport {
+        runs: summaries,
+        global: global_graph(&graph),
+        discovery: discovery_curve(runs, refinement),
+        graph,
+        baseline,
+        oracle_hits,
+    }
+}
+
+fn schedule_summaries(log: &str, refinement: &Refinement) -> Vec<ScheduleSummary> {
+    let graphs = build_schedule_graphs(log, refinement);
+    let contributions: HashMap<usize, ScheduleContribution> = summarize_schedules(&graphs)
+        .into_iter()
+        .map(|c| (c.schedule_id, c))
+        .collect();
+    let edn = parse_nemesis_actions(log);
+
+    let mut steps: BTreeMap<usize, Vec<String>> = BTreeMap::new();
+    for (window, action) in parse_window_actions(log) {
+        steps.entry(window.schedule_id).or_default().push(
+            edn.get(&action)
+                .cloned()
+                .unwrap_or_else(|| action.to_string()),
+        );
+    }
+    for schedule_id in graphs.keys() {
+        steps.entry(*schedule_id).or_default();
+    }
+
+    steps
+        .into_iter()
+        .map(|(schedule_id, actions)| {
+            let contribution = contributions.get(&schedule_id);
+            ScheduleSummary {
+                schedule_id,
+                steps: actions.len(),
+                actions,
+                new_states: contribution.map_or(0, ScheduleContribution::new_state_count),
+                new_edges: contribution.map_or(0, ScheduleContribution::new_edge_count),
+            }
+        })
+        .collect()
+}
+
+/// Merges all nodes into one graph, keeping the initial state of the lowest
+/// node id.
+pub fn global_graph(graph: &EventGraph) -> NodeGraph {
+    let mut ids: Vec<&u32> = graph.nodes.keys().collect();
+    ids.sort_unstable();
+
+    let mut global = EventGraph::default();
+    for id in ids {
+        global.merge(EventGraph {
+            nodes: HashMap::from([(0, graph.nodes[id].clone())]),
+        });
+    }
+    let mut node = global.nodes.remove(&0).unwrap_or_default();
+    node.dwell.clear();
+    node
+}
+
+/// Edges follow the same rule as the graph: consecutive states of a node,
+/// unless the refinement drops the transition.
+fn discovery_curve(runs: &[RunInput], refinement: &Refinement) -> Vec<DiscoveryPoint> {
+    let mut states: HashSet<(u32, String)> = HashSet::new();
+    let mut edges: HashSet<(u32, Edge)> = HashSet::new();
+    let mut points = vec![DiscoveryPoint {
+        events: 0,
+        states: 0,
+        edges: 0,
+    }];
+    let mut events = 0;
+
+    for run in runs {
+        let mut last_for_node: HashMap<u32, (String, String)> = HashMap::new();
+        for rec in parse_event_log(&run.log) {
+            events += 1;
+            let Some(state) = refinement.state(&rec.state, rec.constant) else {
+                continue;
+            };
+            let mut grew = states.insert((rec.node_id, state.clone()));
+            if let Some((from, transition)) = last_for_node.get(&rec.node_id)
+                && refinement.keeps_transition(transition)
+            {
+                grew |= edges.insert((
+                    rec.node_id,
+                    Edge {
+                        from: from.clone(),
+                        transition: transition.clone(),
+                        to: state.clone(),
+                    },
+                ));
+            }
+            last_for_node.insert(rec.node_id, (state, rec.transition));
+            if grew {
+                points.push(DiscoveryPoint {
+                    events,
+                    states: states.len(),
+                    edges: edges.len(),
+                });
+            }
+        }
+    }
+
+    if points.last().is_some_and(|p| p.events != events) {
+        points.push(DiscoveryPoint {
+            events,
+            states: states.len(),
+            edges: edges.len(),
+        });
+    }
+    points
+}
+
+fn sorted_nodes(graph: &EventGraph) -> Vec<(u32, &NodeGraph)> {
+    let mut nodes: Vec<(u32, &NodeGraph)> = graph.nodes.iter().map(|(id, n)| (*id, n)).collect();
+    nodes.sort_unstable_by_key(|(id, _)| *id);
+    nodes
+}
+
+fn validity(valid: Option<bool>) -> &'static str {
+    match valid {
+        Some(true) => "valid",
+        Some(false) => "invalid",
+        None => "unknown",
+    }
+}
+
+fn metadata(run: &RunSummary) -> String {
+    run.metadata
+        .iter()
+        .map(|(k, v)| format!("{}={}", k, v))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// At most `limit` points, evenly spread and always including the last one.
+fn thin(points: &[DiscoveryPoint], limit: usize) -> Vec<&DiscoveryPoint> {
+    if points.len() <= limit {
+        return points.iter().collect();
+    }
+    let step = points.len().div_ceil(limit - 1);
+    let mut thinned: Vec<&DiscoveryPoint> = points.iter().step_by(step).collect();
+    if thinned.last() != points.last().as_ref() {
+        thinned.push(&points[points.len() - 1]);
+    }
+    thinned
+}
+
+fn mermaid_for_node_graph(node: &NodeGraph) -> String {
+    let ids = Identifiers::new(
+        node.states
+            .iter()
+            .chain(&node.initial)
+            .chain(node.edges.iter().flat_map(|e| [&e.from, &e.to]))
+            .map(String::as_str),
+    );
+    let mut out = String::from("```mermaid\nstateDiagram-v2\n");
+    for state in &node.states {
+        out.push_str(&format!(
+            "    state \"{}\" as {}\n",
+            mermaid_escape(state),
+            ids.id(state)
+        ));
+    }
+    if let Some(initial) = &node.initial {
+        out.push_str(&format!("    [*] --> {}\n", ids.id(initial)));
+    }
+    for edge in &node.edges {
+        out.push_str(&format!(
+            "    {} --> {} : {}\n",
+            ids.id(&edge.from),
+            ids.id(&edge.to),
+            mermaid_escape(&edge.transition)
+        ));
+    }
+    out.push_str("```\n");
+    out
+}
+
+/// Mermaid entity codes for the characters that end or quote a label.
+fn mermaid_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '#' => out.push_str("#35;"),
+            '"' => out.push_str("#quot;"),
+            '|' => out.push_str("#124;"),
+            ';' => out.push_str("#59;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+pub fn markdown_for_report(report: &CampaignReport) -> String {
+    let mut out = String::from("# Campaign report\n\n");
+
+    out.push_str("## Runs\n\n");
+    out.push_str("| Run | Metadata | Events | Duration | Nodes | States | Edges | Jepsen |\n");
+    out.push_str("|---|---|---|---|---|---|---|---|\n");
+    for run in &report.runs {
+        out.push_str(&format!(
+            "| {} | {} | {} | {} | {} | {} | {} | {} |\n",
+            run.name,
+            metadata(run),
+            run.events,
+            run.duration_us.map(format_duration).unwrap_or_default(),
+            run.nodes,
+            run.states,
+            run.edges,
+            run.jepsen.as_ref().map_or("", |j| validity(j.valid))
+        ));
+    }
+
+    out.push_str("\n## Oracle hits\n\n");
+    if report.oracle_hits.is_empty() {
+        out.push_str("No oracle flagged any run.\n");
+    }
+    for hit in &report.oracle_hits {
+        out.push_str(&format!(
+            "- **{}** ({}): {}\n",
+            hit.oracle, hit.run, hit.detail
+        ));
+    }
+
+    out.push_str("\n## Discovery\n\n| Events | States | Edges |\n|---|---|---|\n");
+    for point in thin(&report.discovery, 20) {
+        out.push_str(&format!(
+            "| {} | {} | {} |\n",
+            point.events, point.states, point.edges
+        ));
+    }
+
+    if let Some(baseline) = &report.baseline {
+        out.push_str(&format!(
+            "\n## Compared to the baseline\n\n{} new state(s), {} new edge(s).\n\n",
+            baseline.new_state_count(),
+            baseline.new_edge_count()
+        ));
+        for (node_id, states) in &baseline.new_states {
+            out.push_str(&format!(
+                "- Node {}: state {}\n",
+                node_id,
+                states.join(", ")
+            ));
+        }
+        for (node_id, edges) in &baseline.new_edges {
+            for e in edges {
+                out.push_str(&format!(
+                    "- Node {}: {} --{}--> {}\n",
+                    node_id, e.from, e.transition, e.to
+                ));
+            }
+        }
+    }
+
+    out.push_str("\n## Nemesis schedules\n");
+    for run in &report.runs {
+        if run.schedules.is_empty() {
+            continue;
+        }
+        out.push_str(&format!(
+            "\n### {}\n\n| Schedule | Steps | Actions | New states | New edges |\n|---|---|---|---|---|\n",
+            run.name
+        ));
+        for schedule in &run.schedules {
+            out.push_str(&format!(
+                "| {} | {} | {} | {} | {} |\n",
+                schedule.schedule_id,
+                schedule.steps,
+                schedule.actions.join(" → ").replace('|', "\\|"),
+                schedule.new_states,
+                schedule.new_edges
+            ));
+        }
+    }
+
+    out.push_str(&format!(
+        "\n## Global abstraction\n\n{} state(s), {} edge(s) over all nodes.\n\n",
+        report.global.states.len(),
+        report.global.edges.len()
+    ));
+    out.push_str(&mermaid_for_node_graph(&report.global));
+    for (node_id, node) in sorted_nodes(&report.graph) {
+        out.push_str(&format!(
+            "\n## Node {}\n\n{} state(s), {} edge(s).\n\n",
+            node_id,
+            node.states.len(),
+            node.edges.len()
+        ));
+        out.push_str(&mermaid_for_node_graph(node));
+    }
+    out
+}
+
+fn svg_for_discovery(points: &[DiscoveryPoint]) -> String {
+    const WIDTH: f64 = 600.0;
+    const HEIGHT: f64 = 200.0;
+    const PAD: f64 = 30.0;
+    let last = points.last().map_or(1, |p| p.events.max(1)) as f64;
+    let top = points
+        .iter()
+        .map(|p| p.states.max(p.edges))
+        .max()
+        .unwrap_or(0)
+        .max(1) as f64;
+    let x = |events: usize| PAD + events as f64 * (WIDTH - 2.0 * PAD) / last;
+    let y = |count: usize| HEIGHT - PAD - count as f64 * (HEIGHT - 2.0 * PAD) / top;
+
+    // Counts only grow at the recorded points, so the curve is a staircase.
+    let line = |count: fn(&DiscoveryPoint) -> usize| {
+        let mut path = String::new();
+        for (i, point) in points.iter().enumerate() {
+            if i > 0 {
+                path.push_str(&format!(
+                    "{:.1},{:.1} ",
+                    x(point.events),
+                    y(count(&points[i - 1]))
+                ));
+            }
+            path.push_str(&format!("{:.1},{:.1} ", x(point.events), y(count(point))));
+        }
+        path
+    };
+
+    let mut out = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" font-family=\"sans-serif\" font-size=\"11\">\n",
+        WIDTH, HEIGHT
+    );
+    out.push_str(&format!(
+        "  <polyline points=\"{}\" fill=\"none\" stroke=\"#3b7dd8\" stroke-width=\"2\"/>\n",
+        line(|p| p.states)
+    ));
+    out.push_str(&format!(
+        "  <polyline points=\"{}\" fill=\"none\" stroke=\"#d8703b\" stroke-width=\"2\"/>\n",
+        line(|p| p.edges)
+    ));
+    out.push_str(&format!(
+        "  <line x1=\"{p}\" y1=\"{b}\" x2=\"{r}\" y2=\"{b}\" stroke=\"#333\"/>\n  <line x1=\"{p}\" y1=\"{p}\" x2=\"{p}\" y2=\"{b}\" stroke=\"#333\"/>\n",
+        p = PAD,
+        b = HEIGHT - PAD,
+        r = WIDTH - PAD
+    ));
+    out.push_str(&format!(
+        "  <text x=\"{}\" y=\"{}\" text-anchor=\"end\">{} events</text>\n  <text x=\"2\" y=\"{}\">{}</text>\n",
+        WIDTH - PAD,
+        HEIGHT - 8.0,
+        last,
+        PAD,
+        top
+    ));
+    out.push_str(&format!(
+        "  <text x=\"{x}\" y=\"14\" fill=\"#3b7dd8\">states</text>\n  <text x=\"{x2}\" y=\"14\" fill=\"#d8703b\">edges</text>\n",
+        x = PAD + 10.0,
+        x2 = PAD + 60.0
+    ));
+    out.push_str("</svg>\n");
+    out
+}
+
+pub fn html_for_report(report: &CampaignReport) -> String {
+    let mut out = String::new();
+    out.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    out.push_str("<title>Campaign report</title>\n");
+    out.push_str("<style>table{border-collapse:collapse}td,th{border:1px solid #ccc;padding:2px 6px}</style>\n");
+    out.push_str("</head>\n<body>\n<h1>Campaign report</h1>\n");
+
+    out.push_str("<h2>Runs</h2>\n<table>\n<tr><th>Run</th><th>Metadata</th><th>Events</th><th>Duration</th><th>Nodes</th><th>States</th><th>Edges</th><th>Jepsen</th></tr>\n");
+    for run in &report.runs {
+        out.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+            xml_escape(&run.name),
+            xml_escape(&metadata(run)),
+            run.events,
+            run.duration_us.map(format_duration).unwrap_or_default(),
+            run.nodes,
+            run.states,
+            run.edges,
+            run.jepsen.as_ref().map_or("", |j| validity(j.valid))
+        ));
+    }
+    out.push_str("</table>\n");
+
+    out.push_str("<h2>Oracle hits</h2>\n");
+    if report.oracle_hits.is_empty() {
+        out.push_str("<p>No oracle flagged any run.</p>\n");
+    } else {
+        out.push_str("<ul>\n");
+        for hit in &report.oracle_hits {
+            out.push_str(&format!(
+                "<li><b>{}</b> ({}): {}</li>\n",
+                xml_escape(&hit.oracle),
+                xml_escape(&hit.run),
+                xml_escape(&hit.detail)
+            ));
+        }
+        out.push_str("</ul>\n");
+    }
+
+    out.push_str("<h2>Discovery</h2>\n");
+    out.push_str(&svg_for_discovery(&report.discovery));
+
+    if let Some(baseline) = &report.baseline {
+        out.push_str(&format!(
+            "<h2>Compared to the baseline</h2>\n<p>{} new state(s), {} new edge(s).</p>\n<ul>\n",
+            baseline.new_state_count(),
+            baseline.new_edge_count()
+        ));
+        for (node_id, states) in &baseline.new_states {
+            out.push_str(&format!(
+                "<li>Node {}: state {}</li>\n",
+                node_id,
+                xml_escape(&states.join(", "))
+            ));
+        }
+        for (node_id, edges) in &baseline.new_edges {
+            for e in edges {
+                out.push_str(&format!(
+                    "<li>Node {}: {}</li>\n",
+                    node_id,
+                    xml_escape(&format!("{} --{}--> {}", e.from, e.transition, e.to))
+                ));
+            }
+        }
+        out.push_str("</ul>\n");
+    }
+
+    out.push_str("<h2>Nemesis schedules</h2>\n");
+    for run in &report.runs {
+        if run.schedules.is_empty() {
+            continue;
+        }
+        out.push_str(&format!(
+            "<h3>{}</h3>\n<table>\n<tr><th>Schedule</th><th>Steps</th><th>Actions</th><th>New states</th><th>New edges</th></tr>\n",
+            xml_escape(&run.name)
+        ));
+        for schedule in &run.schedules {
+            out.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                schedule.schedule_id,
+                schedule.steps,
+                xml_escape(&schedule.actions.join(" → ")),
+                schedule.new_states,
+                schedule.new_edges
+            ));
+        }
+        out.push_str("</table>\n");
+    }
+
+    out.push_str(&format!(
+        "<h2>Global abstraction</h2>\n<p>{} state(s), {} edge(s) over all nodes.</p>\n",
+        report.global.states.len(),
+        report.global.edges.len()
+    ));
+    out.push_str(&svg_for_node_graph(&report.global, None));
+    for (node_id, node) in sorted_nodes(&report.graph) {
+        out.push_str(&format!(
+            "<h2>Node {}</h2>\n<p>{} state(s), {} edge(s).</p>\n",
+            node_id,
+            node.states.len(),
+            node.edges.len()
+        ));
+        out.push_str(&svg_for_node_graph(node, None));
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::build_event_graph;
+
+    const LOG: &str = "[2025-11-10 19:56:55.000000][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 1 / 1] FunctionExecute 1 @ FunctionName boot @ state Follower
+[2025-11-10 19:56:55.000000][INFO] [FUNC_EVENT_TYPE][Node 2 Batch 1 Entry 1 / 1] FunctionExecute 1 @ FunctionName boot @ state Follower
+[2025-11-10 19:56:55.100000][INFO] [WINDOW][Schedule 0 Step 0] Action 2
+[2025-11-10 19:56:55.100000][INFO] [NEMESIS] (Start schedule)  Generated action id {:f :start-partition, :value (:majority)} (2) at step 0.
+[2025-11-10 19:56:55.200000][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 2 Entry 1 / 1] FunctionExecute 2 @ FunctionName electionTimeout @ state Candidate
+[2025-11-10 19:56:55.300000][INFO] [WINDOW][Schedule 0 Step 1] Action 0
+[2025-11-10 19:56:58.300000][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 3 Entry 1 / 1] FunctionExecute 3 @ FunctionName winElection @ state Leader";
+
+    fn run(results: Option<&str>) -> RunInput {
+        RunInput {
+            name: "dqlite/20251110".to_string(),
+            log: LOG.to_string(),
+            metadata: BTreeMap::from([("schedule".to_string(), "qlearning".to_string())]),
+            results: results.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn summarizes_runs_schedules_and_discovery() {
+        let report = campaign_report(
+            &[run(None)],
+            None,
+            &Refinement::identity(),
+            &Oracles::default(),
+        );
+
+        let summary = &report.runs[0];
+        assert_eq!(summary.events, 4);
+        assert_eq!(summary.duration_us, Some(3_300_000));
+        assert_eq!((summary.nodes, summary.states, summary.edges), (2, 4, 2));
+        assert_eq!(summary.schedules.len(), 1);
+        assert_eq!(summary.schedules[0].steps, 2);
+        assert_eq!(
+            summary.schedules[0].actions,
+            vec!["{:f :start-partition, :value (:majority)}", "0"]
+        );
+        assert_eq!(summary.schedules[0].new_states, 2);
+
+        assert_eq!(
+            report.global.states,
+            vec!["Candidate", "Follower", "Leader"]
+        );
+        assert_eq!(report.global.initial.as_deref(), Some("Follower"));
+        let last = report.discovery.last().unwrap();
+        assert_eq!((last.events, last.states, last.edges), (4, 4, 2));
+        assert_eq!(report.discovery.len(), 5);
+    }
+
+    #[test]
+    fn reports_baseline_differences_and_oracle_hits() {
+        let baseline = build_event_graph(&LOG[..LOG.find("[WINDOW]").unwrap()]);
+        let oracles = Oracles {
+            dwell: DwellThresholds::parse(&["Candidate=1s".to_string()]).unwrap(),
+            spec: None,
+        };
+        let report = campaign_report(
+            &[run(Some(
+                "{:workload {:valid? false}, :perf {:valid? true}, :valid? false}",
+            ))],
+            Some(&baseline),
+            &Refinement::identity(),
+            &oracles,
+        );
+
+        let baseline = report.baseline.as_ref().unwrap();
+        assert_eq!(baseline.new_state_count(), 2);
+        assert_eq!(baseline.new_edge_count(), 2);
+
+        let hits: Vec<(&str, &str)> = report
+            .oracle_hits
+            .iter()
+            .map(|h| (h.oracle.as_str(), h.detail.as_str()))
+            .collect();
+        assert_eq!(hits.len(), 2);
+        assert_eq!(
+            hits[0],
+            ("jepsen", "checker workload found the history invalid")
+        );
+        assert_eq!(hits[1].0, "dwell");
+    }
+
+    #[test]
+    fn escapes_mermaid_labels() {
+        let log = "[2025-11-10 19:56:55.000001][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 1 / 2] FunctionExecute 1 @ FunctionName a|b @ state say \"hi\"
+[2025-11-10 19:56:55.000002][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 2 / 2] FunctionExecute 2 @ FunctionName c @ state s1";
+        let graph = build_event_graph(log);
+        let mermaid = mermaid_for_node_graph(&graph.nodes[&1]);
+
+        assert!(mermaid.contains("    state \"say #quot;hi#quot;\" as say__hi_\n"));
+        assert!(mermaid.contains("    say__hi_ --> s1 : a#124;b\n"));
+    }
+
+    #[test]
+    fn renders_markdown_and_html() {
+        let report = campaign_report(
+            &[run(Some("{:valid? true}"))],
+            None,
+            &Refinement::identity(),
+            &Oracles::default(),
+        );
+
+        let markdown = markdown_for_report(&report);
+        assert!(
+            markdown.contains(
+                "| dqlite/20251110 | schedule=qlearning | 4 | 3.30s | 2 | 4 | 2 | valid |"
+            )
+        );
+        assert!(markdown.contains("stateDiagram-v2"));
+        assert!(markdown.contains("    Follower --> Candidate : boot"));
+        assert!(markdown.contains("## Node 2"));
+
+        let html = html_for_report(&report);
+        assert!(html.contains("<h2>Discovery</h2>\n<svg "));
+        assert_eq!(html.matches("<svg ").count(), 4);
+    }
+}
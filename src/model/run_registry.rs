@@ -0,0 +1,389 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+pub const REGISTRY_DIR: &str = ".abstraktor/runs";
+const MANIFEST_FILE: &str = "manifest.json";
+/// Subdirectory of a run directory that holds the copied artifacts.
+pub const ARTIFACTS_DIR: &str = "artifacts";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RunStatus {
+    /// Still going, or killed before it could record how it ended.
+    Running,
+    Succeeded,
+    Failed,
+}
+
+impl RunStatus {
+    pub fn name(self) -> &'static str {
+        match self {
+            RunStatus::Running => "running",
+            RunStatus::Succeeded => "succeeded",
+            RunStatus::Failed => "failed",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SutInfo {
+    pub path: String,
+    /// `git rev-parse HEAD`, with `-dirty` when the tree has changes. None
+    /// when the SUT is not the top of a git repository of its own.
+    pub revision: Option<String>,
+}
+
+/// What a `run` or `export-graphs` invocation was given and how it ended,
+/// kept in `<registry>/<id>/manifest.json`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RunManifest {
+    pub id: String,
+    pub command: String,
+    /// The parsed command-line options.
+    pub args: String,
+    pub sut: Option<SutInfo>,
+    /// Fingerprint of the annotated sources the targets come from.
+    pub targets_hash: Option<String>,
+    pub mediator_config: Option<String>,
+    pub mediator_config_hash: Option<String>,
+    /// `*SEED` environment variables at the start of the run.
+    pub seeds: BTreeMap<String, String>,
+    /// Unix time in seconds.
+    pub started: u64,
+    pub finished: Option<u64>,
+    pub status: RunStatus,
+    pub error: Option<String>,
+    /// Files and directories the run wrote outside its run directory.
+    pub artifacts: Vec<String>,
+    /// Where in the run directory each artifact that existed once the run
+    /// ended was copied to.
+    #[serde(default)]
+    pub copies: BTreeMap<String, String>,
+    /// Process that recorded the run, to tell a run still going from one
+    /// that was killed. Missing from manifests written before it was kept.
+    #[serde(default)]
+    pub pid: Option<u32>,
+}
+
+impl RunManifest {
+    pub fn new(command: &str, args: String, started: u64) -> Self {
+        RunManifest {
+            id: String::new(),
+            command: command.to_string(),
+            args,
+            sut: None,
+            targets_hash: None,
+            mediator_config: None,
+            mediator_config_hash: None,
+            seeds: BTreeMap::new(),
+            started,
+            finished: None,
+            status: RunStatus::Running,
+            error: None,
+            artifacts: Vec::new(),
+            copies: BTreeMap::new(),
+            pid: None,
+        }
+    }
+
+    pub fn finish(&mut self, finished: u64, error: Option<String>) {
+        self.finished = Some(finished);
+        self.status = if error.is_some() {
+            RunStatus::Failed
+        } else {
+            RunStatus::Succeeded
+        };
+        self.error = error;
+    }
+
+    /// Marked running, but the process that recorded it is gone.
+    pub fn is_stale(&self) -> bool {
+        self.status == RunStatus::Running
+            && self
+                .pid
+                .is_none_or(|pid| !Path::new(&format!("/proc/{}", pid)).exists())
+    }
+
+    pub fn duration_secs(&self) -> Option<u64> {
+        self.finished
+            .map(|finished| finished.saturating_sub(self.started))
+    }
+}
+
+pub struct RunRegistry {
+    pub root: PathBuf,
+}
+
+impl RunRegistry {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        RunRegistry { root: root.into() }
+    }
+
+    pub fn dir(&self, id: &str) -> PathBuf {
+        self.root.join(id)
+    }
+
+    /// Gives the manifest an id from its start time and command, creates its
+    /// directory and writes it.
+    pub fn create(&self, manifest: &mut RunManifest) -> Result<PathBuf> {
+        let base = format!(
+            "{}-{}",
+            compact_timestamp(manifest.started),
+            manifest.command.replace(' ', "-")
+        );
+        let mut id = base.clone();
+        let mut n = 2;
+        while self.dir(&id).exists() {
+            id = format!("{}-{}", base, n);
+            n += 1;
+        }
+        manifest.id = id;
+        let dir = self.dir(&manifest.id);
+        fs::create_dir_all(&dir).with_context(|| format!("creating {}", dir.display()))?;
+        self.save(manifest)?;
+        Ok(dir)
+    }
+
+    pub fn save(&self, manifest: &RunManifest) -> Result<()> {
+        let path = self.dir(&manifest.id).join(MANIFEST_FILE);
+        let content = serde_json::to_string_pretty(manifest)?;
+        fs::write(&path, content).with_context(|| format!("writing {}", path.display()))
+    }
+
+    /// Every recorded run, oldest first. A missing registry has none.
+    pub fn list(&self) -> Result<Vec<RunManifest>> {
+        if !self.root.exists() {
+            return Ok(Vec::new());
+        }
+        let mut runs = Vec::new();
+        let entries = fs::read_dir(&self.root)
+            .with_context(|| format!("reading run registry {}", self.root.display()))?;
+        for entry in entries.flatten() {
+            let path = entry.path().join(MANIFEST_FILE);
+            if !path.is_file() {
+                continue;
+            }
+            let content =
+                fs::read_to_string(&path).with_context(|| format!("reading {}", path.display()))?;
+            let manifest: RunManifest = serde_json::from_str(&content)
+                .with_context(|| format!("parsing {}", path.display()))?;
+            runs.push(manifest);
+        }
+        runs.sort_by(|a, b| a.started.cmp(&b.started).then_with(|| a.id.cmp(&b.id)));
+        Ok(runs)
+    }
+
+    /// A run by id, unique id prefix, or `latest`.
+    pub fn find(&self, selector: &str) -> Result<RunManifest> {
+        let runs = self.list()?;
+        if selector == "latest" {
+            return runs.into_iter().last().context("no runs recorded");
+        }
+        if let Some(run) = runs.iter().find(|run| run.id == selector) {
+            return Ok(run.clone());
+        }
+        let matches: Vec<_> = runs
+            .into_iter()
+            .filter(|run| run.id.starts_with(selector))
+            .collect();
+        match matches.len() {
+            0 => anyhow::bail!("no run matches {}", selector),
+            1 => Ok(matches.into_iter().next().unwrap()),
+            n => anyhow::bail!("{} runs match {}; give more of the id", n, selector),
+        }
+    }
+
+    pub fn remove(&self, id: &str) -> Result<()> {
+        let dir = self.dir(id);
+        fs::remove_dir_all(&dir).with_context(|| format!("removing {}", dir.display()))
+    }
+}
+
+/// Which runs `runs gc` prunes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct GcPolicy {
+    /// Keep the newest N runs whatever else applies.
+    pub keep: Option<usize>,
+    pub older_than_secs: Option<u64>,
+    pub failed_only: bool,
+}
+
+/// Runs to remove from `runs`, which is ordered oldest first. Runs still
+/// marked running are only picked once they are stale, as they may not have
+/// ended otherwise.
+pub fn gc_candidates(runs: &[RunManifest], policy: GcPolicy, now: u64) -> Vec<&RunManifest> {
+    let protected = policy.keep.unwrap_or(0).min(runs.len());
+    runs[..runs.len() - protected]
+        .iter()
+        .filter(|run| run.status != RunStatus::Running || run.is_stale())
+        .filter(|run| !policy.failed_only || run.status == RunStatus::Failed)
+        .filter(|run| {
+            policy
+                .older_than_secs
+                .is_none_or(|age| now.saturating_sub(run.started) >= age)
+        })
+        .collect()
+}
+
+/// Fields that differ between two manifests, with both values as JSON.
+pub fn manifest_diff(a: &RunManifest, b: &RunManifest) -> Vec<(String, String, String)> {
+    let (Ok(serde_json::Value::Object(a)), Ok(serde_json::Value::Object(b))) =
+        (serde_json::to_value(a), serde_json::to_value(b))
+    else {
+        return Vec::new();
+    };
+    a.iter()
+        .filter(|(key, _)| key.as_str() != "id")
+        .filter(|(key, value)| b.get(*key) != Some(*value))
+        .map(|(key, value)| {
+            let other = b.get(key).map(|v| v.to_string()).unwrap_or_default();
+            (key.clone(), value.to_string(), other)
+        })
+        .collect()
+}
+
+/// `YYYY-MM-DD HH:MM:SS` in UTC.
+pub fn format_timestamp(secs: u64) -> String {
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    let rem = secs % 86_400;
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+fn compact_timestamp(secs: u64) -> String {
+    format_timestamp(secs)
+        .replace(['-', ':'], "")
+        .replace(' ', "-")
+}
+
+/// Howard Hinnant's `civil_from_days`: days since 1970-01-01 to a date.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn manifest(command: &str, started: u64, status: RunStatus) -> RunManifest {
+        let mut manifest = RunManifest::new(command, String::new(), started);
+        manifest.status = status;
+        manifest.pid = Some(std::process::id());
+        manifest
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00:00");
+        assert_eq!(format_timestamp(951_782_400), "2000-02-29 00:00:00");
+        assert_eq!(format_timestamp(1_792_330_245), "2026-10-18 13:30:45");
+        assert_eq!(compact_timestamp(1_792_330_245), "20261018-133045");
+    }
+
+    #[test]
+    fn test_registry_create_list_find() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry = RunRegistry::new(temp_dir.path().join("runs"));
+        assert!(registry.list().unwrap().is_empty());
+
+        let mut first = RunManifest::new("run mallory", "args".to_string(), 1_792_330_245);
+        registry.create(&mut first).unwrap();
+        let mut second = RunManifest::new("run mallory", "args".to_string(), 1_792_330_245);
+        registry.create(&mut second).unwrap();
+        second.finish(1_792_330_300, Some("boom".to_string()));
+        registry.save(&second).unwrap();
+
+        assert_eq!(first.id, "20261018-133045-run-mallory");
+        assert_eq!(second.id, "20261018-133045-run-mallory-2");
+        assert!(registry.dir(&first.id).join(MANIFEST_FILE).is_file());
+
+        let runs = registry.list().unwrap();
+        assert_eq!(runs.len(), 2);
+        assert_eq!(registry.find("latest").unwrap().status, RunStatus::Failed);
+        assert_eq!(registry.find(&second.id).unwrap().duration_secs(), Some(55));
+        assert!(registry.find("2026").is_err());
+        assert!(registry.find("1999").is_err());
+
+        let diff = manifest_diff(&first, &second);
+        let fields: Vec<_> = diff.iter().map(|(field, _, _)| field.as_str()).collect();
+        assert_eq!(fields, ["error", "finished", "status"]);
+    }
+
+    #[test]
+    fn test_gc_candidates() {
+        let runs = [
+            manifest("export-graphs", 100, RunStatus::Succeeded),
+            manifest("run mallory", 200, RunStatus::Failed),
+            manifest("run mallory", 300, RunStatus::Running),
+            manifest("export-graphs", 400, RunStatus::Succeeded),
+        ];
+        let started = |picked: Vec<&RunManifest>| -> Vec<u64> {
+            picked.iter().map(|run| run.started).collect()
+        };
+
+        let all = GcPolicy::default();
+        assert_eq!(started(gc_candidates(&runs, all, 500)), [100, 200, 400]);
+        let keep = GcPolicy {
+            keep: Some(1),
+            ..all
+        };
+        assert_eq!(started(gc_candidates(&runs, keep, 500)), [100, 200]);
+        let failed = GcPolicy {
+            failed_only: true,
+            ..all
+        };
+        assert_eq!(started(gc_candidates(&runs, failed, 500)), [200]);
+        let old = GcPolicy {
+            older_than_secs: Some(350),
+            ..all
+        };
+        assert_eq!(started(gc_candidates(&runs, old, 500)), [100]);
+        let keep_all = GcPolicy {
+            keep: Some(10),
+            ..all
+        };
+        assert!(gc_candidates(&runs, keep_all, 500).is_empty());
+    }
+
+    #[test]
+    fn test_gc_picks_stale_running_runs() {
+        let mut killed = manifest("run mallory", 100, RunStatus::Running);
+        killed.pid = Some(u32::MAX);
+        let mut unrecorded = manifest("run mallory", 200, RunStatus::Running);
+        unrecorded.pid = None;
+        let going = manifest("run mallory", 300, RunStatus::Running);
+        assert!(killed.is_stale() && unrecorded.is_stale() && !going.is_stale());
+
+        let runs = [killed, unrecorded, going];
+        let picked: Vec<u64> = gc_candidates(&runs, GcPolicy::default(), 500)
+            .iter()
+            .map(|run| run.started)
+            .collect();
+        assert_eq!(picked, [100, 200]);
+
+        let legacy = r#"{"id":"x","command":"run mallory","args":"","sut":null,"targets_hash":null,"mediator_config":null,"mediator_config_hash":null,"seeds":{},"started":1,"finished":null,"status":"running","error":null,"artifacts":[]}"#;
+        let legacy: RunManifest = serde_json::from_str(legacy).unwrap();
+        assert_eq!(legacy.pid, None);
+    }
+}
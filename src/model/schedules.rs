@@ -0,0 +1,126 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use super::event_graph::{Edge, EventGraph};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub struct ScheduleContribution {
+    pub schedule_id: usize,
+    pub states: usize,
+    pub edges: usize,
+    pub new_states: BTreeMap<u32, Vec<String>>,
+    pub new_edges: BTreeMap<u32, Vec<Edge>>,
+}
+
+impl ScheduleContribution {
+    pub fn is_productive(&self) -> bool {
+        !self.new_states.is_empty() || !self.new_edges.is_empty()
+    }
+
+    pub fn new_state_count(&self) -> usize {
+        self.new_states.values().map(Vec::len).sum()
+    }
+
+    pub fn new_edge_count(&self) -> usize {
+        self.new_edges.values().map(Vec::len).sum()
+    }
+}
+
+/// Compares every schedule against the union of the schedules that ran before
+/// it, in schedule id order.
+pub fn summarize_schedules(graphs: &BTreeMap<usize, EventGraph>) -> Vec<ScheduleContribution> {
+    let mut seen_states: HashMap<u32, HashSet<String>> = HashMap::new();
+    let mut seen_edges: HashMap<u32, HashSet<Edge>> = HashMap::new();
+    let mut summary = Vec::new();
+
+    for (schedule_id, graph) in graphs {
+        let mut contribution = ScheduleContribution {
+            schedule_id: *schedule_id,
+            ..Default::default()
+        };
+
+        for (node_id, node) in &graph.nodes {
+            contribution.states += node.states.len();
+            contribution.edges += node.edges.len();
+
+            let node_states = seen_states.entry(*node_id).or_default();
+            let new_states: Vec<String> = node
+                .states
+                .iter()
+                .filter(|s| node_states.insert((*s).clone()))
+                .cloned()
+                .collect();
+            if !new_states.is_empty() {
+                contribution.new_states.insert(*node_id, new_states);
+            }
+
+            let node_edges = seen_edges.entry(*node_id).or_default();
+            let new_edges: Vec<Edge> = node
+                .edges
+                .iter()
+                .filter(|e| node_edges.insert((*e).clone()))
+                .cloned()
+                .collect();
+            if !new_edges.is_empty() {
+                contribution.new_edges.insert(*node_id, new_edges);
+            }
+        }
+
+        summary.push(contribution);
+    }
+
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::event_graph::build_schedule_graphs;
+    use crate::model::refinement::Refinement;
+
+    #[test]
+    fn reports_new_states_and_edges_per_schedule() {
+        let log = "[2025-11-10 19:56:55.000001][INFO] [WINDOW][Schedule 0 Step 0] Action 1
+[2025-11-10 19:56:55.000002][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 1 / 2] FunctionExecute 1 @ FunctionName a @ state s0
+[2025-11-10 19:56:55.000003][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 2 / 2] FunctionExecute 2 @ FunctionName b @ state s1
+[2025-11-10 19:56:55.000004][INFO] [WINDOW][Schedule 1 Step 0] Action 0
+[2025-11-10 19:56:55.000005][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 2 Entry 1 / 1] FunctionExecute 3 @ FunctionName a @ state s0
+[2025-11-10 19:56:55.000006][INFO] [WINDOW][Schedule 2 Step 0] Action 2
+[2025-11-10 19:56:55.000007][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 3 Entry 1 / 2] FunctionExecute 4 @ FunctionName a @ state s1
+[2025-11-10 19:56:55.000008][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 3 Entry 2 / 2] FunctionExecute 5 @ FunctionName c @ state s2";
+        let graphs = build_schedule_graphs(log, &Refinement::identity());
+        let summary = summarize_schedules(&graphs);
+
+        assert_eq!(summary.len(), 3);
+
+        assert_eq!(summary[0].new_state_count(), 2);
+        assert_eq!(summary[0].new_edge_count(), 1);
+
+        // s1 -b-> s0 crosses into schedule 1 and has not been seen before.
+        assert_eq!(summary[1].new_state_count(), 0);
+        assert_eq!(summary[1].new_edge_count(), 1);
+        assert!(summary[1].is_productive());
+
+        assert_eq!(summary[2].new_states.get(&1), Some(&vec!["s2".to_string()]));
+        assert_eq!(summary[2].new_edge_count(), 1);
+        assert_eq!(summary[2].new_edges[&1][0].transition, "a");
+        assert_eq!(summary[2].states, 2);
+    }
+
+    #[test]
+    fn unproductive_schedules_are_flagged() {
+        let log = "[2025-11-10 19:56:55.000001][INFO] [WINDOW][Schedule 0 Step 0] Action 1
+[2025-11-10 19:56:55.000002][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 1 / 2] FunctionExecute 1 @ FunctionName a @ state s0
+[2025-11-10 19:56:55.000003][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 2 / 2] FunctionExecute 2 @ FunctionName a @ state s0
+[2025-11-10 19:56:55.000004][INFO] [WINDOW][Schedule 1 Step 0] Action 0
+[2025-11-10 19:56:55.000005][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 2 Entry 1 / 1] FunctionExecute 3 @ FunctionName a @ state s0";
+        let graphs = build_schedule_graphs(log, &Refinement::identity());
+        let summary = summarize_schedules(&graphs);
+
+        assert!(summary[0].is_productive());
+        assert!(!summary[1].is_productive());
+        assert_eq!(summary[1].states, 1);
+        assert_eq!(summary[1].edges, 1);
+    }
+}
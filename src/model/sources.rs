@@ -0,0 +1,248 @@
+use serde::{Deserialize, Serialize};
+
+use std::collections::BTreeMap;
+
+use super::coverage::{Manifest, ManifestEntry};
+use super::dwell::{DwellStats, format_duration};
+use super::event_graph::{Edge, NodeGraph};
+use super::layout::svg_for_node_graph;
+
+/// Resolves transition labels to the annotations that produced them.
+#[derive(Debug, Clone, Default)]
+pub struct SourceLinks {
+    pub manifest: Manifest,
+    /// `{file}`, `{line}` and `{function}` are replaced by the location, e.g.
+    /// `https://github.com/canonical/dqlite/blob/main/{file}#L{line}`.
+    pub url_template: Option<String>,
+}
+
+impl SourceLinks {
+    pub fn locations(&self, node: &NodeGraph, transition: &str) -> Vec<&ManifestEntry> {
+        node.transition_events
+            .get(transition)
+            .into_iter()
+            .flatten()
+            .filter_map(|id| self.manifest.get(*id))
+            .collect()
+    }
+
+    pub fn url(&self, entry: &ManifestEntry) -> Option<String> {
+        let template = self.url_template.as_ref()?;
+        Some(
+            template
+                .replace("{file}", &entry.file)
+                .replace("{line}", &entry.line.to_string())
+                .replace("{function}", &entry.function),
+        )
+    }
+}
+
+pub fn describe_location(entry: &ManifestEntry) -> String {
+    format!("{}:{} ({})", entry.file, entry.line, entry.function)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceLocation {
+    pub id: u32,
+    pub file: String,
+    pub line: usize,
+    pub function: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourcedEdge {
+    #[serde(flatten)]
+    pub edge: Edge,
+    pub event_ids: Vec<u32>,
+    pub sources: Vec<SourceLocation>,
+}
+
+/// A node graph whose edges carry the annotations behind their transitions.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourcedNodeGraph {
+    pub node_id: u32,
+    pub states: Vec<String>,
+    pub initial: Option<String>,
+    pub edges: Vec<SourcedEdge>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub dwell: BTreeMap<String, DwellStats>,
+}
+
+pub fn sourced_node_graph(
+    node_id: u32,
+    node: &NodeGraph,
+    sources: Option<&SourceLinks>,
+) -> SourcedNodeGraph {
+    let edges = node
+        .edges
+        .iter()
+        .map(|edge| SourcedEdge {
+            edge: edge.clone(),
+            event_ids: node
+                .transition_events
+                .get(&edge.transition)
+                .cloned()
+                .unwrap_or_default(),
+            sources: sources
+                .map(|s| {
+                    s.locations(node, &edge.transition)
+                        .into_iter()
+                        .map(|e| SourceLocation {
+                            id: e.id,
+                            file: e.file.clone(),
+                            line: e.line,
+                            function: e.function.clone(),
+                            url: s.url(e),
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+        })
+        .collect();
+
+    SourcedNodeGraph {
+        node_id,
+        states: node.states.clone(),
+        initial: node.initial.clone(),
+        edges,
+        dwell: node.dwell.clone(),
+    }
+}
+
+/// A standalone page drawing the node's graph and listing its transitions,
+/// each linked to the annotation that produced it.
+pub fn html_for_node_graph(
+    node_id: u32,
+    node: &NodeGraph,
+    sources: Option<&SourceLinks>,
+) -> String {
+    let graph = sourced_node_graph(node_id, node, sources);
+
+    let mut out = String::new();
+    out.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    out.push_str(&format!("<title>Node {}</title>\n", node_id));
+    out.push_str("</head>\n<body>\n");
+    out.push_str(&format!("<h1>Node {}</h1>\n", node_id));
+
+    let states: Vec<String> = graph.states.iter().map(|s| html_escape(s)).collect();
+    out.push_str(&format!("<p>States: {}</p>\n", states.join(", ")));
+    if let Some(initial) = &graph.initial {
+        out.push_str(&format!("<p>Initial: {}</p>\n", html_escape(initial)));
+    }
+    out.push_str(&svg_for_node_graph(node, sources));
+
+    out.push_str("<table>\n");
+    out.push_str("<tr><th>From</th><th>Transition</th><th>To</th><th>Source</th></tr>\n");
+    for e in &graph.edges {
+        let locations: Vec<String> = e
+            .sources
+            .iter()
+            .map(|s| {
+                let text = html_escape(&format!("{}:{} ({})", s.file, s.line, s.function));
+                match &s.url {
+                    Some(url) => format!("<a href=\"{}\">{}</a>", html_escape(url), text),
+                    None => text,
+                }
+            })
+            .collect();
+        out.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+            html_escape(&e.edge.from),
+            html_escape(&e.edge.transition),
+            html_escape(&e.edge.to),
+            locations.join("<br>")
+        ));
+    }
+    out.push_str("</table>\n");
+
+    if !graph.dwell.is_empty() {
+        out.push_str("<h2>Dwell time</h2>\n<table>\n");
+        out.push_str("<tr><th>State</th><th>Stays</th><th>Min</th><th>Median</th><th>p99</th><th>Max</th></tr>\n");
+        for (state, stats) in &graph.dwell {
+            out.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                html_escape(state),
+                stats.samples,
+                format_duration(stats.min_us),
+                format_duration(stats.median_us),
+                format_duration(stats.p99_us),
+                format_duration(stats.max_us)
+            ));
+        }
+        out.push_str("</table>\n");
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::build_event_graph;
+    use crate::model::dot_for_node_graph;
+
+    const LOG: &str = "[2025-11-10 19:56:55.000001][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 1 / 3] FunctionExecute 4 @ FunctionName electionTimeout @ state Follower
+[2025-11-10 19:56:55.000002][INFO] [BLOCK_EVENT_TYPE][Node 1 Batch 1 Entry 2 / 3] BlockExecute 7 @ FunctionName winElection @ state Candidate
+[2025-11-10 19:56:55.000003][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 3 / 3] FunctionExecute 4 @ FunctionName electionTimeout @ state Leader";
+
+    fn links() -> SourceLinks {
+        SourceLinks {
+            manifest: Manifest::parse(
+                r#"{"id":4,"kind":"function","file":"src/raft.c","line":31,"function":"tick","transition":"electionTimeout"}
+{"id":7,"kind":"block","file":"src/raft.c","line":58,"function":"raft_step","transition":"winElection"}"#,
+            )
+            .unwrap(),
+            url_template: Some("https://example.org/{file}#L{line}".to_string()),
+        }
+    }
+
+    #[test]
+    fn records_event_ids_per_transition() {
+        let g = build_event_graph(LOG);
+        let node = &g.nodes[&1];
+        assert_eq!(node.transition_events["electionTimeout"], vec![4]);
+        assert_eq!(node.transition_events["winElection"], vec![7]);
+    }
+
+    #[test]
+    fn attaches_sources_to_edges() {
+        let g = build_event_graph(LOG);
+        let graph = sourced_node_graph(1, &g.nodes[&1], Some(&links()));
+
+        let edge = &graph.edges[0];
+        assert_eq!(edge.edge.transition, "winElection");
+        assert_eq!(edge.event_ids, vec![7]);
+        assert_eq!(edge.sources[0].line, 58);
+        assert_eq!(
+            edge.sources[0].url.as_deref(),
+            Some("https://example.org/src/raft.c#L58")
+        );
+
+        let without = sourced_node_graph(1, &g.nodes[&1], None);
+        assert_eq!(without.edges[0].event_ids, vec![7]);
+        assert!(without.edges[0].sources.is_empty());
+    }
+
+    #[test]
+    fn renders_dot_tooltips_and_html_links() {
+        let g = build_event_graph(LOG);
+        let dot = dot_for_node_graph(&g.nodes[&1], Some(&links()));
+        assert!(dot.contains(
+            "\"Follower\" -> \"Candidate\" [label=<electionTimeout>, tooltip=\"electionTimeout: src/raft.c:31 (tick)\", URL=\"https://example.org/src/raft.c#L31\"];"
+        ));
+
+        let html = html_for_node_graph(1, &g.nodes[&1], Some(&links()));
+        assert!(html.contains(
+            "<td>Candidate</td><td>winElection</td><td>Leader</td><td><a href=\"https://example.org/src/raft.c#L58\">src/raft.c:58 (raft_step)</a></td>"
+        ));
+    }
+}
@@ -0,0 +1,276 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use super::event_graph::{Edge, EventGraph};
+use super::tlc::{SpecGraph, SpecState};
+
+/// Maps the states and actions of a TLC graph onto Abstraktor labels.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct SpecMapping {
+    pub states: Vec<StateRule>,
+    pub state_variables: Vec<String>,
+    pub actions: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct StateRule {
+    pub label: String,
+    pub when: BTreeMap<String, String>,
+}
+
+impl SpecMapping {
+    pub fn from_file(path: &str) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("reading spec mapping from {}", path))?;
+        serde_json::from_str(&content).with_context(|| format!("parsing spec mapping {}", path))
+    }
+
+    /// Rules are tried in order; otherwise the label is the concatenation of
+    /// the `state_variables` values. States without a label are not mapped.
+    pub fn state(&self, state: &SpecState) -> Option<String> {
+        let value = |name: &str| state.variables.get(name).map(|v| v.trim_matches('"'));
+
+        if let Some(rule) = self
+            .states
+            .iter()
+            .find(|r| r.when.iter().all(|(k, v)| value(k) == Some(v.as_str())))
+        {
+            return Some(rule.label.clone());
+        }
+
+        if self.state_variables.is_empty() {
+            return None;
+        }
+        self.state_variables
+            .iter()
+            .map(|name| value(name))
+            .collect::<Option<Vec<_>>>()
+            .map(|parts| parts.concat())
+    }
+
+    pub fn action(&self, action: &str) -> String {
+        self.actions
+            .get(action)
+            .cloned()
+            .unwrap_or_else(|| action.to_string())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnexpectedEdge {
+    #[serde(flatten)]
+    pub edge: Edge,
+    pub nodes: Vec<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub struct SpecComparison {
+    /// Implementation edges the spec does not allow.
+    pub unexpected_edges: Vec<UnexpectedEdge>,
+    /// Spec edges never exercised by the implementation. An empty transition
+    /// means the TLC graph was dumped without action labels.
+    pub unexercised_edges: Vec<Edge>,
+    pub unknown_states: Vec<String>,
+    pub unreached_states: Vec<String>,
+}
+
+impl SpecComparison {
+    pub fn conforms(&self) -> bool {
+        self.unexpected_edges.is_empty() && self.unknown_states.is_empty()
+    }
+}
+
+/// Abstracts the spec graph through `mapping`. Spec edges touching a state
+/// without a label are left out.
+pub fn abstract_spec(
+    spec: &SpecGraph,
+    mapping: &SpecMapping,
+) -> (BTreeSet<String>, BTreeSet<Edge>) {
+    let labels: BTreeMap<&str, String> = spec
+        .states
+        .iter()
+        .filter_map(|(id, state)| Some((id.as_str(), mapping.state(state)?)))
+        .collect();
+
+    let states = labels.values().cloned().collect();
+    let edges = spec
+        .edges
+        .iter()
+        .filter_map(|e| {
+            Some(Edge {
+                from: labels.get(e.from.as_str())?.clone(),
+                transition: e
+                    .action
+                    .as_deref()
+                    .map(|a| mapping.action(a))
+                    .unwrap_or_default(),
+                to: labels.get(e.to.as_str())?.clone(),
+            })
+        })
+        .collect();
+
+    (states, edges)
+}
+
+pub fn compare_with_spec(
+    implementation: &EventGraph,
+    spec: &SpecGraph,
+    mapping: &SpecMapping,
+) -> SpecComparison {
+    let (spec_states, spec_edges) = abstract_spec(spec, mapping);
+    let allows = |impl_edge: &Edge, spec_edge: &Edge| {
+        impl_edge.from == spec_edge.from
+            && impl_edge.to == spec_edge.to
+            && (spec_edge.transition.is_empty() || impl_edge.transition == spec_edge.transition)
+    };
+
+    let mut impl_states: BTreeSet<String> = BTreeSet::new();
+    let mut impl_edges: BTreeMap<Edge, Vec<u32>> = BTreeMap::new();
+    let mut node_ids: Vec<&u32> = implementation.nodes.keys().collect();
+    node_ids.sort_unstable();
+    for node_id in node_ids {
+        let node = &implementation.nodes[node_id];
+        impl_states.extend(node.states.iter().cloned());
+        for edge in &node.edges {
+            impl_edges.entry(edge.clone()).or_default().push(*node_id);
+        }
+    }
+
+    SpecComparison {
+        unexpected_edges: impl_edges
+            .iter()
+            .filter(|(edge, _)| !spec_edges.iter().any(|s| allows(edge, s)))
+            .map(|(edge, nodes)| UnexpectedEdge {
+                edge: edge.clone(),
+                nodes: nodes.clone(),
+            })
+            .collect(),
+        unexercised_edges: spec_edges
+            .iter()
+            .filter(|s| !impl_edges.keys().any(|edge| allows(edge, s)))
+            .cloned()
+            .collect(),
+        unknown_states: impl_states.difference(&spec_states).cloned().collect(),
+        unreached_states: spec_states.difference(&impl_states).cloned().collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::build_event_graph;
+    use crate::model::tlc::parse_tlc_dot;
+
+    const SPEC: &str = r#"strict digraph DiskGraph {
+1 [label="/\\ role = \"Follower\"\n/\\ term = 0",style = filled]
+2 [label="/\\ role = \"Candidate\"\n/\\ term = 1"]
+3 [label="/\\ role = \"Leader\"\n/\\ term = 1"]
+4 [label="/\\ role = \"Crashed\"\n/\\ term = 1"]
+1 -> 2 [label="Timeout",color="2",fontcolor="2"];
+2 -> 3 [label="BecomeLeader",color="3",fontcolor="3"];
+3 -> 1 [label="StepDown",color="4",fontcolor="4"];
+2 -> 1 [label="StepDown",color="4",fontcolor="4"];
+3 -> 4 [label="Crash",color="5",fontcolor="5"];
+}"#;
+
+    fn mapping() -> SpecMapping {
+        SpecMapping {
+            states: vec![StateRule {
+                label: "Down".to_string(),
+                when: BTreeMap::from([("role".to_string(), "Crashed".to_string())]),
+            }],
+            state_variables: vec!["role".to_string()],
+            actions: BTreeMap::from([
+                ("Timeout".to_string(), "electionTimeout".to_string()),
+                ("BecomeLeader".to_string(), "winElection".to_string()),
+                ("StepDown".to_string(), "stepDown".to_string()),
+            ]),
+        }
+    }
+
+    #[test]
+    fn maps_states_by_rule_then_variables() {
+        let spec = parse_tlc_dot(SPEC);
+        let (states, edges) = abstract_spec(&spec, &mapping());
+
+        assert_eq!(
+            states.into_iter().collect::<Vec<_>>(),
+            vec!["Candidate", "Down", "Follower", "Leader"]
+        );
+        assert!(edges.contains(&Edge {
+            from: "Candidate".to_string(),
+            transition: "winElection".to_string(),
+            to: "Leader".to_string(),
+        }));
+        assert!(edges.contains(&Edge {
+            from: "Leader".to_string(),
+            transition: "Crash".to_string(),
+            to: "Down".to_string(),
+        }));
+    }
+
+    #[test]
+    fn reports_unexpected_and_unexercised_edges() {
+        let log = "[2025-11-10 19:56:55.000001][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 1 / 4] FunctionExecute 1 @ FunctionName electionTimeout @ state Follower
+[2025-11-10 19:56:55.000002][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 2 / 4] FunctionExecute 2 @ FunctionName winElection @ state Candidate
+[2025-11-10 19:56:55.000003][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 3 / 4] FunctionExecute 3 @ FunctionName winElection @ state Leader
+[2025-11-10 19:56:55.000004][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 4 / 4] FunctionExecute 4 @ FunctionName bootstrap @ state Observer";
+        let implementation = build_event_graph(log);
+        let spec = parse_tlc_dot(SPEC);
+
+        let report = compare_with_spec(&implementation, &spec, &mapping());
+
+        assert!(!report.conforms());
+        assert_eq!(report.unexpected_edges.len(), 1);
+        assert_eq!(report.unexpected_edges[0].edge.from, "Leader");
+        assert_eq!(report.unexpected_edges[0].edge.to, "Observer");
+        assert_eq!(report.unexpected_edges[0].nodes, vec![1]);
+        assert_eq!(report.unknown_states, vec!["Observer"]);
+        assert_eq!(report.unreached_states, vec!["Down"]);
+        assert_eq!(
+            report
+                .unexercised_edges
+                .iter()
+                .map(|e| e.transition.as_str())
+                .collect::<Vec<_>>(),
+            vec!["stepDown", "Crash", "stepDown"]
+        );
+    }
+
+    #[test]
+    fn unlabelled_spec_edges_match_any_transition() {
+        let spec = parse_tlc_dot(
+            r#"1 [label="/\\ role = \"Follower\"",style = filled]
+2 [label="/\\ role = \"Candidate\""]
+1 -> 2;"#,
+        );
+        let log = "[2025-11-10 19:56:55.000001][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 1 / 2] FunctionExecute 1 @ FunctionName anything @ state Follower
+[2025-11-10 19:56:55.000002][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 2 / 2] FunctionExecute 2 @ FunctionName other @ state Candidate";
+        let mapping = SpecMapping {
+            state_variables: vec!["role".to_string()],
+            ..Default::default()
+        };
+
+        let report = compare_with_spec(&build_event_graph(log), &spec, &mapping);
+        assert!(report.conforms());
+        assert!(report.unexercised_edges.is_empty());
+    }
+
+    #[test]
+    fn parses_mapping_from_json() {
+        let json = r#"{
+            "state_variables": ["role"],
+            "states": [{"label": "Down", "when": {"role": "Crashed"}}],
+            "actions": {"Timeout": "electionTimeout"}
+        }"#;
+        let mapping: SpecMapping = serde_json::from_str(json).unwrap();
+        assert_eq!(mapping.states[0].label, "Down");
+        assert_eq!(mapping.action("Timeout"), "electionTimeout");
+        assert_eq!(mapping.action("Crash"), "Crash");
+    }
+}
@@ -0,0 +1,144 @@
+/// Two-sided 97.5% quantiles of Student's t for 1 to 30 degrees of freedom.
+const T_975: [f64; 30] = [
+    12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228, 2.201, 2.179, 2.160,
+    2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086, 2.080, 2.074, 2.069, 2.064, 2.060, 2.056,
+    2.052, 2.048, 2.045, 2.042,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Summary {
+    pub n: usize,
+    pub mean: f64,
+    /// Half-width of the 95% confidence interval of the mean; needs at
+    /// least two samples.
+    pub ci95: Option<f64>,
+}
+
+pub fn summarize(samples: &[f64]) -> Option<Summary> {
+    let n = samples.len();
+    if n == 0 {
+        return None;
+    }
+    let mean = samples.iter().sum::<f64>() / n as f64;
+    let ci95 = (n > 1).then(|| {
+        let variance = samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1) as f64;
+        let t = T_975.get(n - 2).copied().unwrap_or(1.96);
+        t * (variance / n as f64).sqrt()
+    });
+    Some(Summary { n, mean, ci95 })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MannWhitney {
+    pub u: f64,
+    /// Two-sided, from the normal approximation with tie correction.
+    pub p_value: f64,
+    /// Vargha-Delaney A12: the probability that a sample of `a` is larger
+    /// than one of `b`, counting ties as half.
+    pub a12: f64,
+}
+
+/// Mann-Whitney U test of `a` against `b`. Without samples on both sides
+/// there is nothing to compare.
+pub fn mann_whitney(a: &[f64], b: &[f64]) -> Option<MannWhitney> {
+    if a.is_empty() || b.is_empty() {
+        return None;
+    }
+    let (n1, n2) = (a.len() as f64, b.len() as f64);
+
+    let mut pooled: Vec<(f64, bool)> = a
+        .iter()
+        .map(|x| (*x, true))
+        .chain(b.iter().map(|x| (*x, false)))
+        .collect();
+    pooled.sort_by(|x, y| x.0.total_cmp(&y.0));
+
+    let mut rank_sum_a = 0.0;
+    let mut tie_term = 0.0;
+    let mut i = 0;
+    while i < pooled.len() {
+        let mut j = i;
+        while j < pooled.len() && pooled[j].0 == pooled[i].0 {
+            j += 1;
+        }
+        // Ranks i+1..=j share their average.
+        let rank = (i + 1 + j) as f64 / 2.0;
+        rank_sum_a += rank * pooled[i..j].iter().filter(|(_, in_a)| *in_a).count() as f64;
+        let tied = (j - i) as f64;
+        tie_term += tied.powi(3) - tied;
+        i = j;
+    }
+
+    let u = rank_sum_a - n1 * (n1 + 1.0) / 2.0;
+    let n = n1 + n2;
+    let variance = n1 * n2 / 12.0 * ((n + 1.0) - tie_term / (n * (n - 1.0)));
+    let p_value = if variance <= 0.0 {
+        1.0
+    } else {
+        let z = ((u - n1 * n2 / 2.0).abs() - 0.5).max(0.0) / variance.sqrt();
+        (2.0 * (1.0 - normal_cdf(z))).min(1.0)
+    };
+    Some(MannWhitney {
+        u,
+        p_value,
+        a12: u / (n1 * n2),
+    })
+}
+
+fn normal_cdf(z: f64) -> f64 {
+    0.5 * (1.0 + erf(z / std::f64::consts::SQRT_2))
+}
+
+/// Abramowitz and Stegun 7.1.26, accurate to about 1.5e-7.
+fn erf(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.3275911 * x.abs());
+    let poly = t
+        * (0.254829592
+            + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    let y = 1.0 - poly * (-x * x).exp();
+    if x < 0.0 { -y } else { y }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summarize() {
+        assert_eq!(summarize(&[]), None);
+        let single = summarize(&[4.0]).unwrap();
+        assert_eq!((single.mean, single.ci95), (4.0, None));
+
+        let summary = summarize(&[2.0, 4.0, 6.0]).unwrap();
+        assert_eq!(summary.mean, 4.0);
+        // sd 2, n 3, t(2) 4.303
+        assert!((summary.ci95.unwrap() - 4.303 * 2.0 / 3f64.sqrt()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_mann_whitney_separated_samples() {
+        let a = [10.0, 11.0, 12.0, 13.0, 14.0];
+        let b = [1.0, 2.0, 3.0, 4.0, 5.0];
+        let test = mann_whitney(&a, &b).unwrap();
+        assert_eq!(test.u, 25.0);
+        assert_eq!(test.a12, 1.0);
+        // scipy.stats.mannwhitneyu(a, b, method="asymptotic") gives 0.0122
+        assert!((test.p_value - 0.0122).abs() < 1e-3, "{}", test.p_value);
+
+        let reversed = mann_whitney(&b, &a).unwrap();
+        assert_eq!(reversed.a12, 0.0);
+        assert!((reversed.p_value - test.p_value).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_mann_whitney_ties_and_identical_samples() {
+        let test = mann_whitney(&[3.0, 3.0, 3.0], &[3.0, 3.0]).unwrap();
+        assert_eq!(test.a12, 0.5);
+        assert_eq!(test.p_value, 1.0);
+
+        let test = mann_whitney(&[1.0, 2.0, 2.0, 3.0], &[2.0, 3.0, 3.0, 4.0]).unwrap();
+        assert!(test.a12 < 0.5);
+        assert!(test.p_value > 0.05);
+        assert!(mann_whitney(&[1.0], &[]).is_none());
+    }
+}
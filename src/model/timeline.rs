@@ -0,0 +1,551 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use super::dwell::format_duration;
+use super::event_log::{
+    Window, parse_event_log, parse_nemesis_actions, parse_timestamp, parse_window_action,
+    parse_window_marker,
+};
+use super::refinement::Refinement;
+
+/// A stretch of time a node spent in one abstract state.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Segment {
+    pub state: String,
+    pub start_us: i64,
+    pub end_us: i64,
+    /// Log line of the event that entered the state.
+    pub line: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lane {
+    pub node_id: u32,
+    pub segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WindowMark {
+    pub window: Window,
+    pub action: Option<usize>,
+    /// The nemesis operation behind `action`, when the mediator logged it.
+    pub edn: Option<String>,
+    pub at_us: i64,
+}
+
+/// A client or nemesis operation from a Jepsen history, from its invocation
+/// to its completion.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClientOp {
+    pub process: String,
+    pub f: String,
+    pub value: String,
+    pub start_us: i64,
+    /// `None` when the history ends before the operation completes.
+    pub end_us: Option<i64>,
+    /// `ok`, `fail` or `info`, or `pending` without a completion.
+    pub outcome: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Timeline {
+    pub start_us: i64,
+    pub end_us: i64,
+    pub lanes: Vec<Lane>,
+    pub windows: Vec<WindowMark>,
+    pub operations: Vec<ClientOp>,
+}
+
+/// Events are placed at their own time, as `parse_event_log` puts them on
+/// the log's wall clock, and window markers at the time they were logged.
+/// Returns `None` when no event carries a timestamp.
+pub fn build_timeline(log: &str, refinement: &Refinement) -> Option<Timeline> {
+    let mut lanes: BTreeMap<u32, Vec<Segment>> = BTreeMap::new();
+    let mut start_us = i64::MAX;
+    let mut end_us = i64::MIN;
+
+    for rec in parse_event_log(log) {
+        let Some(at) = rec.timestamp_us else {
+            continue;
+        };
+        let Some(state) = refinement.state(&rec.state, rec.constant) else {
+            continue;
+        };
+        start_us = start_us.min(at);
+        end_us = end_us.max(at);
+
+        let segments = lanes.entry(rec.node_id).or_default();
+        if let Some(last) = segments.last_mut() {
+            if last.state == state {
+                continue;
+            }
+            last.end_us = at;
+        }
+        segments.push(Segment {
+            state,
+            start_us: at,
+            end_us: at,
+            line: rec.line,
+        });
+    }
+    if lanes.is_empty() {
+        return None;
+    }
+
+    let edn = parse_nemesis_actions(log);
+    let windows: Vec<WindowMark> = log
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            let window = parse_window_marker(line)?;
+            let at_us = parse_timestamp(line)?;
+            let action = parse_window_action(line);
+            Some(WindowMark {
+                window,
+                action,
+                edn: action.and_then(|a| edn.get(&a).cloned()),
+                at_us,
+            })
+        })
+        .collect();
+    for mark in &windows {
+        start_us = start_us.min(mark.at_us);
+        end_us = end_us.max(mark.at_us);
+    }
+
+    let lanes = lanes
+        .into_iter()
+        .map(|(node_id, mut segments)| {
+            if let Some(last) = segments.last_mut() {
+                last.end_us = end_us;
+            }
+            Lane { node_id, segments }
+        })
+        .collect();
+
+    Some(Timeline {
+        start_us,
+        end_us,
+        lanes,
+        windows,
+        operations: Vec::new(),
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryOp {
+    pub process: String,
+    pub kind: String,
+    pub f: String,
+    pub value: String,
+    /// Nanoseconds since the start of the Jepsen test.
+    pub time_ns: i64,
+}
+
+/// Reads a Jepsen `history.edn`, one operation map per line. Lines without
+/// `:type`, `:f`, `:process` or `:time` are skipped.
+pub fn parse_jepsen_history(content: &str) -> Vec<HistoryOp> {
+    let keyword = |key: &str| Regex::new(&format!(r":{}\s+:?([\w\-]+)", key)).unwrap();
+    let (type_re, f_re, process_re, time_re) = (
+        keyword("type"),
+        keyword("f"),
+        keyword("process"),
+        keyword("time"),
+    );
+    let value_re = Regex::new(r":value\s+(.*?)(?:,?\s+:\w[\w\-]*\s|\}\s*$)").unwrap();
+
+    content
+        .lines()
+        .filter_map(|line| {
+            Some(HistoryOp {
+                process: process_re.captures(line)?[1].to_string(),
+                kind: type_re.captures(line)?[1].to_string(),
+                f: f_re.captures(line)?[1].to_string(),
+                value: value_re
+                    .captures(line)
+                    .map(|c| c[1].trim().to_string())
+                    .unwrap_or_default(),
+                time_ns: time_re.captures(line)?[1].parse().ok()?,
+            })
+        })
+        .collect()
+}
+
+impl Timeline {
+    /// Pairs every invocation with the next completion of the same process.
+    /// History times are relative to the test start, which is placed
+    /// `offset_us` after the first event of the timeline.
+    pub fn add_history(&mut self, ops: &[HistoryOp], offset_us: i64) {
+        let origin = self.start_us + offset_us;
+        let mut open: BTreeMap<String, usize> = BTreeMap::new();
+
+        for op in ops {
+            let at = origin + op.time_ns / 1_000;
+            if op.kind == "invoke" {
+                open.insert(op.process.clone(), self.operations.len());
+                self.operations.push(ClientOp {
+                    process: op.process.clone(),
+                    f: op.f.clone(),
+                    value: op.value.clone(),
+                    start_us: at,
+                    end_us: None,
+                    outcome: "pending".to_string(),
+                });
+            } else if let Some(index) = open.remove(&op.process) {
+                let started = &mut self.operations[index];
+                started.end_us = Some(at);
+                started.outcome = op.kind.clone();
+                if !op.value.is_empty() && op.value != "nil" {
+                    started.value = op.value.clone();
+                }
+            }
+            self.start_us = self.start_us.min(at);
+            self.end_us = self.end_us.max(at);
+        }
+    }
+
+    fn processes(&self) -> Vec<&str> {
+        let processes: BTreeSet<&str> = self
+            .operations
+            .iter()
+            .map(|op| op.process.as_str())
+            .collect();
+        let mut processes: Vec<&str> = processes.into_iter().collect();
+        // Numeric client processes first, in numeric order, then the nemesis.
+        processes.sort_by_key(|p| p.parse::<u64>().map_err(|_| p.to_string()));
+        processes
+    }
+}
+
+const LABEL_WIDTH: f64 = 90.0;
+const PLOT_WIDTH: f64 = 1000.0;
+const LANE_HEIGHT: f64 = 26.0;
+const HEADER_HEIGHT: f64 = 30.0;
+const AXIS_HEIGHT: f64 = 30.0;
+const TICKS: i64 = 10;
+
+/// Colors are derived from the state name so that a state keeps its color
+/// across exports.
+pub fn state_color(state: &str) -> String {
+    let hash = state.bytes().fold(0xcbf29ce484222325_u64, |h, b| {
+        (h ^ u64::from(b)).wrapping_mul(0x100000001b3)
+    });
+    format!("hsl({}, 60%, 72%)", hash % 360)
+}
+
+fn outcome_color(outcome: &str) -> &'static str {
+    match outcome {
+        "ok" => "#6cc070",
+        "fail" => "#e06666",
+        "info" => "#f0b04c",
+        _ => "#bbbbbb",
+    }
+}
+
+/// One lane per node, then one per client process, with a dashed line at
+/// every nemesis window.
+pub fn svg_for_timeline(timeline: &Timeline) -> String {
+    let span = (timeline.end_us - timeline.start_us).max(1) as f64;
+    let x = |t: i64| LABEL_WIDTH + (t - timeline.start_us) as f64 * PLOT_WIDTH / span;
+
+    let processes = timeline.processes();
+    let lane_count = timeline.lanes.len() + processes.len();
+    let plot_bottom = HEADER_HEIGHT + lane_count as f64 * LANE_HEIGHT;
+    let width = LABEL_WIDTH + PLOT_WIDTH + 20.0;
+    let height = plot_bottom + AXIS_HEIGHT;
+
+    let mut out = String::new();
+    out.push_str(&format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.0}\" height=\"{:.0}\" font-family=\"sans-serif\" font-size=\"11\">\n",
+        width, height
+    ));
+
+    for (index, lane) in timeline.lanes.iter().enumerate() {
+        let y = HEADER_HEIGHT + index as f64 * LANE_HEIGHT;
+        out.push_str(&format!(
+            "  <text x=\"4\" y=\"{:.1}\">Node {}</text>\n",
+            y + LANE_HEIGHT * 0.65,
+            lane.node_id
+        ));
+        for segment in &lane.segments {
+            let (x0, x1) = (x(segment.start_us), x(segment.end_us));
+            out.push_str(&format!(
+                "  <rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"{}\" stroke=\"#555\" stroke-width=\"0.5\"><title>{}</title></rect>\n",
+                x0,
+                y + 3.0,
+                (x1 - x0).max(1.0),
+                LANE_HEIGHT - 6.0,
+                state_color(&segment.state),
+                xml_escape(&format!(
+                    "Node {}: {} for {} (line {})",
+                    lane.node_id,
+                    segment.state,
+                    format_duration((segment.end_us - segment.start_us) as u64),
+                    segment.line
+                ))
+            ));
+            if x1 - x0 > 7.0 * segment.state.len() as f64 {
+                out.push_str(&format!(
+                    "  <text x=\"{:.1}\" y=\"{:.1}\" pointer-events=\"none\">{}</text>\n",
+                    x0 + 3.0,
+                    y + LANE_HEIGHT * 0.65,
+                    xml_escape(&segment.state)
+                ));
+            }
+        }
+    }
+
+    for (index, process) in processes.iter().enumerate() {
+        let y = HEADER_HEIGHT + (timeline.lanes.len() + index) as f64 * LANE_HEIGHT;
+        out.push_str(&format!(
+            "  <text x=\"4\" y=\"{:.1}\">Process {}</text>\n",
+            y + LANE_HEIGHT * 0.65,
+            xml_escape(process)
+        ));
+        for op in timeline
+            .operations
+            .iter()
+            .filter(|op| op.process == *process)
+        {
+            let end = op.end_us.unwrap_or(timeline.end_us);
+            let (x0, x1) = (x(op.start_us), x(end));
+            out.push_str(&format!(
+                "  <rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"{}\"{}><title>{}</title></rect>\n",
+                x0,
+                y + 6.0,
+                (x1 - x0).max(1.0),
+                LANE_HEIGHT - 12.0,
+                outcome_color(&op.outcome),
+                if op.end_us.is_none() { " fill-opacity=\"0.4\"" } else { "" },
+                xml_escape(&format!("{} {} {} ({})", op.process, op.f, op.value, op.outcome))
+            ));
+        }
+    }
+
+    for mark in &timeline.windows {
+        let mx = x(mark.at_us);
+        let mut title = format!(
+            "Schedule {} step {}",
+            mark.window.schedule_id, mark.window.step_id
+        );
+        if let Some(action) = mark.action {
+            title.push_str(&format!(": action {}", action));
+        }
+        if let Some(edn) = &mark.edn {
+            title.push_str(&format!(" {}", edn));
+        }
+        out.push_str(&format!(
+            "  <g><title>{}</title><line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"#c00\" stroke-dasharray=\"4,3\"/><text x=\"{:.1}\" y=\"{:.1}\" fill=\"#c00\">{}.{}</text></g>\n",
+            xml_escape(&title),
+            mx,
+            HEADER_HEIGHT - 4.0,
+            mx,
+            plot_bottom,
+            mx + 2.0,
+            HEADER_HEIGHT - 8.0,
+            mark.window.schedule_id,
+            mark.window.step_id
+        ));
+    }
+
+    for tick in 0..=TICKS {
+        let at = timeline.start_us + (timeline.end_us - timeline.start_us) * tick / TICKS;
+        let tx = x(at);
+        out.push_str(&format!(
+            "  <line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"#999\"/><text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\" fill=\"#555\">{}</text>\n",
+            tx,
+            plot_bottom,
+            tx,
+            plot_bottom + 5.0,
+            tx,
+            plot_bottom + 18.0,
+            format_duration((at - timeline.start_us) as u64)
+        ));
+    }
+
+    out.push_str("</svg>\n");
+    out
+}
+
+/// The SVG with a legend of state colors and a list of the nemesis windows.
+pub fn html_for_timeline(timeline: &Timeline) -> String {
+    let mut out = String::new();
+    out.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    out.push_str("<title>Timeline</title>\n</head>\n<body>\n<h1>Timeline</h1>\n");
+    out.push_str(&svg_for_timeline(timeline));
+
+    let states: BTreeSet<&str> = timeline
+        .lanes
+        .iter()
+        .flat_map(|lane| lane.segments.iter().map(|s| s.state.as_str()))
+        .collect();
+    out.push_str("<h2>States</h2>\n<p>\n");
+    for state in states {
+        out.push_str(&format!(
+            "<span style=\"background:{};padding:2px 6px;margin-right:4px\">{}</span>\n",
+            state_color(state),
+            xml_escape(state)
+        ));
+    }
+    out.push_str("</p>\n");
+
+    if !timeline.windows.is_empty() {
+        out.push_str("<h2>Nemesis windows</h2>\n<table>\n");
+        out.push_str("<tr><th>At</th><th>Schedule</th><th>Step</th><th>Action</th></tr>\n");
+        for mark in &timeline.windows {
+            let action = match (mark.action, &mark.edn) {
+                (Some(a), Some(edn)) => format!("{} {}", a, edn),
+                (Some(a), None) => a.to_string(),
+                _ => String::new(),
+            };
+            out.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                format_duration((mark.at_us - timeline.start_us) as u64),
+                mark.window.schedule_id,
+                mark.window.step_id,
+                xml_escape(&action)
+            ));
+        }
+        out.push_str("</table>\n");
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+pub fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOG: &str = "[2025-11-10 19:56:55.000000][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 1 / 1] FunctionExecute 1 @ FunctionName boot @ state Follower
+[2025-11-10 19:56:55.000000][INFO] [FUNC_EVENT_TYPE][Node 2 Batch 1 Entry 1 / 1] FunctionExecute 1 @ FunctionName boot @ state Follower
+[2025-11-10 19:56:55.100000][INFO] [WINDOW][Schedule 0 Step 0] Action 2
+[2025-11-10 19:56:55.100000][INFO] [NEMESIS] (Start schedule)  Generated action id {:f :start-partition, :value (:majority)} (2) at step 0.
+[2025-11-10 19:56:55.200000][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 2 Entry 1 / 2] FunctionExecute 2 @ FunctionName electionTimeout @ state Candidate
+[2025-11-10 19:56:55.250000][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 2 Entry 2 / 2] FunctionExecute 2 @ FunctionName electionTimeout @ state Candidate
+[2025-11-10 19:56:55.300000][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 3 Entry 1 / 1] FunctionExecute 3 @ FunctionName winElection @ state Leader
+[2025-11-10 19:56:55.400000][INFO] [WINDOW][Schedule 0 Step 1] Action 0";
+
+    const HISTORY: &str =
+        "{:type :invoke, :f :write, :value 3, :time 50000000, :process 0, :index 0}
+{:type :invoke, :f :read, :value nil, :time 60000000, :process 1, :index 1}
+{:type :ok, :f :write, :value 3, :time 150000000, :process 0, :index 2}
+{:type :info, :f :start-partition, :value :majority, :time 160000000, :process :nemesis, :index 3}
+{:type :fail, :f :read, :value nil, :time 500000000, :process 1, :index 4}";
+
+    #[test]
+    fn builds_one_lane_per_node_with_merged_segments() {
+        let timeline = build_timeline(LOG, &Refinement::identity()).unwrap();
+        assert_eq!(timeline.end_us - timeline.start_us, 400_000);
+
+        let node1: Vec<(&str, i64, i64)> = timeline.lanes[0]
+            .segments
+            .iter()
+            .map(|s| {
+                (
+                    s.state.as_str(),
+                    s.start_us - timeline.start_us,
+                    s.end_us - timeline.start_us,
+                )
+            })
+            .collect();
+        assert_eq!(
+            node1,
+            vec![
+                ("Follower", 0, 200_000),
+                ("Candidate", 200_000, 300_000),
+                ("Leader", 300_000, 400_000),
+            ]
+        );
+        assert_eq!(timeline.lanes[1].segments.len(), 1);
+        assert_eq!(timeline.lanes[1].segments[0].end_us, timeline.end_us);
+
+        assert_eq!(timeline.windows.len(), 2);
+        assert_eq!(timeline.windows[0].action, Some(2));
+        assert_eq!(
+            timeline.windows[0].edn.as_deref(),
+            Some("{:f :start-partition, :value (:majority)}")
+        );
+        assert_eq!(timeline.windows[1].edn, None);
+    }
+
+    #[test]
+    fn pairs_history_invocations_with_completions() {
+        let ops = parse_jepsen_history(HISTORY);
+        assert_eq!(ops.len(), 5);
+        assert_eq!(ops[3].process, "nemesis");
+        assert_eq!(ops[3].value, ":majority");
+
+        let mut timeline = build_timeline(LOG, &Refinement::identity()).unwrap();
+        let start = timeline.start_us;
+        timeline.add_history(&ops, 0);
+
+        assert_eq!(timeline.operations.len(), 2);
+        let write = &timeline.operations[0];
+        assert_eq!((write.f.as_str(), write.outcome.as_str()), ("write", "ok"));
+        assert_eq!(write.start_us - start, 50_000);
+        assert_eq!(write.end_us, Some(start + 150_000));
+        assert_eq!(timeline.operations[1].outcome, "fail");
+        assert_eq!(timeline.end_us - start, 500_000);
+    }
+
+    #[test]
+    fn renders_svg_lanes_and_window_markers() {
+        let mut timeline = build_timeline(LOG, &Refinement::identity()).unwrap();
+        timeline.add_history(&parse_jepsen_history(HISTORY), 0);
+        let svg = svg_for_timeline(&timeline);
+
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.contains(">Node 1</text>"));
+        assert!(svg.contains(">Process 0</text>"));
+        assert!(svg.contains("<title>Node 1: Candidate for 100.00ms (line 5)</title>"));
+        assert!(svg.contains(
+            "<title>Schedule 0 step 0: action 2 {:f :start-partition, :value (:majority)}</title>"
+        ));
+        assert_eq!(svg.matches("stroke-dasharray").count(), 2);
+
+        let html = html_for_timeline(&timeline);
+        assert!(html.contains("<h2>Nemesis windows</h2>"));
+        assert!(html.contains(&format!(
+            "<span style=\"background:{};",
+            state_color("Leader")
+        )));
+    }
+
+    #[test]
+    fn places_events_of_one_batch_at_their_own_time() {
+        // Node 1 became Candidate before the window and Leader after it, but
+        // the mediator logged both events in one batch after the window.
+        let log = "[2025-11-10 19:56:55.000000][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 1 / 1] FunctionExecute 1 @ FunctionName boot @ state Follower @ ts 1000000000
+[2025-11-10 19:56:55.100000][INFO] [WINDOW][Schedule 0 Step 0] Action 2
+[2025-11-10 19:56:55.300000][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 2 Entry 1 / 2] FunctionExecute 2 @ FunctionName electionTimeout @ state Candidate @ ts 1050000000
+[2025-11-10 19:56:55.300000][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 2 Entry 2 / 2] FunctionExecute 3 @ FunctionName winElection @ state Leader @ ts 1300000000";
+        let timeline = build_timeline(log, &Refinement::identity()).unwrap();
+        let start = timeline.start_us;
+
+        let segments: Vec<(&str, i64)> = timeline.lanes[0]
+            .segments
+            .iter()
+            .map(|s| (s.state.as_str(), s.start_us - start))
+            .collect();
+        assert_eq!(
+            segments,
+            vec![("Follower", 0), ("Candidate", 50_000), ("Leader", 300_000)]
+        );
+        assert_eq!(timeline.windows[0].at_us - start, 100_000);
+    }
+
+    #[test]
+    fn skips_logs_without_timestamps() {
+        let log = "[INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 1 / 1] FunctionExecute 1 @ FunctionName boot @ state idle";
+        assert_eq!(build_timeline(log, &Refinement::identity()), None);
+    }
+}
@@ -0,0 +1,229 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::event_graph::{Edge, NodeGraph};
+use super::identifiers::Identifiers;
+
+/// Renders the nodes as a TLA+ module with one action per node and transition
+/// label. Nodes interleave: every action leaves the other nodes unchanged.
+pub fn tla_for_nodes(module: &str, nodes: &[(u32, &NodeGraph)]) -> String {
+    let vars_of = |id: u32| [format!("node_{}_state", id), format!("node_{}_last", id)];
+    let all_vars: Vec<String> = nodes.iter().flat_map(|(id, _)| vars_of(*id)).collect();
+    let ids: Vec<String> = nodes.iter().map(|(id, _)| id.to_string()).collect();
+
+    let mut out = String::new();
+    out.push_str(&format!("---- MODULE {} ----\n", module));
+    out.push_str(&format!(
+        "\\* Abstraction recovered by abstraktor from node(s) {}.\n\n",
+        ids.join(", ")
+    ));
+    out.push_str(&format!("VARIABLES {}\n\n", all_vars.join(", ")));
+    out.push_str(&format!("vars == << {} >>\n\n", all_vars.join(", ")));
+
+    out.push_str("TypeOK ==\n");
+    for (node_id, node) in nodes {
+        let states: Vec<String> = node.states.iter().map(|s| string(s)).collect();
+        out.push_str(&format!(
+            "    /\\ node_{}_state \\in {{{}}}\n",
+            node_id,
+            states.join(", ")
+        ));
+    }
+    out.push('\n');
+
+    // `_last` starts out as a string no transition of the model is named.
+    let transitions: BTreeSet<&str> = nodes
+        .iter()
+        .flat_map(|(_, n)| n.edges.iter().map(|e| e.transition.as_str()))
+        .collect();
+    let none = no_action(&transitions);
+
+    out.push_str("Init ==\n");
+    for (node_id, node) in nodes {
+        match &node.initial {
+            Some(initial) => out.push_str(&format!(
+                "    /\\ node_{}_state = {}\n",
+                node_id,
+                string(initial)
+            )),
+            None => out.push_str(&format!(
+                "    /\\ node_{}_state \\in {{{}}}\n",
+                node_id,
+                node.states
+                    .iter()
+                    .map(|s| string(s))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+        }
+        out.push_str(&format!(
+            "    /\\ node_{}_last = {}\n",
+            node_id,
+            string(&none)
+        ));
+    }
+    out.push('\n');
+
+    let labels = Identifiers::new(transitions.iter().copied());
+    let mut actions = Vec::new();
+    for (node_id, node) in nodes {
+        let mut by_label: BTreeMap<&str, Vec<&Edge>> = BTreeMap::new();
+        for e in &node.edges {
+            by_label.entry(&e.transition).or_default().push(e);
+        }
+
+        let others: Vec<&String> = all_vars
+            .iter()
+            .filter(|v| !vars_of(*node_id).contains(v))
+            .collect();
+
+        for (label, edges) in by_label {
+            let name = format!("node_{}_{}", node_id, labels.id(label));
+            out.push_str(&format!("{} ==\n", name));
+            out.push_str("    /\\ ");
+            for (i, e) in edges.iter().enumerate() {
+                if i > 0 {
+                    out.push_str("       ");
+                }
+                out.push_str(&format!(
+                    "\\/ /\\ node_{}_state = {}\n",
+                    node_id,
+                    string(&e.from)
+                ));
+                out.push_str(&format!(
+                    "          /\\ node_{}_state' = {}\n",
+                    node_id,
+                    string(&e.to)
+                ));
+            }
+            out.push_str(&format!(
+                "    /\\ node_{}_last' = {}\n",
+                node_id,
+                string(label)
+            ));
+            if !others.is_empty() {
+                let others: Vec<&str> = others.iter().map(|v| v.as_str()).collect();
+                out.push_str(&format!("    /\\ UNCHANGED << {} >>\n", others.join(", ")));
+            }
+            out.push('\n');
+            actions.push(name);
+        }
+    }
+
+    out.push_str("Next ==\n");
+    if actions.is_empty() {
+        out.push_str("    UNCHANGED vars\n");
+    }
+    for action in &actions {
+        out.push_str(&format!("    \\/ {}\n", action));
+    }
+    out.push('\n');
+
+    out.push_str("Spec == Init /\\ [][Next]_vars\n");
+    out.push_str("====\n");
+    out
+}
+
+/// `none`, with as many underscores appended as it takes to differ from every
+/// transition name.
+fn no_action(transitions: &BTreeSet<&str>) -> String {
+    let mut none = String::from("none");
+    while transitions.contains(none.as_str()) {
+        none.push('_');
+    }
+    none
+}
+
+fn string(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::build_event_graph;
+
+    const LOG: &str = "[2025-11-10 19:56:55.000001][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 1 / 3] FunctionExecute 1 @ FunctionName electionTimeout @ state Follower
+[2025-11-10 19:56:55.000002][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 2 / 3] FunctionExecute 2 @ FunctionName winElection @ state Candidate
+[2025-11-10 19:56:55.000003][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 3 / 3] FunctionExecute 3 @ FunctionName electionTimeout @ state Leader
+[2025-11-10 19:56:55.000004][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 2 Entry 1 / 1] FunctionExecute 4 @ FunctionName stepDown @ state Follower
+[2025-11-10 19:56:55.000005][INFO] [FUNC_EVENT_TYPE][Node 2 Batch 1 Entry 1 / 1] FunctionExecute 5 @ FunctionName boot @ state Follower";
+
+    #[test]
+    fn renders_module_with_one_action_per_label() {
+        let g = build_event_graph(LOG);
+        let tla = tla_for_nodes("node_1", &[(1, &g.nodes[&1])]);
+
+        assert!(tla.starts_with("---- MODULE node_1 ----\n"));
+        assert!(tla.contains("VARIABLES node_1_state, node_1_last\n"));
+        assert!(
+            tla.contains("    /\\ node_1_state \\in {\"Candidate\", \"Follower\", \"Leader\"}\n")
+        );
+        assert!(tla.contains("Init ==\n    /\\ node_1_state = \"Follower\"\n"));
+        assert!(tla.contains(
+            "node_1_electionTimeout ==
+    /\\ \\/ /\\ node_1_state = \"Follower\"
+          /\\ node_1_state' = \"Candidate\"
+       \\/ /\\ node_1_state = \"Leader\"
+          /\\ node_1_state' = \"Follower\"
+    /\\ node_1_last' = \"electionTimeout\"
+"
+        ));
+        assert!(!tla.contains("UNCHANGED"));
+        assert!(
+            tla.contains("Next ==\n    \\/ node_1_electionTimeout\n    \\/ node_1_winElection\n")
+        );
+        assert!(tla.ends_with("Spec == Init /\\ [][Next]_vars\n====\n"));
+    }
+
+    #[test]
+    fn composed_module_interleaves_nodes() {
+        let g = build_event_graph(LOG);
+        let tla = tla_for_nodes("system", &[(1, &g.nodes[&1]), (2, &g.nodes[&2])]);
+
+        assert!(tla.contains("VARIABLES node_1_state, node_1_last, node_2_state, node_2_last\n"));
+        assert!(tla.contains("    /\\ UNCHANGED << node_2_state, node_2_last >>\n"));
+        assert!(tla.contains("    /\\ node_2_last = \"none\"\n"));
+    }
+
+    #[test]
+    fn defines_each_action_once() {
+        let log = "[2025-11-10 19:56:55.000001][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 1 / 4] FunctionExecute 1 @ FunctionName UNCHANGED @ state VARIABLE
+[2025-11-10 19:56:55.000002][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 2 / 4] FunctionExecute 2 @ FunctionName raft-send @ state Init
+[2025-11-10 19:56:55.000003][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 3 / 4] FunctionExecute 3 @ FunctionName raft_send @ state VARIABLE
+[2025-11-10 19:56:55.000004][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 4 / 4] FunctionExecute 4 @ FunctionName boot @ state Init";
+        let g = build_event_graph(log);
+        let tla = tla_for_nodes("node_1", &[(1, &g.nodes[&1])]);
+
+        let defined: Vec<&str> = tla.lines().filter_map(|l| l.strip_suffix(" ==")).collect();
+        assert_eq!(
+            defined,
+            [
+                "TypeOK",
+                "Init",
+                "node_1_s_UNCHANGED",
+                "node_1_raft_send",
+                "node_1_raft_send_2",
+                "Next"
+            ]
+        );
+        assert!(tla.contains("    /\\ node_1_state \\in {\"Init\", \"VARIABLE\"}\n"));
+    }
+
+    #[test]
+    fn sentinel_differs_from_every_transition() {
+        let log = "[2025-11-10 19:56:55.000001][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 1 / 3] FunctionExecute 1 @ FunctionName none @ state Follower
+[2025-11-10 19:56:55.000002][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 2 / 3] FunctionExecute 2 @ FunctionName none_ @ state Candidate
+[2025-11-10 19:56:55.000003][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 3 / 3] FunctionExecute 3 @ FunctionName boot @ state Leader";
+        let g = build_event_graph(log);
+        let tla = tla_for_nodes("node_1", &[(1, &g.nodes[&1])]);
+
+        assert!(tla.contains("    /\\ node_1_last = \"none__\"\n"));
+        assert!(tla.contains("    /\\ node_1_last' = \"none\"\n"));
+        assert!(tla.contains("node_1_s_none ==\n"));
+    }
+
+    #[test]
+    fn escapes_strings() {
+        assert_eq!(string("a\"b\\c"), "\"a\\\"b\\\\c\"");
+    }
+}
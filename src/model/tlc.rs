@@ -0,0 +1,133 @@
+use std::collections::BTreeMap;
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+/// A state of the TLC graph, as the `/\ var = value` conjuncts of its label.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SpecState {
+    pub variables: BTreeMap<String, String>,
+    pub initial: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpecEdge {
+    pub from: String,
+    pub to: String,
+    pub action: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SpecGraph {
+    pub states: BTreeMap<String, SpecState>,
+    pub edges: Vec<SpecEdge>,
+}
+
+static NODE_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"^\s*(-?\d+)\s*\[label="((?:[^"\\]|\\.)*)"(.*)\]"#).unwrap());
+static EDGE_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"^\s*(-?\d+)\s*->\s*(-?\d+)\s*(?:\[label="((?:[^"\\]|\\.)*)")?"#).unwrap()
+});
+
+/// Parses the graph written by `tlc -dump dot[,actionlabels] <file>`. Lines
+/// that are neither states nor edges (graph attributes, legend) are ignored.
+pub fn parse_tlc_dot(dot: &str) -> SpecGraph {
+    let mut graph = SpecGraph::default();
+
+    for line in dot.lines() {
+        if let Some(caps) = EDGE_REGEX.captures(line) {
+            let action = caps
+                .get(3)
+                .map(|m| unescape(m.as_str()))
+                .filter(|a| !a.is_empty());
+            graph.edges.push(SpecEdge {
+                from: caps[1].to_string(),
+                to: caps[2].to_string(),
+                action,
+            });
+        } else if let Some(caps) = NODE_REGEX.captures(line) {
+            graph.states.insert(
+                caps[1].to_string(),
+                SpecState {
+                    variables: parse_conjuncts(&unescape(&caps[2])),
+                    initial: caps[3].contains("filled"),
+                },
+            );
+        }
+    }
+
+    graph
+}
+
+fn parse_conjuncts(label: &str) -> BTreeMap<String, String> {
+    label
+        .lines()
+        .filter_map(|line| {
+            let conjunct = line.trim().trim_start_matches("/\\").trim();
+            let (name, value) = conjunct.split_once(" = ")?;
+            Some((name.trim().to_string(), value.trim().to_string()))
+        })
+        .collect()
+}
+
+fn unescape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_states_and_labelled_edges() {
+        let dot = r#"strict digraph DiskGraph {
+nodesep=0.35
+subgraph cluster_graph {
+color="white"
+-6850061183385474424 [label="/\\ role = \"Follower\"\n/\\ term = 0",style = filled]
+-6850061183385474424 -> 4223617491637271946 [label="Timeout",color="2",fontcolor="2"];
+4223617491637271946 [label="/\\ role = \"Candidate\"\n/\\ term = 1"]
+4223617491637271946 -> -6850061183385474424 [label="StepDown",color="3",fontcolor="3"];
+}
+}"#;
+        let graph = parse_tlc_dot(dot);
+
+        assert_eq!(graph.states.len(), 2);
+        let init = &graph.states["-6850061183385474424"];
+        assert!(init.initial);
+        assert_eq!(init.variables["role"], "\"Follower\"");
+        assert_eq!(init.variables["term"], "0");
+        assert!(!graph.states["4223617491637271946"].initial);
+
+        assert_eq!(graph.edges.len(), 2);
+        assert_eq!(graph.edges[0].from, "-6850061183385474424");
+        assert_eq!(graph.edges[0].to, "4223617491637271946");
+        assert_eq!(graph.edges[0].action.as_deref(), Some("Timeout"));
+    }
+
+    #[test]
+    fn edges_without_action_labels() {
+        let dot = r#"1 [label="/\\ x = 0",style = filled]
+2 [label="/\\ x = 1"]
+1 -> 2;
+2 -> 1 [color="black"];"#;
+        let graph = parse_tlc_dot(dot);
+
+        assert_eq!(graph.states.len(), 2);
+        assert_eq!(graph.edges.len(), 2);
+        assert!(graph.edges.iter().all(|e| e.action.is_none()));
+    }
+}
@@ -0,0 +1,196 @@
+use std::fs;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// LLVM versions the pass builds against. 11 is the oldest it was written
+/// for; from 19 on it reads debug records where clang used to emit debug
+/// intrinsics.
+pub const SUPPORTED_LLVM: RangeInclusive<u32> = 11..=21;
+
+/// The pass `make -C llvm` builds for whatever `llvm-config` is.
+pub const PASS_FILE: &str = "afl-llvm-pass.so";
+
+/// The clang the instrumentor hands code to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Toolchain {
+    pub cc: String,
+    pub cxx: String,
+    pub major: u32,
+}
+
+impl Toolchain {
+    /// `clang-17` and `clang++-17`, as Debian and the apt.llvm.org packages
+    /// install them.
+    pub fn versioned(major: u32) -> Self {
+        Toolchain {
+            cc: format!("clang-{}", major),
+            cxx: format!("clang++-{}", major),
+            major,
+        }
+    }
+
+    pub fn supported(&self) -> bool {
+        SUPPORTED_LLVM.contains(&self.major)
+    }
+}
+
+/// The C++ driver next to a C compiler: `clang-17` gives `clang++-17`,
+/// `/opt/llvm/bin/clang` gives `/opt/llvm/bin/clang++`.
+pub fn cxx_for(cc: &str) -> String {
+    let name_start = cc.rfind('/').map_or(0, |slash| slash + 1);
+    match cc[name_start..].rfind("clang") {
+        Some(at) => {
+            let end = name_start + at + "clang".len();
+            format!("{}++{}", &cc[..end], &cc[end..])
+        }
+        None => format!("{}++", cc),
+    }
+}
+
+/// Every clang `version` reports a major version for, newest first: the
+/// versioned `clang-N` of each supported release, then a plain `clang`
+/// when it is a release not already found.
+pub fn detect_toolchains(version: impl Fn(&str) -> Option<u32>) -> Vec<Toolchain> {
+    let mut toolchains: Vec<Toolchain> = SUPPORTED_LLVM
+        .rev()
+        .map(Toolchain::versioned)
+        .filter(|toolchain| version(&toolchain.cc) == Some(toolchain.major))
+        .collect();
+    if let Some(major) = version("clang")
+        && !toolchains.iter().any(|toolchain| toolchain.major == major)
+    {
+        toolchains.push(Toolchain {
+            cc: "clang".to_string(),
+            cxx: "clang++".to_string(),
+            major,
+        });
+    }
+    toolchains
+}
+
+/// The pass to load with LLVM `major`: the `afl-llvm-pass-<major>.so` the
+/// Makefile builds, or else the unversioned pass when it links against
+/// that LLVM. A pass linked statically does not tell, so `fallback` (the
+/// version of the `llvm-config` it was presumably built with) stands in.
+pub fn select_pass(llvm_dir: &Path, major: u32, fallback: Option<u32>) -> Option<PathBuf> {
+    let versioned = llvm_dir.join(format!("afl-llvm-pass-{}.so", major));
+    if versioned.is_file() {
+        return Some(versioned);
+    }
+    let unversioned = llvm_dir.join(PASS_FILE);
+    let binary = fs::read(&unversioned).ok()?;
+    match linked_llvm_major(&binary).or(fallback) {
+        Some(built) if built != major => None,
+        _ => Some(unversioned),
+    }
+}
+
+/// Major version of the shared LLVM library a built pass links against,
+/// from the `libLLVM-11.so` or `libLLVM.so.11` name in its dynamic section.
+pub fn linked_llvm_major(binary: &[u8]) -> Option<u32> {
+    const NEEDLE: &[u8] = b"libLLVM";
+    let mut start = 0;
+    while let Some(offset) = binary[start..]
+        .windows(NEEDLE.len())
+        .position(|window| window == NEEDLE)
+    {
+        let rest = &binary[start + offset + NEEDLE.len()..];
+        let digits_at = match rest {
+            [b'-', ..] => Some(1),
+            [b'.', b's', b'o', b'.', ..] => Some(4),
+            _ => None,
+        };
+        if let Some(at) = digits_at {
+            let digits: String = rest[at..]
+                .iter()
+                .take_while(|b| b.is_ascii_digit())
+                .map(|b| *b as char)
+                .collect();
+            if let Ok(major) = digits.parse() {
+                return Some(major);
+            }
+        }
+        start += offset + NEEDLE.len();
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_cxx_for() {
+        assert_eq!(cxx_for("clang-17"), "clang++-17");
+        assert_eq!(cxx_for("clang"), "clang++");
+        assert_eq!(
+            cxx_for("/opt/clang-15/bin/clang"),
+            "/opt/clang-15/bin/clang++"
+        );
+        assert_eq!(
+            cxx_for("x86_64-linux-gnu-clang-14"),
+            "x86_64-linux-gnu-clang++-14"
+        );
+        assert_eq!(cxx_for("cc"), "cc++");
+    }
+
+    #[test]
+    fn test_detect_toolchains() {
+        let installed = |tool: &str| match tool {
+            "clang-17" => Some(17),
+            "clang-14" => Some(14),
+            // a wrapper named after the wrong release is not trusted
+            "clang-12" => Some(11),
+            "clang" => Some(14),
+            _ => None,
+        };
+        let found: Vec<_> = detect_toolchains(installed)
+            .into_iter()
+            .map(|toolchain| toolchain.cc)
+            .collect();
+        assert_eq!(found, ["clang-17", "clang-14"]);
+
+        let plain = detect_toolchains(|tool| (tool == "clang").then_some(22));
+        assert_eq!(plain.len(), 1);
+        assert_eq!(plain[0].cxx, "clang++");
+        assert!(!plain[0].supported());
+        assert!(Toolchain::versioned(19).supported());
+    }
+
+    #[test]
+    fn test_linked_llvm_major() {
+        assert_eq!(
+            linked_llvm_major(b"\0libc.so.6\0libLLVM-11.so.1\0"),
+            Some(11)
+        );
+        assert_eq!(
+            linked_llvm_major(b"libLLVMSupport\0libLLVM.so.18.1\0"),
+            Some(18)
+        );
+        assert_eq!(linked_llvm_major(b"\0libstdc++.so.6\0"), None);
+    }
+
+    #[test]
+    fn test_select_pass() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        assert_eq!(select_pass(dir, 14, None), None);
+
+        fs::write(dir.join(PASS_FILE), b"\0libLLVM-11.so\0").unwrap();
+        assert_eq!(select_pass(dir, 11, None), Some(dir.join(PASS_FILE)));
+        assert_eq!(select_pass(dir, 14, Some(14)), None);
+
+        fs::write(dir.join("afl-llvm-pass-14.so"), b"").unwrap();
+        assert_eq!(
+            select_pass(dir, 14, None),
+            Some(dir.join("afl-llvm-pass-14.so"))
+        );
+
+        fs::write(dir.join(PASS_FILE), b"statically linked").unwrap();
+        assert_eq!(select_pass(dir, 17, Some(17)), Some(dir.join(PASS_FILE)));
+        assert_eq!(select_pass(dir, 17, Some(14)), None);
+    }
+}
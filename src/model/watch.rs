@@ -0,0 +1,323 @@
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+
+use super::event_graph::{Edge, EventGraph};
+use super::event_log::parse_event_log;
+use super::refinement::Refinement;
+
+/// What a poll of the log returned.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct TailUpdate {
+    /// Complete lines appended since the last poll.
+    pub lines: Vec<String>,
+    /// The file was truncated or replaced, so `lines` start a new log.
+    pub restarted: bool,
+}
+
+/// Follows a log file by offset. A shorter file means it was truncated and a
+/// different inode means it was rotated; either way it is read again from the
+/// start.
+#[derive(Debug)]
+pub struct LogTail {
+    path: PathBuf,
+    offset: u64,
+    inode: Option<u64>,
+    partial: String,
+}
+
+impl LogTail {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            offset: 0,
+            inode: None,
+            partial: String::new(),
+        }
+    }
+
+    /// A missing file is not an error: the mediator may not have created it
+    /// yet, or may be in the middle of rotating it.
+    pub fn poll(&mut self) -> Result<TailUpdate> {
+        let Ok(metadata) = fs::metadata(&self.path) else {
+            return Ok(TailUpdate::default());
+        };
+
+        let inode = inode(&metadata);
+        let mut restarted = false;
+        if (self.inode.is_some() && inode != self.inode) || metadata.len() < self.offset {
+            self.offset = 0;
+            self.partial.clear();
+            restarted = true;
+        }
+        self.inode = inode;
+
+        if metadata.len() == self.offset {
+            return Ok(TailUpdate {
+                lines: Vec::new(),
+                restarted,
+            });
+        }
+
+        let mut file =
+            File::open(&self.path).with_context(|| format!("opening {}", self.path.display()))?;
+        file.seek(SeekFrom::Start(self.offset))?;
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)
+            .with_context(|| format!("reading {}", self.path.display()))?;
+        self.offset += bytes.len() as u64;
+
+        self.partial.push_str(&String::from_utf8_lossy(&bytes));
+        let complete = match self.partial.rfind('\n') {
+            Some(end) => {
+                let rest = self.partial.split_off(end + 1);
+                std::mem::replace(&mut self.partial, rest)
+            }
+            None => String::new(),
+        };
+
+        Ok(TailUpdate {
+            lines: complete.lines().map(str::to_string).collect(),
+            restarted,
+        })
+    }
+}
+
+#[cfg(unix)]
+fn inode(metadata: &fs::Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.ino())
+}
+
+#[cfg(not(unix))]
+fn inode(_metadata: &fs::Metadata) -> Option<u64> {
+    None
+}
+
+/// The states and edges one update added to a [`WatchedGraph`], per node.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Discoveries {
+    pub new_states: BTreeMap<u32, Vec<String>>,
+    pub new_edges: BTreeMap<u32, Vec<Edge>>,
+}
+
+impl Discoveries {
+    pub fn is_empty(&self) -> bool {
+        self.new_states.is_empty() && self.new_edges.is_empty()
+    }
+
+    /// Whether the update added anything to the node.
+    pub fn touches(&self, node_id: u32) -> bool {
+        self.new_states.contains_key(&node_id) || self.new_edges.contains_key(&node_id)
+    }
+
+    pub fn new_state_count(&self) -> usize {
+        self.new_states.values().map(Vec::len).sum()
+    }
+
+    pub fn new_edge_count(&self) -> usize {
+        self.new_edges.values().map(Vec::len).sum()
+    }
+}
+
+/// The abstraction of a log that keeps growing, built one update at a time:
+/// only the new lines are parsed, and all that is kept besides the graph is
+/// the last state of every node. Logs that were truncated or rotated away
+/// still count: their states and edges are kept, without inventing edges
+/// across the restart. Dwell times are not tracked.
+pub struct WatchedGraph {
+    refinement: Refinement,
+    last_for_node: HashMap<u32, (String, String, Option<u32>)>,
+    graph: EventGraph,
+}
+
+impl WatchedGraph {
+    pub fn new(refinement: Refinement) -> Self {
+        Self {
+            refinement,
+            last_for_node: HashMap::new(),
+            graph: EventGraph::default(),
+        }
+    }
+
+    pub fn graph(&self) -> &EventGraph {
+        &self.graph
+    }
+
+    /// Applies an update and returns the states and edges it discovered.
+    pub fn apply(&mut self, update: &TailUpdate) -> Discoveries {
+        if update.restarted {
+            self.last_for_node.clear();
+        }
+
+        let mut discovered = Discoveries::default();
+        for rec in parse_event_log(&update.lines.join("\n")) {
+            let Some(state) = self.refinement.state(&rec.state, rec.constant) else {
+                continue;
+            };
+            let node = self.graph.nodes.entry(rec.node_id).or_default();
+            node.initial.get_or_insert_with(|| state.clone());
+            if let Err(at) = node.states.binary_search(&state) {
+                node.states.insert(at, state.clone());
+                discovered
+                    .new_states
+                    .entry(rec.node_id)
+                    .or_default()
+                    .push(state.clone());
+            }
+
+            if let Some((prev_state, prev_transition, prev_event)) =
+                self.last_for_node.get(&rec.node_id)
+                && self.refinement.keeps_transition(prev_transition)
+            {
+                if let Some(event_id) = prev_event {
+                    let ids = node
+                        .transition_events
+                        .entry(prev_transition.clone())
+                        .or_default();
+                    if let Err(at) = ids.binary_search(event_id) {
+                        ids.insert(at, *event_id);
+                    }
+                }
+                let edge = Edge {
+                    from: prev_state.clone(),
+                    transition: prev_transition.clone(),
+                    to: state.clone(),
+                };
+                if let Err(at) = node.edges.binary_search_by(|e| edge_order(e, &edge)) {
+                    node.edges.insert(at, edge.clone());
+                    discovered
+                        .new_edges
+                        .entry(rec.node_id)
+                        .or_default()
+                        .push(edge);
+                }
+            }
+
+            self.last_for_node
+                .insert(rec.node_id, (state, rec.transition, rec.event_id));
+        }
+
+        discovered
+    }
+}
+
+/// The order `build_event_graph_with` sorts edges in.
+fn edge_order(l: &Edge, r: &Edge) -> Ordering {
+    l.from
+        .cmp(&r.from)
+        .then_with(|| l.to.cmp(&r.to))
+        .then_with(|| l.transition.cmp(&r.transition))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::event_graph::build_event_graph;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    const FOLLOWER: &str = "[2025-11-10 19:56:55.000001][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 1 / 1] FunctionExecute 1 @ FunctionName boot @ state Follower\n";
+    const CANDIDATE: &str = "[2025-11-10 19:56:55.000002][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 1 / 1] FunctionExecute 2 @ FunctionName electionTimeout @ state Candidate\n";
+    const LEADER: &str = "[2025-11-10 19:56:55.000003][INFO] [FUNC_EVENT_TYPE][Node 1 Batch 1 Entry 1 / 1] FunctionExecute 3 @ FunctionName winElection @ state Leader\n";
+
+    fn append(path: &std::path::Path, content: &str) {
+        fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .unwrap()
+            .write_all(content.as_bytes())
+            .unwrap();
+    }
+
+    #[test]
+    fn returns_only_complete_new_lines() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("events.log");
+        let mut tail = LogTail::new(&path);
+
+        assert_eq!(tail.poll().unwrap(), TailUpdate::default());
+
+        append(&path, "first\nsec");
+        assert_eq!(tail.poll().unwrap().lines, vec!["first"]);
+        append(&path, "ond\nthird\n");
+        assert_eq!(tail.poll().unwrap().lines, vec!["second", "third"]);
+        assert!(tail.poll().unwrap().lines.is_empty());
+    }
+
+    #[test]
+    fn restarts_after_truncation_and_rotation() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("events.log");
+        let mut tail = LogTail::new(&path);
+
+        append(&path, "one\ntwo\n");
+        tail.poll().unwrap();
+
+        fs::write(&path, "new\n").unwrap();
+        let update = tail.poll().unwrap();
+        assert!(update.restarted);
+        assert_eq!(update.lines, vec!["new"]);
+
+        fs::rename(&path, temp.path().join("events.log.1")).unwrap();
+        append(&path, "rotated and longer\n");
+        let update = tail.poll().unwrap();
+        assert!(update.restarted);
+        assert_eq!(update.lines, vec!["rotated and longer"]);
+    }
+
+    #[test]
+    fn reports_discoveries_and_keeps_graphs_across_restarts() {
+        let mut watched = WatchedGraph::new(Refinement::identity());
+
+        let first = watched.apply(&TailUpdate {
+            lines: vec![FOLLOWER.trim().to_string(), CANDIDATE.trim().to_string()],
+            restarted: false,
+        });
+        assert!(first.touches(1) && !first.touches(2));
+        assert_eq!(first.new_state_count(), 2);
+        assert_eq!(first.new_edge_count(), 1);
+
+        let again = watched.apply(&TailUpdate {
+            lines: vec![FOLLOWER.trim().to_string()],
+            restarted: false,
+        });
+        assert_eq!(again.new_state_count(), 0);
+        assert_eq!(again.new_edges[&1][0].transition, "electionTimeout");
+
+        let restarted = watched.apply(&TailUpdate {
+            lines: vec![LEADER.trim().to_string()],
+            restarted: true,
+        });
+        assert_eq!(restarted.new_states[&1], vec!["Leader".to_string()]);
+        assert_eq!(restarted.new_edge_count(), 0);
+        assert!(watched.apply(&TailUpdate::default()).is_empty());
+
+        let node = &watched.graph().nodes[&1];
+        assert_eq!(node.states, vec!["Candidate", "Follower", "Leader"]);
+        assert_eq!(node.edges.len(), 2);
+    }
+
+    #[test]
+    fn builds_the_same_graph_as_a_full_export() {
+        let log = fs::read_to_string("tests/export_graphs_test/windowed_events.log").unwrap();
+        let mut watched = WatchedGraph::new(Refinement::identity());
+        for chunk in log.lines().collect::<Vec<_>>().chunks(3) {
+            watched.apply(&TailUpdate {
+                lines: chunk.iter().map(|line| line.to_string()).collect(),
+                restarted: false,
+            });
+        }
+
+        let mut full = build_event_graph(&log);
+        for node in full.nodes.values_mut() {
+            node.dwell.clear();
+        }
+        assert_eq!(watched.graph(), &full);
+    }
+}